@@ -51,10 +51,20 @@ export const sendSats = async (
   changeDescriptor: string,
   address: string,
   amount: bigint,
-  feeRate: number
+  feeRate: number,
+  nostrHexSk: string,
+  coinControl?: CoinControl
 ): Promise<TransactionData> =>
   JSON.parse(
-    await BMC.send_sats(descriptor, changeDescriptor, address, amount, feeRate)
+    await BMC.send_sats(
+      descriptor,
+      changeDescriptor,
+      address,
+      amount,
+      feeRate,
+      nostrHexSk,
+      coinControl
+    )
   );
 
 export const fundVault = async (
@@ -62,7 +72,8 @@ export const fundVault = async (
   changeDescriptor: string,
   assetAddress1: string,
   udaAddress1: string,
-  feeRate: number
+  feeRate: number,
+  nostrHexSk: string
 ): Promise<FundVaultDetails> =>
   JSON.parse(
     await BMC.fund_vault(
@@ -70,7 +81,8 @@ export const fundVault = async (
       changeDescriptor,
       assetAddress1,
       udaAddress1,
-      feeRate
+      feeRate,
+      nostrHexSk
     )
   );
 
@@ -85,13 +97,34 @@ export const getAssetsVault = async (
 export const drainWallet = async (
   destination: string,
   descriptor: string,
-  changeDescriptor?: string,
-  feeRate?: number
+  changeDescriptor: string | undefined,
+  feeRate: number | undefined,
+  nostrHexSk: string,
+  coinControl?: CoinControl
 ): Promise<TransactionData> =>
   JSON.parse(
-    await BMC.drain_wallet(destination, descriptor, changeDescriptor, feeRate)
+    await BMC.drain_wallet(
+      destination,
+      descriptor,
+      changeDescriptor,
+      feeRate,
+      nostrHexSk,
+      coinControl
+    )
   );
 
+export const lockUtxos = async (
+  nostrHexSk: string,
+  outpoints: string[]
+): Promise<string[]> =>
+  JSON.parse(await BMC.lock_utxos(nostrHexSk, outpoints));
+
+export const unlockUtxos = async (
+  nostrHexSk: string,
+  outpoints: string[]
+): Promise<string[]> =>
+  JSON.parse(await BMC.unlock_utxos(nostrHexSk, outpoints));
+
 export const bumpFee = async (
   txid: string,
   feeRate: number,
@@ -220,6 +253,13 @@ export interface WalletData {
   utxos: string[];
 }
 
+export interface CoinControl {
+  // Outpoints that must be spent
+  include: string[];
+  // Outpoints that must not be spent
+  exclude: string[];
+}
+
 export interface FundVaultDetails {
   assetsOutput?: string;
   udasOutput?: string;
@@ -21,6 +21,21 @@ export const retrieveMetadata = (
   lookup: string
 ): Promise<FileMetadata> => BMC.retrieve_metadata(nostrHexSk, lookup);
 
+export const storeMedia = async (
+  nostrHexSk: string,
+  name: string,
+  data: Uint8Array
+): Promise<SegmentManifest> =>
+  JSON.parse(await BMC.store_media(nostrHexSk, name, data));
+
+// Only the segments holding the range are fetched, the whole file without one
+export const retrieveMedia = (
+  nostrHexSk: string,
+  name: string,
+  start?: bigint,
+  end?: bigint
+): Promise<Uint8Array> => BMC.retrieve_media(nostrHexSk, name, start, end);
+
 export const encodeHex = (bytes: Uint8Array): string => BMC.encode_hex(bytes);
 export const encodeBase64 = (bytes: Uint8Array): string =>
   BMC.encode_base64(bytes);
@@ -32,3 +47,10 @@ export interface FileMetadata {
   filename: string;
   metadata: Uint8Array;
 }
+
+export interface SegmentManifest {
+  segment_size: number;
+  len: number;
+  // versions of the encoded segments
+  segments: string[];
+}
@@ -4,7 +4,7 @@
 use std::{
     env,
     fs::OpenOptions,
-    io::ErrorKind,
+    io::{ErrorKind, SeekFrom},
     net::SocketAddr,
     path,
     str::FromStr,
@@ -14,11 +14,14 @@ use std::{
 use amplify::hex::FromHex;
 use anyhow::Result;
 use axum::{
-    body::Bytes,
+    body::{Bytes, StreamBody},
     extract::Path,
     headers::{authorization::Bearer, Authorization, CacheControl},
-    http::StatusCode,
-    response::{IntoResponse, Response},
+    http::{
+        header::{ACCEPT_RANGES, CONTENT_RANGE, RANGE},
+        HeaderMap, StatusCode,
+    },
+    response::{AppendHeaders, IntoResponse, Response},
     routing::{delete, get, post, put},
     Json, Router, TypedHeader,
 };
@@ -26,40 +29,59 @@ use bitcoin_30::secp256k1::{ecdh::SharedSecret, PublicKey, SecretKey};
 use bitmask_core::{
     bitcoin::{save_mnemonic, sign_and_publish_psbt_file},
     carbonado::{
-        auctions_retrieve, auctions_store, handle_file, marketplace_retrieve, marketplace_store,
-        metrics, store,
+        auctions_allow, auctions_encode, auctions_retrieve,
+        auth::{auction_bidders, auction_writer, WriteAuth, AUTH_PUBKEY_HEADER},
+        error::CarbonadoError,
+        gc, handle_file, history, marketplace_retrieve, marketplace_store, metrics,
+        quota::{self, StorageCategory},
+        segments::parse_range,
+        store,
+        versions::{lock_file, persist_version, replace_file, write_if_matches, IF_MATCH_HEADER},
     },
+    chain::resolver_cache_metrics,
     constants::{
         get_marketplace_nostr_key, get_marketplace_seed, get_network, get_udas_utxo, switch_network,
     },
+    labels::{export_labels, import_labels, list_labels, remove_label, set_labels},
     proxy::{
         handle_file as proxy_handle_file, proxy_consig_retrieve, proxy_consig_store,
-        proxy_media_data_store, proxy_media_retrieve, proxy_metadata_retrieve,
+        proxy_media_data_store, proxy_media_file, proxy_metadata_retrieve,
     },
     rgb::{
         accept_transfer,
         carbonado::retrieve_auctions_offers,
-        clear_watcher as rgb_clear_watcher, create_invoice, create_psbt, create_watcher,
-        full_transfer_asset, get_contract, import as rgb_import, issue_contract, list_contracts,
-        list_interfaces, list_schemas, list_transfers as list_rgb_transfers, reissue_contract,
-        remove_transfer as remove_rgb_transfer, save_transfer as save_rgb_transfer,
+        check_reorgs, clear_watcher as rgb_clear_watcher, create_invoice, create_psbt,
+        create_watcher, create_watchers, export_backup, full_transfer_asset, get_balances,
+        get_contract, import as rgb_import, issue_contract, list_contracts, list_interfaces,
+        list_schemas, list_transfers as list_rgb_transfers, migrate_account, migration_plan,
+        provision_utxos, reissue_contract, remove_transfer as remove_rgb_transfer,
+        reorg::on_reorg,
+        rescan_watcher, restore_backup, save_transfer as save_rgb_transfer, set_utxo_pool,
         structs::{
             RgbProxyConsigCarbonadoReq, RgbProxyConsigFileReq, RgbProxyConsigUpload,
             RgbProxyMediaCarbonadoReq, RgbProxyMediaFileReq,
         },
-        transfer_asset, watcher_address, watcher_details as rgb_watcher_details,
-        watcher_next_address, watcher_next_utxo, watcher_utxo,
+        sync_documents, top_up_utxos, transfer_asset, watcher_address,
+        watcher_details as rgb_watcher_details, watcher_next_address, watcher_next_utxo,
+        watcher_rescan_status, watcher_utxo,
     },
     structs::{
         AcceptRequest, FileMetadata, FullRgbTransferRequest, ImportRequest, InvoiceRequest,
-        IssueRequest, MediaEncode, MediaExtractRequest, MediaItemRequest, PsbtFeeRequest,
-        PsbtRequest, ReIssueRequest, RgbRemoveTransferRequest, RgbSaveTransferRequest,
-        RgbTransferRequest, SecretString, SelfFullRgbTransferRequest, SelfInvoiceRequest,
-        SelfIssueRequest, SignPsbtRequest, WatcherRequest,
+        IssueRequest, LabelType, LabelsRequest, MediaEncode, MediaExtractRequest, MediaItemRequest,
+        PsbtFeeRequest, PsbtRequest, ReIssueRequest, RgbBackupRequest, RgbProvisionRequest,
+        RgbRemoveTransferRequest, RgbRescanRequest, RgbRestoreRequest, RgbSaveTransferRequest,
+        RgbSyncRequest, RgbTopUpRequest, RgbTransferRequest, RgbUtxoPoolRequest, SecretString,
+        SelfFullRgbTransferRequest, SelfInvoiceRequest, SelfIssueRequest, SignPsbtRequest,
+        WatcherRequest, WatchersRequest,
     },
 };
-use log::{debug, error, info};
-use tokio::{fs, time::sleep};
+use futures::{stream, Stream};
+use log::{debug, error, info, warn};
+use tokio::{
+    fs,
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt},
+    time::sleep,
+};
 use tower_http::cors::CorsLayer;
 
 async fn issue(
@@ -320,6 +342,18 @@ async fn watcher(
     Ok((StatusCode::OK, Json(resp)))
 }
 
+async fn watchers(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<WatchersRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("POST /watchers {request:?}");
+
+    let nostr_hex_sk = auth.token();
+    let resp = create_watchers(nostr_hex_sk, request).await?;
+
+    Ok((StatusCode::OK, Json(resp)))
+}
+
 async fn watcher_details(
     TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
     Path(name): Path<String>,
@@ -344,6 +378,79 @@ async fn clear_watcher(
     Ok((StatusCode::OK, Json(resp)))
 }
 
+async fn balances(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("GET /balances");
+
+    let nostr_hex_sk = auth.token();
+    let resp = get_balances(nostr_hex_sk).await?;
+
+    Ok((StatusCode::OK, Json(resp)))
+}
+
+async fn labels(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("GET /labels");
+
+    let nostr_hex_sk = auth.token();
+    let resp = list_labels(nostr_hex_sk).await?;
+
+    Ok((StatusCode::OK, Json(resp)))
+}
+
+async fn update_labels(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<LabelsRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("POST /labels {request:?}");
+
+    let nostr_hex_sk = auth.token();
+    let resp = set_labels(nostr_hex_sk, request).await?;
+
+    Ok((StatusCode::OK, Json(resp)))
+}
+
+async fn delete_label(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path((ty, reference)): Path<(LabelType, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("DELETE /label/{ty}/{reference:?}");
+
+    let nostr_hex_sk = auth.token();
+    let resp = remove_label(nostr_hex_sk, ty, &reference).await?;
+
+    Ok((StatusCode::OK, Json(resp)))
+}
+
+async fn labels_import(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    body: String,
+) -> Result<impl IntoResponse, AppError> {
+    info!("POST /labels/import");
+
+    let nostr_hex_sk = auth.token();
+    let resp = import_labels(nostr_hex_sk, &body).await?;
+
+    Ok((StatusCode::OK, Json(resp)))
+}
+
+async fn labels_export(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("GET /labels/export");
+
+    let nostr_hex_sk = auth.token();
+    let jsonl = export_labels(nostr_hex_sk).await?;
+
+    Ok((
+        StatusCode::OK,
+        [("content-type", "application/jsonl")],
+        jsonl,
+    ))
+}
+
 async fn next_address(
     TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
     Path((name, asset)): Path<(String, String)>,
@@ -393,6 +500,135 @@ async fn register_utxo(
     Ok((StatusCode::OK, Json(resp)))
 }
 
+async fn rescan(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<RgbRescanRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("POST /rescan {request:?}");
+
+    let nostr_hex_sk = auth.token();
+    let resp = rescan_watcher(nostr_hex_sk, request).await?;
+
+    Ok((StatusCode::OK, Json(resp)))
+}
+
+async fn rescan_status(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("GET /watcher/{name:?}/rescan");
+
+    let nostr_hex_sk = auth.token();
+    let resp = watcher_rescan_status(nostr_hex_sk, &name).await?;
+
+    Ok((StatusCode::OK, Json(resp)))
+}
+
+async fn reorgs(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("GET /reorgs");
+
+    let nostr_hex_sk = auth.token();
+    let resp = check_reorgs(nostr_hex_sk).await?;
+
+    Ok((StatusCode::OK, Json(resp)))
+}
+
+async fn backup(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<RgbBackupRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("POST /backup");
+
+    let nostr_hex_sk = auth.token();
+    let resp = export_backup(nostr_hex_sk, request).await?;
+
+    Ok((StatusCode::OK, Json(resp)))
+}
+
+async fn restore(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<RgbRestoreRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("POST /restore");
+
+    let nostr_hex_sk = auth.token();
+    let resp = restore_backup(nostr_hex_sk, request).await?;
+
+    Ok((StatusCode::OK, Json(resp)))
+}
+
+async fn migration(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("GET /migration");
+
+    let nostr_hex_sk = auth.token();
+    let resp = migration_plan(nostr_hex_sk).await?;
+
+    Ok((StatusCode::OK, Json(resp)))
+}
+
+async fn migrate(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("POST /migration");
+
+    let nostr_hex_sk = auth.token();
+    let resp = migrate_account(nostr_hex_sk).await?;
+
+    Ok((StatusCode::OK, Json(resp)))
+}
+
+async fn sync(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<RgbSyncRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("POST /sync");
+
+    let nostr_hex_sk = auth.token();
+    let resp = sync_documents(nostr_hex_sk, request).await?;
+
+    Ok((StatusCode::OK, Json(resp)))
+}
+
+async fn provision(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<RgbProvisionRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("POST /utxos/provision");
+
+    let nostr_hex_sk = auth.token();
+    let resp = provision_utxos(nostr_hex_sk, request).await?;
+
+    Ok((StatusCode::OK, Json(resp)))
+}
+
+async fn utxo_pool(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<RgbUtxoPoolRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("POST /utxos/pool {request:?}");
+
+    let nostr_hex_sk = auth.token();
+    let resp = set_utxo_pool(nostr_hex_sk, request).await?;
+
+    Ok((StatusCode::OK, Json(resp)))
+}
+
+async fn top_up(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<RgbTopUpRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("POST /utxos/topup");
+
+    let nostr_hex_sk = auth.token();
+    let resp = top_up_utxos(nostr_hex_sk, request).await?;
+
+    Ok((StatusCode::OK, Json(resp)))
+}
+
 async fn list_transfers(
     TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
     Path(contract_id): Path<String>,
@@ -429,14 +665,52 @@ async fn remove_transfer(
     Ok((StatusCode::OK, Json(import_res)))
 }
 
+/// Whether a write of `body` to `resource` is signed by `pk`, or by anyone when `None`
+async fn authorize_write(
+    headers: &HeaderMap,
+    pk: Option<&str>,
+    resource: &str,
+    body: &[u8],
+) -> bool {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let authorized = match WriteAuth::from_headers(header) {
+        Ok(auth) => auth.authorize(pk, resource, body).await,
+        Err(err) => Err(err),
+    };
+
+    match authorized {
+        Ok(()) => true,
+        Err(err) => {
+            warn!("unauthorized write to {resource}: {err}");
+            false
+        }
+    }
+}
+
+/// Status of a write over the storage limits, see `carbonado::quota`
+fn storage_rejection(err: CarbonadoError) -> Result<(StatusCode, &'static str), AppError> {
+    match err {
+        CarbonadoError::FileTooLarge(..) => {
+            Ok((StatusCode::PAYLOAD_TOO_LARGE, "Payload Too Large"))
+        }
+        CarbonadoError::QuotaExceeded(_) => {
+            Ok((StatusCode::INSUFFICIENT_STORAGE, "Insufficient Storage"))
+        }
+        err => Err(err.into()),
+    }
+}
+
 async fn co_store(
     Path((pk, name)): Path<(String, String)>,
+    headers: HeaderMap,
     body: Bytes,
 ) -> Result<impl IntoResponse, AppError> {
     let cc = CacheControl::new().with_no_cache();
 
     let incoming_header = carbonado::file::Header::try_from(&body)?;
-    if incoming_header.pubkey.to_string() != pk {
+    if incoming_header.pubkey.to_string() != pk
+        || !authorize_write(&headers, Some(&pk), &format!("{pk}/{name}"), &body).await
+    {
         return Ok((StatusCode::UNAUTHORIZED, TypedHeader(cc), "Unauthorized"));
     }
 
@@ -444,86 +718,79 @@ async fn co_store(
     info!("POST /carbonado/{pk}/{name}, {body_len} bytes");
 
     let filepath = handle_file(&pk, &name, body_len.try_into()?).await?;
+    let category = StorageCategory::of(&name);
+    if let Err(err) = quota::check_write(&filepath, category, body.len() as u64).await {
+        warn!("rejected write of {name}: {err}");
+        let (status, message) = storage_rejection(err)?;
+        return Ok((status, TypedHeader(cc), message));
+    }
 
-    match OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .open(&filepath)
-    {
+    // Conditional write, over the version the client retrieved last
+    if let Some(expected) = headers.get(IF_MATCH_HEADER) {
+        let expected = expected.to_str()?;
+        if !write_if_matches(&filepath, Some(expected), &body).await? {
+            debug!("stale write of {name}, expected version {expected}.");
+            return Ok((StatusCode::CONFLICT, TypedHeader(cc), "Conflict"));
+        }
+        return Ok((StatusCode::OK, TypedHeader(cc), "Success"));
+    }
+
+    // Legacy writes only replace smaller files
+    let _lock = lock_file(&filepath).await;
+    match std::fs::File::open(&filepath) {
         Ok(file) => {
-            let present_header = match carbonado::file::Header::try_from(&file) {
-                Ok(header) => header,
-                _ => carbonado::file::Header::try_from(&body)?,
-            };
-            let present_len = present_header.encoded_len - present_header.padding_len;
-            debug!("body len: {body_len} present_len: {present_len}");
-            if body_len >= present_len {
-                debug!("body is bigger, overwriting.");
-                let resp = fs::write(&filepath, &body).await;
-                debug!("write file status {}", resp.is_ok());
-            } else {
-                debug!("no file written.");
+            if let Ok(present_header) = carbonado::file::Header::try_from(&file) {
+                let present_len = present_header.encoded_len - present_header.padding_len;
+                debug!("body len: {body_len} present_len: {present_len}");
+                if body_len < present_len {
+                    debug!("body is smaller, no file written.");
+                    return Ok((StatusCode::CONFLICT, TypedHeader(cc), "Conflict"));
+                }
             }
         }
-        Err(err) => match err.kind() {
-            ErrorKind::NotFound => {
-                debug!("no file found, writing {body_len} bytes.");
-                fs::write(&filepath, &body).await?;
-            }
-            _ => {
-                error!("error in POST /carbonado/{pk}/{name}: {err}");
-                return Err(err.into());
-            }
-        },
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            debug!("no file found, writing {body_len} bytes.");
+        }
+        Err(err) => {
+            error!("error in POST /carbonado/{pk}/{name}: {err}");
+            return Err(err.into());
+        }
     }
 
-    // metrics::update(&filepath).await?;
+    history::archive(&filepath).await?;
+    replace_file(&filepath, &body).await?;
 
     Ok((StatusCode::OK, TypedHeader(cc), "Success"))
 }
 
 async fn co_force_store(
     Path((pk, name)): Path<(String, String)>,
+    headers: HeaderMap,
     body: Bytes,
 ) -> Result<impl IntoResponse, AppError> {
+    let cc = CacheControl::new().with_no_cache();
+
     let incoming_header = carbonado::file::Header::try_from(&body)?;
+    if incoming_header.pubkey.to_string() != pk
+        || !authorize_write(&headers, Some(&pk), &format!("{pk}/{name}/force"), &body).await
+    {
+        return Ok((StatusCode::UNAUTHORIZED, TypedHeader(cc), "Unauthorized"));
+    }
+
     let body_len = incoming_header.encoded_len - incoming_header.padding_len;
     info!("POST /carbonado/{pk}/{name}/force, {body_len} bytes");
 
     let filepath = handle_file(&pk, &name, body_len.try_into()?).await?;
-
-    match OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .open(&filepath)
-    {
-        Ok(file) => {
-            let present_header = match carbonado::file::Header::try_from(&file) {
-                Ok(header) => header,
-                _ => carbonado::file::Header::try_from(&body)?,
-            };
-            let present_len = present_header.encoded_len - present_header.padding_len;
-            debug!("body len: {body_len} present_len: {present_len}");
-            let resp = fs::write(&filepath, &body).await;
-            debug!("file override status {}", resp.is_ok());
-        }
-        Err(err) => match err.kind() {
-            ErrorKind::NotFound => {
-                debug!("no file found, writing {body_len} bytes.");
-                fs::write(&filepath, &body).await?;
-            }
-            _ => {
-                error!("error in POST /carbonado/{pk}/{name}/force: {err}");
-                return Err(err.into());
-            }
-        },
+    let category = StorageCategory::of(&name);
+    if let Err(err) = quota::check_write(&filepath, category, body.len() as u64).await {
+        warn!("rejected write of {name}: {err}");
+        let (status, message) = storage_rejection(err)?;
+        return Ok((status, TypedHeader(cc), message));
     }
 
-    let cc = CacheControl::new().with_no_cache();
+    write_if_matches(&filepath, None, &body).await?;
 
-    Ok((StatusCode::OK, TypedHeader(cc)))
+    Ok((StatusCode::OK, TypedHeader(cc), "Success"))
 }
 
 async fn co_server_store(
@@ -568,30 +835,146 @@ async fn co_server_store(
 
 async fn co_retrieve(
     Path((pk, name)): Path<(String, String)>,
-) -> Result<impl IntoResponse, AppError> {
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
     info!("GET /carbonado/{pk}/{name}");
 
     let filepath = &handle_file(&pk, &name, 0).await?;
     let fullpath = filepath.to_string_lossy();
-    let bytes = fs::read(filepath).await;
     let cc = CacheControl::new().with_no_cache();
 
-    match bytes {
-        Ok(bytes) => {
-            debug!("read {0} bytes.", bytes.len());
-            Ok((StatusCode::OK, TypedHeader(cc), bytes))
-        }
+    let file = match fs::File::open(filepath).await {
+        Ok(file) => file,
         Err(e) => {
             debug!(
                 "file read error {0} .Details: {1}.",
                 fullpath,
                 e.to_string()
             );
-            Ok((StatusCode::OK, TypedHeader(cc), Vec::<u8>::new()))
+            return Ok((StatusCode::OK, TypedHeader(cc), Vec::<u8>::new()).into_response());
+        }
+    };
+
+    ranged_file_response(file, &headers, cc).await
+}
+
+/// Streams a file, in a single byte range when asked
+async fn ranged_file_response(
+    mut file: fs::File,
+    headers: &HeaderMap,
+    cc: CacheControl,
+) -> Result<Response, AppError> {
+    let len = file.metadata().await?.len();
+    let (status, range) = match headers.get(RANGE).and_then(|range| range.to_str().ok()) {
+        None => (StatusCode::OK, 0..len),
+        Some(range) => match parse_range(range, len) {
+            Some(range) => (StatusCode::PARTIAL_CONTENT, range),
+            None => {
+                let content_range = format!("bytes */{len}");
+                return Ok((
+                    StatusCode::RANGE_NOT_SATISFIABLE,
+                    TypedHeader(cc),
+                    [(CONTENT_RANGE, content_range)],
+                )
+                    .into_response());
+            }
+        },
+    };
+
+    debug!("read {0} bytes.", range.end - range.start);
+    file.seek(SeekFrom::Start(range.start)).await?;
+    let mut parts = vec![(ACCEPT_RANGES, "bytes".to_owned())];
+    if status == StatusCode::PARTIAL_CONTENT {
+        let content_range = format!("bytes {}-{}/{len}", range.start, range.end - 1);
+        parts.push((CONTENT_RANGE, content_range));
+    }
+
+    let body = StreamBody::new(reader_stream(file.take(range.end - range.start)));
+    Ok((status, TypedHeader(cc), AppendHeaders(parts), body).into_response())
+}
+
+/// Stream of a reader, in 64 KiB chunks
+fn reader_stream<R>(reader: R) -> impl Stream<Item = std::io::Result<Bytes>>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    stream::unfold(Some(reader), |reader| async move {
+        let mut reader = reader?;
+        let mut buf = vec![0u8; 64 * 1024];
+        match reader.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(read) => {
+                buf.truncate(read);
+                Some((Ok(Bytes::from(buf)), Some(reader)))
+            }
+            Err(err) => Some((Err(err), None)),
+        }
+    })
+}
+
+async fn co_history(
+    Path((pk, name)): Path<(String, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("GET /carbonado/{pk}/{name}/history");
+
+    let filepath = handle_file(&pk, &name, 0).await?;
+    let versions = history::list_versions(&filepath).await?;
+    let cc = CacheControl::new().with_no_cache();
+
+    Ok((StatusCode::OK, TypedHeader(cc), Json(versions)))
+}
+
+async fn co_history_retrieve(
+    Path((pk, name, version)): Path<(String, String, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("GET /carbonado/{pk}/{name}/history/{version}");
+
+    let filepath = handle_file(&pk, &name, 0).await?;
+    let cc = CacheControl::new().with_no_cache();
+
+    match history::read_version(&filepath, &version).await {
+        Ok(bytes) => Ok((StatusCode::OK, TypedHeader(cc), bytes)),
+        Err(CarbonadoError::VersionNotFound(_)) => {
+            Ok((StatusCode::NOT_FOUND, TypedHeader(cc), Vec::<u8>::new()))
         }
+        Err(err) => Err(err.into()),
     }
 }
 
+async fn co_rollback(
+    Path((pk, name, version)): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    info!("POST /carbonado/{pk}/{name}/rollback/{version}");
+
+    let cc = CacheControl::new().with_no_cache();
+    let resource = format!("{pk}/{name}/rollback/{version}");
+    if !authorize_write(&headers, Some(&pk), &resource, &[]).await {
+        return Ok((StatusCode::UNAUTHORIZED, TypedHeader(cc), "Unauthorized"));
+    }
+
+    let filepath = handle_file(&pk, &name, 0).await?;
+    let final_name = filepath
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or(name);
+
+    // Restored like a write, the present version goes to the history
+    let _lock = lock_file(&filepath).await;
+    let encoded = match history::read_version(&filepath, &version).await {
+        Ok(encoded) => encoded,
+        Err(CarbonadoError::VersionNotFound(_)) => {
+            return Ok((StatusCode::NOT_FOUND, TypedHeader(cc), "Version not found"));
+        }
+        Err(err) => return Err(err.into()),
+    };
+    history::archive(&filepath).await?;
+    replace_file(&filepath, &encoded).await?;
+    persist_version(&filepath, &pk, &final_name, &encoded).await?;
+
+    Ok((StatusCode::OK, TypedHeader(cc), "Success"))
+}
+
 async fn co_metadata(
     Path((pk, name)): Path<(String, String)>,
 ) -> Result<impl IntoResponse, AppError> {
@@ -652,8 +1035,18 @@ async fn rgb_proxy_consig_save(
 ) -> Result<impl IntoResponse, AppError> {
     info!("POST /proxy/consignment/{id}");
     let request = RgbProxyConsigFileReq::from(request);
+
+    // consignments are written to the proxy directory before the upload
+    let filepath = proxy_handle_file(&request.file_name, 0).await?;
+    let len = request.bytes.len() as u64;
+    if let Err(err) = quota::check_write(&filepath, StorageCategory::Consignments, len).await {
+        warn!("rejected consignment {id}: {err}");
+        let (status, message) = storage_rejection(err)?;
+        return Ok((status, message).into_response());
+    }
+
     let resp = proxy_consig_store(request).await?;
-    Ok((StatusCode::OK, Json(resp)))
+    Ok((StatusCode::OK, Json(resp)).into_response())
 }
 
 async fn rgb_proxy_consig_retrieve(Path(id): Path<String>) -> Result<impl IntoResponse, AppError> {
@@ -664,10 +1057,22 @@ async fn rgb_proxy_consig_retrieve(Path(id): Path<String>) -> Result<impl IntoRe
     Ok((StatusCode::OK, Json(resp)))
 }
 
-async fn rgb_proxy_media_retrieve(Path(id): Path<String>) -> Result<impl IntoResponse, AppError> {
+async fn rgb_proxy_media_stream(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
     info!("GET /proxy/media/{id}");
-    let resp = proxy_media_retrieve(&id).await?;
-    Ok((StatusCode::OK, Json(resp)))
+
+    let Some(filepath) = proxy_media_file(&id).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    // Media never changes for the same id
+    let cc = CacheControl::new()
+        .with_public()
+        .with_max_age(Duration::from_secs(365 * 24 * 60 * 60));
+    let file = fs::File::open(filepath).await?;
+    ranged_file_response(file, &headers, cc).await
 }
 
 async fn rgb_proxy_metadata_retrieve(
@@ -712,10 +1117,38 @@ async fn rgb_retrieve_auction(
 
 async fn rgb_store_auction(
     Path((bundle_id, name)): Path<(String, String)>,
+    headers: HeaderMap,
     body: Bytes,
 ) -> Result<impl IntoResponse, AppError> {
     info!("POST /auction/{bundle_id}");
-    let (filepath, encoded) = auctions_store(&bundle_id, &name, &body, None).await?;
+    let cc = CacheControl::new().with_no_cache();
+
+    let (filepath, encoded) = auctions_encode(&bundle_id, &name, &body, None).await?;
+
+    // the seller writes to its auction, and so do the bidders it allowed
+    let bidders = match filepath.parent() {
+        Some(dir) => auction_bidders(dir).await,
+        None => vec![],
+    };
+    let signer = headers
+        .get(AUTH_PUBKEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let writer = auction_writer(&bundle_id, &bidders, signer);
+    let resource = format!("auction/{bundle_id}/{name}");
+    if !authorize_write(&headers, Some(&writer), &resource, &body).await {
+        return Ok((StatusCode::UNAUTHORIZED, TypedHeader(cc), "Unauthorized"));
+    }
+
+    let category = StorageCategory::Auctions;
+    if let Err(err) = quota::check_write(&filepath, category, encoded.len() as u64).await {
+        warn!("rejected write of {name}: {err}");
+        let (status, message) = storage_rejection(err)?;
+        return Ok((status, TypedHeader(cc), message));
+    }
+    if let Some(dir) = filepath.parent() {
+        gc::track_auction(dir, &bundle_id).await?;
+    }
 
     match OpenOptions::new()
         .read(true)
@@ -745,14 +1178,41 @@ async fn rgb_store_auction(
         },
     }
 
+    Ok((StatusCode::OK, TypedHeader(cc), "Success"))
+}
+
+async fn rgb_allow_auction(
+    Path(bundle_id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, AppError> {
+    info!("POST /auction/{bundle_id}");
     let cc = CacheControl::new().with_no_cache();
-    Ok((StatusCode::OK, TypedHeader(cc)))
+
+    // only the seller, owner of the bundle key, chooses the bidders
+    let resource = format!("auction/{bundle_id}");
+    if !authorize_write(&headers, Some(&bundle_id), &resource, &body).await {
+        return Ok((StatusCode::UNAUTHORIZED, TypedHeader(cc), "Unauthorized"));
+    }
+
+    let bidders: Vec<String> = serde_json::from_slice(&body)?;
+    auctions_allow("", &bundle_id, &bidders).await?;
+
+    Ok((StatusCode::OK, TypedHeader(cc), "Success"))
 }
 
 async fn rgb_destroy_auction(
-    Path((bundle_id, _name)): Path<(String, String)>,
+    Path((bundle_id, name)): Path<(String, String)>,
+    headers: HeaderMap,
 ) -> Result<impl IntoResponse, AppError> {
     info!("DELETE /auction/{bundle_id}");
+
+    // only the seller, owner of the bundle key, can destroy the auction
+    let resource = format!("auction/{bundle_id}/{name}");
+    if !authorize_write(&headers, Some(&bundle_id), &resource, &[]).await {
+        return Ok((StatusCode::UNAUTHORIZED, Json("Unauthorized")));
+    }
+
     Ok((StatusCode::OK, Json("")))
 }
 
@@ -810,6 +1270,11 @@ async fn csv_metrics() -> Result<impl IntoResponse, AppError> {
     Ok((StatusCode::OK, [("content-type", "text/csv")], metrics_csv))
 }
 
+async fn resolver_metrics() -> Result<impl IntoResponse, AppError> {
+    info!("GET /metrics/resolver.json");
+    Ok((StatusCode::OK, Json(resolver_cache_metrics())))
+}
+
 async fn init_metrics() -> Result<()> {
     let dir = env::var("CARBONADO_DIR").unwrap_or("/tmp/bitmaskd/carbonado".to_owned());
     let dir = path::Path::new(&dir);
@@ -838,6 +1303,13 @@ async fn main() -> Result<()> {
 
     pretty_env_logger::init();
 
+    on_reorg(Box::new(|event| {
+        warn!(
+            "Reorg of transfer {} ({}), witness {} is {:?}, consignment valid: {}",
+            event.consig_id, event.contract_id, event.txid, event.status, event.is_valid
+        );
+    }));
+
     let mut app = Router::new()
         .route("/issue", post(issue))
         .route("/reissue", post(reissue))
@@ -852,10 +1324,17 @@ async fn main() -> Result<()> {
         .route("/selfaccept", post(self_accept))
         .route("/contracts", get(contracts))
         .route("/contracts/:id", get(contract_detail))
+        .route("/balances", get(balances))
+        .route("/labels", get(labels))
+        .route("/labels", post(update_labels))
+        .route("/labels/import", post(labels_import))
+        .route("/labels/export", get(labels_export))
+        .route("/label/:ty/:reference", delete(delete_label))
         .route("/interfaces", get(interfaces))
         .route("/schemas", get(schemas))
         .route("/import", post(import))
         .route("/watcher", post(watcher))
+        .route("/watchers", post(watchers))
         .route("/watcher/:name", get(watcher_details))
         .route("/watcher/:name/:asset/address", get(next_address))
         .route("/watcher/:name/:asset/utxo", get(next_utxo))
@@ -865,6 +1344,17 @@ async fn main() -> Result<()> {
         )
         .route("/watcher/:name/:asset/utxo/:utxo", put(register_utxo))
         .route("/watcher/:name", delete(clear_watcher))
+        .route("/watcher/:name/rescan", get(rescan_status))
+        .route("/rescan", post(rescan))
+        .route("/reorgs", get(reorgs))
+        .route("/backup", post(backup))
+        .route("/restore", post(restore))
+        .route("/migration", get(migration))
+        .route("/migration", post(migrate))
+        .route("/sync", post(sync))
+        .route("/utxos/provision", post(provision))
+        .route("/utxos/pool", post(utxo_pool))
+        .route("/utxos/topup", post(top_up))
         .route("/transfers/:id", get(list_transfers))
         .route("/transfers/", post(save_transfer))
         .route("/transfers/", delete(remove_transfer))
@@ -876,16 +1366,27 @@ async fn main() -> Result<()> {
         .route("/carbonado/:pk/:name", post(co_store))
         .route("/carbonado/:pk/:name/force", post(co_force_store))
         .route("/carbonado/:pk/:name/metadata", get(co_metadata))
+        .route("/carbonado/:pk/:name/history", get(co_history))
+        .route(
+            "/carbonado/:pk/:name/history/:version",
+            get(co_history_retrieve),
+        )
+        .route("/carbonado/:pk/:name/rollback/:version", post(co_rollback))
         .route("/proxy/consignment/:id", post(rgb_proxy_consig_save))
         .route("/proxy/consignment/:id", get(rgb_proxy_consig_retrieve))
         .route("/proxy/media-metadata", post(rgb_proxy_media_data_save))
-        .route("/proxy/media-metadata/:id", get(rgb_proxy_media_retrieve))
-        .route("/proxy/media/:id", get(rgb_proxy_metadata_retrieve))
+        .route(
+            "/proxy/media-metadata/:id",
+            get(rgb_proxy_metadata_retrieve),
+        )
+        .route("/proxy/media/:id", get(rgb_proxy_media_stream))
+        .route("/auction/:bundle_id", post(rgb_allow_auction))
         .route("/auction/:bundle_id/:name", get(rgb_retrieve_auction))
         .route("/auction/:bundle_id/:name", post(rgb_store_auction))
         .route("/auction/:bundle_id/:name", delete(rgb_destroy_auction))
         .route("/metrics.json", get(json_metrics))
-        .route("/metrics.csv", get(csv_metrics));
+        .route("/metrics.csv", get(csv_metrics))
+        .route("/metrics/resolver.json", get(resolver_metrics));
 
     let network = get_network().await;
     switch_network(&network).await?;
@@ -902,6 +1403,29 @@ async fn main() -> Result<()> {
         }
     });
 
+    tokio::spawn(async {
+        loop {
+            let interval = gc::gc_interval().await;
+            if interval == 0 {
+                break;
+            }
+            sleep(Duration::from_secs(interval)).await;
+
+            match gc::collect(chrono::Utc::now().timestamp()).await {
+                Ok(report) => {
+                    info!(
+                        "Garbage collection removed {} proxy files and {} auction files",
+                        report.proxy_files, report.auction_files
+                    );
+                    if let Err(e) = metrics::record_gc(report).await {
+                        error!("Error in recording garbage collection: {e}");
+                    }
+                }
+                Err(e) => error!("Error in garbage collection: {e}"),
+            }
+        }
+    });
+
     let app = app.layer(CorsLayer::permissive());
     let addr = SocketAddr::from(([0, 0, 0, 0], 7070));
 
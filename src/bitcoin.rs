@@ -4,7 +4,11 @@ use ::bitcoin::util::address::Address;
 use ::psbt::Psbt;
 use amplify::hex::ToHex;
 use argon2::Argon2;
-use bdk::{wallet::AddressIndex, FeeRate, LocalUtxo, SignOptions, TransactionDetails};
+use bdk::{
+    blockchain::{Blockchain, GetTx},
+    wallet::AddressIndex,
+    FeeRate, LocalUtxo, SignOptions, TransactionDetails,
+};
 use bitcoin::{consensus::encode, psbt::PartiallySignedTransaction, Txid};
 use rand::{rngs::StdRng, Rng, SeedableRng};
 use serde_encrypt::{
@@ -15,6 +19,11 @@ use thiserror::Error;
 use zeroize::Zeroize;
 
 mod assets;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bitcoind;
+mod coins;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod electrum;
 mod keys;
 mod payment;
 mod psbt;
@@ -22,6 +31,7 @@ mod wallet;
 
 pub use crate::bitcoin::{
     assets::dust_tx,
+    coins::{list_locked_utxos, lock_utxos, unlock_utxos, unspendable_utxos},
     keys::{new_mnemonic, save_mnemonic, BitcoinKeysError},
     payment::{create_payjoin, create_transaction, BitcoinPaymentError},
     psbt::{
@@ -29,7 +39,8 @@ pub use crate::bitcoin::{
         sign_psbt, BitcoinPsbtError,
     },
     wallet::{
-        get_blockchain, get_wallet, sync_wallet, sync_wallets, BitcoinWalletError, MemoryWallet,
+        get_blockchain, get_wallet, sync_wallet, sync_wallets, BitcoinBlockchain,
+        BitcoinWalletError, MemoryWallet,
     },
 };
 
@@ -38,9 +49,9 @@ use crate::{
     constants::{DIBA_DESCRIPTOR, DIBA_DESCRIPTOR_VERSION, DIBA_MAGIC_NO, NETWORK},
     debug, info,
     structs::{
-        DecryptedWalletData, EncryptedWalletDataV04, FundVaultDetails, PublishPsbtRequest,
-        PublishedPsbtResponse, SatsInvoice, SecretString, SignPsbtRequest, SignedPsbtResponse,
-        TransactionData, WalletData, WalletTransaction,
+        CoinControl, DecryptedWalletData, EncryptedWalletDataV04, FundVaultDetails,
+        PublishPsbtRequest, PublishedPsbtResponse, SatsInvoice, SecretString, SignPsbtRequest,
+        SignedPsbtResponse, TransactionData, WalletData, WalletTransaction,
     },
     trace,
 };
@@ -302,6 +313,7 @@ pub async fn get_wallet_data(
                 confirmation_time: tx.confirmation_time,
                 vsize,
                 fee_rate,
+                label: None,
             }
         })
         .collect();
@@ -348,12 +360,16 @@ pub async fn validate_address(address: &Address) -> Result<(), BitcoinError> {
     }
 }
 
+/// Send sats from the BTC wallet of an account, leaving its locked, reserved
+/// and colored UTXOs unspent unless the coin control includes them
 pub async fn send_sats(
+    sk: &str,
     descriptor: &SecretString,
     change_descriptor: &SecretString,
     destination: &str, // bip21 uri or address
     amount: u64,
     fee_rate: Option<f32>,
+    coin_control: Option<CoinControl>,
 ) -> Result<TransactionData, BitcoinError> {
     use payjoin::UriExt;
 
@@ -366,20 +382,36 @@ pub async fn send_sats(
             validate_address(&address).await?;
             if let Ok(pj_uri) = uri.check_pj_supported() {
                 create_payjoin(
+                    sk,
                     vec![SatsInvoice { address, amount }],
                     &wallet,
                     fee_rate,
                     pj_uri,
+                    coin_control,
                 )
                 .await?
             } else {
-                create_transaction(vec![SatsInvoice { address, amount }], &wallet, fee_rate).await?
+                create_transaction(
+                    sk,
+                    vec![SatsInvoice { address, amount }],
+                    &wallet,
+                    fee_rate,
+                    coin_control,
+                )
+                .await?
             }
         }
         _ => {
             let address = Address::from_str(destination)?;
             validate_address(&address).await?;
-            create_transaction(vec![SatsInvoice { address, amount }], &wallet, fee_rate).await?
+            create_transaction(
+                sk,
+                vec![SatsInvoice { address, amount }],
+                &wallet,
+                fee_rate,
+                coin_control,
+            )
+            .await?
         }
     };
 
@@ -397,6 +429,7 @@ pub async fn send_sats(
 }
 
 pub async fn fund_vault(
+    sk: &str,
     btc_descriptor_xprv: &SecretString,
     btc_change_descriptor_xprv: &SecretString,
     assets_address_1: &str,
@@ -425,8 +458,14 @@ pub async fn fund_vault(
         amount: rng.gen_range(600..1500),
     };
 
-    let asset_tx_details =
-        create_transaction(vec![asset_invoice_1, uda_invoice_1], &wallet, fee_rate).await?;
+    let asset_tx_details = create_transaction(
+        sk,
+        vec![asset_invoice_1, uda_invoice_1],
+        &wallet,
+        fee_rate,
+        None,
+    )
+    .await?;
 
     let asset_txid = asset_tx_details.txid;
 
@@ -556,10 +595,12 @@ pub async fn sign_and_publish_psbt_file(
 }
 
 pub async fn drain_wallet(
+    sk: &str,
     destination: &str,
     descriptor: &SecretString,
     change_descriptor: Option<&SecretString>,
     fee_rate: Option<f32>,
+    coin_control: Option<CoinControl>,
 ) -> Result<TransactionData, BitcoinError> {
     let address = Address::from_str(destination)?;
     validate_address(&address).await?;
@@ -569,6 +610,7 @@ pub async fn drain_wallet(
     sync_wallet(&wallet).await?;
 
     let fee_rate = fee_rate.map(FeeRate::from_sat_per_vb);
+    let unspendable = unspendable_utxos(sk, &coin_control.unwrap_or_default()).await?;
 
     let (mut psbt, details) = {
         let locked_wallet = wallet.lock().await;
@@ -576,6 +618,7 @@ pub async fn drain_wallet(
         if let Some(fee_rate) = fee_rate {
             builder.fee_rate(fee_rate);
         }
+        builder.unspendable(unspendable);
         builder.drain_wallet();
         builder.drain_to(address.script_pubkey());
         builder.finish()?
@@ -592,7 +635,7 @@ pub async fn drain_wallet(
     }
     debug!(format!("Finalized: {finalized}"));
 
-    let blockchain = get_blockchain().await;
+    let blockchain = get_blockchain().await?;
     let tx = psbt.extract_tx();
     blockchain.broadcast(&tx).await?;
     let tx = blockchain.get_tx(&details.txid).await?;
@@ -661,7 +704,7 @@ pub async fn bump_fee(
     let tx = psbt.extract_tx();
 
     if broadcast {
-        let blockchain = get_blockchain().await;
+        let blockchain = get_blockchain().await?;
         blockchain.broadcast(&tx).await?;
     }
 
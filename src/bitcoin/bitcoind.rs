@@ -0,0 +1,64 @@
+use bitcoin::{Transaction, Txid};
+use bitcoincore_rpc::{
+    json::{ScanTxOutRequest, ScanTxOutResult},
+    Auth, Client, RpcApi,
+};
+
+/// Bitcoin Core answers unknown transactions with RPC_INVALID_ADDRESS_OR_KEY
+const RPC_INVALID_ADDRESS_OR_KEY: i32 = -5;
+
+pub fn bitcoind_client(
+    url: &str,
+    user: &str,
+    password: &str,
+) -> Result<Client, bitcoincore_rpc::Error> {
+    let auth = if user.is_empty() {
+        Auth::None
+    } else {
+        Auth::UserPass(user.to_owned(), password.to_owned())
+    };
+    Client::new(url, auth)
+}
+
+/// Get a transaction from the node (requires `txindex=1` for non-wallet transactions)
+pub fn bitcoind_get_tx(
+    client: &Client,
+    txid: &Txid,
+) -> Result<Option<Transaction>, bitcoincore_rpc::Error> {
+    match client.get_raw_transaction(txid, None) {
+        Ok(tx) => Ok(Some(tx)),
+        Err(bitcoincore_rpc::Error::JsonRpc(bitcoincore_rpc::jsonrpc::Error::Rpc(err)))
+            if err.code == RPC_INVALID_ADDRESS_OR_KEY =>
+        {
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Get the height of a transaction, `None` when it is in the mempool
+pub fn bitcoind_tx_height(
+    client: &Client,
+    txid: &Txid,
+) -> Result<Option<u32>, bitcoincore_rpc::Error> {
+    let info = client.get_raw_transaction_info(txid, None)?;
+    match info.blockhash {
+        Some(blockhash) => {
+            let header = client.get_block_header_info(&blockhash)?;
+            Ok(Some(header.height as u32))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Scan the UTXO set for the scripts (`scantxoutset`), mempool outputs are not included
+pub fn bitcoind_scan_scripts(
+    client: &Client,
+    scripts: &[bitcoin::Script],
+) -> Result<ScanTxOutResult, bitcoincore_rpc::Error> {
+    let descriptors: Vec<ScanTxOutRequest> = scripts
+        .iter()
+        .map(|script| ScanTxOutRequest::Single(format!("raw({})", hex::encode(script.as_bytes()))))
+        .collect();
+    client.scan_tx_out_set_blocking(&descriptors)
+}
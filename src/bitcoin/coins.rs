@@ -0,0 +1,64 @@
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+use bitcoin::OutPoint;
+
+use crate::{
+    bitcoin::payment::BitcoinPaymentError,
+    rgb::{
+        colored_coin_control,
+        fs::{retrieve_locked_utxos, store_locked_utxos, RgbPersistenceError},
+    },
+    structs::CoinControl,
+};
+
+/// Lock outpoints of the account, the BTC wallet leaves them until they are unlocked
+pub async fn lock_utxos(sk: &str, outpoints: Vec<OutPoint>) -> Result<(), RgbPersistenceError> {
+    let mut locked = retrieve_locked_utxos(sk).await?;
+    locked
+        .utxos
+        .extend(outpoints.iter().map(|outpoint| outpoint.to_string()));
+    store_locked_utxos(sk, locked).await
+}
+
+pub async fn unlock_utxos(sk: &str, outpoints: Vec<OutPoint>) -> Result<(), RgbPersistenceError> {
+    let mut locked = retrieve_locked_utxos(sk).await?;
+    for outpoint in outpoints {
+        locked.utxos.remove(&outpoint.to_string());
+    }
+    store_locked_utxos(sk, locked).await
+}
+
+pub async fn list_locked_utxos(sk: &str) -> Result<Vec<OutPoint>, RgbPersistenceError> {
+    let locked = retrieve_locked_utxos(sk).await?;
+    Ok(locked
+        .utxos
+        .iter()
+        .filter_map(|outpoint| OutPoint::from_str(outpoint).ok())
+        .collect())
+}
+
+/// Outpoints the BTC wallet of the account must not spend
+///
+/// The locked outpoints, the ones reserved for RGB and the ones carrying
+/// allocations (see [`crate::rgb::colored_coin_control`]) are always excluded.
+/// Outpoints explicitly included by the caller are always spendable.
+pub async fn unspendable_utxos(
+    sk: &str,
+    coin_control: &CoinControl,
+) -> Result<Vec<OutPoint>, BitcoinPaymentError> {
+    let colored = colored_coin_control(sk, Some(coin_control.clone()))
+        .await
+        .map_err(|op| BitcoinPaymentError::CoinControl(op.to_string()))?;
+    let locked = list_locked_utxos(sk)
+        .await
+        .map_err(|op| BitcoinPaymentError::CoinControl(op.to_string()))?;
+
+    let mut unspendable: BTreeSet<OutPoint> = colored.exclude.into_iter().collect();
+    unspendable.extend(locked);
+
+    Ok(unspendable
+        .into_iter()
+        .filter(|outpoint| !coin_control.include.contains(outpoint))
+        .collect())
+}
@@ -0,0 +1,24 @@
+use bitcoin::{Transaction, Txid};
+use electrum_client::{Client, ConfigBuilder, ElectrumApi};
+
+use crate::constants::ELECTRUM_TIMEOUT;
+
+pub fn electrum_client(url: &str) -> Result<Client, electrum_client::Error> {
+    let config = ConfigBuilder::new()
+        .timeout(Some(ELECTRUM_TIMEOUT))?
+        .build();
+    Client::from_config(url, config)
+}
+
+/// Get a transaction from the server, `None` when it is unknown
+pub fn electrum_get_tx(
+    client: &Client,
+    txid: &Txid,
+) -> Result<Option<Transaction>, electrum_client::Error> {
+    match client.transaction_get(txid) {
+        Ok(tx) => Ok(Some(tx)),
+        // Electrum servers answer unknown transactions with a protocol error
+        Err(electrum_client::Error::Protocol(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
@@ -10,11 +10,12 @@ use thiserror::Error;
 
 use crate::{
     bitcoin::{
+        coins::unspendable_utxos,
         psbt::{sign_and_publish_psbt, sign_psbt, BitcoinPsbtError},
         wallet::MemoryWallet,
     },
     debug, info,
-    structs::SatsInvoice,
+    structs::{CoinControl, SatsInvoice},
 };
 
 #[derive(Error, Debug)]
@@ -37,13 +38,20 @@ pub enum BitcoinPaymentError {
     /// Reqwest error
     #[error(transparent)]
     ReqwestError(#[from] reqwest::Error),
+    /// Unspendable outpoints of the account not resolved
+    #[error("Coin control of the account cannot be resolved: {0}")]
+    CoinControl(String),
 }
 
 pub async fn create_transaction(
+    sk: &str,
     invoices: Vec<SatsInvoice>,
     wallet: &MemoryWallet,
     fee_rate: Option<FeeRate>,
+    coin_control: Option<CoinControl>,
 ) -> Result<TransactionDetails, BitcoinPaymentError> {
+    let coin_control = coin_control.unwrap_or_default();
+    let unspendable = unspendable_utxos(sk, &coin_control).await?;
     let (psbt, details) = {
         let locked_wallet = wallet.lock().await;
         let mut builder = locked_wallet.build_tx();
@@ -51,6 +59,11 @@ pub async fn create_transaction(
             builder.add_recipient(invoice.address.script_pubkey(), invoice.amount);
         }
 
+        builder.unspendable(unspendable);
+        if !coin_control.include.is_empty() {
+            builder.add_utxos(&coin_control.include)?;
+        }
+
         builder.ordering(TxOrdering::Untouched); // TODO: Remove after implementing wallet persistence
         builder.enable_rbf().fee_rate(fee_rate.unwrap_or_default());
         builder.finish()?
@@ -65,18 +78,26 @@ pub async fn create_transaction(
 }
 
 pub async fn create_payjoin(
+    sk: &str,
     invoices: Vec<SatsInvoice>,
     wallet: &MemoryWallet,
     fee_rate: Option<FeeRate>,
     pj_uri: PjUri<'_>, // TODO specify Uri<PayJoinParams>
+    coin_control: Option<CoinControl>,
 ) -> Result<TransactionDetails, BitcoinPaymentError> {
     let enacted_fee_rate = fee_rate.unwrap_or_default();
+    let coin_control = coin_control.unwrap_or_default();
+    let unspendable = unspendable_utxos(sk, &coin_control).await?;
     let (psbt, details) = {
         let locked_wallet = wallet.lock().await;
         let mut builder = locked_wallet.build_tx();
         for invoice in &invoices {
             builder.add_recipient(invoice.address.script_pubkey(), invoice.amount);
         }
+        builder.unspendable(unspendable);
+        if !coin_control.include.is_empty() {
+            builder.add_utxos(&coin_control.include)?;
+        }
         builder.enable_rbf().fee_rate(enacted_fee_rate);
         builder.finish()?
     };
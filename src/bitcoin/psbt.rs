@@ -1,4 +1,8 @@
-use bdk::{blockchain::Blockchain, psbt::PsbtUtils, SignOptions, TransactionDetails};
+use bdk::{
+    blockchain::{Blockchain, GetTx},
+    psbt::PsbtUtils,
+    SignOptions, TransactionDetails,
+};
 use bitcoin::{consensus::serialize, hashes::hex::ToHex, util::psbt::PartiallySignedTransaction};
 use thiserror::Error;
 
@@ -75,7 +79,7 @@ pub async fn publish_psbt(
     let fee_amount = psbt.fee_amount().expect("fee amount on PSBT is known");
     let tx = psbt.extract_tx();
     debug!("tx:", &serialize(&tx.clone()).to_hex());
-    let blockchain = get_blockchain().await;
+    let blockchain = get_blockchain().await?;
     blockchain
         .broadcast(&tx)
         .await
@@ -120,11 +124,12 @@ pub async fn sign_and_publish_psbt(
         let fee_amount = psbt.fee_amount().expect("fee amount on PSBT is known");
         let tx = psbt.extract_tx();
         debug!("tx:", &serialize(&tx.clone()).to_hex());
-        let blockchain = get_blockchain().await;
+        let blockchain = get_blockchain().await?;
         blockchain.broadcast(&tx).await?;
 
         let txid = tx.txid();
-        let tx = blockchain.get_tx(&txid).await?;
+        // the backend may not have indexed the broadcast transaction yet
+        let tx = blockchain.get_tx(&txid).await?.or(Some(tx));
 
         let mut sent = 0;
         let mut received = 0;
@@ -181,11 +186,12 @@ pub async fn multi_sign_and_publish_psbt(
         let fee_amount = psbt.fee_amount().expect("fee amount on PSBT is known");
         let tx = psbt.extract_tx();
         debug!("tx:", &serialize(&tx.clone()).to_hex());
-        let blockchain = get_blockchain().await;
+        let blockchain = get_blockchain().await?;
         blockchain.broadcast(&tx).await?;
 
         let txid = tx.txid();
-        let tx = blockchain.get_tx(&txid).await?;
+        // the backend may not have indexed the broadcast transaction yet
+        let tx = blockchain.get_tx(&txid).await?.or(Some(tx));
 
         let mut sent = 0;
         let mut received = 0;
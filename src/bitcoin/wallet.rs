@@ -1,6 +1,6 @@
 use std::{collections::BTreeMap, sync::Arc};
 
-use bdk::{blockchain::esplora::EsploraBlockchain, database::MemoryDatabase, SyncOptions, Wallet};
+use bdk::{database::MemoryDatabase, SyncOptions, Wallet};
 use bitcoin::Network;
 use bitcoin_hashes::{sha256, Hash};
 use futures::Future;
@@ -9,7 +9,8 @@ use thiserror::Error;
 use tokio::sync::{Mutex, RwLock};
 
 use crate::{
-    constants::{BITCOIN_EXPLORER_API, NETWORK},
+    chain::{chain_backend, ChainBackend, ChainBlockchain},
+    constants::NETWORK,
     debug,
     structs::SecretString,
 };
@@ -118,13 +119,20 @@ pub async fn get_wallet(
     Ok(new_wallet)
 }
 
-pub async fn get_blockchain() -> EsploraBlockchain {
+/// Blockchain of the BDK wallets, the same backend as the RGB resolvers
+pub type BitcoinBlockchain = ChainBlockchain<Box<dyn ChainBackend>>;
+
+pub async fn get_blockchain() -> Result<BitcoinBlockchain, bdk::Error> {
     debug!("Getting blockchain");
-    EsploraBlockchain::new(&BITCOIN_EXPLORER_API.read().await, 1)
+
+    let backend = chain_backend()
+        .await
+        .map_err(|err| bdk::Error::Generic(err.to_string()))?;
+    Ok(ChainBlockchain::new(backend))
 }
 
 pub async fn sync_wallet(wallet: &MemoryWallet) -> Result<(), BitcoinWalletError> {
-    let blockchain = get_blockchain().await;
+    let blockchain = get_blockchain().await?;
     wallet
         .lock()
         .await
@@ -157,7 +165,7 @@ pub async fn sync_wallets() -> Result<(), BitcoinWalletError> {
         Network::Bitcoin => {
             let wallets = BDK.bitcoin.clone();
             for (_key, wallet) in wallets.write().await.iter_mut() {
-                let blockchain = get_blockchain().await;
+                let blockchain = get_blockchain().await?;
                 let wallet = wallet.lock().await;
                 let wallet_sync_fut = wallet.sync(&blockchain, SyncOptions::default());
                 wallet_sync_fut.await?;
@@ -166,7 +174,7 @@ pub async fn sync_wallets() -> Result<(), BitcoinWalletError> {
         Network::Testnet => {
             let wallets = BDK.testnet.clone();
             for (_key, wallet) in wallets.write().await.iter_mut() {
-                let blockchain = get_blockchain().await;
+                let blockchain = get_blockchain().await?;
                 let wallet = wallet.lock().await;
                 let wallet_sync_fut = wallet.sync(&blockchain, SyncOptions::default());
                 wallet_sync_fut.await?;
@@ -175,7 +183,7 @@ pub async fn sync_wallets() -> Result<(), BitcoinWalletError> {
         Network::Signet => {
             let wallets = BDK.signet.clone();
             for (_key, wallet) in wallets.write().await.iter_mut() {
-                let blockchain = get_blockchain().await;
+                let blockchain = get_blockchain().await?;
                 let wallet = wallet.lock().await;
                 let wallet_sync_fut = wallet.sync(&blockchain, SyncOptions::default());
                 wallet_sync_fut.await?;
@@ -184,7 +192,7 @@ pub async fn sync_wallets() -> Result<(), BitcoinWalletError> {
         Network::Regtest => {
             let wallets = BDK.regtest.clone();
             for (_key, wallet) in wallets.write().await.iter_mut() {
-                let blockchain = get_blockchain().await;
+                let blockchain = get_blockchain().await?;
                 let wallet = wallet.lock().await;
                 let wallet_sync_fut = wallet.sync(&blockchain, SyncOptions::default());
                 wallet_sync_fut.await?;
@@ -3,20 +3,44 @@ use bitcoin_30::secp256k1::{PublicKey, SecretKey};
 
 use crate::{carbonado::error::CarbonadoError, constants::NETWORK, info, structs::FileMetadata};
 
+pub mod auth;
 pub mod error;
+pub mod replication;
+pub mod segments;
+pub mod versions;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod gc;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod history;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod media;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod metrics;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod quota;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use media::{media_manifest, retrieve_media, store_media, stream_media};
 
 #[cfg(not(target_arch = "wasm32"))]
 pub use server::{
-    auctions_retrieve, auctions_store, handle_file, marketplace_retrieve, marketplace_store,
-    retrieve, retrieve_metadata, store,
+    auctions_allow, auctions_dir, auctions_encode, auctions_retrieve, auctions_store,
+    auctions_write, handle_file, list_versions, marketplace_retrieve, marketplace_store, remove,
+    retrieve, retrieve_metadata, retrieve_version, rollback, store,
 };
 
 #[cfg(not(target_arch = "wasm32"))]
 mod server {
-    use crate::constants::{get_coordinator_nostr_key, get_marketplace_nostr_key};
+    use crate::{
+        carbonado::{
+            auth::{allow_auction_bidders, AUCTION_BIDDERS_FILE},
+            history,
+            versions::{lock_file, persist_version, recall_version, write_if_matches},
+        },
+        constants::{get_coordinator_nostr_key, get_marketplace_nostr_key},
+        structs::FileVersion,
+    };
 
     use super::*;
 
@@ -29,11 +53,15 @@ mod server {
     use bitcoin_30::secp256k1::ecdh::SharedSecret;
     use tokio::fs;
 
+    /// Store a file of the owner of `sk`
+    ///
+    /// Unless forced, the write fails with [`CarbonadoError::Conflict`] when
+    /// the file changed since this process last retrieved or stored it.
     pub async fn store(
         sk: &str,
         name: &str,
         input: &[u8],
-        _force: bool,
+        force: bool,
         metadata: Option<Vec<u8>>,
     ) -> Result<(), CarbonadoError> {
         let level = 15;
@@ -50,13 +78,42 @@ mod server {
             meta = Some(inner);
         }
 
+        let mut final_name = name.to_string();
+        let network = NETWORK.read().await.to_string();
+        let networks = ["bitcoin", "testnet", "signet", "regtest"];
+        if !networks.into_iter().any(|x| name.contains(x)) {
+            final_name = format!("{network}-{name}");
+        }
+
         let (body, _encode_info) = carbonado::file::encode(&sk, Some(&pk), input, level, meta)?;
         let filepath = handle_file(&pk_hex, name, body.len()).await?;
-        fs::write(&filepath, body).await?;
-        // metrics::update(&filepath).await?;
+        let expected = match force {
+            true => None,
+            false => recall_version(&filepath, &pk_hex, &final_name).await,
+        };
+        if !write_if_matches(&filepath, expected.as_deref(), &body).await? {
+            return Err(CarbonadoError::Conflict(final_name));
+        }
+
+        persist_version(&filepath, &pk_hex, &final_name, &body).await?;
         Ok(())
     }
 
+    /// Remove a file of the owner of `sk`, it is not kept in the history
+    pub async fn remove(sk: &str, name: &str) -> Result<(), CarbonadoError> {
+        let sk = hex::decode(sk)?;
+        let secret_key = SecretKey::from_slice(&sk)?;
+        let public_key = PublicKey::from_secret_key_global(&secret_key);
+        let pk = public_key.to_hex();
+
+        let filepath = handle_file(&pk, name, 0).await?;
+        let _lock = lock_file(&filepath).await;
+        match fs::remove_file(&filepath).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
     pub async fn marketplace_store(
         name: &str,
         input: &[u8],
@@ -81,19 +138,38 @@ mod server {
         let (body, _encode_info) = carbonado::file::encode(&sk, Some(&pk), input, level, meta)?;
         let filepath = handle_file(&pk_hex, name, body.len()).await?;
         fs::write(&filepath, body.clone()).await?;
-        // metrics::update(&filepath).await?;
         Ok((filepath, body))
     }
 
+    /// Store a file of an auction
+    ///
+    /// Only remote writes are signed with `sk`, see [`WriteAuth`](crate::carbonado::auth::WriteAuth).
     pub async fn auctions_store(
+        _sk: &str,
         bundle_id: &str,
         name: &str,
         input: &[u8],
         metadata: Option<Vec<u8>>,
     ) -> Result<(PathBuf, Vec<u8>), CarbonadoError> {
+        auctions_write(bundle_id, name, input, metadata).await
+    }
+
+    /// Encode and write a file of an auction, with the coordinator key
+    pub async fn auctions_write(
+        bundle_id: &str,
+        name: &str,
+        input: &[u8],
+        metadata: Option<Vec<u8>>,
+    ) -> Result<(PathBuf, Vec<u8>), CarbonadoError> {
+        let (filepath, body) = auctions_encode(bundle_id, name, input, metadata).await?;
+        fs::write(filepath.clone(), body.clone()).await?;
+        Ok((filepath, body))
+    }
+
+    /// Key of the files of an auction, shared by its bundle and the coordinator
+    async fn auction_key(bundle_id: &str) -> Result<SecretKey, CarbonadoError> {
         let coordinator_key: String = get_coordinator_nostr_key().await;
 
-        let level = 15;
         let coordinator_sk = hex::decode(coordinator_key)?;
         let coordinator_secret_key = SecretKey::from_slice(&coordinator_sk)?;
         let bundle_public_key =
@@ -103,7 +179,43 @@ mod server {
         let share_sk = share_sk.display_secret().to_string();
 
         let sk = hex::decode(share_sk)?;
-        let secret_key = SecretKey::from_slice(&sk)?;
+        Ok(SecretKey::from_slice(&sk)?)
+    }
+
+    /// Directory of the files of an auction
+    pub async fn auctions_dir(bundle_id: &str) -> Result<PathBuf, CarbonadoError> {
+        let secret_key = auction_key(bundle_id).await?;
+        let pk = PublicKey::from_secret_key_global(&secret_key).to_hex();
+
+        let filepath = handle_file(&pk, AUCTION_BIDDERS_FILE, 0).await?;
+        Ok(filepath
+            .parent()
+            .expect("carbonado files are stored in a directory")
+            .to_path_buf())
+    }
+
+    /// Allow `bidders` to write to an auction, besides its seller
+    ///
+    /// Only remote writes are signed with `sk`, see [`auction_writer`](crate::carbonado::auth::auction_writer).
+    pub async fn auctions_allow(
+        _sk: &str,
+        bundle_id: &str,
+        bidders: &[String],
+    ) -> Result<(), CarbonadoError> {
+        let dir = auctions_dir(bundle_id).await?;
+        allow_auction_bidders(&dir, bidders).await
+    }
+
+    /// Encode a file of an auction with the coordinator key, and its path
+    pub async fn auctions_encode(
+        bundle_id: &str,
+        name: &str,
+        input: &[u8],
+        metadata: Option<Vec<u8>>,
+    ) -> Result<(PathBuf, Vec<u8>), CarbonadoError> {
+        let level = 15;
+        let secret_key = auction_key(bundle_id).await?;
+        let sk = secret_key.secret_bytes();
         let public_key = PublicKey::from_secret_key_global(&secret_key);
 
         let pk = public_key.serialize();
@@ -118,7 +230,6 @@ mod server {
 
         let (body, _encode_info) = carbonado::file::encode(&sk, Some(&pk), input, level, meta)?;
         let filepath = handle_file(&pk_hex, name, body.len()).await?;
-        fs::write(filepath.clone(), body.clone()).await?;
         Ok((filepath, body))
     }
 
@@ -142,8 +253,9 @@ mod server {
         }
 
         let filepath = handle_file(&pk, &final_name, 0).await?;
-        if let Ok(bytes) = fs::read(filepath).await {
+        if let Ok(bytes) = fs::read(&filepath).await {
             let (header, decoded) = carbonado::file::decode(&sk, &bytes)?;
+            persist_version(&filepath, &pk, &final_name, &bytes).await?;
             return Ok((decoded, header.metadata.map(|m| m.to_vec())));
         }
 
@@ -194,18 +306,8 @@ mod server {
         bundle_id: &str,
         name: &str,
     ) -> Result<(Vec<u8>, Option<Vec<u8>>), CarbonadoError> {
-        let coordinator_key: String = get_coordinator_nostr_key().await;
-
-        let coordinator_sk = hex::decode(coordinator_key)?;
-        let coordinator_secret_key = SecretKey::from_slice(&coordinator_sk)?;
-        let bundle_public_key =
-            PublicKey::from_str(bundle_id).map_err(|_| CarbonadoError::WrongNostrPublicKey)?;
-
-        let share_sk = SharedSecret::new(&bundle_public_key, &coordinator_secret_key);
-        let share_sk = share_sk.display_secret().to_string();
-
-        let sk = hex::decode(share_sk)?;
-        let secret_key = SecretKey::from_slice(&sk)?;
+        let secret_key = auction_key(bundle_id).await?;
+        let sk = secret_key.secret_bytes();
         let public_key = PublicKey::from_secret_key_global(&secret_key);
 
         let pk = public_key.to_hex();
@@ -226,6 +328,55 @@ mod server {
         Ok((Vec::new(), None))
     }
 
+    /// Previous versions of a file of the owner of `sk`, newest first
+    pub async fn list_versions(sk: &str, name: &str) -> Result<Vec<FileVersion>, CarbonadoError> {
+        let sk = hex::decode(sk)?;
+        let secret_key = SecretKey::from_slice(&sk)?;
+        let public_key = PublicKey::from_secret_key_global(&secret_key);
+
+        let filepath = handle_file(&public_key.to_hex(), name, 0).await?;
+        history::list_versions(&filepath).await
+    }
+
+    pub async fn retrieve_version(
+        sk: &str,
+        name: &str,
+        version: &str,
+    ) -> Result<(Vec<u8>, Option<Vec<u8>>), CarbonadoError> {
+        let sk = hex::decode(sk)?;
+        let secret_key = SecretKey::from_slice(&sk)?;
+        let public_key = PublicKey::from_secret_key_global(&secret_key);
+
+        let filepath = handle_file(&public_key.to_hex(), name, 0).await?;
+        let bytes = history::read_version(&filepath, version).await?;
+        let (header, decoded) = carbonado::file::decode(&sk, &bytes)?;
+        Ok((decoded, header.metadata.map(|m| m.to_vec())))
+    }
+
+    /// Restore a previous version of a file, the present one goes to the history
+    pub async fn rollback(sk: &str, name: &str, version: &str) -> Result<(), CarbonadoError> {
+        let sk = hex::decode(sk)?;
+        let secret_key = SecretKey::from_slice(&sk)?;
+        let public_key = PublicKey::from_secret_key_global(&secret_key);
+        let pk = public_key.to_hex();
+
+        let mut final_name = name.to_string();
+        let network = NETWORK.read().await.to_string();
+        let networks = ["bitcoin", "testnet", "signet", "regtest"];
+        if !networks.into_iter().any(|x| name.contains(x)) {
+            final_name = format!("{network}-{name}");
+        }
+
+        // only restore a version the owner can decode
+        let filepath = handle_file(&pk, &final_name, 0).await?;
+        let encoded = history::read_version(&filepath, version).await?;
+        carbonado::file::decode(&sk, &encoded)?;
+
+        history::rollback(&filepath, version).await?;
+        persist_version(&filepath, &pk, &final_name, &encoded).await?;
+        Ok(())
+    }
+
     pub async fn handle_file(
         pk: &str,
         name: &str,
@@ -293,23 +444,32 @@ mod server {
 
 #[cfg(target_arch = "wasm32")]
 pub use client::{
-    auctions_retrieve, auctions_store, marketplace_retrieve, marketplace_store, retrieve,
-    retrieve_metadata, store,
+    auctions_allow, auctions_retrieve, auctions_store, marketplace_retrieve, marketplace_store,
+    media_manifest, retrieve, retrieve_media, retrieve_metadata, store, store_media,
 };
 
 #[cfg(target_arch = "wasm32")]
 mod client {
     use super::*;
-    use js_sys::{Array, Promise, Uint8Array};
+    use js_sys::{Array, Promise, Reflect, Uint8Array};
     use serde::Deserialize;
-    use std::sync::Arc;
+    use std::{ops::Range, str::FromStr, sync::Arc};
     use wasm_bindgen::JsValue;
-    use wasm_bindgen_futures::{future_to_promise, JsFuture};
+    use wasm_bindgen_futures::{future_to_promise, spawn_local, JsFuture};
 
     use gloo_net::http::Request;
     use gloo_utils::errors::JsError;
 
-    use crate::constants::{BITMASK_ENDPOINT, CARBONADO_ENDPOINT};
+    use crate::{
+        carbonado::{
+            auth::WriteAuth,
+            replication::{select_replica, split_endpoints, SelectedReplica, WriteQuorum},
+            segments::{SegmentManifest, MEDIA_SEGMENTS_VERSION, MEDIA_SEGMENT_SIZE},
+            versions::{known_version, remember_version, IF_MATCH_HEADER},
+        },
+        constants::{BITMASK_ENDPOINT, CARBONADO_ENDPOINT, CARBONADO_WRITE_QUORUM},
+        warn,
+    };
 
     fn js_to_error(js_value: JsValue) -> CarbonadoError {
         CarbonadoError::JsError(js_to_js_error(js_value))
@@ -324,7 +484,7 @@ mod client {
 
     #[derive(Debug, Deserialize)]
     struct PostStorePromiseResult {
-        value: f64,
+        value: Option<f64>,
     }
 
     pub async fn store(
@@ -357,12 +517,26 @@ mod client {
             force_write = "/force";
         }
 
-        let endpoints = CARBONADO_ENDPOINT.read().await.to_string();
-        let endpoints: Vec<&str> = endpoints.split(',').collect();
+        // Unless forced, replicas only accept the write over the version retrieved last
+        let file_name = format!("{network}-{name}");
+        let if_match = match force {
+            true => None,
+            false => known_version(&pk_hex, &file_name),
+        };
+
+        let resource = format!("{pk_hex}/{file_name}{force_write}");
+        let endpoints = split_endpoints(&CARBONADO_ENDPOINT.read().await);
+        let quorum = WriteQuorum::from_str(&CARBONADO_WRITE_QUORUM.read().await)?;
         let requests = Array::new();
-        for endpoint in endpoints {
-            let url = format!("{endpoint}/{pk_hex}/{network}-{name}{force_write}");
-            let fetch_fn = future_to_promise(fetch_post(url, body.clone())); // TODO: try using .value_of();
+        for endpoint in endpoints.iter() {
+            // each replica sees its own nonce
+            let mut headers = WriteAuth::sign(&hex::encode(&sk), &resource, &body)?.headers();
+            if let Some(version) = &if_match {
+                headers.push((IF_MATCH_HEADER, version.clone()));
+            }
+
+            let url = format!("{endpoint}/{resource}");
+            let fetch_fn = future_to_promise(fetch_post(url, body.clone(), headers)); // TODO: try using .value_of();
             requests.push(&fetch_fn);
         }
 
@@ -372,15 +546,42 @@ mod client {
 
         info!(format!("Store results: {results:?}"));
 
-        let results = serde_wasm_bindgen::from_value::<Vec<PostStorePromiseResult>>(results)?;
-        let success = results.iter().any(|result| result.value == 200.0);
-        if success {
-            Ok(())
-        } else {
-            Err(CarbonadoError::AllEndpointsFailed)
+        let statuses = settled_statuses(&results);
+        let succeeded = statuses
+            .iter()
+            .filter(|status| **status == Some(200))
+            .count();
+        let conflicts = statuses
+            .iter()
+            .filter(|status| **status == Some(409))
+            .count();
+        match quorum.check(succeeded, endpoints.len()) {
+            Ok(()) => {
+                // lagging replicas which refused the write are repaired on the next retrieve
+                remember_version(&pk_hex, &file_name, &body);
+                Ok(())
+            }
+            Err(_) if conflicts > 0 => Err(CarbonadoError::Conflict(file_name)),
+            Err(err) => Err(err),
         }
     }
 
+    /// Status code of each settled request, `None` when it failed without response
+    fn settled_statuses(results: &JsValue) -> Vec<Option<u16>> {
+        Array::from(results)
+            .iter()
+            .map(|result| {
+                let status = Reflect::get(&result, &JsValue::from("status")).ok()?;
+                let key = match status.as_string().as_deref() {
+                    Some("fulfilled") => "value",
+                    _ => "reason",
+                };
+                let code = Reflect::get(&result, &JsValue::from(key)).ok()?.as_f64()?;
+                Some(code as u16)
+            })
+            .collect()
+    }
+
     pub async fn marketplace_store(
         name: &str,
         input: &[u8],
@@ -394,7 +595,7 @@ mod client {
 
         for endpoint in endpoints {
             let url = format!("{endpoint}/server/{network}-{name}");
-            let fetch_fn = future_to_promise(fetch_post(url, body.clone()));
+            let fetch_fn = future_to_promise(fetch_post(url, body.clone(), vec![]));
             requests.push(&fetch_fn);
         }
 
@@ -405,7 +606,7 @@ mod client {
         info!(format!("Store results: {results:?}"));
 
         let results = serde_wasm_bindgen::from_value::<Vec<PostStorePromiseResult>>(results)?;
-        let success = results.iter().any(|result| result.value == 200.0);
+        let success = results.iter().any(|result| result.value == Some(200.0));
         if success {
             Ok(())
         } else {
@@ -413,7 +614,9 @@ mod client {
         }
     }
 
+    /// Store a file of an auction, the write is signed with `sk`
     pub async fn auctions_store(
+        sk: &str,
         bundle_id: &str,
         name: &str,
         input: &[u8],
@@ -425,9 +628,11 @@ mod client {
         let endpoints: Vec<&str> = endpoints.split(',').collect();
         let requests = Array::new();
 
+        let resource = format!("auction/{bundle_id}/{network}-{name}");
         for endpoint in endpoints {
-            let url = format!("{endpoint}/auction/{bundle_id}/{network}-{name}");
-            let fetch_fn = future_to_promise(fetch_post(url, body.clone()));
+            let headers = WriteAuth::sign(sk, &resource, &body)?.headers();
+            let url = format!("{endpoint}/{resource}");
+            let fetch_fn = future_to_promise(fetch_post(url, body.clone(), headers));
             requests.push(&fetch_fn);
         }
 
@@ -438,7 +643,39 @@ mod client {
         info!(format!("Store results: {results:?}"));
 
         let results = serde_wasm_bindgen::from_value::<Vec<PostStorePromiseResult>>(results)?;
-        let success = results.iter().any(|result| result.value == 200.0);
+        let success = results.iter().any(|result| result.value == Some(200.0));
+        if success {
+            Ok(())
+        } else {
+            Err(CarbonadoError::AllEndpointsFailed)
+        }
+    }
+
+    /// Allow `bidders` to write to an auction, the write is signed with `sk`, the seller key
+    pub async fn auctions_allow(
+        sk: &str,
+        bundle_id: &str,
+        bidders: &[String],
+    ) -> Result<(), CarbonadoError> {
+        let body = Arc::new(serde_json::to_vec(bidders)?);
+        let endpoints = BITMASK_ENDPOINT.read().await.to_string();
+        let endpoints: Vec<&str> = endpoints.split(',').collect();
+        let requests = Array::new();
+
+        let resource = format!("auction/{bundle_id}");
+        for endpoint in endpoints {
+            let headers = WriteAuth::sign(sk, &resource, &body)?.headers();
+            let url = format!("{endpoint}/{resource}");
+            let fetch_fn = future_to_promise(fetch_post(url, body.clone(), headers));
+            requests.push(&fetch_fn);
+        }
+
+        let results = JsFuture::from(Promise::all_settled(&JsValue::from(requests)))
+            .await
+            .map_err(js_to_error)?;
+
+        let results = serde_wasm_bindgen::from_value::<Vec<PostStorePromiseResult>>(results)?;
+        let success = results.iter().any(|result| result.value == Some(200.0));
         if success {
             Ok(())
         } else {
@@ -478,60 +715,192 @@ mod client {
         name: &str,
         alt_names: Vec<&String>,
     ) -> Result<(Vec<u8>, Option<Vec<u8>>), CarbonadoError> {
-        use carbonado::file::Header;
-
         let sk = hex::decode(sk)?;
         let secret_key = SecretKey::from_slice(&sk)?;
         let public_key = PublicKey::from_secret_key_global(&secret_key);
         let pk = public_key.to_hex();
 
         let network = NETWORK.read().await.to_string();
-        let endpoints = CARBONADO_ENDPOINT.read().await.to_string();
-        let endpoints: Vec<&str> = endpoints.split(',').collect();
+        let endpoints = split_endpoints(&CARBONADO_ENDPOINT.read().await);
+
+        let file_name = format!("{network}-{name}");
+        let responses = fetch_replicas(&endpoints, &format!("{pk}/{file_name}")).await?;
+        let known = known_version(&pk, &file_name);
+        if let Some(replica) = select_replica(&sk, &responses, known.as_deref()) {
+            remember_version(&pk, &file_name, &replica.encoded);
+            repair_replicas(&sk, &endpoints, &format!("{pk}/{file_name}"), &replica);
+            return Ok((replica.decoded, replica.metadata));
+        }
 
-        let requests = Array::new();
-        for endpoint in endpoints.iter() {
-            let url = format!("{endpoint}/{pk}/{network}-{name}");
-            let fetch_fn = future_to_promise(fetch_get_byte_array(url));
-            requests.push(&fetch_fn);
+        // Check alternative names
+        for alt_name in alt_names {
+            let responses =
+                fetch_replicas(&endpoints, &format!("{pk}/{network}-{alt_name}")).await?;
+            if let Some(replica) = select_replica(&sk, &responses, None) {
+                return Ok((replica.decoded, replica.metadata));
+            }
         }
 
-        let result = JsFuture::from(Promise::any(&JsValue::from(requests)))
-            .await
-            .map_err(js_to_error)?;
+        Ok((Vec::new(), None))
+    }
 
-        let array = Uint8Array::from(result);
-        let encoded = array.to_vec();
+    /// Store a media file in segments, the manifest last
+    pub async fn store_media(
+        sk: &str,
+        name: &str,
+        input: &[u8],
+    ) -> Result<SegmentManifest, CarbonadoError> {
+        let level = 15;
+        let secret = hex::decode(sk)?;
+        let secret_key = SecretKey::from_slice(&secret)?;
+        let public_key = PublicKey::from_secret_key_global(&secret_key);
+        let pk = public_key.serialize();
+        let pk_hex = public_key.to_hex();
 
-        if encoded.len() > Header::len() {
-            let (header, decoded) = carbonado::file::decode(&sk, &encoded)?;
-            return Ok((decoded, header.metadata.map(|m| m.to_vec())));
-        }
+        let network = NETWORK.read().await.to_string();
+        let endpoints = split_endpoints(&CARBONADO_ENDPOINT.read().await);
+        let quorum = WriteQuorum::from_str(&CARBONADO_WRITE_QUORUM.read().await)?;
 
-        // Check alternative names
-        for alt_name in alt_names {
+        let mut manifest = SegmentManifest::new(MEDIA_SEGMENT_SIZE);
+        for segment in input.chunks(MEDIA_SEGMENT_SIZE) {
+            let (body, _) = carbonado::file::encode(&secret, Some(&pk), segment, level, None)?;
+            let segment_name = SegmentManifest::segment_name(name, manifest.segments.len());
+            manifest.push(&body, segment.len());
+
+            let body = Arc::new(body);
+            let resource = format!("{pk_hex}/{network}-{segment_name}/force");
             let requests = Array::new();
             for endpoint in endpoints.iter() {
-                let url = format!("{endpoint}/{pk}/{network}-{alt_name}");
-
-                let fetch_fn = future_to_promise(fetch_get_byte_array(url));
-                requests.push(&fetch_fn);
+                let headers = WriteAuth::sign(sk, &resource, &body)?.headers();
+                let url = format!("{endpoint}/{resource}");
+                requests.push(&future_to_promise(fetch_post(url, body.clone(), headers)));
             }
 
-            let result = JsFuture::from(Promise::any(&JsValue::from(requests)))
+            let results = JsFuture::from(Promise::all_settled(&JsValue::from(requests)))
                 .await
                 .map_err(js_to_error)?;
+            let statuses = settled_statuses(&results);
+            let succeeded = statuses
+                .iter()
+                .filter(|status| **status == Some(200))
+                .count();
+            quorum.check(succeeded, endpoints.len())?;
+        }
 
-            let array = Uint8Array::from(result);
-            let encoded = array.to_vec();
+        let data = postcard::to_allocvec(&manifest)
+            .map_err(|op| CarbonadoError::SegmentManifest(op.to_string()))?;
+        store(sk, name, &data, true, Some(MEDIA_SEGMENTS_VERSION.to_vec())).await?;
+        Ok(manifest)
+    }
 
-            if encoded.len() > Header::len() {
-                let (header, decoded) = carbonado::file::decode(&sk, &encoded)?;
-                return Ok((decoded, header.metadata.map(|m| m.to_vec())));
-            }
+    pub async fn media_manifest(sk: &str, name: &str) -> Result<SegmentManifest, CarbonadoError> {
+        let (data, metadata) = retrieve(sk, name, vec![]).await?;
+        if metadata != Some(MEDIA_SEGMENTS_VERSION.to_vec()) {
+            return Err(CarbonadoError::NotSegmented(name.to_owned()));
         }
 
-        Ok((Vec::new(), None))
+        postcard::from_bytes(&data).map_err(|op| CarbonadoError::SegmentManifest(op.to_string()))
+    }
+
+    /// Decoded bytes of a range of a media file, the whole file by default
+    ///
+    /// Only the segments holding the range are fetched, each one verified
+    /// on its own.
+    pub async fn retrieve_media(
+        sk: &str,
+        name: &str,
+        range: Option<Range<u64>>,
+    ) -> Result<Vec<u8>, CarbonadoError> {
+        let manifest = media_manifest(sk, name).await?;
+        let range = manifest.clamp(range);
+
+        let secret = hex::decode(sk)?;
+        let secret_key = SecretKey::from_slice(&secret)?;
+        let pk = PublicKey::from_secret_key_global(&secret_key).to_hex();
+        let network = NETWORK.read().await.to_string();
+        let endpoints = split_endpoints(&CARBONADO_ENDPOINT.read().await);
+
+        let mut data = Vec::with_capacity((range.end - range.start) as usize);
+        for index in manifest.segments_for(&range) {
+            let segment_name = SegmentManifest::segment_name(name, index);
+            let responses =
+                fetch_replicas(&endpoints, &format!("{pk}/{network}-{segment_name}")).await?;
+
+            let segment = responses
+                .iter()
+                .flatten()
+                .find_map(|encoded| manifest.decode(&secret, index, encoded).ok())
+                .ok_or(CarbonadoError::SegmentCorrupted(index))?;
+            data.extend_from_slice(manifest.slice(index, &segment, &range));
+        }
+
+        Ok(data)
+    }
+
+    /// Copy of a file on each endpoint, `None` when the endpoint failed
+    async fn fetch_replicas(
+        endpoints: &[String],
+        path: &str,
+    ) -> Result<Vec<Option<Vec<u8>>>, CarbonadoError> {
+        let requests = Array::new();
+        for endpoint in endpoints {
+            let url = format!("{endpoint}/{path}");
+            let fetch_fn = future_to_promise(fetch_get_byte_array(url));
+            requests.push(&fetch_fn);
+        }
+
+        let results = JsFuture::from(Promise::all_settled(&JsValue::from(requests)))
+            .await
+            .map_err(js_to_error)?;
+
+        let responses = Array::from(&results)
+            .iter()
+            .map(|result| {
+                let status = Reflect::get(&result, &JsValue::from("status")).ok()?;
+                if status.as_string().as_deref() != Some("fulfilled") {
+                    return None;
+                }
+                let value = Reflect::get(&result, &JsValue::from("value")).ok()?;
+                Some(Uint8Array::new(&value).to_vec())
+            })
+            .collect();
+        Ok(responses)
+    }
+
+    /// Write the selected copy on the lagging endpoints, in the background
+    ///
+    /// Repairs are conditional on the copy each endpoint returned, so a write
+    /// made since the retrieve is never replaced.
+    fn repair_replicas(sk: &[u8], endpoints: &[String], path: &str, replica: &SelectedReplica) {
+        if replica.lagging.is_empty() {
+            return;
+        }
+
+        let sk = hex::encode(sk);
+        let body = Arc::new(replica.encoded.clone());
+        let resource = path.to_string();
+        let repairs: Vec<(String, Option<String>)> = replica
+            .lagging
+            .iter()
+            .map(|(index, version)| (format!("{}/{resource}", endpoints[*index]), version.clone()))
+            .collect();
+        spawn_local(async move {
+            for (url, version) in repairs {
+                let mut headers = match WriteAuth::sign(&sk, &resource, &body) {
+                    Ok(auth) => auth.headers(),
+                    Err(err) => {
+                        warn!(format!("Cannot sign carbonado repair {url}: {err:?}"));
+                        return;
+                    }
+                };
+                if let Some(version) = version {
+                    headers.push((IF_MATCH_HEADER, version));
+                }
+                if let Err(err) = fetch_post(url.clone(), body.clone(), headers).await {
+                    warn!(format!("Cannot repair carbonado replica {url}: {err:?}"));
+                }
+            }
+        });
     }
 
     pub async fn marketplace_retrieve(
@@ -583,14 +952,21 @@ mod client {
         Ok((encoded.to_vec(), None))
     }
 
-    async fn fetch_post(url: String, body: Arc<Vec<u8>>) -> Result<JsValue, JsValue> {
+    async fn fetch_post(
+        url: String,
+        body: Arc<Vec<u8>>,
+        headers: Vec<(&'static str, String)>,
+    ) -> Result<JsValue, JsValue> {
         let array = Uint8Array::new_with_length(body.len() as u32);
         array.copy_from(&body);
 
-        let request = Request::post(&url)
+        let mut request = Request::post(&url)
             .header("Content-Type", "application/octet-stream")
-            .header("Cache-Control", "no-cache")
-            .body(array);
+            .header("Cache-Control", "no-cache");
+        for (name, value) in headers {
+            request = request.header(name, &value);
+        }
+        let request = request.body(array);
 
         let request = match request {
             Ok(request) => request,
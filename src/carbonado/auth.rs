@@ -0,0 +1,206 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use std::{collections::BTreeMap, str::FromStr, sync::Mutex};
+
+use amplify::hex::ToHex;
+use bitcoin_30::secp256k1::{
+    schnorr::Signature, KeyPair, Message, PublicKey, SecretKey, XOnlyPublicKey, SECP256K1,
+};
+use once_cell::sync::Lazy;
+
+use crate::{carbonado::error::CarbonadoError, constants::CARBONADO_AUTH_WINDOW};
+
+/// Headers of a signed write
+pub const AUTH_PUBKEY_HEADER: &str = "X-Bitmask-Pubkey";
+pub const AUTH_SIGNATURE_HEADER: &str = "X-Bitmask-Signature";
+pub const AUTH_TIMESTAMP_HEADER: &str = "X-Bitmask-Timestamp";
+pub const AUTH_NONCE_HEADER: &str = "X-Bitmask-Nonce";
+
+const AUTH_DOMAIN: &[u8] = b"bitmask:carbonado-write:v1";
+
+/// File of an auction directory with the keys of the bidders the seller allows to write
+pub const AUCTION_BIDDERS_FILE: &str = ".bidders";
+
+/// BIP340 signature of a write, sent along the body in the `X-Bitmask-*` headers
+///
+/// The signature commits to the route of the write (`resource`, the path
+/// below the endpoint, e.g. `{pk}/{name}/force` or `auction/{bundle_id}/{name}`),
+/// the hash of the body, the timestamp and a random nonce, so it can't be
+/// used for another file, another body or twice.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WriteAuth {
+    pub pubkey: String,
+    pub signature: String,
+    pub timestamp: i64,
+    pub nonce: String,
+}
+
+fn auth_message(resource: &str, body: &[u8], timestamp: i64, nonce: &str) -> Message {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(AUTH_DOMAIN);
+    hasher.update(&(resource.len() as u64).to_le_bytes());
+    hasher.update(resource.as_bytes());
+    hasher.update(blake3::hash(body).as_bytes());
+    hasher.update(&timestamp.to_le_bytes());
+    hasher.update(nonce.as_bytes());
+
+    Message::from_slice(hasher.finalize().as_bytes()).expect("blake3 hashes are 32 bytes")
+}
+
+impl WriteAuth {
+    /// Sign a write of `body` to `resource` with the key of the owner
+    pub fn sign(sk: &str, resource: &str, body: &[u8]) -> Result<Self, CarbonadoError> {
+        Self::sign_at(sk, resource, body, chrono::Utc::now().timestamp())
+    }
+
+    pub fn sign_at(
+        sk: &str,
+        resource: &str,
+        body: &[u8],
+        timestamp: i64,
+    ) -> Result<Self, CarbonadoError> {
+        let secret_key = SecretKey::from_slice(&hex::decode(sk)?)?;
+        let keypair = KeyPair::from_secret_key(SECP256K1, &secret_key);
+        let nonce = rand::random::<[u8; 16]>().to_hex();
+
+        let message = auth_message(resource, body, timestamp, &nonce);
+        let signature = SECP256K1.sign_schnorr_no_aux_rand(&message, &keypair);
+
+        Ok(Self {
+            pubkey: PublicKey::from_secret_key_global(&secret_key).to_hex(),
+            signature: signature.to_string(),
+            timestamp,
+            nonce,
+        })
+    }
+
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        vec![
+            (AUTH_PUBKEY_HEADER, self.pubkey.clone()),
+            (AUTH_SIGNATURE_HEADER, self.signature.clone()),
+            (AUTH_TIMESTAMP_HEADER, self.timestamp.to_string()),
+            (AUTH_NONCE_HEADER, self.nonce.clone()),
+        ]
+    }
+
+    /// Read the signature of a write, `header` returns the value of a header by name
+    pub fn from_headers<'a>(
+        header: impl Fn(&str) -> Option<&'a str>,
+    ) -> Result<Self, CarbonadoError> {
+        let get = |name: &str| {
+            header(name)
+                .map(ToOwned::to_owned)
+                .ok_or(CarbonadoError::MissingWriteAuth(name.to_owned()))
+        };
+
+        let timestamp = get(AUTH_TIMESTAMP_HEADER)?;
+        let timestamp = timestamp
+            .parse()
+            .map_err(|_| CarbonadoError::MissingWriteAuth(AUTH_TIMESTAMP_HEADER.to_owned()))?;
+
+        Ok(Self {
+            pubkey: get(AUTH_PUBKEY_HEADER)?,
+            signature: get(AUTH_SIGNATURE_HEADER)?,
+            timestamp,
+            nonce: get(AUTH_NONCE_HEADER)?,
+        })
+    }
+
+    /// Check the signature of a write of `body` to `resource`
+    pub fn verify(&self, resource: &str, body: &[u8]) -> Result<(), CarbonadoError> {
+        let public_key =
+            PublicKey::from_str(&self.pubkey).map_err(|_| CarbonadoError::WrongWriteSignature)?;
+        let signature = Signature::from_str(&self.signature)
+            .map_err(|_| CarbonadoError::WrongWriteSignature)?;
+
+        let message = auth_message(resource, body, self.timestamp, &self.nonce);
+        SECP256K1
+            .verify_schnorr(&signature, &message, &public_key.x_only_public_key().0)
+            .map_err(|_| CarbonadoError::WrongWriteSignature)
+    }
+
+    /// Check the write was signed within `window` seconds of `now`, and only used once
+    pub fn check_fresh(&self, now: i64, window: i64) -> Result<(), CarbonadoError> {
+        if (now - self.timestamp).abs() > window {
+            return Err(CarbonadoError::ExpiredWriteAuth(self.timestamp));
+        }
+
+        let mut seen = SEEN_NONCES.lock().expect("seen nonces poisoned");
+        // nonces outside of the window are rejected by their timestamp
+        seen.retain(|_, timestamp| (now - *timestamp).abs() <= window);
+
+        let key = format!("{}/{}", self.pubkey, self.nonce);
+        if seen.insert(key, self.timestamp).is_some() {
+            return Err(CarbonadoError::ReplayedWriteAuth(self.nonce.clone()));
+        }
+        Ok(())
+    }
+
+    /// Authorize a write of `body` to `resource`, signed by `pk` or by anyone when `None`
+    pub async fn authorize(
+        &self,
+        pk: Option<&str>,
+        resource: &str,
+        body: &[u8],
+    ) -> Result<(), CarbonadoError> {
+        if let Some(pk) = pk {
+            if pk != self.pubkey {
+                return Err(CarbonadoError::WrongWriteSigner(self.pubkey.clone()));
+            }
+        }
+
+        self.verify(resource, body)?;
+        let window = CARBONADO_AUTH_WINDOW.read().await.parse().unwrap_or(300);
+        self.check_fresh(chrono::Utc::now().timestamp(), window)
+    }
+}
+
+/// Nonces of the writes accepted by this process, by signer, with their timestamp
+static SEEN_NONCES: Lazy<Mutex<BTreeMap<String, i64>>> = Lazy::new(Default::default);
+
+/// Key without its parity, bidders are allowed by their nostr (x-only) key
+fn x_only_key(pubkey: &str) -> Option<XOnlyPublicKey> {
+    match PublicKey::from_str(pubkey) {
+        Ok(public_key) => Some(public_key.x_only_public_key().0),
+        Err(_) => XOnlyPublicKey::from_str(pubkey).ok(),
+    }
+}
+
+/// Key the write of `signer` to the auction of `bundle_id` must be signed with
+///
+/// The seller, owner of the bundle key, writes to its auction, and so do the
+/// `bidders` it allowed. Any other signer is checked against the bundle key,
+/// so its write is rejected.
+pub fn auction_writer(bundle_id: &str, bidders: &[String], signer: &str) -> String {
+    let allowed = match x_only_key(signer) {
+        Some(signer) => bidders
+            .iter()
+            .any(|bidder| x_only_key(bidder) == Some(signer)),
+        None => false,
+    };
+
+    if allowed {
+        signer.to_owned()
+    } else {
+        bundle_id.to_owned()
+    }
+}
+
+/// Bidders the seller allowed to write to the auction stored in `dir`
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn auction_bidders(dir: &Path) -> Vec<String> {
+    match tokio::fs::read(dir.join(AUCTION_BIDDERS_FILE)).await {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+        Err(_) => vec![],
+    }
+}
+
+/// Replace the bidders allowed to write to the auction stored in `dir`
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn allow_auction_bidders(dir: &Path, bidders: &[String]) -> Result<(), CarbonadoError> {
+    let filepath = dir.join(AUCTION_BIDDERS_FILE);
+    let tmp = filepath.with_extension("tmp");
+    tokio::fs::write(&tmp, serde_json::to_vec(bidders)?).await?;
+    tokio::fs::rename(&tmp, &filepath).await?;
+    Ok(())
+}
@@ -25,6 +25,36 @@ pub enum CarbonadoError {
     SerdeWasm(#[from] serde_wasm_bindgen::Error),
     /// All endpoints failed error
     AllEndpointsFailed,
+    /// Only {0} endpoints stored the file, {1} required
+    WriteQuorumFailed(usize, usize),
+    /// File {0} was changed by another device since it was retrieved
+    Conflict(String),
+    /// Version {0} is not in the history of the file
+    VersionNotFound(String),
+    /// Segment {0} is missing or corrupted
+    SegmentCorrupted(usize),
+    /// File {0} is not stored in segments
+    NotSegmented(String),
+    /// Segment manifest error: {0}
+    SegmentManifest(String),
+    /// Write is not signed, missing header {0}
+    MissingWriteAuth(String),
+    /// Wrong write signature
+    WrongWriteSignature,
+    /// Write signed by {0}, not by the owner of the file
+    WrongWriteSigner(String),
+    /// Write signed at {0} is expired
+    ExpiredWriteAuth(i64),
+    /// Write with nonce {0} was already received
+    ReplayedWriteAuth(String),
+    /// File is larger than the {1} bytes allowed for {0}
+    FileTooLarge(String, u64),
+    /// Storage quota of {0} bytes exceeded
+    QuotaExceeded(u64),
+    /// Wrong storage limit '{0}'
+    WrongStorageLimits(String),
+    /// Wrong write quorum '{0}', expected 'all', 'majority' or a number of endpoints
+    WrongWriteQuorum(String),
     /// Wrong Nostr private key
     WrongNostrPrivateKey,
     /// Wrong Nostr public key
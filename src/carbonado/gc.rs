@@ -0,0 +1,165 @@
+#![cfg(not(target_arch = "wasm32"))]
+use std::{
+    env,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use log::{debug, warn};
+use postcard::from_bytes;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use walkdir::WalkDir;
+
+use crate::{
+    carbonado::{auctions_retrieve, error::CarbonadoError},
+    constants::CARBONADO_GC_INTERVAL,
+    rgb::swap::RgbAuctionSwaps,
+};
+
+/// File of an auction directory with the bundle of the auction, since the
+/// directory is named after the key shared with the coordinator
+pub const AUCTION_BUNDLE_FILE: &str = ".bundle";
+
+/// Files removed by a garbage collection
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcReport {
+    pub ran_at: i64,
+    pub proxy_files: usize,
+    pub proxy_bytes: u64,
+    pub auction_files: usize,
+    pub auction_bytes: u64,
+}
+
+/// Seconds between garbage collections, read from `CARBONADO_GC_INTERVAL`
+pub async fn gc_interval() -> u64 {
+    CARBONADO_GC_INTERVAL.read().await.parse().unwrap_or(3600)
+}
+
+/// Keep the bundle of the auction stored in `dir`
+pub async fn track_auction(dir: &Path, bundle_id: &str) -> Result<(), CarbonadoError> {
+    let filepath = dir.join(AUCTION_BUNDLE_FILE);
+    if !filepath.exists() {
+        fs::write(filepath, bundle_id).await?;
+    }
+    Ok(())
+}
+
+fn modified_at(path: &Path) -> Option<i64> {
+    let modified = path.metadata().ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64)
+}
+
+fn files(dir: &Path) -> Vec<(PathBuf, u64)> {
+    WalkDir::new(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            metadata
+                .is_file()
+                .then(|| (entry.path().to_path_buf(), metadata.len()))
+        })
+        .collect()
+}
+
+/// Remove the consignments and media left by failed uploads to the RGB proxy
+///
+/// They are only kept on disk while they are uploaded, so any file older
+/// than `grace` seconds is orphaned.
+pub async fn collect_proxy_files(
+    proxy_dir: &Path,
+    now: i64,
+    grace: i64,
+    report: &mut GcReport,
+) -> Result<(), CarbonadoError> {
+    for (path, bytes) in files(proxy_dir) {
+        match modified_at(&path) {
+            Some(modified) if now - modified > grace => {
+                debug!("removing orphaned proxy file {path:?}");
+                fs::remove_file(&path).await?;
+                report.proxy_files += 1;
+                report.proxy_bytes += bytes;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Whether all the offers of an auction are expired at `now`
+pub fn auction_expired(auction: &RgbAuctionSwaps, now: i64) -> bool {
+    !auction.items.is_empty()
+        && auction
+            .items
+            .iter()
+            .all(|offer| matches!(offer.expire_at, Some(expire_at) if expire_at < now))
+}
+
+async fn expired_auction(dir: &Path, now: i64) -> Result<bool, CarbonadoError> {
+    let bundle_id = fs::read_to_string(dir.join(AUCTION_BUNDLE_FILE)).await?;
+
+    for (path, _) in files(dir) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if name.starts_with('.') || name.ends_with("-diff.c15") || path.parent() != Some(dir) {
+            continue;
+        }
+
+        let (data, _) = auctions_retrieve(bundle_id.trim(), &name).await?;
+        match from_bytes::<RgbAuctionSwaps>(&data) {
+            Ok(auction) if !auction_expired(&auction, now) => return Ok(false),
+            Ok(_) => {}
+            Err(_) => return Ok(false),
+        }
+    }
+
+    Ok(true)
+}
+
+/// Remove the auctions whose offers are all expired, with their bids
+pub async fn collect_auctions(
+    carbonado_dir: &Path,
+    now: i64,
+    report: &mut GcReport,
+) -> Result<(), CarbonadoError> {
+    let mut dirs = match fs::read_dir(carbonado_dir).await {
+        Ok(dirs) => dirs,
+        Err(_) => return Ok(()),
+    };
+
+    while let Some(entry) = dirs.next_entry().await? {
+        let dir = entry.path();
+        if !dir.join(AUCTION_BUNDLE_FILE).exists() {
+            continue;
+        }
+
+        match expired_auction(&dir, now).await {
+            Ok(true) => {
+                debug!("removing expired auction {dir:?}");
+                let removed = files(&dir);
+                fs::remove_dir_all(&dir).await?;
+                report.auction_files += removed.len();
+                report.auction_bytes += removed.iter().map(|(_, bytes)| bytes).sum::<u64>();
+            }
+            Ok(false) => {}
+            Err(err) => warn!("cannot read auction {dir:?}: {err}"),
+        }
+    }
+    Ok(())
+}
+
+/// Remove orphaned proxy files and expired auctions
+pub async fn collect(now: i64) -> Result<GcReport, CarbonadoError> {
+    let carbonado_dir = env::var("CARBONADO_DIR").unwrap_or("/tmp/bitmaskd/carbonado".to_owned());
+    let proxy_dir = env::var("RGB_PROXY_DIR").unwrap_or("/tmp/bitmaskd/proxy".to_owned());
+    let grace = gc_interval().await as i64;
+
+    let mut report = GcReport {
+        ran_at: now,
+        ..Default::default()
+    };
+    collect_proxy_files(Path::new(&proxy_dir), now, grace, &mut report).await?;
+    collect_auctions(Path::new(&carbonado_dir), now, &mut report).await?;
+    Ok(report)
+}
@@ -0,0 +1,167 @@
+#![cfg(not(target_arch = "wasm32"))]
+use std::path::{Component, Path, PathBuf};
+
+use tokio::fs;
+
+use crate::{
+    carbonado::{
+        error::CarbonadoError,
+        metrics,
+        versions::{file_version, write_if_matches},
+    },
+    constants::CARBONADO_HISTORY_LIMIT,
+    structs::FileVersion,
+    warn,
+};
+
+/// Directory of the previous versions, next to the files of an owner
+pub const HISTORY_DIR: &str = ".history";
+
+/// Previous versions kept per file, read from `CARBONADO_HISTORY_LIMIT`
+pub async fn history_limit() -> usize {
+    CARBONADO_HISTORY_LIMIT.read().await.parse().unwrap_or(10)
+}
+
+pub fn is_history(path: &Path) -> bool {
+    path.components()
+        .any(|component| component == Component::Normal(HISTORY_DIR.as_ref()))
+}
+
+/// `$CARBONADO_DIR/{pk}/.history/{name}`, for the file `$CARBONADO_DIR/{pk}/{name}`
+fn history_dir(filepath: &Path) -> PathBuf {
+    let dir = filepath.parent().unwrap_or(Path::new("."));
+    let name = filepath.file_name().unwrap_or_default();
+    dir.join(HISTORY_DIR).join(name)
+}
+
+/// Versions are stored as `{micros}-{version}.c15`
+fn parse_entry(entry: &str) -> Option<(i64, String)> {
+    let (micros, version) = entry.strip_suffix(".c15")?.split_once('-')?;
+    Some((micros.parse().ok()?, version.to_owned()))
+}
+
+/// Version of the history: micros, version, path and bytes
+type Entry = (i64, String, PathBuf, u64);
+
+async fn entries(filepath: &Path) -> Result<Vec<Entry>, CarbonadoError> {
+    let mut dir = match fs::read_dir(history_dir(filepath)).await {
+        Ok(dir) => dir,
+        Err(_) => return Ok(vec![]),
+    };
+
+    let mut entries = vec![];
+    while let Some(entry) = dir.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if let Some((micros, version)) = parse_entry(&name) {
+            let bytes = entry.metadata().await?.len();
+            entries.push((micros, version, entry.path(), bytes));
+        }
+    }
+
+    // newest first
+    entries.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(entries)
+}
+
+/// Previous versions of a file, newest first
+pub async fn list_versions(filepath: &Path) -> Result<Vec<FileVersion>, CarbonadoError> {
+    Ok(entries(filepath)
+        .await?
+        .into_iter()
+        .map(|(micros, version, _, bytes)| FileVersion {
+            version,
+            created_at: micros / 1_000_000,
+            bytes,
+        })
+        .collect())
+}
+
+/// Present version of a file when it is not archived yet, with the history of the file
+async fn pending_archive(
+    filepath: &Path,
+) -> Result<(Option<(String, Vec<u8>)>, Vec<Entry>), CarbonadoError> {
+    let entries = entries(filepath).await?;
+
+    // bitmaskd creates an empty file before its first write
+    let present = fs::read(filepath).await.unwrap_or_default();
+    if present.is_empty() {
+        return Ok((None, entries));
+    }
+
+    let version = file_version(&present);
+    if entries.first().map(|entry| &entry.1) == Some(&version) {
+        return Ok((None, entries));
+    }
+    Ok((Some((version, present)), entries))
+}
+
+/// Keep the present version of a file before it is overwritten
+///
+/// Only the newest `CARBONADO_HISTORY_LIMIT` versions are kept, a limit of 0
+/// disables the history.
+pub async fn archive(filepath: &Path) -> Result<(), CarbonadoError> {
+    let limit = history_limit().await;
+    if limit == 0 {
+        return Ok(());
+    }
+
+    let (pending, mut entries) = pending_archive(filepath).await?;
+    let mut added = 0;
+    if let Some((version, present)) = pending {
+        let dir = history_dir(filepath);
+        fs::create_dir_all(&dir).await?;
+
+        let micros = chrono::Utc::now().timestamp_micros();
+        let path = dir.join(format!("{micros}-{version}.c15"));
+        fs::write(&path, &present).await?;
+        added = present.len() as u64;
+        entries.insert(0, (micros, version, path, added));
+    }
+
+    let mut removed = 0;
+    for (_, _, path, bytes) in entries.iter().skip(limit) {
+        fs::remove_file(path).await?;
+        removed += bytes;
+    }
+
+    // the file is already written, a metric out of date is not worth failing for
+    if let Err(err) = metrics::record_history(added, removed).await {
+        warn!(format!("history metrics not updated: {err}"));
+    }
+    Ok(())
+}
+
+/// Bytes [`archive`] would add to the history of a file, and the bytes of the
+/// versions it would remove over the limit
+pub async fn archive_growth(filepath: &Path) -> Result<(u64, u64), CarbonadoError> {
+    let limit = history_limit().await;
+    if limit == 0 {
+        return Ok((0, 0));
+    }
+
+    let (pending, entries) = pending_archive(filepath).await?;
+    let added = pending
+        .as_ref()
+        .map(|(_, present)| present.len() as u64)
+        .unwrap_or_default();
+    let kept = limit - pending.is_some() as usize;
+    let removed = entries.iter().skip(kept).map(|entry| entry.3).sum();
+    Ok((added, removed))
+}
+
+/// Encoded file of a previous version
+pub async fn read_version(filepath: &Path, version: &str) -> Result<Vec<u8>, CarbonadoError> {
+    let entries = entries(filepath).await?;
+    let Some((_, _, path, _)) = entries.iter().find(|entry| entry.1 == version) else {
+        return Err(CarbonadoError::VersionNotFound(version.to_owned()));
+    };
+
+    Ok(fs::read(path).await?)
+}
+
+/// Restore a previous version of a file, the present one goes to the history
+pub async fn rollback(filepath: &Path, version: &str) -> Result<Vec<u8>, CarbonadoError> {
+    let encoded = read_version(filepath, version).await?;
+    write_if_matches(filepath, None, &encoded).await?;
+    Ok(encoded)
+}
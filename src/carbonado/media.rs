@@ -0,0 +1,170 @@
+#![cfg(not(target_arch = "wasm32"))]
+use std::ops::Range;
+
+use amplify::hex::ToHex;
+use bitcoin_30::secp256k1::{PublicKey, SecretKey};
+use futures::{stream, Stream, TryStreamExt};
+use postcard::{from_bytes, to_allocvec};
+use tokio::{
+    fs,
+    io::{AsyncRead, AsyncReadExt},
+};
+
+use crate::{
+    carbonado::{
+        error::CarbonadoError,
+        handle_file,
+        quota::{self, StorageCategory},
+        retrieve,
+        segments::{SegmentManifest, MEDIA_SEGMENTS_VERSION, MEDIA_SEGMENT_SIZE},
+        store,
+        versions::{persist_version, write_if_matches},
+    },
+    constants::NETWORK,
+};
+
+/// Store a media file in segments, see [`SegmentManifest`]
+pub async fn store_media(
+    sk: &str,
+    name: &str,
+    input: &[u8],
+) -> Result<SegmentManifest, CarbonadoError> {
+    store_media_from(sk, name, &mut &input[..]).await
+}
+
+/// Store a media file in segments, reading one segment at a time
+///
+/// Segments are written like [`store`] writes files, within the storage
+/// quota and with their previous versions kept in the history. The manifest
+/// is written last, so readers never see a partial file.
+pub async fn store_media_from<R: AsyncRead + Unpin>(
+    sk: &str,
+    name: &str,
+    reader: &mut R,
+) -> Result<SegmentManifest, CarbonadoError> {
+    let level = 15;
+    let secret = hex::decode(sk)?;
+    let secret_key = SecretKey::from_slice(&secret)?;
+    let public_key = PublicKey::from_secret_key_global(&secret_key);
+    let pk = public_key.serialize();
+    let pk_hex = public_key.to_hex();
+    let network = NETWORK.read().await.to_string();
+
+    let mut manifest = SegmentManifest::new(MEDIA_SEGMENT_SIZE);
+    let mut segment = vec![0u8; MEDIA_SEGMENT_SIZE];
+    loop {
+        let mut len = 0;
+        while len < MEDIA_SEGMENT_SIZE {
+            match reader.read(&mut segment[len..]).await? {
+                0 => break,
+                read => len += read,
+            }
+        }
+        if len == 0 {
+            break;
+        }
+
+        let (body, _) = carbonado::file::encode(&secret, Some(&pk), &segment[..len], level, None)?;
+        let segment_name = SegmentManifest::segment_name(name, manifest.segments.len());
+        let filepath = handle_file(&pk_hex, &segment_name, body.len()).await?;
+        quota::check_write(&filepath, StorageCategory::Media, body.len() as u64).await?;
+        write_if_matches(&filepath, None, &body).await?;
+        let final_name = format!("{network}-{segment_name}");
+        persist_version(&filepath, &pk_hex, &final_name, &body).await?;
+        manifest.push(&body, len);
+
+        if len < MEDIA_SEGMENT_SIZE {
+            break;
+        }
+    }
+
+    let data =
+        to_allocvec(&manifest).map_err(|op| CarbonadoError::SegmentManifest(op.to_string()))?;
+    store(sk, name, &data, true, Some(MEDIA_SEGMENTS_VERSION.to_vec())).await?;
+    Ok(manifest)
+}
+
+pub async fn media_manifest(sk: &str, name: &str) -> Result<SegmentManifest, CarbonadoError> {
+    let (data, metadata) = retrieve(sk, name, vec![]).await?;
+    if metadata != Some(MEDIA_SEGMENTS_VERSION.to_vec()) {
+        return Err(CarbonadoError::NotSegmented(name.to_owned()));
+    }
+
+    from_bytes(&data).map_err(|op| CarbonadoError::SegmentManifest(op.to_string()))
+}
+
+struct MediaReader {
+    sk: Vec<u8>,
+    pk: String,
+    name: String,
+    manifest: SegmentManifest,
+    range: Range<u64>,
+}
+
+impl MediaReader {
+    async fn read(&self, index: usize) -> Result<Vec<u8>, CarbonadoError> {
+        let segment_name = SegmentManifest::segment_name(&self.name, index);
+        let filepath = handle_file(&self.pk, &segment_name, 0).await?;
+        let encoded = fs::read(&filepath)
+            .await
+            .map_err(|_| CarbonadoError::SegmentCorrupted(index))?;
+
+        let segment = self.manifest.decode(&self.sk, index, &encoded)?;
+        Ok(self.manifest.slice(index, &segment, &self.range).to_vec())
+    }
+}
+
+/// Decoded bytes of a media file, one segment at a time
+///
+/// Only the segments holding `range` (the whole file by default) are read,
+/// so memory use is bound by the segment size. Returns the manifest and the
+/// range clamped to the file.
+pub async fn stream_media(
+    sk: &str,
+    name: &str,
+    range: Option<Range<u64>>,
+) -> Result<
+    (
+        SegmentManifest,
+        Range<u64>,
+        impl Stream<Item = Result<Vec<u8>, CarbonadoError>>,
+    ),
+    CarbonadoError,
+> {
+    let manifest = media_manifest(sk, name).await?;
+    let range = manifest.clamp(range);
+    let indexes = manifest.segments_for(&range);
+
+    let secret = hex::decode(sk)?;
+    let secret_key = SecretKey::from_slice(&secret)?;
+    let reader = MediaReader {
+        sk: secret,
+        pk: PublicKey::from_secret_key_global(&secret_key).to_hex(),
+        name: name.to_owned(),
+        manifest: manifest.clone(),
+        range: range.clone(),
+    };
+
+    let segments = stream::unfold((indexes, reader), |(mut indexes, reader)| async move {
+        let index = indexes.next()?;
+        let segment = reader.read(index).await;
+        if segment.is_err() {
+            // stop at the first missing or corrupted segment
+            indexes = indexes.end..indexes.end;
+        }
+        Some((segment, (indexes, reader)))
+    });
+
+    Ok((manifest, range, segments))
+}
+
+/// Decoded bytes of a range of a media file, the whole file by default
+pub async fn retrieve_media(
+    sk: &str,
+    name: &str,
+    range: Option<Range<u64>>,
+) -> Result<Vec<u8>, CarbonadoError> {
+    let (_, _, segments) = stream_media(sk, name, range).await?;
+    let segments: Vec<Vec<u8>> = segments.try_collect().await?;
+    Ok(segments.concat())
+}
@@ -15,9 +15,17 @@ use sled::{Config, Db, Mode};
 use tokio::fs;
 use walkdir::WalkDir;
 
+use crate::carbonado::{gc::GcReport, history::is_history};
+
 #[derive(Serialize, Deserialize, Default)]
 pub struct MetricsData {
     bytes: u64,
+    /// Bytes of the previous versions of the files, see `carbonado::history`
+    #[serde(default)]
+    history_bytes: u64,
+    /// Files removed by the last garbage collection, see `carbonado::gc`
+    #[serde(default)]
+    last_gc: Option<GcReport>,
     bytes_by_day: BTreeMap<String, u64>,
     bitcoin_wallets_by_day: BTreeMap<String, usize>,
     signet_wallets_by_day: BTreeMap<String, usize>,
@@ -47,6 +55,9 @@ const NETWORK_TOTAL: &str = "total";
 const NETWORK_RGB_STOCKS: &str = "rgb_stocks";
 const NETWORK_RGB_TRANSFER_FILES: &str = "rgb_transfer_files";
 
+/// Serializes the updates of `metrics.json`
+static METRICS_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
 const DB_PATHS: &str = "PATHS";
 const DB_DAYS: &str = "DAYS";
 
@@ -104,6 +115,13 @@ pub async fn init(dir: &Path) -> Result<()> {
         let entry = entry?;
         let filename = entry.file_name().to_string_lossy().to_string();
         let metadata = entry.metadata()?;
+
+        if is_history(entry.path()) {
+            if metadata.is_file() {
+                metrics.history_bytes += metadata.len();
+            }
+            continue;
+        }
         let day_created = metadata.created()?;
         let day = round_datetime_to_day(day_created.into());
 
@@ -317,6 +335,16 @@ pub async fn update(path: &Path) -> Result<()> {
         .to_string_lossy()
         .to_string();
     let metadata = path.metadata()?;
+    let dir = env::var("CARBONADO_DIR").unwrap_or("/tmp/bitmaskd/carbonado".to_owned());
+    let mut metrics: MetricsData =
+        serde_json::from_str(&fs::read_to_string(format!("{dir}/metrics.json")).await?)?;
+
+    if is_history(path) {
+        metrics.history_bytes += metadata.len();
+        fs::write(&format!("{dir}/metrics.json"), &json(&metrics).await?).await?;
+        return Ok(());
+    }
+
     let day_created = metadata.created()?;
     let day_prior = day_created
         .checked_sub(Duration::days(1).to_std()?)
@@ -337,10 +365,6 @@ pub async fn update(path: &Path) -> Result<()> {
         true
     };
 
-    let dir = env::var("CARBONADO_DIR").unwrap_or("/tmp/bitmaskd/carbonado".to_owned());
-    let mut metrics: MetricsData =
-        serde_json::from_str(&fs::read_to_string(format!("{dir}/metrics.json")).await?)?;
-
     if metadata.is_file() {
         if first_of_day {
             let bytes_day_prior = {
@@ -501,6 +525,42 @@ pub async fn update(path: &Path) -> Result<()> {
     Ok(())
 }
 
+pub async fn record_gc(report: GcReport) -> Result<()> {
+    let _lock = METRICS_LOCK.lock().await;
+    let dir = env::var("CARBONADO_DIR").unwrap_or("/tmp/bitmaskd/carbonado".to_owned());
+    let mut metrics: MetricsData =
+        serde_json::from_str(&fs::read_to_string(format!("{dir}/metrics.json")).await?)?;
+
+    metrics.last_gc = Some(report);
+    fs::write(&format!("{dir}/metrics.json"), &json(&metrics).await?).await?;
+
+    Ok(())
+}
+
+/// Count the bytes archived to the history and the ones removed from it
+///
+/// Nothing is recorded until [`init`] writes the metrics.
+pub async fn record_history(added: u64, removed: u64) -> Result<()> {
+    if added == 0 && removed == 0 {
+        return Ok(());
+    }
+
+    let _lock = METRICS_LOCK.lock().await;
+    let dir = env::var("CARBONADO_DIR").unwrap_or("/tmp/bitmaskd/carbonado".to_owned());
+    let Ok(data) = fs::read_to_string(format!("{dir}/metrics.json")).await else {
+        return Ok(());
+    };
+    let mut metrics: MetricsData = serde_json::from_str(&data)?;
+
+    metrics.history_bytes = metrics
+        .history_bytes
+        .saturating_add(added)
+        .saturating_sub(removed);
+    fs::write(&format!("{dir}/metrics.json"), &json(&metrics).await?).await?;
+
+    Ok(())
+}
+
 pub async fn csv(metrics: &MetricsData) -> String {
     let mut lines = vec![vec![
         "Wallet".to_owned(),
@@ -0,0 +1,174 @@
+#![cfg(not(target_arch = "wasm32"))]
+use std::{collections::BTreeMap, path::Path, str::FromStr};
+
+use rgbstd::stl::LIB_ID_RGB;
+use walkdir::WalkDir;
+
+use crate::{
+    carbonado::{error::CarbonadoError, history},
+    constants::{
+        storage_keys::{ASSETS_STOCK, ASSETS_TRANSFERS},
+        CARBONADO_FILE_LIMITS, CARBONADO_QUOTA,
+    },
+};
+
+/// Kind of a carbonado file, each one with its own size limit
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq, PartialOrd, Ord)]
+#[display(lowercase)]
+pub enum StorageCategory {
+    Stock,
+    Media,
+    Consignments,
+    Auctions,
+    Other,
+}
+
+impl FromStr for StorageCategory {
+    type Err = CarbonadoError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "stock" => Ok(StorageCategory::Stock),
+            "media" => Ok(StorageCategory::Media),
+            "consignments" => Ok(StorageCategory::Consignments),
+            "auctions" => Ok(StorageCategory::Auctions),
+            "other" => Ok(StorageCategory::Other),
+            _ => Err(CarbonadoError::WrongStorageLimits(value.to_owned())),
+        }
+    }
+}
+
+/// Name of an RGB file, as `rgb::carbonado` stores it
+fn rgb_file_name(name: &str) -> String {
+    blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase()
+}
+
+impl StorageCategory {
+    /// Category of a file written to `/carbonado/{pk}/{name}`
+    ///
+    /// Auction files are known by their route, see `/auction/{bundle_id}/{name}`.
+    pub fn of(name: &str) -> Self {
+        let name = ["bitcoin-", "testnet-", "signet-", "regtest-"]
+            .into_iter()
+            .find_map(|network| name.strip_prefix(network))
+            .unwrap_or(name);
+
+        // the stock is a manifest with its chunks, `{stock}-{chunk}.c15`
+        if name.starts_with(&rgb_file_name(ASSETS_STOCK)) {
+            StorageCategory::Stock
+        } else if name.starts_with(&rgb_file_name(ASSETS_TRANSFERS)) {
+            StorageCategory::Consignments
+        } else if name.contains("-segment-") {
+            StorageCategory::Media
+        } else {
+            StorageCategory::Other
+        }
+    }
+}
+
+/// Storage limits of bitmaskd, in bytes, 0 for no limit
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StorageLimits {
+    /// Bytes of all the files of an owner, the previous versions included
+    pub quota: u64,
+    /// Bytes of a single file, by category
+    pub files: BTreeMap<StorageCategory, u64>,
+}
+
+impl StorageLimits {
+    /// Parse a quota and the file limits, as `stock=104857600,media=524288000`
+    pub fn parse(quota: &str, files: &str) -> Result<Self, CarbonadoError> {
+        let quota = quota
+            .trim()
+            .parse()
+            .map_err(|_| CarbonadoError::WrongStorageLimits(quota.to_owned()))?;
+
+        let mut limits = BTreeMap::new();
+        for limit in files.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+            let (category, bytes) = limit
+                .split_once('=')
+                .ok_or(CarbonadoError::WrongStorageLimits(limit.to_owned()))?;
+            let bytes = bytes
+                .trim()
+                .parse()
+                .map_err(|_| CarbonadoError::WrongStorageLimits(limit.to_owned()))?;
+            limits.insert(StorageCategory::from_str(category)?, bytes);
+        }
+
+        Ok(Self {
+            quota,
+            files: limits,
+        })
+    }
+
+    /// Limits read from `CARBONADO_QUOTA` and `CARBONADO_FILE_LIMITS`
+    pub async fn current() -> Result<Self, CarbonadoError> {
+        Self::parse(
+            &CARBONADO_QUOTA.read().await,
+            &CARBONADO_FILE_LIMITS.read().await,
+        )
+    }
+
+    /// Check a write of `len` bytes over a file of `present` bytes, by an owner using `usage` bytes
+    pub fn check(
+        &self,
+        category: StorageCategory,
+        len: u64,
+        present: u64,
+        usage: u64,
+    ) -> Result<(), CarbonadoError> {
+        match self.files.get(&category) {
+            Some(limit) if *limit > 0 && len > *limit => {
+                return Err(CarbonadoError::FileTooLarge(category.to_string(), *limit));
+            }
+            _ => {}
+        }
+
+        let usage = usage.saturating_sub(present).saturating_add(len);
+        if self.quota > 0 && usage > self.quota {
+            return Err(CarbonadoError::QuotaExceeded(self.quota));
+        }
+        Ok(())
+    }
+}
+
+/// Bytes of the files in a directory, the previous versions included
+///
+/// The directory is walked on the blocking pool, so it does not stall the
+/// async workers.
+pub async fn usage(dir: &Path) -> Result<u64, CarbonadoError> {
+    let dir = dir.to_owned();
+    let usage = tokio::task::spawn_blocking(move || {
+        WalkDir::new(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.metadata().ok())
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len())
+            .sum()
+    })
+    .await
+    .map_err(std::io::Error::from)?;
+    Ok(usage)
+}
+
+/// Check a write of `len` bytes to `filepath`, against the quota of the directory of the file
+///
+/// The usage counts the present version the write moves to the history.
+pub async fn check_write(
+    filepath: &Path,
+    category: StorageCategory,
+    len: u64,
+) -> Result<(), CarbonadoError> {
+    let limits = StorageLimits::current().await?;
+    let present = filepath.metadata().map(|x| x.len()).unwrap_or_default();
+    let usage = match filepath.parent() {
+        Some(dir) => usage(dir).await?,
+        None => 0,
+    };
+    let (archived, removed) = history::archive_growth(filepath).await?;
+    let usage = usage.saturating_add(archived).saturating_sub(removed);
+    limits.check(category, len, present, usage)
+}
@@ -0,0 +1,169 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use carbonado::file::Header;
+
+use crate::{
+    carbonado::{error::CarbonadoError, versions::file_version},
+    warn,
+};
+
+/// Number of carbonado endpoints which must store a file for the write to succeed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WriteQuorum {
+    /// Every endpoint
+    All,
+    /// More than half of the endpoints
+    #[default]
+    Majority,
+    /// At least this many endpoints (capped to the number of endpoints)
+    Count(usize),
+}
+
+impl FromStr for WriteQuorum {
+    type Err = CarbonadoError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "majority" => Ok(WriteQuorum::Majority),
+            "all" => Ok(WriteQuorum::All),
+            count => match count.parse::<usize>() {
+                Ok(count) if count > 0 => Ok(WriteQuorum::Count(count)),
+                _ => Err(CarbonadoError::WrongWriteQuorum(value.to_owned())),
+            },
+        }
+    }
+}
+
+impl WriteQuorum {
+    /// Successful writes required with `endpoints` endpoints
+    pub fn required(&self, endpoints: usize) -> usize {
+        let required = match self {
+            WriteQuorum::All => endpoints,
+            WriteQuorum::Majority => endpoints / 2 + 1,
+            WriteQuorum::Count(count) => *count,
+        };
+        required.clamp(1, endpoints.max(1))
+    }
+
+    pub fn check(&self, succeeded: usize, endpoints: usize) -> Result<(), CarbonadoError> {
+        let required = self.required(endpoints);
+        match succeeded {
+            0 => Err(CarbonadoError::AllEndpointsFailed),
+            succeeded if succeeded < required => {
+                Err(CarbonadoError::WriteQuorumFailed(succeeded, required))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Endpoints of a comma-separated list (`CARBONADO_ENDPOINT`)
+pub fn split_endpoints(endpoints: &str) -> Vec<String> {
+    endpoints
+        .split(',')
+        .map(str::trim)
+        .filter(|endpoint| !endpoint.is_empty())
+        .map(|endpoint| endpoint.trim_end_matches('/').to_owned())
+        .collect()
+}
+
+/// Copy of a file chosen among the replicas of the endpoints
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelectedReplica {
+    /// Index of the endpoint which returned the copy
+    pub endpoint: usize,
+    /// Encoded file, to repair the lagging replicas
+    pub encoded: Vec<u8>,
+    pub decoded: Vec<u8>,
+    pub metadata: Option<Vec<u8>>,
+    /// Endpoints with a missing, invalid or other copy, with the version of
+    /// their copy (`None` when missing). Only filled when most endpoints have
+    /// the selected copy, so a repair never replaces a copy without a majority.
+    pub lagging: Vec<(usize, Option<String>)>,
+}
+
+struct ValidReplica {
+    endpoint: usize,
+    version: String,
+    decoded: Vec<u8>,
+    metadata: Option<Vec<u8>>,
+}
+
+/// Pick a valid copy of a file, among the responses of the endpoints
+///
+/// Every copy is decoded, which verifies its Bao hash. The copy returned by
+/// the most endpoints wins; on a tie, the `known` version (the one this
+/// process wrote or read last), then the first endpoint. `None` when no
+/// endpoint returned a valid copy.
+pub fn select_replica(
+    sk: &[u8],
+    responses: &[Option<Vec<u8>>],
+    known: Option<&str>,
+) -> Option<SelectedReplica> {
+    let mut replicas = vec![];
+    for (endpoint, response) in responses.iter().enumerate() {
+        let Some(encoded) = response
+            .as_ref()
+            .filter(|bytes| bytes.len() > Header::len())
+        else {
+            continue;
+        };
+
+        match carbonado::file::decode(sk, encoded) {
+            Ok((header, decoded)) => replicas.push(ValidReplica {
+                endpoint,
+                version: file_version(encoded),
+                decoded,
+                metadata: header.metadata.map(|m| m.to_vec()),
+            }),
+            Err(err) => warn!(format!(
+                "Invalid carbonado replica on endpoint {endpoint}: {err}"
+            )),
+        }
+    }
+
+    let mut copies: BTreeMap<&str, usize> = BTreeMap::new();
+    for replica in &replicas {
+        *copies.entry(&replica.version).or_default() += 1;
+    }
+
+    let selected = replicas.iter().max_by(|a, b| {
+        let rank = |replica: &ValidReplica| {
+            (
+                copies[replica.version.as_str()],
+                Some(replica.version.as_str()) == known,
+            )
+        };
+        rank(a)
+            .cmp(&rank(b))
+            // the first endpoint wins a tie
+            .then(b.endpoint.cmp(&a.endpoint))
+    })?;
+
+    let majority = copies[selected.version.as_str()] > responses.len() / 2;
+    let lagging = match majority {
+        true => responses
+            .iter()
+            .enumerate()
+            .filter_map(|(endpoint, response)| {
+                let version = response
+                    .as_ref()
+                    .filter(|bytes| !bytes.is_empty())
+                    .map(|bytes| file_version(bytes));
+                match version {
+                    Some(version) if version == selected.version => None,
+                    version => Some((endpoint, version)),
+                }
+            })
+            .collect(),
+        false => vec![],
+    };
+
+    Some(SelectedReplica {
+        endpoint: selected.endpoint,
+        encoded: responses[selected.endpoint].clone().unwrap_or_default(),
+        decoded: selected.decoded.clone(),
+        metadata: selected.metadata.clone(),
+        lagging,
+    })
+}
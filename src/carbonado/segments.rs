@@ -0,0 +1,102 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::carbonado::{error::CarbonadoError, versions::file_version};
+
+/// Media files are encoded in segments of this size, so they can be
+/// retrieved by range without loading the whole file
+pub const MEDIA_SEGMENT_SIZE: usize = 1024 * 1024;
+
+/// Metadata of the manifest of a file stored in segments
+pub const MEDIA_SEGMENTS_VERSION: [u8; 8] = *b"cbsegm01";
+
+/// Segments of a media file, in order
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentManifest {
+    pub segment_size: u64,
+    pub len: u64,
+    /// Versions (see `carbonado::versions`) of the encoded segments
+    pub segments: Vec<String>,
+}
+
+impl SegmentManifest {
+    pub fn new(segment_size: usize) -> Self {
+        Self {
+            segment_size: segment_size as u64,
+            ..Default::default()
+        }
+    }
+
+    pub fn segment_name(name: &str, index: usize) -> String {
+        format!("{name}-segment-{index}")
+    }
+
+    /// Add an encoded segment of `len` bytes
+    pub fn push(&mut self, encoded: &[u8], len: usize) {
+        self.segments.push(file_version(encoded));
+        self.len += len as u64;
+    }
+
+    /// Byte range clamped to the file, the whole file by default
+    pub fn clamp(&self, range: Option<Range<u64>>) -> Range<u64> {
+        let range = range.unwrap_or(0..self.len);
+        range.start.min(self.len)..range.end.min(self.len)
+    }
+
+    /// Segments holding a byte range
+    pub fn segments_for(&self, range: &Range<u64>) -> Range<usize> {
+        if range.is_empty() || self.segment_size == 0 {
+            return 0..0;
+        }
+        let first = range.start / self.segment_size;
+        let last = (range.end - 1) / self.segment_size;
+        first as usize..last as usize + 1
+    }
+
+    /// Part of a decoded segment within a byte range
+    pub fn slice<'a>(&self, index: usize, segment: &'a [u8], range: &Range<u64>) -> &'a [u8] {
+        let offset = index as u64 * self.segment_size;
+        let start = range.start.saturating_sub(offset).min(segment.len() as u64);
+        let end = (range.end - offset).min(segment.len() as u64).max(start);
+        &segment[start as usize..end as usize]
+    }
+
+    /// Decode a segment, checking it is the one of the manifest
+    ///
+    /// Each segment is a carbonado file of its own, so decoding it verifies
+    /// its Bao hash without the other segments.
+    pub fn decode(
+        &self,
+        sk: &[u8],
+        index: usize,
+        encoded: &[u8],
+    ) -> Result<Vec<u8>, CarbonadoError> {
+        if self.segments.get(index) != Some(&file_version(encoded)) {
+            return Err(CarbonadoError::SegmentCorrupted(index));
+        }
+
+        let (_, decoded) = carbonado::file::decode(sk, encoded)?;
+        Ok(decoded)
+    }
+}
+
+/// Byte range of an HTTP `Range` header (`bytes=0-99`, `bytes=100-`, `bytes=-100`)
+///
+/// `None` when the header is not a single satisfiable range of a file of `len` bytes.
+pub fn parse_range(header: &str, len: u64) -> Option<Range<u64>> {
+    let (start, end) = header.trim().strip_prefix("bytes=")?.split_once('-')?;
+    let range = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            len.saturating_sub(suffix)..len
+        }
+        (start, "") => start.parse().ok()?..len,
+        (start, end) => {
+            let end: u64 = end.parse().ok()?;
+            start.parse().ok()?..end.saturating_add(1).min(len)
+        }
+    };
+
+    (range.start < range.end).then_some(range)
+}
@@ -0,0 +1,213 @@
+use std::{collections::BTreeMap, sync::Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use once_cell::sync::Lazy;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::{fs, sync::OwnedMutexGuard};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::carbonado::{error::CarbonadoError, history};
+
+/// Header of a conditional carbonado write, with the version the client
+/// retrieved last
+pub const IF_MATCH_HEADER: &str = "If-Match";
+
+/// Version of an encoded carbonado file, the Blake3 hash of its bytes
+///
+/// Servers store the body of a write as is, so clients know the version of
+/// what they wrote without asking.
+pub fn file_version(encoded: &[u8]) -> String {
+    blake3::hash(encoded).to_hex().to_lowercase()
+}
+
+/// Whether a conditional write expecting `expected` can replace `present`
+///
+/// Unconditional writes always can, and so can any write to a missing file
+/// since there is nothing to lose.
+pub fn version_matches(expected: Option<&str>, present: Option<&[u8]>) -> bool {
+    match (expected, present) {
+        (Some(expected), Some(present)) => file_version(present) == expected,
+        _ => true,
+    }
+}
+
+/// Last version of each file retrieved or stored by this process, by owner and name
+static KNOWN_VERSIONS: Lazy<Mutex<BTreeMap<String, String>>> = Lazy::new(Default::default);
+
+fn version_key(pk: &str, name: &str) -> String {
+    format!("{pk}/{name}")
+}
+
+/// Browsers keep the known versions in the local storage, so they outlive the page
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage_key(key: &str) -> String {
+    format!("bitmask-version:{key}")
+}
+
+pub fn known_version(pk: &str, name: &str) -> Option<String> {
+    let key = version_key(pk, name);
+    let version = KNOWN_VERSIONS
+        .lock()
+        .expect("known versions poisoned")
+        .get(&key)
+        .cloned();
+
+    #[cfg(target_arch = "wasm32")]
+    if version.is_none() {
+        return local_storage()?.get_item(&local_storage_key(&key)).ok()?;
+    }
+
+    version
+}
+
+pub fn remember_version(pk: &str, name: &str, encoded: &[u8]) {
+    let key = version_key(pk, name);
+    let version = file_version(encoded);
+
+    #[cfg(target_arch = "wasm32")]
+    if let Some(storage) = local_storage() {
+        let _ = storage.set_item(&local_storage_key(&key), &version);
+    }
+
+    KNOWN_VERSIONS
+        .lock()
+        .expect("known versions poisoned")
+        .insert(key, version);
+}
+
+/// Forget the versions of an owner, its next writes are unconditional
+pub fn forget_versions(pk: &str) {
+    let prefix = format!("{pk}/");
+    KNOWN_VERSIONS
+        .lock()
+        .expect("known versions poisoned")
+        .retain(|key, _| !key.starts_with(&prefix));
+
+    #[cfg(target_arch = "wasm32")]
+    if let Some(storage) = local_storage() {
+        let prefix = local_storage_key(&prefix);
+        let keys: Vec<String> = (0..storage.length().unwrap_or_default())
+            .filter_map(|index| storage.key(index).ok().flatten())
+            .filter(|key| key.starts_with(&prefix))
+            .collect();
+        for key in keys {
+            let _ = storage.remove_item(&key);
+        }
+    }
+}
+
+/// File of an owner directory with the versions known by this process, so
+/// the conditional writes of the owner hold across restarts
+#[cfg(not(target_arch = "wasm32"))]
+pub const VERSIONS_FILE: &str = ".versions";
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn persisted_versions(dir: &Path) -> BTreeMap<String, String> {
+    match fs::read(dir.join(VERSIONS_FILE)).await {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+/// Version of the file at `filepath` known by this process, or by the previous ones
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn recall_version(filepath: &Path, pk: &str, name: &str) -> Option<String> {
+    if let Some(version) = known_version(pk, name) {
+        return Some(version);
+    }
+
+    let version = persisted_versions(filepath.parent()?).await.remove(name)?;
+    KNOWN_VERSIONS
+        .lock()
+        .expect("known versions poisoned")
+        .insert(version_key(pk, name), version.clone());
+    Some(version)
+}
+
+/// Remember the version of the file at `filepath`, and persist it next to the file
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn persist_version(
+    filepath: &Path,
+    pk: &str,
+    name: &str,
+    encoded: &[u8],
+) -> Result<(), CarbonadoError> {
+    let version = file_version(encoded);
+    if known_version(pk, name).as_ref() == Some(&version) {
+        return Ok(());
+    }
+    remember_version(pk, name, encoded);
+
+    let Some(dir) = filepath.parent() else {
+        return Ok(());
+    };
+    let versions_path = dir.join(VERSIONS_FILE);
+    let _lock = lock_file(&versions_path).await;
+    let mut versions = persisted_versions(dir).await;
+    versions.insert(name.to_owned(), version);
+    replace_file(&versions_path, &serde_json::to_vec(&versions)?).await
+}
+
+/// Locks of the files being written, by path
+#[cfg(not(target_arch = "wasm32"))]
+static FILE_LOCKS: Lazy<Mutex<BTreeMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>> =
+    Lazy::new(Default::default);
+
+/// Lock the file at `filepath` until the guard is dropped, so its writes don't interleave
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn lock_file(filepath: &Path) -> OwnedMutexGuard<()> {
+    let lock = {
+        let mut locks = FILE_LOCKS.lock().expect("file locks poisoned");
+        // drop the locks nobody holds or waits for
+        locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+        locks.entry(filepath.to_path_buf()).or_default().clone()
+    };
+    lock.lock_owned().await
+}
+
+/// Replace the file at `filepath` with `body` through a temporary file, so
+/// readers never see a partial write
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn replace_file(filepath: &Path, body: &[u8]) -> Result<(), CarbonadoError> {
+    let mut tmp = filepath.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, body).await?;
+    fs::rename(&tmp, filepath).await?;
+    Ok(())
+}
+
+/// Write `body` to `filepath` when the present file is at version `expected`
+///
+/// The version is read from the present file, and the check and the write
+/// hold the lock of the file, so two writes over the same version can't both
+/// succeed. The replaced version is kept in the history. Returns whether the
+/// file was written.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn write_if_matches(
+    filepath: &Path,
+    expected: Option<&str>,
+    body: &[u8],
+) -> Result<bool, CarbonadoError> {
+    let _lock = lock_file(filepath).await;
+    // bitmaskd creates an empty file when the metadata of a missing one is read
+    let present = fs::read(filepath)
+        .await
+        .ok()
+        .filter(|present| !present.is_empty());
+    if !version_matches(expected, present.as_deref()) {
+        return Ok(false);
+    }
+
+    history::archive(filepath).await?;
+    replace_file(filepath, body).await?;
+    Ok(true)
+}
@@ -0,0 +1,944 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::{Arc, Mutex};
+use std::{collections::BTreeMap, future::Future};
+
+use async_trait::async_trait;
+use bdk::esplora_client::{AsyncClient, Builder};
+use bitcoin::{BlockHash, Script, Transaction, Txid};
+use once_cell::sync::Lazy;
+use thiserror::Error;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::constants::{
+    BITCOIN_ELECTRUM_API, BITCOIN_RPC_API, BITCOIN_RPC_PASSWORD, BITCOIN_RPC_USER,
+};
+use crate::{
+    constants::{BITCOIN_BACKEND, BITCOIN_EXPLORER_API},
+    structs::TxStatus,
+    util::MaybeSendSync,
+};
+
+mod cache;
+mod mock;
+mod wallet;
+
+pub use cache::{
+    merge_resolver_cache, resolver_account, resolver_cache_metrics, resolver_cache_snapshot,
+    with_resolver_cache, CachedBackend, ResolverCache, RESOLVER_CACHE,
+};
+pub use mock::MockBackend;
+pub use wallet::ChainBlockchain;
+
+#[derive(Error, Debug)]
+pub enum ChainError {
+    /// Esplora error
+    #[error("Esplora error: {0}")]
+    Esplora(String),
+    /// Electrum error
+    #[error("Electrum error: {0}")]
+    Electrum(String),
+    /// Bitcoin Core RPC error
+    #[error("Bitcoin Core RPC error: {0}")]
+    Bitcoind(String),
+    /// Transaction rejected by the mock chain
+    #[error("Transaction rejected: {0}")]
+    Rejected(String),
+    /// Backend not available in this target
+    #[error("Blockchain backend {0} is not supported")]
+    UnsupportedBackend(String),
+}
+
+/// Fee rate (sat/vB) used when a backend has no estimate
+pub const MIN_RELAY_FEE: f32 = 1.0;
+
+/// Spending status of a transaction output
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputStatus {
+    pub spent: bool,
+    /// Spending transaction, when the backend knows it
+    pub txid: Option<Txid>,
+    /// Status of the spending transaction
+    pub status: TxStatus,
+}
+
+/// Transaction related to a script, with its mining status
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScriptTx {
+    pub tx: Transaction,
+    pub status: TxStatus,
+}
+
+/// Blockchain data source shared by the RGB resolvers, the prefetch
+/// functions and the BDK wallets
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait ChainBackend: MaybeSendSync {
+    /// Get a transaction, `None` when the backend doesn't know it
+    async fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>, ChainError>;
+
+    /// Get the mining status of a transaction (`TxStatus::NotFound` when unknown)
+    async fn get_tx_status(&self, txid: &Txid) -> Result<TxStatus, ChainError>;
+
+    /// Get the spending status of an output, `None` when the output doesn't exist
+    async fn get_output_status(
+        &self,
+        txid: &Txid,
+        vout: u32,
+    ) -> Result<Option<OutputStatus>, ChainError>;
+
+    /// Get the transactions paying to a script
+    async fn get_script_txs(&self, script: &Script) -> Result<Vec<ScriptTx>, ChainError>;
+
+    /// Get the height of the chain tip
+    async fn get_height(&self) -> Result<u32, ChainError>;
+
+    /// Get the hash of the block at a height, `None` above the chain tip
+    async fn get_block_hash(&self, height: u32) -> Result<Option<BlockHash>, ChainError>;
+
+    /// Get the timestamp of the block at a height, `None` above the chain tip
+    async fn get_block_time(&self, height: u32) -> Result<Option<u64>, ChainError>;
+
+    /// Estimate the fee rate (sat/vB) to confirm in `target` blocks
+    async fn estimate_fee(&self, target: usize) -> Result<f32, ChainError>;
+
+    /// Broadcast a transaction
+    async fn broadcast(&self, tx: &Transaction) -> Result<(), ChainError>;
+
+    /// Whether [`ChainBackend::get_script_txs`] returns the spent transactions
+    /// too, and not only the ones with unspent outputs
+    fn full_history(&self) -> bool {
+        true
+    }
+
+    /// Forget what is cached about a reorganized transaction
+    fn forget(&self, _txid: &Txid) {}
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T: ChainBackend + ?Sized> ChainBackend for Box<T> {
+    async fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>, ChainError> {
+        (**self).get_tx(txid).await
+    }
+
+    async fn get_tx_status(&self, txid: &Txid) -> Result<TxStatus, ChainError> {
+        (**self).get_tx_status(txid).await
+    }
+
+    async fn get_output_status(
+        &self,
+        txid: &Txid,
+        vout: u32,
+    ) -> Result<Option<OutputStatus>, ChainError> {
+        (**self).get_output_status(txid, vout).await
+    }
+
+    async fn get_script_txs(&self, script: &Script) -> Result<Vec<ScriptTx>, ChainError> {
+        (**self).get_script_txs(script).await
+    }
+
+    async fn get_height(&self) -> Result<u32, ChainError> {
+        (**self).get_height().await
+    }
+
+    async fn get_block_hash(&self, height: u32) -> Result<Option<BlockHash>, ChainError> {
+        (**self).get_block_hash(height).await
+    }
+
+    async fn get_block_time(&self, height: u32) -> Result<Option<u64>, ChainError> {
+        (**self).get_block_time(height).await
+    }
+
+    async fn estimate_fee(&self, target: usize) -> Result<f32, ChainError> {
+        (**self).estimate_fee(target).await
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<(), ChainError> {
+        (**self).broadcast(tx).await
+    }
+
+    fn full_history(&self) -> bool {
+        (**self).full_history()
+    }
+
+    fn forget(&self, txid: &Txid) {
+        (**self).forget(txid)
+    }
+}
+
+fn esplora_err(err: bdk::esplora_client::Error) -> ChainError {
+    ChainError::Esplora(err.to_string())
+}
+
+fn esplora_status(status: &bdk::esplora_client::TxStatus) -> TxStatus {
+    if status.confirmed {
+        TxStatus::Block(status.block_height.unwrap_or_default())
+    } else {
+        TxStatus::Mempool
+    }
+}
+
+/// Backend of an Esplora HTTP API (blockstream.info, mempool.space, electrs)
+pub struct EsploraBackend {
+    client: AsyncClient,
+}
+
+impl EsploraBackend {
+    pub fn new(url: &str) -> Result<Self, ChainError> {
+        let client = Builder::new(url).build_async().map_err(esplora_err)?;
+        Ok(Self { client })
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl ChainBackend for EsploraBackend {
+    async fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>, ChainError> {
+        self.client.get_tx(txid).await.map_err(esplora_err)
+    }
+
+    async fn get_tx_status(&self, txid: &Txid) -> Result<TxStatus, ChainError> {
+        let status = self.client.get_tx_status(txid).await.map_err(esplora_err)?;
+        Ok(match status {
+            Some(status) => esplora_status(&status),
+            None => TxStatus::NotFound,
+        })
+    }
+
+    async fn get_output_status(
+        &self,
+        txid: &Txid,
+        vout: u32,
+    ) -> Result<Option<OutputStatus>, ChainError> {
+        let output_status = self
+            .client
+            .get_output_status(txid, vout as u64)
+            .await
+            .map_err(esplora_err)?;
+
+        Ok(output_status.map(|output_status| {
+            let status = if !output_status.spent && output_status.txid.is_none() {
+                TxStatus::NotFound
+            } else {
+                match &output_status.status {
+                    Some(status) => esplora_status(status),
+                    None => TxStatus::NotFound,
+                }
+            };
+
+            OutputStatus {
+                spent: output_status.spent,
+                txid: output_status.txid,
+                status,
+            }
+        }))
+    }
+
+    async fn get_script_txs(&self, script: &Script) -> Result<Vec<ScriptTx>, ChainError> {
+        let mut related_txs = self
+            .client
+            .scripthash_txs(script, None)
+            .await
+            .map_err(esplora_err)?;
+        let n_confirmed = related_txs.iter().filter(|tx| tx.status.confirmed).count();
+        // esplora pages on 25 confirmed transactions. If there are 25 or more we
+        // keep requesting to see if there's more.
+        if n_confirmed >= 25 {
+            loop {
+                let last_seen = related_txs.last().map(|tx| tx.txid);
+                let new_related_txs = self
+                    .client
+                    .scripthash_txs(script, last_seen)
+                    .await
+                    .map_err(esplora_err)?;
+                let n = new_related_txs.len();
+                related_txs.extend(new_related_txs);
+                // we've reached the end
+                if n < 25 {
+                    break;
+                }
+            }
+        }
+
+        Ok(related_txs
+            .into_iter()
+            .map(|tx| ScriptTx {
+                status: esplora_status(&tx.status),
+                tx: tx.to_tx(),
+            })
+            .collect())
+    }
+
+    async fn get_height(&self) -> Result<u32, ChainError> {
+        self.client.get_height().await.map_err(esplora_err)
+    }
+
+    async fn get_block_hash(&self, height: u32) -> Result<Option<BlockHash>, ChainError> {
+        if height > self.get_height().await? {
+            return Ok(None);
+        }
+
+        let hash = self
+            .client
+            .get_block_hash(height)
+            .await
+            .map_err(esplora_err)?;
+        Ok(Some(hash))
+    }
+
+    async fn get_block_time(&self, height: u32) -> Result<Option<u64>, ChainError> {
+        let Some(hash) = self.get_block_hash(height).await? else {
+            return Ok(None);
+        };
+
+        let header = self
+            .client
+            .get_header_by_hash(&hash)
+            .await
+            .map_err(esplora_err)?;
+        Ok(Some(header.time as u64))
+    }
+
+    /// Esplora estimates by number of blocks, the nearest target below `target` is used
+    async fn estimate_fee(&self, target: usize) -> Result<f32, ChainError> {
+        let estimates = self.client.get_fee_estimates().await.map_err(esplora_err)?;
+        let fee_rate = estimates
+            .into_iter()
+            .filter_map(|(blocks, fee_rate)| Some((blocks.parse::<usize>().ok()?, fee_rate)))
+            .filter(|(blocks, _)| *blocks <= target)
+            .max_by_key(|(blocks, _)| *blocks)
+            .map(|(_, fee_rate)| fee_rate as f32);
+        Ok(fee_rate.unwrap_or(MIN_RELAY_FEE))
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<(), ChainError> {
+        self.client.broadcast(tx).await.map_err(esplora_err)
+    }
+}
+
+/// Run a call of a blocking client on the blocking pool of the tokio runtime,
+/// so it does not stall the async workers, or inline when there is no runtime
+#[cfg(not(target_arch = "wasm32"))]
+async fn run_blocking<C, T, F>(
+    client: &Arc<C>,
+    join_err: fn(String) -> ChainError,
+    call: F,
+) -> Result<T, ChainError>
+where
+    C: Send + Sync + 'static,
+    T: Send + 'static,
+    F: FnOnce(&C) -> Result<T, ChainError> + Send + 'static,
+{
+    let client = client.clone();
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => handle
+            .spawn_blocking(move || call(&client))
+            .await
+            .map_err(|err| join_err(err.to_string()))?,
+        Err(_) => call(&client),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn electrum_err(err: electrum_client::Error) -> ChainError {
+    ChainError::Electrum(err.to_string())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn bitcoind_err(err: bitcoincore_rpc::Error) -> ChainError {
+    ChainError::Bitcoind(err.to_string())
+}
+
+/// Run a future from synchronous code, on the tokio runtime when there is one
+///
+/// On a multi-thread runtime the worker is moved off with `block_in_place`,
+/// so the other tasks keep running.
+pub fn block_on_runtime<F: Future>(future: F) -> F::Output {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use tokio::runtime::{Handle, RuntimeFlavor};
+
+        if let Ok(handle) = Handle::try_current() {
+            if handle.runtime_flavor() == RuntimeFlavor::MultiThread {
+                return tokio::task::block_in_place(|| handle.block_on(future));
+            }
+        }
+    }
+    futures::executor::block_on(future)
+}
+
+/// Clients of the Electrum and Bitcoin Core backends, by URL (and credentials)
+///
+/// Connections are built once, every backend of the same server shares them.
+#[cfg(not(target_arch = "wasm32"))]
+static ELECTRUM_BACKENDS: Lazy<Mutex<BTreeMap<String, ElectrumBackend>>> =
+    Lazy::new(Default::default);
+#[cfg(not(target_arch = "wasm32"))]
+static BITCOIND_BACKENDS: Lazy<Mutex<BTreeMap<String, BitcoindBackend>>> =
+    Lazy::new(Default::default);
+
+/// Backend of an Electrum server (electrs, Fulcrum, ElectrumX)
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone)]
+pub struct ElectrumBackend {
+    client: Arc<electrum_client::Client>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ElectrumBackend {
+    pub fn new(url: &str) -> Result<Self, ChainError> {
+        let client = crate::bitcoin::electrum::electrum_client(url).map_err(electrum_err)?;
+        Ok(Self {
+            client: Arc::new(client),
+        })
+    }
+
+    /// Backend of the server at `url`, its client is shared (see [`ElectrumBackend::new`])
+    pub fn shared(url: &str) -> Result<Self, ChainError> {
+        let mut backends = ELECTRUM_BACKENDS
+            .lock()
+            .expect("electrum backends poisoned");
+        if let Some(backend) = backends.get(url) {
+            return Ok(backend.clone());
+        }
+        let backend = Self::new(url)?;
+        backends.insert(url.to_owned(), backend.clone());
+        Ok(backend)
+    }
+
+    async fn call<T, F>(&self, call: F) -> Result<T, ChainError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&electrum_client::Client) -> Result<T, ChainError> + Send + 'static,
+    {
+        run_blocking(&self.client, ChainError::Electrum, call).await
+    }
+
+    fn get_tx(
+        client: &electrum_client::Client,
+        txid: &Txid,
+    ) -> Result<Option<Transaction>, ChainError> {
+        crate::bitcoin::electrum::electrum_get_tx(client, txid).map_err(electrum_err)
+    }
+
+    fn history_status(height: i32) -> TxStatus {
+        // Electrum reports mempool transactions with height 0 or -1
+        if height > 0 {
+            TxStatus::Block(height as u32)
+        } else {
+            TxStatus::Mempool
+        }
+    }
+
+    /// Electrum has no txid index, so the status comes from the history of an output script
+    fn tx_status(client: &electrum_client::Client, txid: &Txid) -> Result<TxStatus, ChainError> {
+        use electrum_client::ElectrumApi;
+
+        let Some(tx) = Self::get_tx(client, txid)? else {
+            return Ok(TxStatus::NotFound);
+        };
+        let Some(output) = tx.output.first() else {
+            return Ok(TxStatus::NotFound);
+        };
+
+        let history = client
+            .script_get_history(&output.script_pubkey)
+            .map_err(electrum_err)?;
+
+        Ok(history
+            .into_iter()
+            .find(|item| &item.tx_hash == txid)
+            .map(|item| Self::history_status(item.height))
+            .unwrap_or(TxStatus::NotFound))
+    }
+
+    /// The spending transaction is in the history of the output script
+    fn output_status(
+        client: &electrum_client::Client,
+        txid: &Txid,
+        vout: u32,
+    ) -> Result<Option<OutputStatus>, ChainError> {
+        use electrum_client::ElectrumApi;
+
+        let output = match Self::get_tx(client, txid)? {
+            Some(tx) => tx.output.get(vout as usize).cloned(),
+            None => None,
+        };
+        let Some(output) = output else {
+            return Ok(None);
+        };
+
+        let history = client
+            .script_get_history(&output.script_pubkey)
+            .map_err(electrum_err)?;
+
+        for item in history.into_iter().filter(|item| &item.tx_hash != txid) {
+            let Some(tx) = Self::get_tx(client, &item.tx_hash)? else {
+                continue;
+            };
+            let spends = tx.input.iter().any(|input| {
+                &input.previous_output.txid == txid && input.previous_output.vout == vout
+            });
+            if spends {
+                return Ok(Some(OutputStatus {
+                    spent: true,
+                    txid: Some(item.tx_hash),
+                    status: Self::history_status(item.height),
+                }));
+            }
+        }
+
+        Ok(Some(OutputStatus {
+            spent: false,
+            txid: None,
+            status: TxStatus::NotFound,
+        }))
+    }
+
+    fn script_txs(
+        client: &electrum_client::Client,
+        script: &Script,
+    ) -> Result<Vec<ScriptTx>, ChainError> {
+        use electrum_client::ElectrumApi;
+
+        let history = client.script_get_history(script).map_err(electrum_err)?;
+
+        let mut txs = vec![];
+        for item in history {
+            if let Some(tx) = Self::get_tx(client, &item.tx_hash)? {
+                txs.push(ScriptTx {
+                    tx,
+                    status: Self::history_status(item.height),
+                });
+            }
+        }
+        Ok(txs)
+    }
+
+    fn height(client: &electrum_client::Client) -> Result<u32, ChainError> {
+        use electrum_client::ElectrumApi;
+
+        let tip = client.block_headers_subscribe().map_err(electrum_err)?;
+        Ok(tip.height as u32)
+    }
+
+    fn header(
+        client: &electrum_client::Client,
+        height: u32,
+    ) -> Result<Option<bitcoin::BlockHeader>, ChainError> {
+        use electrum_client::ElectrumApi;
+
+        if height > Self::height(client)? {
+            return Ok(None);
+        }
+
+        let header = client.block_header(height as usize).map_err(electrum_err)?;
+        Ok(Some(header))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl ChainBackend for ElectrumBackend {
+    async fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>, ChainError> {
+        let txid = *txid;
+        self.call(move |client| Self::get_tx(client, &txid)).await
+    }
+
+    async fn get_tx_status(&self, txid: &Txid) -> Result<TxStatus, ChainError> {
+        let txid = *txid;
+        self.call(move |client| Self::tx_status(client, &txid))
+            .await
+    }
+
+    async fn get_output_status(
+        &self,
+        txid: &Txid,
+        vout: u32,
+    ) -> Result<Option<OutputStatus>, ChainError> {
+        let txid = *txid;
+        self.call(move |client| Self::output_status(client, &txid, vout))
+            .await
+    }
+
+    async fn get_script_txs(&self, script: &Script) -> Result<Vec<ScriptTx>, ChainError> {
+        let script = script.clone();
+        self.call(move |client| Self::script_txs(client, &script))
+            .await
+    }
+
+    async fn get_height(&self) -> Result<u32, ChainError> {
+        self.call(Self::height).await
+    }
+
+    async fn get_block_hash(&self, height: u32) -> Result<Option<BlockHash>, ChainError> {
+        let header = self
+            .call(move |client| Self::header(client, height))
+            .await?;
+        Ok(header.map(|header| header.block_hash()))
+    }
+
+    async fn get_block_time(&self, height: u32) -> Result<Option<u64>, ChainError> {
+        let header = self
+            .call(move |client| Self::header(client, height))
+            .await?;
+        Ok(header.map(|header| header.time as u64))
+    }
+
+    /// Electrum estimates in BTC/kvB, and -1 when the server has no estimate
+    async fn estimate_fee(&self, target: usize) -> Result<f32, ChainError> {
+        use electrum_client::ElectrumApi;
+
+        let fee_rate = self
+            .call(move |client| client.estimate_fee(target).map_err(electrum_err))
+            .await?;
+        if fee_rate <= 0.0 {
+            return Ok(MIN_RELAY_FEE);
+        }
+        Ok((fee_rate * 100_000.0) as f32)
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<(), ChainError> {
+        use electrum_client::ElectrumApi;
+
+        let tx = tx.clone();
+        self.call(move |client| {
+            client.transaction_broadcast(&tx).map_err(electrum_err)?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Backend of a Bitcoin Core node (requires `txindex=1`)
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone)]
+pub struct BitcoindBackend {
+    client: Arc<bitcoincore_rpc::Client>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl BitcoindBackend {
+    pub fn new(url: &str, user: &str, password: &str) -> Result<Self, ChainError> {
+        let client =
+            crate::bitcoin::bitcoind::bitcoind_client(url, user, password).map_err(bitcoind_err)?;
+        Ok(Self {
+            client: Arc::new(client),
+        })
+    }
+
+    /// Backend of the node at `url`, its client is shared (see [`BitcoindBackend::new`])
+    pub fn shared(url: &str, user: &str, password: &str) -> Result<Self, ChainError> {
+        let key = format!("{user}:{password}@{url}");
+        let mut backends = BITCOIND_BACKENDS
+            .lock()
+            .expect("bitcoind backends poisoned");
+        if let Some(backend) = backends.get(&key) {
+            return Ok(backend.clone());
+        }
+        let backend = Self::new(url, user, password)?;
+        backends.insert(key, backend.clone());
+        Ok(backend)
+    }
+
+    async fn call<T, F>(&self, call: F) -> Result<T, ChainError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&bitcoincore_rpc::Client) -> Result<T, ChainError> + Send + 'static,
+    {
+        run_blocking(&self.client, ChainError::Bitcoind, call).await
+    }
+
+    fn get_tx(
+        client: &bitcoincore_rpc::Client,
+        txid: &Txid,
+    ) -> Result<Option<Transaction>, ChainError> {
+        crate::bitcoin::bitcoind::bitcoind_get_tx(client, txid).map_err(bitcoind_err)
+    }
+
+    fn tx_status(client: &bitcoincore_rpc::Client, txid: &Txid) -> Result<TxStatus, ChainError> {
+        if Self::get_tx(client, txid)?.is_none() {
+            return Ok(TxStatus::NotFound);
+        }
+
+        let height =
+            crate::bitcoin::bitcoind::bitcoind_tx_height(client, txid).map_err(bitcoind_err)?;
+        Ok(match height {
+            Some(height) => TxStatus::Block(height),
+            None => TxStatus::Mempool,
+        })
+    }
+
+    /// `gettxout` does not report where an output was spent, so outputs spent
+    /// in a block have an unknown spending transaction and status
+    fn output_status(
+        client: &bitcoincore_rpc::Client,
+        txid: &Txid,
+        vout: u32,
+    ) -> Result<Option<OutputStatus>, ChainError> {
+        use bitcoincore_rpc::RpcApi;
+
+        let with_mempool = client
+            .get_tx_out(txid, vout, Some(true))
+            .map_err(bitcoind_err)?;
+        if with_mempool.is_some() {
+            return Ok(Some(OutputStatus {
+                spent: false,
+                txid: None,
+                status: TxStatus::NotFound,
+            }));
+        }
+
+        let without_mempool = client
+            .get_tx_out(txid, vout, Some(false))
+            .map_err(bitcoind_err)?;
+        if without_mempool.is_some() {
+            return Ok(Some(OutputStatus {
+                spent: true,
+                txid: None,
+                status: TxStatus::Mempool,
+            }));
+        }
+
+        match Self::get_tx(client, txid)? {
+            Some(tx) if (vout as usize) < tx.output.len() => Ok(Some(OutputStatus {
+                spent: true,
+                txid: None,
+                status: TxStatus::NotFound,
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    /// Only unspent and confirmed outputs are found by `scantxoutset`
+    fn script_txs(
+        client: &bitcoincore_rpc::Client,
+        script: &Script,
+    ) -> Result<Vec<ScriptTx>, ChainError> {
+        let scan = crate::bitcoin::bitcoind::bitcoind_scan_scripts(client, &[script.clone()])
+            .map_err(bitcoind_err)?;
+
+        let mut txs: BTreeMap<Txid, ScriptTx> = BTreeMap::new();
+        for unspent in scan.unspents {
+            if txs.contains_key(&unspent.txid) {
+                continue;
+            }
+            if let Some(tx) = Self::get_tx(client, &unspent.txid)? {
+                txs.insert(
+                    unspent.txid,
+                    ScriptTx {
+                        tx,
+                        status: TxStatus::Block(unspent.height as u32),
+                    },
+                );
+            }
+        }
+        Ok(txs.into_values().collect())
+    }
+
+    fn height(client: &bitcoincore_rpc::Client) -> Result<u32, ChainError> {
+        use bitcoincore_rpc::RpcApi;
+
+        let height = client.get_block_count().map_err(bitcoind_err)?;
+        Ok(height as u32)
+    }
+
+    fn block_hash(
+        client: &bitcoincore_rpc::Client,
+        height: u32,
+    ) -> Result<Option<BlockHash>, ChainError> {
+        use bitcoincore_rpc::RpcApi;
+
+        if height > Self::height(client)? {
+            return Ok(None);
+        }
+
+        let hash = client.get_block_hash(height as u64).map_err(bitcoind_err)?;
+        Ok(Some(hash))
+    }
+
+    fn block_time(
+        client: &bitcoincore_rpc::Client,
+        height: u32,
+    ) -> Result<Option<u64>, ChainError> {
+        use bitcoincore_rpc::RpcApi;
+
+        let Some(hash) = Self::block_hash(client, height)? else {
+            return Ok(None);
+        };
+
+        let header = client.get_block_header(&hash).map_err(bitcoind_err)?;
+        Ok(Some(header.time as u64))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl ChainBackend for BitcoindBackend {
+    async fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>, ChainError> {
+        let txid = *txid;
+        self.call(move |client| Self::get_tx(client, &txid)).await
+    }
+
+    async fn get_tx_status(&self, txid: &Txid) -> Result<TxStatus, ChainError> {
+        let txid = *txid;
+        self.call(move |client| Self::tx_status(client, &txid))
+            .await
+    }
+
+    async fn get_output_status(
+        &self,
+        txid: &Txid,
+        vout: u32,
+    ) -> Result<Option<OutputStatus>, ChainError> {
+        let txid = *txid;
+        self.call(move |client| Self::output_status(client, &txid, vout))
+            .await
+    }
+
+    async fn get_script_txs(&self, script: &Script) -> Result<Vec<ScriptTx>, ChainError> {
+        let script = script.clone();
+        self.call(move |client| Self::script_txs(client, &script))
+            .await
+    }
+
+    async fn get_height(&self) -> Result<u32, ChainError> {
+        self.call(Self::height).await
+    }
+
+    async fn get_block_hash(&self, height: u32) -> Result<Option<BlockHash>, ChainError> {
+        self.call(move |client| Self::block_hash(client, height))
+            .await
+    }
+
+    async fn get_block_time(&self, height: u32) -> Result<Option<u64>, ChainError> {
+        self.call(move |client| Self::block_time(client, height))
+            .await
+    }
+
+    async fn estimate_fee(&self, target: usize) -> Result<f32, ChainError> {
+        use bitcoincore_rpc::RpcApi;
+
+        let estimate = self
+            .call(move |client| {
+                client
+                    .estimate_smart_fee(target as u16, None)
+                    .map_err(bitcoind_err)
+            })
+            .await?;
+        Ok(match estimate.fee_rate {
+            // fee rates of Bitcoin Core are per kvB
+            Some(fee_rate) => fee_rate.to_sat() as f32 / 1000.0,
+            None => MIN_RELAY_FEE,
+        })
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<(), ChainError> {
+        use bitcoincore_rpc::RpcApi;
+
+        let tx = tx.clone();
+        self.call(move |client| {
+            client.send_raw_transaction(&tx).map_err(bitcoind_err)?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// `scantxoutset` only finds the transactions with unspent outputs
+    fn full_history(&self) -> bool {
+        false
+    }
+}
+
+// the backends of bitmaskd are held across `.await` by `Send` handlers
+#[cfg(not(target_arch = "wasm32"))]
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync + ?Sized>() {}
+    assert_send_sync::<dyn ChainBackend>();
+};
+
+/// Chain shared by all the `mock` backends, only built for tests (`mock-chain` feature)
+#[cfg(feature = "mock-chain")]
+pub static MOCK_BACKEND: Lazy<MockBackend> = Lazy::new(MockBackend::default);
+
+/// Backend selected by `BITCOIN_BACKEND` ("esplora", "electrum", "bitcoind", or "mock"
+/// with the `mock-chain` feature)
+pub async fn chain_backend() -> Result<Box<dyn ChainBackend>, ChainError> {
+    account_chain_backend("").await
+}
+
+/// Same as [`chain_backend`], caching in the resolver cache of an account
+/// (see [`resolver_account`])
+pub async fn account_chain_backend(account: &str) -> Result<Box<dyn ChainBackend>, ChainError> {
+    let explorer_url = BITCOIN_EXPLORER_API.read().await.clone();
+    chain_backend_with_explorer(&explorer_url, account).await
+}
+
+/// Same as [`account_chain_backend`], with the Esplora URL of an RGB resolver
+///
+/// Real backends are wrapped in a [`CachedBackend`], the mock chain is not
+/// cached because its blocks can be reorganized at will.
+pub async fn chain_backend_with_explorer(
+    explorer_url: &str,
+    account: &str,
+) -> Result<Box<dyn ChainBackend>, ChainError> {
+    let backend = BITCOIN_BACKEND.read().await.clone();
+    match backend.as_str() {
+        "esplora" => Ok(Box::new(CachedBackend::for_account(
+            EsploraBackend::new(explorer_url)?,
+            account,
+        ))),
+        #[cfg(feature = "mock-chain")]
+        "mock" => Ok(Box::new(MOCK_BACKEND.clone())),
+        #[cfg(not(target_arch = "wasm32"))]
+        "electrum" => Ok(Box::new(CachedBackend::for_account(
+            ElectrumBackend::shared(&BITCOIN_ELECTRUM_API.read().await)?,
+            account,
+        ))),
+        #[cfg(not(target_arch = "wasm32"))]
+        "bitcoind" => Ok(Box::new(CachedBackend::for_account(
+            BitcoindBackend::shared(
+                &BITCOIN_RPC_API.read().await,
+                &BITCOIN_RPC_USER.read().await,
+                &BITCOIN_RPC_PASSWORD.read().await,
+            )?,
+            account,
+        ))),
+        _ => Err(ChainError::UnsupportedBackend(backend)),
+    }
+}
+
+/// Backend selected by `BITCOIN_BACKEND` for synchronous callers, `None` for
+/// Esplora (its client needs an async runtime, use the blocking client instead)
+#[cfg(not(target_arch = "wasm32"))]
+pub fn blocking_chain_backend(account: &str) -> Result<Option<Box<dyn ChainBackend>>, ChainError> {
+    // wait for the settings being written, they are only locked for a moment
+    let name = block_on_runtime(BITCOIN_BACKEND.read()).clone();
+    let backend: Box<dyn ChainBackend> = match name.as_str() {
+        "esplora" => return Ok(None),
+        #[cfg(feature = "mock-chain")]
+        "mock" => Box::new(MOCK_BACKEND.clone()),
+        "electrum" => {
+            let url = block_on_runtime(BITCOIN_ELECTRUM_API.read()).clone();
+            Box::new(CachedBackend::for_account(
+                ElectrumBackend::shared(&url)?,
+                account,
+            ))
+        }
+        "bitcoind" => {
+            let (url, user, password) = block_on_runtime(async {
+                (
+                    BITCOIN_RPC_API.read().await.clone(),
+                    BITCOIN_RPC_USER.read().await.clone(),
+                    BITCOIN_RPC_PASSWORD.read().await.clone(),
+                )
+            });
+            Box::new(CachedBackend::for_account(
+                BitcoindBackend::shared(&url, &user, &password)?,
+                account,
+            ))
+        }
+        _ => return Err(ChainError::UnsupportedBackend(name)),
+    };
+    Ok(Some(backend))
+}
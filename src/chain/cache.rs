@@ -0,0 +1,476 @@
+use std::{collections::BTreeMap, str::FromStr, sync::Mutex};
+
+use async_trait::async_trait;
+use bitcoin::{
+    consensus::encode::{deserialize, serialize_hex},
+    hashes::hex::FromHex,
+    BlockHash, Script, Transaction, Txid,
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use super::{ChainBackend, ChainError, OutputStatus, ScriptTx};
+use crate::{
+    constants::{
+        RESOLVER_CACHE_FINAL_DEPTH, RESOLVER_CACHE_MAX_TXS, RESOLVER_CACHE_TTL,
+        RESOLVER_CACHE_TX_TTL,
+    },
+    structs::{ResolverCacheMetrics, TxStatus},
+};
+
+/// Resolver caches of the process, by account (see [`resolver_account`])
+///
+/// The cache of the empty account is used by the callers without one, it is
+/// never stored.
+pub static RESOLVER_CACHE: Lazy<Mutex<BTreeMap<String, ResolverCache>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// Key of the resolver cache of an account
+pub fn resolver_account(sk: &str) -> String {
+    blake3::hash(sk.as_bytes()).to_hex().to_string()
+}
+
+/// Run `f` on the resolver cache of an account
+pub fn with_resolver_cache<T>(account: &str, f: impl FnOnce(&mut ResolverCache) -> T) -> T {
+    let mut caches = RESOLVER_CACHE.lock().expect("resolver cache poisoned");
+    f(caches.entry(account.to_string()).or_default())
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CachedTx {
+    hex: String,
+    cached_at: i64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CachedStatus {
+    status: TxStatus,
+    cached_at: i64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CachedOutput {
+    spent: bool,
+    txid: Option<String>,
+    status: TxStatus,
+    cached_at: i64,
+}
+
+impl CachedOutput {
+    /// A spend buried deep enough doesn't change anymore
+    fn is_final(&self, tip: u32) -> bool {
+        self.spent && is_final_status(&self.status, tip)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+struct CachedTip {
+    height: u32,
+    cached_at: i64,
+}
+
+/// Blocks can be reorganized, so only the confirmations buried
+/// [`RESOLVER_CACHE_FINAL_DEPTH`] blocks under the tip are final
+fn is_final_status(status: &TxStatus, tip: u32) -> bool {
+    match status {
+        TxStatus::Block(height) => tip.saturating_sub(*height) + 1 >= RESOLVER_CACHE_FINAL_DEPTH,
+        _ => false,
+    }
+}
+
+fn is_fresh(is_final: bool, cached_at: i64, now: i64) -> bool {
+    is_final || now - cached_at < RESOLVER_CACHE_TTL
+}
+
+fn outpoint_key(txid: &Txid, vout: u32) -> String {
+    format!("{txid}:{vout}")
+}
+
+/// Transactions, mining statuses and spent outputs already resolved
+///
+/// Transactions are kept [`RESOLVER_CACHE_TX_TTL`] seconds, and at most
+/// [`RESOLVER_CACHE_MAX_TXS`] of them. The data confirmed
+/// [`RESOLVER_CACHE_FINAL_DEPTH`] blocks under the last known tip is kept
+/// forever. Shallow confirmations, mempool data (and unknown transactions or
+/// unspent outputs) expire after [`RESOLVER_CACHE_TTL`] seconds.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ResolverCache {
+    /// Transactions in consensus hex, by txid (the undated `txs` of older caches are dropped)
+    #[serde(default, rename = "dated_txs")]
+    txs: BTreeMap<String, CachedTx>,
+    statuses: BTreeMap<String, CachedStatus>,
+    /// Output status, by outpoint (`txid:vout`)
+    outputs: BTreeMap<String, CachedOutput>,
+    /// Last known chain tip, the depth of the confirmations is measured from it
+    #[serde(default)]
+    tip: Option<CachedTip>,
+    #[serde(skip)]
+    metrics: ResolverCacheMetrics,
+}
+
+impl ResolverCache {
+    /// Height of the chain tip, `None` when it expired
+    pub fn tip(&self, now: i64) -> Option<u32> {
+        self.tip
+            .filter(|tip| is_fresh(false, tip.cached_at, now))
+            .map(|tip| tip.height)
+    }
+
+    pub fn set_tip(&mut self, height: u32, now: i64) {
+        self.tip = Some(CachedTip {
+            height,
+            cached_at: now,
+        });
+    }
+
+    /// An expired tip is lower than the real one, so it only makes confirmations shallower
+    fn tip_height(&self) -> u32 {
+        self.tip.map(|tip| tip.height).unwrap_or_default()
+    }
+
+    pub fn tx(&mut self, txid: &Txid) -> Option<Transaction> {
+        let tx = self
+            .txs
+            .get(&txid.to_string())
+            .and_then(|cached| Vec::<u8>::from_hex(&cached.hex).ok())
+            .and_then(|bytes| deserialize(&bytes).ok());
+
+        match tx {
+            Some(_) => self.metrics.tx_hits += 1,
+            None => self.metrics.tx_misses += 1,
+        }
+        tx
+    }
+
+    pub fn insert_tx(&mut self, tx: &Transaction, now: i64) {
+        self.txs.insert(
+            tx.txid().to_string(),
+            CachedTx {
+                hex: serialize_hex(tx),
+                cached_at: now,
+            },
+        );
+        self.trim_txs();
+    }
+
+    /// Drop the oldest transactions over [`RESOLVER_CACHE_MAX_TXS`]
+    fn trim_txs(&mut self) {
+        let Some(excess) = self.txs.len().checked_sub(RESOLVER_CACHE_MAX_TXS) else {
+            return;
+        };
+        let mut dates: Vec<_> = self
+            .txs
+            .iter()
+            .map(|(txid, cached)| (cached.cached_at, txid.clone()))
+            .collect();
+        dates.sort();
+        for (_, txid) in dates.into_iter().take(excess) {
+            self.txs.remove(&txid);
+        }
+    }
+
+    pub fn tx_status(&mut self, txid: &Txid, now: i64) -> Option<TxStatus> {
+        let tip = self.tip_height();
+        let status = self
+            .statuses
+            .get(&txid.to_string())
+            .filter(|cached| is_fresh(is_final_status(&cached.status, tip), cached.cached_at, now))
+            .map(|cached| cached.status.clone());
+
+        match status {
+            Some(_) => self.metrics.status_hits += 1,
+            None => self.metrics.status_misses += 1,
+        }
+        status
+    }
+
+    pub fn insert_tx_status(&mut self, txid: &Txid, status: TxStatus, now: i64) {
+        if matches!(status, TxStatus::Error(_)) {
+            return;
+        }
+        self.statuses.insert(
+            txid.to_string(),
+            CachedStatus {
+                status,
+                cached_at: now,
+            },
+        );
+    }
+
+    pub fn output_status(&mut self, txid: &Txid, vout: u32, now: i64) -> Option<OutputStatus> {
+        let tip = self.tip_height();
+        let status = self
+            .outputs
+            .get(&outpoint_key(txid, vout))
+            .filter(|cached| is_fresh(cached.is_final(tip), cached.cached_at, now))
+            .map(|cached| OutputStatus {
+                spent: cached.spent,
+                txid: cached
+                    .txid
+                    .as_ref()
+                    .and_then(|txid| Txid::from_str(txid).ok()),
+                status: cached.status.clone(),
+            });
+
+        match status {
+            Some(_) => self.metrics.output_hits += 1,
+            None => self.metrics.output_misses += 1,
+        }
+        status
+    }
+
+    pub fn insert_output_status(&mut self, txid: &Txid, vout: u32, status: OutputStatus, now: i64) {
+        if matches!(status.status, TxStatus::Error(_)) {
+            return;
+        }
+        self.outputs.insert(
+            outpoint_key(txid, vout),
+            CachedOutput {
+                spent: status.spent,
+                txid: status.txid.map(|txid| txid.to_string()),
+                status: status.status,
+                cached_at: now,
+            },
+        );
+    }
+
+    /// Forget the status of a new transaction and of the outputs it spends
+    pub fn invalidate(&mut self, tx: &Transaction) {
+        self.statuses.remove(&tx.txid().to_string());
+        for input in &tx.input {
+            let prevout = input.previous_output;
+            self.outputs
+                .remove(&outpoint_key(&prevout.txid, prevout.vout));
+        }
+    }
+
+    /// Forget the status of a reorganized transaction and of its outputs
+    pub fn forget(&mut self, txid: &Txid) {
+        let txid = txid.to_string();
+        self.statuses.remove(&txid);
+        self.outputs.retain(|outpoint, cached| {
+            !outpoint.starts_with(&format!("{txid}:")) && cached.txid.as_ref() != Some(&txid)
+        });
+    }
+
+    /// Remove the expired entries
+    pub fn prune(&mut self, now: i64) {
+        let tip = self.tip_height();
+        self.txs
+            .retain(|_, cached| now - cached.cached_at < RESOLVER_CACHE_TX_TTL);
+        self.trim_txs();
+        self.statuses.retain(|_, cached| {
+            is_fresh(is_final_status(&cached.status, tip), cached.cached_at, now)
+        });
+        self.outputs
+            .retain(|_, cached| is_fresh(cached.is_final(tip), cached.cached_at, now));
+    }
+
+    /// Add the entries of another cache, keeping the most recent ones
+    pub fn merge(&mut self, other: ResolverCache) {
+        match (self.tip, other.tip) {
+            (Some(current), Some(tip)) if current.cached_at >= tip.cached_at => {}
+            (_, Some(tip)) => self.tip = Some(tip),
+            _ => {}
+        }
+        for (txid, cached) in other.txs {
+            match self.txs.get(&txid) {
+                Some(current) if current.cached_at >= cached.cached_at => {}
+                _ => {
+                    self.txs.insert(txid, cached);
+                }
+            }
+        }
+        self.trim_txs();
+        for (txid, cached) in other.statuses {
+            match self.statuses.get(&txid) {
+                Some(current) if current.cached_at >= cached.cached_at => {}
+                _ => {
+                    self.statuses.insert(txid, cached);
+                }
+            }
+        }
+        for (outpoint, cached) in other.outputs {
+            match self.outputs.get(&outpoint) {
+                Some(current) if current.cached_at >= cached.cached_at => {}
+                _ => {
+                    self.outputs.insert(outpoint, cached);
+                }
+            }
+        }
+    }
+
+    pub fn metrics(&self) -> ResolverCacheMetrics {
+        ResolverCacheMetrics {
+            entries: (self.txs.len() + self.statuses.len() + self.outputs.len()) as u64,
+            ..self.metrics
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = ResolverCache::default();
+    }
+}
+
+/// Hits and misses of the resolver caches of the process
+pub fn resolver_cache_metrics() -> ResolverCacheMetrics {
+    let caches = RESOLVER_CACHE.lock().expect("resolver cache poisoned");
+    caches.values().map(ResolverCache::metrics).fold(
+        ResolverCacheMetrics::default(),
+        |total, metrics| ResolverCacheMetrics {
+            tx_hits: total.tx_hits + metrics.tx_hits,
+            tx_misses: total.tx_misses + metrics.tx_misses,
+            status_hits: total.status_hits + metrics.status_hits,
+            status_misses: total.status_misses + metrics.status_misses,
+            output_hits: total.output_hits + metrics.output_hits,
+            output_misses: total.output_misses + metrics.output_misses,
+            entries: total.entries + metrics.entries,
+        },
+    )
+}
+
+/// Add the stored cache of an account to its process one
+pub fn merge_resolver_cache(account: &str, cache: ResolverCache) {
+    with_resolver_cache(account, |current| current.merge(cache));
+}
+
+/// Copy of the cache of an account without the expired entries, ready to be stored
+pub fn resolver_cache_snapshot(account: &str) -> ResolverCache {
+    with_resolver_cache(account, |cache| {
+        cache.prune(chrono::Utc::now().timestamp());
+        cache.clone()
+    })
+}
+
+/// Chain backend answering from the [`RESOLVER_CACHE`] of an account before
+/// asking the inner one
+#[derive(Clone, Debug)]
+pub struct CachedBackend<B: ChainBackend> {
+    pub backend: B,
+    pub account: String,
+}
+
+impl<B: ChainBackend> CachedBackend<B> {
+    /// Backend caching in the cache of the empty account
+    pub fn new(backend: B) -> Self {
+        Self::for_account(backend, "")
+    }
+
+    pub fn for_account(backend: B, account: &str) -> Self {
+        Self {
+            backend,
+            account: account.to_string(),
+        }
+    }
+
+    fn cache<T>(&self, f: impl FnOnce(&mut ResolverCache) -> T) -> T {
+        with_resolver_cache(&self.account, f)
+    }
+
+    /// Ask the tip again once it expired, the cached statuses are only final under it
+    async fn refresh_tip(&self, now: i64) -> Result<(), ChainError> {
+        let tip = self.cache(|cache| cache.tip(now));
+        if tip.is_none() {
+            ChainBackend::get_height(self).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<B: ChainBackend> ChainBackend for CachedBackend<B> {
+    async fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>, ChainError> {
+        let cached = self.cache(|cache| cache.tx(txid));
+        if cached.is_some() {
+            return Ok(cached);
+        }
+
+        let tx = self.backend.get_tx(txid).await?;
+        if let Some(tx) = &tx {
+            let now = chrono::Utc::now().timestamp();
+            self.cache(|cache| cache.insert_tx(tx, now));
+        }
+        Ok(tx)
+    }
+
+    async fn get_tx_status(&self, txid: &Txid) -> Result<TxStatus, ChainError> {
+        let now = chrono::Utc::now().timestamp();
+        self.refresh_tip(now).await?;
+        let cached = self.cache(|cache| cache.tx_status(txid, now));
+        if let Some(status) = cached {
+            return Ok(status);
+        }
+
+        let status = self.backend.get_tx_status(txid).await?;
+        self.cache(|cache| cache.insert_tx_status(txid, status.clone(), now));
+        Ok(status)
+    }
+
+    async fn get_output_status(
+        &self,
+        txid: &Txid,
+        vout: u32,
+    ) -> Result<Option<OutputStatus>, ChainError> {
+        let now = chrono::Utc::now().timestamp();
+        self.refresh_tip(now).await?;
+        let cached = self.cache(|cache| cache.output_status(txid, vout, now));
+        if cached.is_some() {
+            return Ok(cached);
+        }
+
+        let status = self.backend.get_output_status(txid, vout).await?;
+        if let Some(status) = &status {
+            self.cache(|cache| cache.insert_output_status(txid, vout, status.clone(), now));
+        }
+        Ok(status)
+    }
+
+    async fn get_script_txs(&self, script: &Script) -> Result<Vec<ScriptTx>, ChainError> {
+        // The history of a script can change at any time, only its transactions are cached
+        let txs = self.backend.get_script_txs(script).await?;
+
+        let now = chrono::Utc::now().timestamp();
+        self.cache(|cache| {
+            for script_tx in &txs {
+                cache.insert_tx(&script_tx.tx, now);
+                cache.insert_tx_status(&script_tx.tx.txid(), script_tx.status.clone(), now);
+            }
+        });
+        Ok(txs)
+    }
+
+    async fn get_height(&self) -> Result<u32, ChainError> {
+        let height = self.backend.get_height().await?;
+        let now = chrono::Utc::now().timestamp();
+        self.cache(|cache| cache.set_tip(height, now));
+        Ok(height)
+    }
+
+    async fn get_block_hash(&self, height: u32) -> Result<Option<BlockHash>, ChainError> {
+        self.backend.get_block_hash(height).await
+    }
+
+    async fn get_block_time(&self, height: u32) -> Result<Option<u64>, ChainError> {
+        self.backend.get_block_time(height).await
+    }
+
+    async fn estimate_fee(&self, target: usize) -> Result<f32, ChainError> {
+        self.backend.estimate_fee(target).await
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<(), ChainError> {
+        self.backend.broadcast(tx).await?;
+        self.cache(|cache| cache.invalidate(tx));
+        Ok(())
+    }
+
+    fn full_history(&self) -> bool {
+        self.backend.full_history()
+    }
+
+    fn forget(&self, txid: &Txid) {
+        self.cache(|cache| cache.forget(txid));
+        self.backend.forget(txid);
+    }
+}
@@ -0,0 +1,372 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use bitcoin::{
+    hashes::Hash, BlockHash, OutPoint, PackedLockTime, Script, Sequence, Transaction, TxIn, TxOut,
+    Txid, Witness,
+};
+
+use super::{ChainBackend, ChainError, OutputStatus, ScriptTx, MIN_RELAY_FEE};
+use crate::structs::TxStatus;
+
+/// Timestamp of the genesis block, each block is mined 10 minutes later
+const GENESIS_TIME: u64 = 1_231_006_505;
+const BLOCK_INTERVAL: u64 = 600;
+
+#[derive(Clone, Debug)]
+struct MockBlock {
+    hash: BlockHash,
+    time: u64,
+    txids: Vec<Txid>,
+}
+
+#[derive(Debug)]
+struct MockChain {
+    blocks: Vec<MockBlock>,
+    mempool: Vec<Txid>,
+    txs: BTreeMap<Txid, Transaction>,
+    heights: HashMap<Txid, u32>,
+    /// Spending transaction of each output, mined or in the mempool
+    spends: HashMap<OutPoint, Txid>,
+    /// Makes the funding transactions unique
+    nonce: u32,
+}
+
+impl Default for MockChain {
+    fn default() -> Self {
+        let genesis = MockBlock {
+            hash: BlockHash::hash(&[]),
+            time: GENESIS_TIME,
+            txids: vec![],
+        };
+
+        Self {
+            blocks: vec![genesis],
+            mempool: vec![],
+            txs: BTreeMap::new(),
+            heights: HashMap::new(),
+            spends: HashMap::new(),
+            nonce: 0,
+        }
+    }
+}
+
+impl MockChain {
+    fn height(&self) -> u32 {
+        (self.blocks.len() - 1) as u32
+    }
+
+    fn status(&self, txid: &Txid) -> TxStatus {
+        match self.heights.get(txid) {
+            Some(height) => TxStatus::Block(*height),
+            None if self.mempool.contains(txid) => TxStatus::Mempool,
+            None => TxStatus::NotFound,
+        }
+    }
+
+    fn output(&self, outpoint: &OutPoint) -> Option<&TxOut> {
+        self.txs
+            .get(&outpoint.txid)
+            .and_then(|tx| tx.output.get(outpoint.vout as usize))
+    }
+
+    /// Fee of a transaction, `None` for the funding transactions
+    fn fee(&self, tx: &Transaction) -> Option<u64> {
+        if tx.is_coin_base() {
+            return None;
+        }
+
+        let inputs: u64 = tx
+            .input
+            .iter()
+            .filter_map(|input| self.output(&input.previous_output))
+            .map(|output| output.value)
+            .sum();
+        let outputs: u64 = tx.output.iter().map(|output| output.value).sum();
+        Some(inputs.saturating_sub(outputs))
+    }
+
+    /// Remove a mempool transaction and the ones spending its outputs
+    fn evict(&mut self, txid: &Txid) {
+        if self.heights.contains_key(txid) {
+            return;
+        }
+        let Some(tx) = self.txs.remove(txid) else {
+            return;
+        };
+
+        self.mempool.retain(|id| id != txid);
+        for input in tx.input.iter() {
+            if self.spends.get(&input.previous_output) == Some(txid) {
+                self.spends.remove(&input.previous_output);
+            }
+        }
+
+        for vout in 0..tx.output.len() {
+            if let Some(child) = self.spends.get(&OutPoint::new(*txid, vout as u32)).cloned() {
+                self.evict(&child);
+            }
+        }
+    }
+
+    /// Accept a transaction in the mempool, replacing the conflicting ones
+    /// when it pays a higher fee
+    fn accept(&mut self, tx: Transaction) -> Result<Txid, ChainError> {
+        let txid = tx.txid();
+        if self.txs.contains_key(&txid) {
+            return Ok(txid);
+        }
+
+        let mut conflicts = HashSet::new();
+        if !tx.is_coin_base() {
+            let mut inputs = 0;
+            for input in tx.input.iter() {
+                let prevout = input.previous_output;
+                let Some(output) = self.output(&prevout) else {
+                    return Err(ChainError::Rejected(format!("missing input {prevout}")));
+                };
+                inputs += output.value;
+
+                if let Some(spending) = self.spends.get(&prevout) {
+                    if self.heights.contains_key(spending) {
+                        return Err(ChainError::Rejected(format!(
+                            "input {prevout} already spent"
+                        )));
+                    }
+                    conflicts.insert(*spending);
+                }
+            }
+
+            let outputs: u64 = tx.output.iter().map(|output| output.value).sum();
+            if outputs > inputs {
+                return Err(ChainError::Rejected(format!(
+                    "outputs ({outputs}) greater than inputs ({inputs})"
+                )));
+            }
+        }
+
+        if !conflicts.is_empty() {
+            let replaced_fee: u64 = conflicts
+                .iter()
+                .filter_map(|conflict| self.txs.get(conflict))
+                .filter_map(|conflict| self.fee(conflict))
+                .sum();
+            let fee = self.fee(&tx).unwrap_or_default();
+            if fee <= replaced_fee {
+                return Err(ChainError::Rejected(format!(
+                    "fee {fee} is not greater than the replaced fee {replaced_fee}"
+                )));
+            }
+
+            for conflict in conflicts {
+                self.evict(&conflict);
+            }
+        }
+
+        if !tx.is_coin_base() {
+            for input in tx.input.iter() {
+                self.spends.insert(input.previous_output, txid);
+            }
+        }
+        self.txs.insert(txid, tx);
+        self.mempool.push(txid);
+
+        Ok(txid)
+    }
+
+    fn mine(&mut self) -> BlockHash {
+        let height = self.height() + 1;
+        let prev = self.blocks[self.blocks.len() - 1].hash;
+        let txids: Vec<Txid> = self.mempool.drain(..).collect();
+
+        let mut data = prev.into_inner().to_vec();
+        data.extend(height.to_le_bytes());
+        for txid in txids.iter() {
+            data.extend(txid.into_inner());
+            self.heights.insert(*txid, height);
+        }
+
+        let hash = BlockHash::hash(&data);
+        self.blocks.push(MockBlock {
+            hash,
+            time: GENESIS_TIME + height as u64 * BLOCK_INTERVAL,
+            txids,
+        });
+        hash
+    }
+
+    /// Disconnect the last blocks, their transactions go back to the mempool
+    fn reorg(&mut self, depth: u32) {
+        let depth = depth.min(self.height()) as usize;
+        let disconnected = self.blocks.split_off(self.blocks.len() - depth);
+
+        let mut mempool = vec![];
+        for block in disconnected {
+            for txid in block.txids {
+                self.heights.remove(&txid);
+                mempool.push(txid);
+            }
+        }
+        mempool.append(&mut self.mempool);
+        self.mempool = mempool;
+    }
+}
+
+/// In-memory simulated chain, used for offline tests (`BITCOIN_BACKEND=mock`)
+///
+/// Transactions are accepted in the mempool, with replacement by fee, and
+/// confirmed by mining blocks. Funding transactions have no inputs besides a
+/// null outpoint and act like coinbases. Clones share the same chain.
+#[derive(Clone, Debug, Default)]
+pub struct MockBackend {
+    chain: Arc<Mutex<MockChain>>,
+}
+
+impl MockBackend {
+    fn chain(&self) -> MutexGuard<'_, MockChain> {
+        self.chain.lock().expect("mock chain poisoned")
+    }
+
+    /// Send coins to a script from nowhere, the transaction stays in the mempool
+    pub fn send_to(&self, script: &Script, amount: u64) -> Txid {
+        let mut chain = self.chain();
+        chain.nonce += 1;
+
+        let tx = Transaction {
+            version: 2,
+            lock_time: PackedLockTime(0),
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: Script::from(chain.nonce.to_le_bytes().to_vec()),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: amount,
+                script_pubkey: script.clone(),
+            }],
+        };
+        chain
+            .accept(tx)
+            .expect("funding transactions are always valid")
+    }
+
+    /// Mine blocks with all the mempool transactions, returns the new tip
+    pub fn mine(&self, blocks: u32) -> BlockHash {
+        let mut chain = self.chain();
+        let mut tip = chain.blocks[chain.blocks.len() - 1].hash;
+        for _ in 0..blocks {
+            tip = chain.mine();
+        }
+        tip
+    }
+
+    /// Disconnect the last `depth` blocks, returning their transactions to the mempool
+    pub fn reorg(&self, depth: u32) {
+        self.chain().reorg(depth);
+    }
+
+    /// Evict a transaction from the mempool, with its descendants
+    pub fn drop_tx(&self, txid: &Txid) {
+        self.chain().evict(txid);
+    }
+
+    pub fn mempool(&self) -> Vec<Txid> {
+        self.chain().mempool.clone()
+    }
+
+    /// Restart from the genesis block
+    pub fn clear(&self) {
+        *self.chain() = MockChain::default();
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl ChainBackend for MockBackend {
+    async fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>, ChainError> {
+        Ok(self.chain().txs.get(txid).cloned())
+    }
+
+    async fn get_tx_status(&self, txid: &Txid) -> Result<TxStatus, ChainError> {
+        Ok(self.chain().status(txid))
+    }
+
+    async fn get_output_status(
+        &self,
+        txid: &Txid,
+        vout: u32,
+    ) -> Result<Option<OutputStatus>, ChainError> {
+        let chain = self.chain();
+        let outpoint = OutPoint::new(*txid, vout);
+        if chain.output(&outpoint).is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(match chain.spends.get(&outpoint) {
+            Some(spending) => OutputStatus {
+                spent: true,
+                txid: Some(*spending),
+                status: chain.status(spending),
+            },
+            None => OutputStatus {
+                spent: false,
+                txid: None,
+                status: TxStatus::NotFound,
+            },
+        }))
+    }
+
+    async fn get_script_txs(&self, script: &Script) -> Result<Vec<ScriptTx>, ChainError> {
+        let chain = self.chain();
+        Ok(chain
+            .txs
+            .iter()
+            .filter(|(_, tx)| {
+                // like Esplora and Electrum, the history has the spends of the script too
+                tx.output.iter().any(|out| &out.script_pubkey == script)
+                    || tx.input.iter().any(|input| {
+                        chain
+                            .output(&input.previous_output)
+                            .is_some_and(|out| &out.script_pubkey == script)
+                    })
+            })
+            .map(|(txid, tx)| ScriptTx {
+                tx: tx.clone(),
+                status: chain.status(txid),
+            })
+            .collect())
+    }
+
+    async fn get_height(&self) -> Result<u32, ChainError> {
+        Ok(self.chain().height())
+    }
+
+    async fn get_block_hash(&self, height: u32) -> Result<Option<BlockHash>, ChainError> {
+        Ok(self
+            .chain()
+            .blocks
+            .get(height as usize)
+            .map(|block| block.hash))
+    }
+
+    async fn get_block_time(&self, height: u32) -> Result<Option<u64>, ChainError> {
+        Ok(self
+            .chain()
+            .blocks
+            .get(height as usize)
+            .map(|block| block.time))
+    }
+
+    async fn estimate_fee(&self, _target: usize) -> Result<f32, ChainError> {
+        Ok(MIN_RELAY_FEE)
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<(), ChainError> {
+        self.chain().accept(tx.clone())?;
+        Ok(())
+    }
+}
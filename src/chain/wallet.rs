@@ -0,0 +1,282 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+};
+
+use async_trait::async_trait;
+use bdk::{
+    blockchain::{Blockchain, Capability, GetBlockHash, GetHeight, GetTx, Progress, WalletSync},
+    database::{BatchDatabase, BatchOperations, Database, SyncTime},
+    BlockTime, FeeRate, KeychainKind, LocalUtxo, TransactionDetails,
+};
+use bitcoin::{BlockHash, OutPoint, Transaction, TxOut, Txid};
+
+use super::{ChainBackend, ChainError, ScriptTx};
+use crate::{debug, structs::TxStatus};
+
+/// Unused scripts in a row after which a keychain is not scanned anymore
+const STOP_GAP: u32 = 20;
+
+fn bdk_err(err: ChainError) -> bdk::Error {
+    bdk::Error::Generic(err.to_string())
+}
+
+/// BDK blockchain over a [`ChainBackend`], so the wallets and the RGB
+/// resolvers read the chain from the same backend
+pub struct ChainBlockchain<B: ChainBackend> {
+    pub backend: B,
+}
+
+impl<B: ChainBackend> ChainBlockchain<B> {
+    pub fn new(backend: B) -> Self {
+        Self { backend }
+    }
+
+    async fn block_time(
+        &self,
+        height: u32,
+        times: &mut BTreeMap<u32, u64>,
+    ) -> Result<BlockTime, bdk::Error> {
+        let timestamp = match times.get(&height) {
+            Some(timestamp) => *timestamp,
+            None => {
+                let timestamp = self
+                    .backend
+                    .get_block_time(height)
+                    .await
+                    .map_err(bdk_err)?
+                    .ok_or_else(|| bdk::Error::Generic(format!("Block {height} not found")))?;
+                times.insert(height, timestamp);
+                timestamp
+            }
+        };
+        Ok(BlockTime { height, timestamp })
+    }
+
+    async fn prev_output(
+        &self,
+        outpoint: &OutPoint,
+        txs: &mut HashMap<Txid, Transaction>,
+    ) -> Result<TxOut, bdk::Error> {
+        if !txs.contains_key(&outpoint.txid) {
+            let tx = self
+                .backend
+                .get_tx(&outpoint.txid)
+                .await
+                .map_err(bdk_err)?
+                .ok_or(bdk::Error::TransactionNotFound)?;
+            txs.insert(outpoint.txid, tx);
+        }
+
+        txs[&outpoint.txid]
+            .output
+            .get(outpoint.vout as usize)
+            .cloned()
+            .ok_or(bdk::Error::InvalidOutpoint(*outpoint))
+    }
+}
+
+#[async_trait(?Send)]
+impl<B: ChainBackend> Blockchain for ChainBlockchain<B> {
+    fn get_capabilities(&self) -> HashSet<Capability> {
+        let mut capabilities: HashSet<Capability> =
+            vec![Capability::GetAnyTx, Capability::AccurateFees]
+                .into_iter()
+                .collect();
+        if self.backend.full_history() {
+            capabilities.insert(Capability::FullHistory);
+        }
+        capabilities
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<(), bdk::Error> {
+        self.backend.broadcast(tx).await.map_err(bdk_err)
+    }
+
+    async fn estimate_fee(&self, target: usize) -> Result<FeeRate, bdk::Error> {
+        let fee_rate = self.backend.estimate_fee(target).await.map_err(bdk_err)?;
+        Ok(FeeRate::from_sat_per_vb(fee_rate))
+    }
+}
+
+#[async_trait(?Send)]
+impl<B: ChainBackend> GetHeight for ChainBlockchain<B> {
+    async fn get_height(&self) -> Result<u32, bdk::Error> {
+        self.backend.get_height().await.map_err(bdk_err)
+    }
+}
+
+#[async_trait(?Send)]
+impl<B: ChainBackend> GetTx for ChainBlockchain<B> {
+    async fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>, bdk::Error> {
+        self.backend.get_tx(txid).await.map_err(bdk_err)
+    }
+}
+
+#[async_trait(?Send)]
+impl<B: ChainBackend> GetBlockHash for ChainBlockchain<B> {
+    async fn get_block_hash(&self, height: u64) -> Result<BlockHash, bdk::Error> {
+        self.backend
+            .get_block_hash(height as u32)
+            .await
+            .map_err(bdk_err)?
+            .ok_or_else(|| bdk::Error::Generic(format!("Block {height} not found")))
+    }
+}
+
+#[async_trait(?Send)]
+impl<B: ChainBackend> WalletSync for ChainBlockchain<B> {
+    async fn wallet_setup<D: BatchDatabase>(
+        &self,
+        database: &RefCell<D>,
+        _progress_update: Box<dyn Progress>,
+    ) -> Result<(), bdk::Error> {
+        let mut history: BTreeMap<Txid, ScriptTx> = BTreeMap::new();
+        let mut last_indexes: HashMap<KeychainKind, u32> = HashMap::new();
+
+        for keychain in [KeychainKind::External, KeychainKind::Internal] {
+            let mut index = 0;
+            let mut unused = 0;
+            while unused < STOP_GAP {
+                let script = database
+                    .borrow()
+                    .get_script_pubkey_from_path(keychain, index)?;
+                let Some(script) = script else {
+                    break;
+                };
+
+                let script_txs = self
+                    .backend
+                    .get_script_txs(&script)
+                    .await
+                    .map_err(bdk_err)?;
+                if script_txs.is_empty() {
+                    unused += 1;
+                } else {
+                    unused = 0;
+                    last_indexes.insert(keychain, index);
+                }
+
+                for script_tx in script_txs {
+                    history.insert(script_tx.tx.txid(), script_tx);
+                }
+                index += 1;
+            }
+        }
+        debug!(format!("Wallet history: {} transactions", history.len()));
+
+        let spent: HashSet<OutPoint> = history
+            .values()
+            .flat_map(|script_tx| script_tx.tx.input.iter())
+            .map(|input| input.previous_output)
+            .collect();
+        let mut txs: HashMap<Txid, Transaction> = history
+            .iter()
+            .map(|(txid, script_tx)| (*txid, script_tx.tx.clone()))
+            .collect();
+
+        let mut times = BTreeMap::new();
+        let mut details = vec![];
+        let mut utxos = vec![];
+        for (txid, script_tx) in history.iter() {
+            let tx = &script_tx.tx;
+
+            let mut received = 0;
+            for (vout, output) in tx.output.iter().enumerate() {
+                let path = database
+                    .borrow()
+                    .get_path_from_script_pubkey(&output.script_pubkey)?;
+                if let Some((keychain, _)) = path {
+                    received += output.value;
+
+                    let outpoint = OutPoint::new(*txid, vout as u32);
+                    utxos.push(LocalUtxo {
+                        outpoint,
+                        txout: output.clone(),
+                        keychain,
+                        is_spent: spent.contains(&outpoint),
+                    });
+                }
+            }
+
+            let mut sent = 0;
+            let mut inputs_value = 0;
+            // coinbases, and the funding transactions of the mock chain, have no inputs to fetch
+            if !tx.is_coin_base() {
+                for input in tx.input.iter() {
+                    let prev_output = self.prev_output(&input.previous_output, &mut txs).await?;
+                    inputs_value += prev_output.value;
+
+                    let path = database
+                        .borrow()
+                        .get_path_from_script_pubkey(&prev_output.script_pubkey)?;
+                    if path.is_some() {
+                        sent += prev_output.value;
+                    }
+                }
+            }
+
+            let outputs_value: u64 = tx.output.iter().map(|output| output.value).sum();
+            let confirmation_time = match script_tx.status {
+                TxStatus::Block(height) => Some(self.block_time(height, &mut times).await?),
+                _ => None,
+            };
+
+            details.push(TransactionDetails {
+                transaction: Some(tx.clone()),
+                txid: *txid,
+                received,
+                sent,
+                fee: Some(inputs_value.saturating_sub(outputs_value)),
+                confirmation_time,
+            });
+        }
+
+        let tip = self.backend.get_height().await.map_err(bdk_err)?;
+        let tip_time = self.block_time(tip, &mut times).await?;
+
+        // Without the full history the spent transactions are missing, so the ones
+        // of previous syncs are kept with their outputs marked as spent (this also
+        // keeps the transactions dropped by a reorg)
+        let full_history = self.backend.full_history();
+        let mut batch = database.borrow().begin_batch();
+        for utxo in database.borrow().iter_utxos()? {
+            if utxos.iter().any(|new| new.outpoint == utxo.outpoint) {
+                continue;
+            }
+            if full_history {
+                batch.del_utxo(&utxo.outpoint)?;
+            } else if !utxo.is_spent {
+                batch.set_utxo(&LocalUtxo {
+                    is_spent: true,
+                    ..utxo
+                })?;
+            }
+        }
+        if full_history {
+            for tx in database.borrow().iter_txs(false)? {
+                if !history.contains_key(&tx.txid) {
+                    batch.del_tx(&tx.txid, true)?;
+                }
+            }
+        }
+        for detail in details {
+            if let Some(tx) = &detail.transaction {
+                batch.set_raw_tx(tx)?;
+            }
+            batch.set_tx(&detail)?;
+        }
+        for utxo in utxos {
+            batch.set_utxo(&utxo)?;
+        }
+        for (keychain, index) in last_indexes {
+            batch.set_last_index(keychain, index)?;
+        }
+        batch.set_sync_time(SyncTime {
+            block_time: tip_time,
+        })?;
+        database.borrow_mut().commit_batch(batch)?;
+
+        Ok(())
+    }
+}
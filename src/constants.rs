@@ -26,6 +26,18 @@ pub const BMC_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub static ELECTRUM_TIMEOUT: u8 = 4;
 
+/// Seconds the unconfirmed (or shallow) entries of the resolver cache are kept
+pub static RESOLVER_CACHE_TTL: i64 = 60;
+
+/// Confirmations after which the resolved status of a transaction is final
+pub static RESOLVER_CACHE_FINAL_DEPTH: u32 = 6;
+
+/// Seconds a transaction is kept in the resolver cache of an account
+pub static RESOLVER_CACHE_TX_TTL: i64 = 30 * 24 * 60 * 60;
+
+/// Transactions kept in the resolver cache of an account, the oldest ones are dropped first
+pub static RESOLVER_CACHE_MAX_TXS: usize = 2_000;
+
 static BITCOIN_EXPLORER_API_MAINNET: Lazy<RwLock<String>> =
     Lazy::new(|| RwLock::new(dot_env("BITCOIN_EXPLORER_API_MAINNET")));
 static BITCOIN_EXPLORER_API_TESTNET: Lazy<RwLock<String>> =
@@ -36,6 +48,15 @@ static BITCOIN_EXPLORER_API_REGTEST: Lazy<RwLock<String>> =
     Lazy::new(|| RwLock::new(dot_env("BITCOIN_EXPLORER_API_REGTEST")));
 pub static BITCOIN_EXPLORER_API: Lazy<RwLock<String>> =
     Lazy::new(|| RwLock::new(dot_env("BITCOIN_EXPLORER_API_REGTEST")));
+/// Comma-separated Esplora URLs tried, in order, when the explorer fails
+pub static BITCOIN_EXPLORER_API_FALLBACK: Lazy<RwLock<String>> =
+    Lazy::new(|| RwLock::new(env::var("BITCOIN_EXPLORER_API_FALLBACK").unwrap_or_default()));
+/// Retries of an explorer call before moving to the next explorer
+pub static EXPLORER_MAX_RETRIES: Lazy<RwLock<String>> =
+    Lazy::new(|| RwLock::new(env::var("EXPLORER_MAX_RETRIES").unwrap_or("2".to_owned())));
+/// Milliseconds before the first retry, doubled on each attempt
+pub static EXPLORER_RETRY_BACKOFF_MS: Lazy<RwLock<String>> =
+    Lazy::new(|| RwLock::new(env::var("EXPLORER_RETRY_BACKOFF_MS").unwrap_or("250".to_owned())));
 
 static BITCOIN_ELECTRUM_API_MAINNET: Lazy<RwLock<String>> =
     Lazy::new(|| RwLock::new(dot_env("BITCOIN_ELECTRUM_API_MAINNET")));
@@ -48,6 +69,19 @@ static BITCOIN_ELECTRUM_API_REGTEST: Lazy<RwLock<String>> =
 pub static BITCOIN_ELECTRUM_API: Lazy<RwLock<String>> =
     Lazy::new(|| RwLock::new(dot_env("BITCOIN_ELECTRUM_API_REGTEST")));
 
+/// Blockchain backend of the bitcoin wallet and RGB (esplora, electrum, bitcoind or mock)
+pub static BITCOIN_BACKEND: Lazy<RwLock<String>> =
+    Lazy::new(|| RwLock::new(env::var("BITCOIN_BACKEND").unwrap_or("esplora".to_owned())));
+
+// bitcoin core json-rpc (bitcoind backend)
+pub static BITCOIN_RPC_API: Lazy<RwLock<String>> = Lazy::new(|| {
+    RwLock::new(env::var("BITCOIN_RPC_API").unwrap_or("http://localhost:18443".to_owned()))
+});
+pub static BITCOIN_RPC_USER: Lazy<RwLock<String>> =
+    Lazy::new(|| RwLock::new(env::var("BITCOIN_RPC_USER").unwrap_or_default()));
+pub static BITCOIN_RPC_PASSWORD: Lazy<RwLock<String>> =
+    Lazy::new(|| RwLock::new(env::var("BITCOIN_RPC_PASSWORD").unwrap_or_default()));
+
 pub static MARKETPLACE_SEED: Lazy<RwLock<String>> =
     Lazy::new(|| RwLock::new(dot_env("MARKETPLACE_SEED")));
 
@@ -172,11 +206,49 @@ pub static RGB_PROXY_ENDPOINT: Lazy<RwLock<String>> =
 pub static CARBONADO_ENDPOINT: Lazy<RwLock<String>> =
     Lazy::new(|| RwLock::new(dot_env("CARBONADO_ENDPOINT")));
 
+// endpoints which must store a carbonado file ("all", "majority" or a number)
+pub static CARBONADO_WRITE_QUORUM: Lazy<RwLock<String>> =
+    Lazy::new(|| RwLock::new(env::var("CARBONADO_WRITE_QUORUM").unwrap_or("majority".to_owned())));
+
+// previous versions kept per carbonado file, 0 disables the history
+pub static CARBONADO_HISTORY_LIMIT: Lazy<RwLock<String>> =
+    Lazy::new(|| RwLock::new(env::var("CARBONADO_HISTORY_LIMIT").unwrap_or("10".to_owned())));
+
+// seconds a signed carbonado write is accepted after it was signed
+pub static CARBONADO_AUTH_WINDOW: Lazy<RwLock<String>> =
+    Lazy::new(|| RwLock::new(env::var("CARBONADO_AUTH_WINDOW").unwrap_or("300".to_owned())));
+
+// bytes stored per owner by bitmaskd, 0 disables the quota
+pub static CARBONADO_QUOTA: Lazy<RwLock<String>> =
+    Lazy::new(|| RwLock::new(env::var("CARBONADO_QUOTA").unwrap_or("1073741824".to_owned())));
+
+// bytes of a single file stored by bitmaskd, by category
+pub static CARBONADO_FILE_LIMITS: Lazy<RwLock<String>> = Lazy::new(|| {
+    RwLock::new(env::var("CARBONADO_FILE_LIMITS").unwrap_or(
+        "stock=268435456,media=2097152,consignments=134217728,auctions=16777216".to_owned(),
+    ))
+});
+
+// seconds between garbage collections of bitmaskd, 0 disables them
+pub static CARBONADO_GC_INTERVAL: Lazy<RwLock<String>> =
+    Lazy::new(|| RwLock::new(env::var("CARBONADO_GC_INTERVAL").unwrap_or("3600".to_owned())));
+
+// rgb persistence ("carbonado", "sled" or "memory")
+pub static STORAGE_BACKEND: Lazy<RwLock<String>> =
+    Lazy::new(|| RwLock::new(env::var("STORAGE_BACKEND").unwrap_or("carbonado".to_owned())));
+
 pub async fn get_env(key: &str) -> String {
     match key {
         "LNDHUB_ENDPOINT" => LNDHUB_ENDPOINT.read().await.to_string(),
         "BITMASK_ENDPOINT" => BITMASK_ENDPOINT.read().await.to_string(),
         "CARBONADO_ENDPOINT" => CARBONADO_ENDPOINT.read().await.to_string(),
+        "CARBONADO_WRITE_QUORUM" => CARBONADO_WRITE_QUORUM.read().await.to_string(),
+        "CARBONADO_HISTORY_LIMIT" => CARBONADO_HISTORY_LIMIT.read().await.to_string(),
+        "CARBONADO_AUTH_WINDOW" => CARBONADO_AUTH_WINDOW.read().await.to_string(),
+        "CARBONADO_QUOTA" => CARBONADO_QUOTA.read().await.to_string(),
+        "CARBONADO_FILE_LIMITS" => CARBONADO_FILE_LIMITS.read().await.to_string(),
+        "CARBONADO_GC_INTERVAL" => CARBONADO_GC_INTERVAL.read().await.to_string(),
+        "STORAGE_BACKEND" => STORAGE_BACKEND.read().await.to_string(),
         "BITCOIN_EXPLORER_API_MAINNET" => BITCOIN_EXPLORER_API_MAINNET.read().await.to_string(),
         "BITCOIN_EXPLORER_API_TESTNET" => BITCOIN_EXPLORER_API_TESTNET.read().await.to_string(),
         "BITCOIN_EXPLORER_API_SIGNET" => BITCOIN_EXPLORER_API_SIGNET.read().await.to_string(),
@@ -185,6 +257,12 @@ pub async fn get_env(key: &str) -> String {
         "BITCOIN_ELECTRUM_API_TESTNET" => BITCOIN_ELECTRUM_API_TESTNET.read().await.to_string(),
         "BITCOIN_ELECTRUM_API_SIGNET" => BITCOIN_ELECTRUM_API_SIGNET.read().await.to_string(),
         "BITCOIN_ELECTRUM_API_REGTEST" => BITCOIN_ELECTRUM_API_REGTEST.read().await.to_string(),
+        "BITCOIN_EXPLORER_API_FALLBACK" => BITCOIN_EXPLORER_API_FALLBACK.read().await.to_string(),
+        "EXPLORER_MAX_RETRIES" => EXPLORER_MAX_RETRIES.read().await.to_string(),
+        "EXPLORER_RETRY_BACKOFF_MS" => EXPLORER_RETRY_BACKOFF_MS.read().await.to_string(),
+        "BITCOIN_BACKEND" => BITCOIN_BACKEND.read().await.to_string(),
+        "BITCOIN_RPC_API" => BITCOIN_RPC_API.read().await.to_string(),
+        "BITCOIN_RPC_USER" => BITCOIN_RPC_USER.read().await.to_string(),
         _ => {
             error!(format!("get_env called an unknown key, {key}"));
             "".to_owned()
@@ -197,6 +275,13 @@ pub async fn set_env(key: &str, value: &str) {
         "LNDHUB_ENDPOINT" => *LNDHUB_ENDPOINT.write().await = value.to_owned(),
         "BITMASK_ENDPOINT" => *BITMASK_ENDPOINT.write().await = value.to_owned(),
         "CARBONADO_ENDPOINT" => *CARBONADO_ENDPOINT.write().await = value.to_owned(),
+        "CARBONADO_WRITE_QUORUM" => *CARBONADO_WRITE_QUORUM.write().await = value.to_owned(),
+        "CARBONADO_HISTORY_LIMIT" => *CARBONADO_HISTORY_LIMIT.write().await = value.to_owned(),
+        "CARBONADO_AUTH_WINDOW" => *CARBONADO_AUTH_WINDOW.write().await = value.to_owned(),
+        "CARBONADO_QUOTA" => *CARBONADO_QUOTA.write().await = value.to_owned(),
+        "CARBONADO_FILE_LIMITS" => *CARBONADO_FILE_LIMITS.write().await = value.to_owned(),
+        "CARBONADO_GC_INTERVAL" => *CARBONADO_GC_INTERVAL.write().await = value.to_owned(),
+        "STORAGE_BACKEND" => *STORAGE_BACKEND.write().await = value.to_owned(),
         "BITCOIN_EXPLORER_API_MAINNET" => {
             *BITCOIN_EXPLORER_API_MAINNET.write().await = value.to_owned()
         }
@@ -221,6 +306,15 @@ pub async fn set_env(key: &str, value: &str) {
         "BITCOIN_ELECTRUM_API_REGTEST" => {
             *BITCOIN_ELECTRUM_API_REGTEST.write().await = value.to_owned()
         }
+        "BITCOIN_EXPLORER_API_FALLBACK" => {
+            *BITCOIN_EXPLORER_API_FALLBACK.write().await = value.to_owned()
+        }
+        "EXPLORER_MAX_RETRIES" => *EXPLORER_MAX_RETRIES.write().await = value.to_owned(),
+        "EXPLORER_RETRY_BACKOFF_MS" => *EXPLORER_RETRY_BACKOFF_MS.write().await = value.to_owned(),
+        "BITCOIN_BACKEND" => *BITCOIN_BACKEND.write().await = value.to_owned(),
+        "BITCOIN_RPC_API" => *BITCOIN_RPC_API.write().await = value.to_owned(),
+        "BITCOIN_RPC_USER" => *BITCOIN_RPC_USER.write().await = value.to_owned(),
+        "BITCOIN_RPC_PASSWORD" => *BITCOIN_RPC_PASSWORD.write().await = value.to_owned(),
         _ => {
             error!(format!("set_env called an unknown key, {key}"));
         }
@@ -233,5 +327,11 @@ pub mod storage_keys {
     pub const ASSETS_TRANSFERS: &str = "bitmask_assets_transfers.c15";
     pub const ASSETS_OFFERS: &str = "bitmask-asset_offers.c15";
     pub const ASSETS_BIDS: &str = "bitmask-asset_bids.c15";
+    pub const ASSETS_WATCHERS: &str = "bitmask-asset_watchers.c15";
+    pub const ASSETS_ANCHORS: &str = "bitmask-asset_anchors.c15";
+    pub const ASSETS_RESERVED: &str = "bitmask-asset_reserved_utxos.c15";
+    pub const ACCOUNT_LOCKED: &str = "bitmask-account_locked_utxos.c15";
+    pub const ACCOUNT_LABELS: &str = "bitmask-account_labels.c15";
+    pub const RESOLVER_CACHE: &str = "bitmask-resolver_cache.c15";
     pub const MARKETPLACE_OFFERS: &str = "bitmask-marketplace_public_offers.c15";
 }
@@ -0,0 +1,228 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use bitcoin::OutPoint;
+use thiserror::Error;
+
+use crate::{
+    bitcoin::{get_wallet_data, lock_utxos, unlock_utxos, BitcoinError},
+    rgb::fs::{retrieve_labels, store_labels, RgbPersistenceError},
+    structs::{
+        AllocationDetail, ContractResponse, Label, LabelType, LabelsRequest, LabelsResponse,
+        RgbTransferDetail, SecretString, WalletData,
+    },
+};
+
+#[derive(Error, Debug)]
+pub enum LabelError {
+    /// Wrong BIP-329 record
+    #[error("Wrong BIP-329 record at line {0}: {1}")]
+    WrongRecord(usize, String),
+    /// Empty reference
+    #[error("Label reference cannot be empty")]
+    EmptyReference,
+    /// Spendable flag is only allowed on outputs
+    #[error("Spendable flag is only allowed on outputs. Reference: {0}")]
+    WrongSpendable(String),
+    /// Wrong outpoint reference
+    #[error("Wrong outpoint reference: {0}")]
+    WrongOutpoint(String),
+    /// Storage error
+    #[error(transparent)]
+    IO(#[from] RgbPersistenceError),
+    /// Bitcoin error
+    #[error(transparent)]
+    Bitcoin(#[from] BitcoinError),
+}
+
+/// Labels of the user account, indexed by type and reference
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Labels {
+    labels: BTreeMap<(LabelType, String), Label>,
+}
+
+impl Labels {
+    pub fn get(&self, ty: LabelType, reference: &str) -> Option<&Label> {
+        self.labels.get(&(ty, reference.to_string()))
+    }
+
+    pub fn label(&self, ty: LabelType, reference: &str) -> Option<String> {
+        self.get(ty, reference)
+            .and_then(|label| label.label.clone())
+    }
+
+    pub fn insert(&mut self, label: Label) -> Result<(), LabelError> {
+        if label.reference.trim().is_empty() {
+            return Err(LabelError::EmptyReference);
+        }
+        if label.spendable.is_some() && label.ty != LabelType::Output {
+            return Err(LabelError::WrongSpendable(label.reference));
+        }
+
+        self.labels
+            .insert((label.ty, label.reference.clone()), label);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, ty: LabelType, reference: &str) -> Option<Label> {
+        self.labels.remove(&(ty, reference.to_string()))
+    }
+
+    pub fn list(&self) -> Vec<Label> {
+        self.labels.values().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Parse BIP-329 JSON Lines (blank lines are ignored)
+    pub fn from_jsonl(data: &str) -> Result<Self, LabelError> {
+        let mut labels = Labels::default();
+        for (index, line) in data.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let label: Label = serde_json::from_str(line)
+                .map_err(|op| LabelError::WrongRecord(index + 1, op.to_string()))?;
+            labels
+                .insert(label)
+                .map_err(|op| LabelError::WrongRecord(index + 1, op.to_string()))?;
+        }
+        Ok(labels)
+    }
+
+    /// Export as BIP-329 JSON Lines
+    pub fn to_jsonl(&self) -> String {
+        self.labels
+            .values()
+            .map(|label| serde_json::to_string(label).expect("label serialization never fails"))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Merge labels, the incoming records overwrite the existing ones
+    pub fn extend(&mut self, other: Labels) {
+        self.labels.extend(other.labels);
+    }
+
+    pub fn label_allocations(&self, allocations: &mut [AllocationDetail]) {
+        for allocation in allocations {
+            allocation.label = self.label(LabelType::Output, &allocation.utxo);
+        }
+    }
+
+    pub fn label_contract(&self, contract: &mut ContractResponse) {
+        contract.label = self.label(LabelType::Contract, &contract.contract_id);
+        self.label_allocations(&mut contract.allocations);
+    }
+
+    pub fn label_transfers(&self, transfers: &mut [RgbTransferDetail]) {
+        for transfer in transfers {
+            transfer.label = self.label(LabelType::Consignment, &transfer.consig_id);
+        }
+    }
+
+    pub fn label_wallet_data(&self, wallet_data: &mut WalletData) {
+        for tx in wallet_data.transactions.iter_mut() {
+            tx.label = self.label(LabelType::Tx, &tx.txid.to_string());
+        }
+    }
+}
+
+/// Apply the BIP-329 `spendable` flag of the outputs to the coin control
+async fn apply_spendable(sk: &str, labels: &[Label]) -> Result<(), LabelError> {
+    let mut locked = vec![];
+    let mut unlocked = vec![];
+    for label in labels.iter().filter(|label| label.ty == LabelType::Output) {
+        let outpoint = match label.spendable {
+            Some(_) => OutPoint::from_str(&label.reference)
+                .map_err(|_| LabelError::WrongOutpoint(label.reference.clone()))?,
+            None => continue,
+        };
+
+        match label.spendable {
+            Some(false) => locked.push(outpoint),
+            _ => unlocked.push(outpoint),
+        }
+    }
+
+    lock_utxos(sk, locked).await?;
+    unlock_utxos(sk, unlocked).await?;
+    Ok(())
+}
+
+pub async fn list_labels(sk: &str) -> Result<LabelsResponse, LabelError> {
+    let labels = retrieve_labels(sk).await?;
+    Ok(LabelsResponse {
+        labels: labels.list(),
+    })
+}
+
+pub async fn set_labels(sk: &str, request: LabelsRequest) -> Result<LabelsResponse, LabelError> {
+    let mut labels = retrieve_labels(sk).await?;
+    for label in request.labels.clone() {
+        labels.insert(label)?;
+    }
+
+    apply_spendable(sk, &request.labels).await?;
+    store_labels(sk, labels.clone()).await?;
+
+    Ok(LabelsResponse {
+        labels: labels.list(),
+    })
+}
+
+pub async fn remove_label(
+    sk: &str,
+    ty: LabelType,
+    reference: &str,
+) -> Result<LabelsResponse, LabelError> {
+    let mut labels = retrieve_labels(sk).await?;
+    if let Some(label) = labels.remove(ty, reference) {
+        if label.spendable == Some(false) {
+            if let Ok(outpoint) = OutPoint::from_str(&label.reference) {
+                unlock_utxos(sk, vec![outpoint]).await?;
+            }
+        }
+        store_labels(sk, labels.clone()).await?;
+    }
+
+    Ok(LabelsResponse {
+        labels: labels.list(),
+    })
+}
+
+pub async fn import_labels(sk: &str, jsonl: &str) -> Result<LabelsResponse, LabelError> {
+    let imported = Labels::from_jsonl(jsonl)?;
+    apply_spendable(sk, &imported.list()).await?;
+
+    let mut labels = retrieve_labels(sk).await?;
+    labels.extend(imported);
+    store_labels(sk, labels.clone()).await?;
+
+    Ok(LabelsResponse {
+        labels: labels.list(),
+    })
+}
+
+pub async fn export_labels(sk: &str) -> Result<String, LabelError> {
+    let labels = retrieve_labels(sk).await?;
+    Ok(labels.to_jsonl())
+}
+
+pub async fn get_labeled_wallet_data(
+    sk: &str,
+    descriptor: &SecretString,
+    change_descriptor: Option<&SecretString>,
+) -> Result<WalletData, LabelError> {
+    let mut wallet_data = get_wallet_data(descriptor, change_descriptor).await?;
+    let labels = retrieve_labels(sk).await?;
+    labels.label_wallet_data(&mut wallet_data);
+
+    Ok(wallet_data)
+}
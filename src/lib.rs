@@ -3,8 +3,10 @@ extern crate amplify;
 
 pub mod bitcoin;
 pub mod carbonado;
+pub mod chain;
 pub mod constants;
 pub mod error;
+pub mod labels;
 pub mod lightning;
 pub mod nostr;
 pub mod proxy;
@@ -14,7 +14,7 @@ pub enum ProxyServerError {
 #[cfg(not(target_arch = "wasm32"))]
 pub use server::{
     handle_file, proxy_consig_retrieve, proxy_consig_store, proxy_media_data_store,
-    proxy_media_retrieve, proxy_metadata_retrieve,
+    proxy_media_file, proxy_media_retrieve, proxy_metadata_retrieve,
 };
 #[cfg(not(target_arch = "wasm32"))]
 mod server {
@@ -87,6 +87,47 @@ mod server {
         fetch_media_get(attachment_id).await
     }
 
+    /// Media of the RGB proxy, decoded in a local file so it can be read by ranges
+    ///
+    /// Media ids are content hashes, so the file is kept for the next reads.
+    /// `None` when the proxy doesn't have the media.
+    pub async fn proxy_media_file(
+        attachment_id: &str,
+    ) -> Result<Option<PathBuf>, ProxyServerError> {
+        if !attachment_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(ProxyServerError::Parse(format!(
+                "Invalid media id {attachment_id}"
+            )));
+        }
+
+        let filepath = handle_file(&format!("media-{attachment_id}"), 0).await?;
+        if fs::metadata(&filepath).await.is_ok() {
+            return Ok(Some(filepath));
+        }
+
+        let Some(resp) = fetch_media_get(attachment_id).await? else {
+            return Ok(None);
+        };
+        let bytes =
+            base64::decode(&resp.result).map_err(|op| ProxyServerError::Parse(op.to_string()))?;
+        if bytes.is_empty() {
+            return Ok(None);
+        }
+
+        // readers never see a partial file
+        let partial = filepath.with_extension("partial");
+        fs::write(&partial, &bytes)
+            .await
+            .map_err(|op| ProxyServerError::IO(op.to_string()))?;
+        fs::rename(&partial, &filepath)
+            .await
+            .map_err(|op| ProxyServerError::IO(op.to_string()))?;
+        Ok(Some(filepath))
+    }
+
     pub async fn proxy_media_data_store(
         media: MediaItemRequest,
         encode: MediaEncode,
@@ -399,16 +440,32 @@ mod client {
     ) -> Result<Option<RgbProxyMediaRes>, ProxyServerError> {
         let endpoint = BITMASK_ENDPOINT.read().await.to_string();
 
+        // bitmaskd serves the decoded media, the proxy response is rebuilt around it
         let url = format!("{endpoint}/proxy/media/{attachment_id}");
-        let response = get(&url, None)
+        let response = reqwest::Client::new()
+            .get(&url)
+            .send()
             .await
-            .map_err(|op| ProxyServerError::Parse(op.to_string()))?;
+            .map_err(|op| ProxyServerError::Server(op.to_string()))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(ProxyServerError::Server(format!(
+                "GET {url} answered {}",
+                response.status()
+            )));
+        }
 
-        let resp = match serde_json::from_str::<RgbProxyMediaRes>(&response) {
-            Ok(resp) => Some(resp),
-            Err(_) => None,
-        };
-        Ok(resp)
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|op| ProxyServerError::Parse(op.to_string()))?;
+        Ok(Some(RgbProxyMediaRes {
+            jsonrpc: "2.0".to_string(),
+            id: attachment_id.to_string(),
+            result: base64::encode(&bytes),
+        }))
     }
 
     pub async fn proxy_metadata_retrieve(
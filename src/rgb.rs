@@ -2,14 +2,16 @@ use ::psbt::{serialize::Serialize, Psbt};
 use amplify::{confinement::U32, hex::ToHex};
 use anyhow::Result;
 use autosurgeon::reconcile;
-use bitcoin::{psbt::PartiallySignedTransaction as PsbtV0, Network, Txid};
+use bdk::FeeRate;
+use bitcoin::{psbt::PartiallySignedTransaction as PsbtV0, Network, OutPoint, Txid};
 use bitcoin_30::bip32::ExtendedPubKey;
 use bitcoin_scripts::address::AddressNetwork;
+use bp::Outpoint;
 use futures::TryFutureExt;
 use garde::Validate;
 
-use miniscript_crate::DescriptorPublicKey;
-use rgb::RgbDescr;
+use miniscript_crate::{descriptor::Wildcard, Descriptor, DescriptorPublicKey};
+use rgb::{MiningStatus, RgbDescr, RgbWallet};
 use rgbstd::{
     containers::BindleContent,
     contract::ContractId,
@@ -28,8 +30,10 @@ use strict_encoding::{tn, StrictSerialize};
 use thiserror::Error;
 
 pub mod accept;
+pub mod backup;
 pub mod cambria;
 pub mod carbonado;
+pub mod chunks;
 pub mod consignment;
 pub mod constants;
 pub mod contract;
@@ -37,19 +41,38 @@ pub mod crdt;
 pub mod fs;
 pub mod import;
 pub mod issue;
+pub mod migration;
 pub mod prebuild;
 pub mod prefetch;
 pub mod proxy;
 pub mod psbt;
+pub mod reorg;
+pub mod rescan;
 pub mod resolvers;
+pub mod storage;
 pub mod structs;
 pub mod swap;
+pub mod sync;
 pub mod transfer;
 pub mod wallet;
 
 use crate::{
-    bitcoin::{publish_psbt_file, sign_psbt_file},
-    constants::{get_network, BITCOIN_EXPLORER_API, NETWORK},
+    bitcoin::{
+        create_transaction, get_wallet, list_locked_utxos, publish_psbt_file, sign_psbt_file,
+    },
+    chain::{
+        account_chain_backend, merge_resolver_cache, resolver_account, resolver_cache_snapshot,
+        ChainBackend,
+    },
+    constants::{
+        get_network,
+        storage_keys::{
+            ACCOUNT_LABELS, ACCOUNT_LOCKED, ASSETS_ANCHORS, ASSETS_BIDS, ASSETS_OFFERS,
+            ASSETS_RESERVED, ASSETS_STOCK, ASSETS_TRANSFERS, ASSETS_WALLETS, ASSETS_WATCHERS,
+        },
+        BITCOIN_EXPLORER_API, NETWORK,
+    },
+    info,
     rgb::{
         issue::{issue_contract as create_contract, IssueContractError},
         psbt::{create_psbt as create_rgb_psbt, extract_output_commit},
@@ -61,42 +84,56 @@ use crate::{
         wallet::list_allocations,
     },
     structs::{
-        AcceptRequest, AcceptResponse, AssetType, BatchRgbTransferItem, BatchRgbTransferResponse,
-        ContractHiddenResponse, ContractResponse, ContractsResponse, FullRgbTransferRequest,
-        ImportRequest, InterfaceDetail, InterfacesResponse, InvoiceRequest, InvoiceResponse,
-        IssueMediaRequest, IssueRequest, IssueResponse, MediaEncode, MediaRequest, MediaResponse,
-        MediaView, NextAddressResponse, NextUtxoResponse, NextUtxosResponse, PsbtFeeRequest,
-        PsbtRequest, PsbtResponse, PublicRgbBidResponse, PublicRgbOfferResponse,
-        PublicRgbOffersResponse, PublishPsbtRequest, ReIssueRequest, ReIssueResponse,
-        RgbAuctionBidRequest, RgbAuctionBidResponse, RgbAuctionFinishResponse,
-        RgbAuctionOfferRequest, RgbAuctionOfferResponse, RgbBidDetail, RgbBidRequest,
+        AcceptRequest, AcceptResponse, Address, AssetType, BalancesResponse, BatchRgbTransferItem,
+        BatchRgbTransferResponse, CoinControl, ContractHiddenResponse, ContractResponse,
+        ContractsResponse, FullRgbTransferRequest, ImportRequest, InterfaceDetail,
+        InterfacesResponse, InvoiceRequest, InvoiceResponse, IssueMediaRequest, IssueRequest,
+        IssueResponse, LabelType, MediaEncode, MediaRequest, MediaResponse, MediaView,
+        NextAddressResponse, NextUtxoResponse, NextUtxosResponse, PsbtFeeRequest, PsbtRequest,
+        PsbtResponse, PublicRgbBidResponse, PublicRgbOfferResponse, PublicRgbOffersResponse,
+        PublishPsbtRequest, ReIssueRequest, ReIssueResponse, RgbAuctionBidRequest,
+        RgbAuctionBidResponse, RgbAuctionFinishResponse, RgbAuctionOfferRequest,
+        RgbAuctionOfferResponse, RgbBackupRequest, RgbBackupResponse, RgbBidDetail, RgbBidRequest,
         RgbBidResponse, RgbBidsResponse, RgbInternalSaveTransferRequest,
-        RgbInternalTransferResponse, RgbInvoiceResponse, RgbOfferBidsResponse, RgbOfferDetail,
-        RgbOfferRequest, RgbOfferResponse, RgbOfferUpdateRequest, RgbOfferUpdateResponse,
-        RgbOffersResponse, RgbRemoveTransferRequest, RgbReplaceResponse, RgbSaveTransferRequest,
-        RgbSwapItem, RgbSwapRequest, RgbSwapResponse, RgbTransferDetail, RgbTransferRequest,
-        RgbTransferResponse, RgbTransferStatusResponse, RgbTransfersResponse, SchemaDetail,
-        SchemasResponse, SignPsbtRequest, SignedPsbtResponse, SimpleContractResponse, TransferType,
+        RgbInternalTransferResponse, RgbInvoiceResponse, RgbMigrationResponse,
+        RgbOfferBidsResponse, RgbOfferDetail, RgbOfferRequest, RgbOfferResponse,
+        RgbOfferUpdateRequest, RgbOfferUpdateResponse, RgbOffersResponse, RgbProvisionRequest,
+        RgbProvisionResponse, RgbRemoveTransferRequest, RgbReorgEvent, RgbReorgResponse,
+        RgbReplaceResponse, RgbRescanRequest, RgbRescanResponse, RgbRestoreRequest,
+        RgbRestoreResponse, RgbSaveTransferRequest, RgbSwapItem, RgbSwapRequest, RgbSwapResponse,
+        RgbSyncDocument, RgbSyncRequest, RgbSyncResponse, RgbTopUpRequest, RgbTransferDetail,
+        RgbTransferRequest, RgbTransferResponse, RgbTransferStatusResponse, RgbTransfersResponse,
+        RgbUtxoPoolRequest, RgbUtxoPoolResponse, SatsInvoice, SchemaDetail, SchemasResponse,
+        SecretString, SignPsbtRequest, SignedPsbtResponse, SimpleContractResponse, TransferType,
         TxStatus, UtxoResponse, WatcherDetailResponse, WatcherRequest, WatcherResponse,
-        WatcherUtxoResponse,
+        WatcherUtxoResponse, WatchersRequest, WatchersResponse,
     },
     validators::RGBContext,
+    warn,
 };
 
 use self::{
+    backup::{check_lib_ids, decrypt_backup, encrypt_backup, BackupError, RgbBackup},
     consignment::NewTransferOptions,
-    constants::{RGB_DEFAULT_FETCH_LIMIT, RGB_DEFAULT_NAME},
+    constants::{
+        RGB_ACCOUNT_VERSION, RGB_DEFAULT_FETCH_LIMIT, RGB_DEFAULT_NAME, RGB_STRICT_TYPE_VERSION,
+        RGB_TRANSFER_VERSION,
+    },
     contract::{export_boilerplate, export_contract, extract_metadata, ExportContractError},
     crdt::{LocalRgbAccount, RawRgbAccount, RgbMerge},
     fs::{
-        retrieve_account, retrieve_bids, retrieve_local_account, retrieve_offers,
-        retrieve_public_offers, retrieve_stock as retrieve_rgb_stock, retrieve_stock_account,
+        retrieve_account, retrieve_anchors, retrieve_bids, retrieve_labels, retrieve_local_account,
+        retrieve_locked_utxos, retrieve_offers, retrieve_public_offers, retrieve_reserved_utxos,
+        retrieve_resolver_cache, retrieve_stock as retrieve_rgb_stock, retrieve_stock_account,
         retrieve_stock_account_transfers, retrieve_stock_transfers, retrieve_transfers,
-        store_account, store_bids, store_local_account, store_offers,
-        store_stock as store_rgb_stock, store_stock_account, store_stock_account_transfers,
-        store_stock_transfers, store_transfers, RgbPersistenceError,
+        retrieve_watchers, store_account, store_anchors, store_bids, store_labels,
+        store_local_account, store_locked_utxos, store_offers, store_reserved_utxos,
+        store_resolver_cache, store_stock as store_rgb_stock, store_stock_account,
+        store_stock_account_transfers, store_stock_transfers, store_transfers, store_watchers,
+        RgbPersistenceError,
     },
     import::{import_contract, ImportContractError},
+    migration::{migrate_account_files, plan_account_files, MigrationError, PersistedModel},
     prebuild::{
         prebuild_buyer_swap, prebuild_extract_transfer, prebuild_seller_swap,
         prebuild_transfer_asset,
@@ -113,9 +150,16 @@ use self::{
     psbt::{
         save_rgb_commit_str, set_tapret_output, CreatePsbtError, EstimateFeeError, NewPsbtOptions,
     },
+    reorg::{
+        apply_anchor_check, check_anchor, mark_pending_contract, notify_reorg, prune_anchors,
+        revalidate_transfer, track_anchors,
+    },
+    rescan::{clear_rescan_progress, get_rescan_progress, rescan_wallet},
+    storage::storage_backend,
     structs::{
-        ContractAmount, ContractBoilerplate, MediaMetadata, RgbAccountV1, RgbExtractTransfer,
-        RgbTransferV1, RgbTransfersV1,
+        ContractAmount, ContractBoilerplate, MediaMetadata, RgbAccountV1, RgbAnchors,
+        RgbExtractTransfer, RgbLockedUtxos, RgbReservedUtxos, RgbTransferV1, RgbTransfersV1,
+        RgbUtxoPool, RgbWatchers,
     },
     swap::{
         complete_bid, complete_offer, get_auction, get_auction_fifo_bids, get_auction_highest_bids,
@@ -123,13 +167,14 @@ use self::{
         get_swap_bid_by_seller, get_swap_bids_by_offer, publish_auction_bid,
         publish_auction_offers, publish_public_bid, publish_public_offer, publish_swap_bid,
         remove_public_offers, update_transfer_bid, update_transfer_offer, PsbtSwapEx,
-        RgbAuctionStrategy, RgbAuctionSwaps, RgbBid, RgbBidSwap, RgbOffer, RgbOfferErrors,
-        RgbOfferOptions, RgbOfferSwap, RgbSwapStrategy,
+        RgbAuctionStrategy, RgbAuctionSwaps, RgbBid, RgbBidSwap, RgbBids, RgbOffer, RgbOfferErrors,
+        RgbOfferOptions, RgbOfferSwap, RgbOffers, RgbSwapStrategy,
     },
+    sync::{publish_sync_event, sync_account_documents, sync_account_from_relays, SyncError},
     transfer::{extract_transfer, AcceptTransferError, NewInvoiceError, NewPaymentError},
     wallet::{
-        create_wallet, next_address, next_utxo, next_utxos, register_address, register_utxo,
-        sync_wallet,
+        create_wallet, list_balances, list_free_utxos, next_address, next_addresses, next_utxo,
+        next_utxos, register_address, register_utxo, save_new_utxo, sync_wallet,
     },
 };
 
@@ -173,6 +218,7 @@ pub async fn issue_contract(sk: &str, request: IssueRequest) -> Result<IssueResp
 
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
 
@@ -294,6 +340,7 @@ pub async fn reissue_contract(
 
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
 
@@ -535,6 +582,7 @@ pub enum PsbtError {
 pub async fn create_psbt(sk: &str, request: PsbtRequest) -> Result<PsbtResponse, PsbtError> {
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
 
@@ -662,6 +710,8 @@ pub enum TransferError {
     Save(SaveTransferError),
     /// Occurs an error in retrieve proxy step. {0}
     Proxy(ProxyError),
+    /// Bitcoin explorer is unavailable. {0}
+    Explorer(String),
 }
 
 pub async fn full_transfer_asset(
@@ -694,6 +744,7 @@ pub async fn full_transfer_asset(
 
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
 
@@ -919,6 +970,8 @@ pub enum RgbSwapError {
     WrongConsigSwap(String),
     /// Final PSBT cannot be finished. {0}
     WrongPsbtFinal(String),
+    /// Bitcoin explorer is unavailable. {0}
+    Explorer(String),
 }
 
 pub async fn create_seller_offer(
@@ -935,6 +988,7 @@ pub async fn create_seller_offer(
 
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
 
@@ -1012,6 +1066,7 @@ pub async fn create_auction_offers(
 
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
 
@@ -1107,7 +1162,7 @@ pub async fn create_auction_offers(
         .await
         .map_err(RgbSwapError::IO)?;
 
-    publish_auction_offers(request.strategy, collection)
+    publish_auction_offers(sk, request.strategy, request.bidders, collection)
         .await
         .map_err(RgbSwapError::Auction)?;
 
@@ -1273,6 +1328,7 @@ pub async fn create_buyer_bid(
 
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
 
@@ -1329,6 +1385,7 @@ pub async fn create_auction_bid(
 
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
 
@@ -1456,7 +1513,7 @@ pub async fn create_auction_bid(
 
             bid_swap.buyer_psbt = buyer_swap_psbt;
             bid_swap.swap_psbt = Some(final_swap_psbt.clone());
-            publish_auction_bid(bid_swap)
+            publish_auction_bid(sk, bid_swap)
                 .await
                 .map_err(RgbSwapError::Auction)?;
 
@@ -2275,6 +2332,7 @@ pub async fn accept_transfer(
     let mut stock = retrieve_rgb_stock(sk).await.map_err(TransferError::IO)?;
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
 
@@ -2483,7 +2541,24 @@ pub async fn remove_transfer(
     })
 }
 
+/// Reuse the transactions and heights resolved by previous calls, the cache
+/// only saves lookups so its errors are not fatal
+async fn load_resolver_cache(sk: &str) {
+    match retrieve_resolver_cache(sk).await {
+        Ok(cache) => merge_resolver_cache(&resolver_account(sk), cache),
+        Err(err) => warn!(format!("resolver cache not loaded: {err}")),
+    }
+}
+
+async fn save_resolver_cache(sk: &str) {
+    let cache = resolver_cache_snapshot(&resolver_account(sk));
+    if let Err(err) = store_resolver_cache(sk, cache).await {
+        warn!(format!("resolver cache not stored: {err}"));
+    }
+}
+
 pub async fn verify_transfers(sk: &str) -> Result<BatchRgbTransferResponse, TransferError> {
+    load_resolver_cache(sk).await;
     let (mut stock, mut rgb_accounts, mut rgb_transfers) = retrieve_stock_account_transfers(sk)
         .await
         .map_err(TransferError::IO)?;
@@ -2493,7 +2568,11 @@ pub async fn verify_transfers(sk: &str) -> Result<BatchRgbTransferResponse, Tran
         .await
         .map_err(TransferError::Save)?;
 
-    let (rgb_pending, transfers) = internal_verify_transfers(&mut stock, rgb_transfers).await?;
+    let (rgb_pending, transfers) =
+        internal_verify_transfers(sk, &mut stock, rgb_transfers.clone()).await?;
+    if let Err(err) = watch_accepted_transfers(sk, &rgb_transfers, &transfers).await {
+        warn!(format!("anchors not tracked: {err}"));
+    }
 
     let mut my_public_offers = vec![];
     let check_offers: Vec<_> = transfers
@@ -2544,10 +2623,111 @@ pub async fn verify_transfers(sk: &str) -> Result<BatchRgbTransferResponse, Tran
     store_stock_account_transfers(sk, stock, rgb_accounts, rgb_pending)
         .await
         .map_err(TransferError::IO)?;
+    save_resolver_cache(sk).await;
 
     Ok(BatchRgbTransferResponse { transfers })
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, Display, From, Error)]
+#[display(doc_comments)]
+pub enum ReorgError {
+    /// I/O or connectivity error. {0}
+    IO(RgbPersistenceError),
+    /// Blockchain backend error. {0}
+    Backend(String),
+}
+
+/// Watch the witness transactions of the accepted transfers for reorganizations
+async fn watch_accepted_transfers(
+    sk: &str,
+    rgb_transfers: &RgbTransfersV1,
+    transfers: &[BatchRgbTransferItem],
+) -> Result<(), ReorgError> {
+    if !transfers.iter().any(|item| item.is_accept) {
+        return Ok(());
+    }
+
+    let backend = account_chain_backend(&resolver_account(sk))
+        .await
+        .map_err(|op| ReorgError::Backend(op.to_string()))?;
+    let mut rgb_anchors = retrieve_anchors(sk).await.map_err(ReorgError::IO)?;
+    track_anchors(&backend, &mut rgb_anchors, rgb_transfers, transfers)
+        .await
+        .map_err(|op| ReorgError::Backend(op.to_string()))?;
+
+    store_anchors(sk, rgb_anchors)
+        .await
+        .map_err(ReorgError::IO)?;
+    Ok(())
+}
+
+pub async fn check_reorgs(sk: &str) -> Result<RgbReorgResponse, ReorgError> {
+    let backend = account_chain_backend(&resolver_account(sk))
+        .await
+        .map_err(|op| ReorgError::Backend(op.to_string()))?;
+    let tip = backend
+        .get_height()
+        .await
+        .map_err(|op| ReorgError::Backend(op.to_string()))?;
+
+    let mut rgb_anchors = retrieve_anchors(sk).await.map_err(ReorgError::IO)?;
+    let mut rgb_account = retrieve_account(sk).await.map_err(ReorgError::IO)?;
+    let mut rgb_transfers = retrieve_transfers(sk).await.map_err(ReorgError::IO)?;
+
+    let mut resolver = ExplorerResolver {
+        explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
+        ..default!()
+    };
+
+    let mut reorgs = vec![];
+    for (consig_id, anchor) in rgb_anchors.anchors.clone() {
+        let check = check_anchor(&backend, &anchor)
+            .await
+            .map_err(|op| ReorgError::Backend(op.to_string()))?;
+
+        let Some(status) = apply_anchor_check(
+            &mut rgb_anchors,
+            &mut rgb_account,
+            &mut rgb_transfers,
+            &consig_id,
+            check,
+        ) else {
+            continue;
+        };
+
+        prefetch_resolver_rgb(&anchor.transfer.consig, &mut resolver, None).await;
+        let event = RgbReorgEvent {
+            contract_id: anchor.contract_id,
+            consig_id,
+            txid: anchor.transfer.tx_id.to_hex(),
+            height: anchor.height,
+            status,
+            is_valid: revalidate_transfer(&anchor.transfer.consig, &mut resolver),
+        };
+
+        notify_reorg(&event);
+        reorgs.push(event);
+    }
+    let pruned = prune_anchors(&mut rgb_anchors, tip);
+
+    if !reorgs.is_empty() {
+        store_account(sk, rgb_account)
+            .await
+            .map_err(ReorgError::IO)?;
+        store_transfers(sk, rgb_transfers)
+            .await
+            .map_err(ReorgError::IO)?;
+    }
+    if !reorgs.is_empty() || pruned > 0 {
+        store_anchors(sk, rgb_anchors)
+            .await
+            .map_err(ReorgError::IO)?;
+    }
+
+    Ok(RgbReorgResponse { tip, reorgs })
+}
+
 pub async fn internal_extract_transfers_swaps(
     sk: &str,
     rgb_accounts: &mut RgbAccountV1,
@@ -2728,11 +2908,13 @@ pub async fn internal_extract_transfers_seals(
 }
 
 pub async fn internal_verify_transfers(
+    sk: &str,
     stock: &mut Stock,
     rgb_transfers: RgbTransfersV1,
 ) -> Result<(RgbTransfersV1, Vec<BatchRgbTransferItem>), TransferError> {
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
 
@@ -2815,6 +2997,7 @@ pub async fn internal_verify_transfers(
 pub async fn get_contract(sk: &str, contract_id: &str) -> Result<ContractResponse> {
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
 
@@ -2834,7 +3017,7 @@ pub async fn get_contract(sk: &str, contract_id: &str) -> Result<ContractRespons
                     .map_err(|_| TransferError::NoIface)?;
 
                 if let Ok(contract_iface) = stock.contract_iface(contract_id, iface.iface_id()) {
-                    sync_wallet(contract_index, &mut fetch_wallet, &mut resolver);
+                    sync_wallet(contract_index, &mut fetch_wallet, &mut resolver)?;
                     prefetch_resolver_allocations(contract_iface, &mut resolver, true).await;
                     prefetch_resolver_utxos(
                         contract_index,
@@ -2861,6 +3044,8 @@ pub async fn get_contract(sk: &str, contract_id: &str) -> Result<ContractRespons
     } else {
         None
     };
+    retrieve_labels(sk).await?.label_contract(&mut contract);
+    mark_pending_contract(&retrieve_anchors(sk).await?, &mut contract);
 
     if let Some(wallet) = wallet {
         rgb_account
@@ -2907,8 +3092,10 @@ pub async fn hidden_contract(sk: &str, contract_id: &str) -> Result<ContractHidd
 }
 
 pub async fn list_contracts(sk: &str, hidden_contracts: bool) -> Result<ContractsResponse> {
+    load_resolver_cache(sk).await;
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
 
@@ -2920,7 +3107,7 @@ pub async fn list_contracts(sk: &str, hidden_contracts: bool) -> Result<Contract
             let mut fetch_wallet = wallet.to_owned();
             for contract_type in [AssetType::RGB20, AssetType::RGB21] {
                 let contract_index = contract_type as u32;
-                sync_wallet(contract_index, &mut fetch_wallet, &mut resolver);
+                sync_wallet(contract_index, &mut fetch_wallet, &mut resolver)?;
                 prefetch_resolver_utxos(
                     contract_index,
                     &mut fetch_wallet,
@@ -2934,6 +3121,8 @@ pub async fn list_contracts(sk: &str, hidden_contracts: bool) -> Result<Contract
         _ => None,
     };
 
+    let labels = retrieve_labels(sk).await?;
+    let rgb_anchors = retrieve_anchors(sk).await?;
     let mut contracts = vec![];
     for contract_type in [AssetType::RGB20, AssetType::RGB21] {
         let iface_name = contract_type.to_string().to_uppercase().clone();
@@ -2972,6 +3161,8 @@ pub async fn list_contracts(sk: &str, hidden_contracts: bool) -> Result<Contract
             } else {
                 None
             };
+            labels.label_contract(&mut resp);
+            mark_pending_contract(&rgb_anchors, &mut resp);
             contracts.push(resp);
         }
     }
@@ -2982,6 +3173,7 @@ pub async fn list_contracts(sk: &str, hidden_contracts: bool) -> Result<Contract
             .insert(RGB_DEFAULT_NAME.to_string(), wallet);
         store_account(sk, rgb_account).await?;
     };
+    save_resolver_cache(sk).await;
 
     Ok(ContractsResponse { contracts })
 }
@@ -3029,9 +3221,11 @@ pub async fn list_schemas(sk: &str) -> Result<SchemasResponse> {
 
 pub async fn list_transfers(sk: &str, contract_id: String) -> Result<RgbTransfersResponse> {
     let rgb_transfers = retrieve_transfers(sk).await?;
+    let labels = retrieve_labels(sk).await?;
 
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
 
@@ -3060,6 +3254,7 @@ pub async fn list_transfers(sk: &str, contract_id: String) -> Result<RgbTransfer
                 .to_owned();
 
             let detail = RgbTransferDetail {
+                label: labels.label(LabelType::Consignment, &activity.consig_id),
                 consig_id: activity.consig_id,
                 status,
                 ty,
@@ -3157,6 +3352,7 @@ pub enum ImportError {
 pub async fn import(sk: &str, request: ImportRequest) -> Result<ContractResponse, ImportError> {
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
 
@@ -3218,56 +3414,162 @@ pub enum WatcherError {
     WrongDesc(String),
     /// Occurs an error in parse xpub step. {0}
     WrongXPub(String),
+    /// Descriptor type {0} is not supported, use a taproot (tr) descriptor.
+    UnsupportedDesc(String),
+    /// Taproot descriptors with script tree are not supported.
+    ScriptTree,
+    /// Derivation path {0} is not supported, use the account xpub.
+    WrongDerivation(String),
+    /// Key origin {0} does not match the xpub depth.
+    WrongOrigin(String),
     /// Occurs an error in create watcher step. {0}
     Create(String),
     /// Occurs an error in migrate watcher step. {0}
     Legacy(String),
+    /// Bitcoin explorer is unavailable. {0}
+    Explorer(String),
 }
 
 pub async fn create_watcher(
     sk: &str,
     request: WatcherRequest,
 ) -> Result<WatcherResponse, WatcherError> {
+    if let Err(err) = request.validate(&RGBContext::default()) {
+        return Err(WatcherError::Validation(err.to_string()));
+    }
+
     let WatcherRequest { name, xpub, force } = request;
+    let xpub = parse_watcher_xpub(&xpub)?;
+    let mut rgb_account = retrieve_account(sk).await.map_err(WatcherError::IO)?;
+
+    let migrate = internal_create_watcher(&name, xpub, force, &mut rgb_account)?;
+
+    store_account(sk, rgb_account)
+        .await
+        .map_err(WatcherError::IO)?;
+
+    Ok(WatcherResponse { name, migrate })
+}
+
+pub async fn create_watchers(
+    sk: &str,
+    request: WatchersRequest,
+) -> Result<WatchersResponse, WatcherError> {
+    if let Err(err) = request.validate(&RGBContext::default()) {
+        return Err(WatcherError::Validation(err.to_string()));
+    }
+
+    let WatchersRequest {
+        name,
+        descriptors,
+        force,
+    } = request;
+
+    let mut xpubs = vec![];
+    for descriptor in descriptors {
+        xpubs.push(parse_watcher_xpub(&descriptor)?);
+    }
+
     let mut rgb_account = retrieve_account(sk).await.map_err(WatcherError::IO)?;
 
-    if rgb_account.wallets.contains_key(&name) && force {
-        rgb_account.wallets.remove(&name);
+    let mut watchers = vec![];
+    for (index, xpub) in xpubs.into_iter().enumerate() {
+        let name = if index == 0 {
+            name.clone()
+        } else {
+            format!("{name}-{index}")
+        };
+        let migrate = internal_create_watcher(&name, xpub, force, &mut rgb_account)?;
+        watchers.push(WatcherResponse { name, migrate });
+    }
+
+    store_account(sk, rgb_account)
+        .await
+        .map_err(WatcherError::IO)?;
+
+    Ok(WatchersResponse { watchers })
+}
+
+fn internal_create_watcher(
+    name: &str,
+    xpub: ExtendedPubKey,
+    force: bool,
+    rgb_account: &mut RgbAccountV1,
+) -> Result<bool, WatcherError> {
+    if rgb_account.wallets.contains_key(name) && force {
+        rgb_account.wallets.remove(name);
     }
 
     let mut migrate = false;
-    if let Some(current_wallet) = rgb_account.wallets.get(&name) {
+    if let Some(current_wallet) = rgb_account.wallets.get(name) {
         let current_wallet = current_wallet.clone();
         let RgbDescr::Tapret(tapret_desc) = current_wallet.clone().descr;
 
-        if xpub != tapret_desc.xpub.to_string() {
+        if xpub != tapret_desc.xpub {
             rgb_account
                 .wallets
                 .insert("legacy".to_string(), current_wallet);
-            rgb_account.wallets.remove(&name);
+            rgb_account.wallets.remove(name);
             migrate = true;
         }
     }
 
-    if !rgb_account.wallets.contains_key(&name) {
-        let xdesc = DescriptorPublicKey::from_str(&xpub)
+    if !rgb_account.wallets.contains_key(name) {
+        create_wallet(name, xpub, &mut rgb_account.wallets)
+            .map_err(|err| WatcherError::Create(err.to_string()))?;
+    }
+
+    Ok(migrate)
+}
+
+/// Extract the account xpub from a watcher descriptor
+///
+/// Accepts a bare xpub, a key with origin and derivation
+/// (ex. `[fp/86h/1h/0h]xpub/*`) or a taproot output descriptor
+/// (ex. `tr([fp/86h/1h/0h]xpub/20/*)#checksum`).
+pub fn parse_watcher_xpub(descriptor: &str) -> Result<ExtendedPubKey, WatcherError> {
+    let descriptor = descriptor.trim();
+    let key = if descriptor.contains('(') {
+        let desc = Descriptor::<DescriptorPublicKey>::from_str(descriptor)
             .map_err(|err| WatcherError::WrongDesc(err.to_string()))?;
-        if let DescriptorPublicKey::XPub(xpub) = xdesc {
-            let xpub = xpub.xkey;
-            let xpub = ExtendedPubKey::from_str(&xpub.to_string())
-                .map_err(|err| WatcherError::WrongXPub(err.to_string()))?;
-            create_wallet(&name, xpub, &mut rgb_account.wallets)
-                .map_err(|err| WatcherError::Create(err.to_string()))?;
-        } else {
-            return Err(WatcherError::WrongXPub("invalid xpub type".to_string()));
+        match desc {
+            Descriptor::Tr(tr) => {
+                if tr.taptree().is_some() {
+                    return Err(WatcherError::ScriptTree);
+                }
+                tr.internal_key().clone()
+            }
+            other => {
+                return Err(WatcherError::UnsupportedDesc(format!(
+                    "{:?}",
+                    other.desc_type()
+                )))
+            }
         }
+    } else {
+        DescriptorPublicKey::from_str(descriptor)
+            .map_err(|err| WatcherError::WrongDesc(err.to_string()))?
+    };
+
+    let xkey = match key {
+        DescriptorPublicKey::XPub(xkey) => xkey,
+        _ => return Err(WatcherError::WrongXPub("invalid xpub type".to_string())),
+    };
+
+    if xkey.derivation_path.as_ref().len() > 1 || xkey.wildcard == Wildcard::Hardened {
+        return Err(WatcherError::WrongDerivation(
+            xkey.derivation_path.to_string(),
+        ));
     }
 
-    store_account(sk, rgb_account)
-        .await
-        .map_err(WatcherError::IO)?;
+    if let Some((fingerprint, path)) = &xkey.origin {
+        if path.as_ref().len() != xkey.xkey.depth as usize {
+            return Err(WatcherError::WrongOrigin(format!("{fingerprint}/{path}")));
+        }
+    }
 
-    Ok(WatcherResponse { name, migrate })
+    ExtendedPubKey::from_str(&xkey.xkey.to_string())
+        .map_err(|err| WatcherError::WrongXPub(err.to_string()))
 }
 
 pub async fn clear_watcher(sk: &str, name: &str) -> Result<WatcherResponse, WatcherError> {
@@ -3297,24 +3599,24 @@ pub async fn watcher_details(sk: &str, name: &str) -> Result<WatcherDetailRespon
 
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
+    let gap_limit = watcher_gap_limit(sk, name).await?;
+    let labels = retrieve_labels(sk).await.map_err(WatcherError::IO)?;
 
     let mut allocations = vec![];
     for contract_type in [AssetType::RGB20, AssetType::RGB21] {
         let iface_index = contract_type as u32;
-        prefetch_resolver_utxos(
-            iface_index,
-            &mut wallet,
-            &mut resolver,
-            Some(RGB_DEFAULT_FETCH_LIMIT),
-        )
-        .await;
+        prefetch_resolver_utxos(iface_index, &mut wallet, &mut resolver, Some(gap_limit)).await;
         prefetch_resolver_user_utxo_status(iface_index, &mut wallet, &mut resolver, false).await;
         let mut result = list_allocations(&mut wallet, &mut stock, iface_index, &mut resolver)
             .map_err(|op| WatcherError::Validation(op.to_string()))?;
         allocations.append(&mut result);
     }
+    for detail in allocations.iter_mut() {
+        labels.label_allocations(&mut detail.allocations);
+    }
 
     let resp = WatcherDetailResponse {
         contracts: allocations,
@@ -3329,6 +3631,51 @@ pub async fn watcher_details(sk: &str, name: &str) -> Result<WatcherDetailRespon
     Ok(resp)
 }
 
+pub async fn get_balances(sk: &str) -> Result<BalancesResponse, WatcherError> {
+    let (mut stock, mut rgb_account) =
+        retrieve_stock_account(sk).await.map_err(WatcherError::IO)?;
+
+    let mut wallet = match rgb_account.wallets.get(RGB_DEFAULT_NAME) {
+        Some(wallet) => wallet.to_owned(),
+        _ => return Err(WatcherError::NoWatcher),
+    };
+
+    let mut resolver = ExplorerResolver {
+        explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
+        ..default!()
+    };
+    let gap_limit = watcher_gap_limit(sk, RGB_DEFAULT_NAME).await?;
+
+    let locked: Vec<String> = list_locked_utxos(sk)
+        .await
+        .map_err(WatcherError::IO)?
+        .into_iter()
+        .map(|outpoint| outpoint.to_string())
+        .collect();
+
+    let mut contracts = vec![];
+    for contract_type in [AssetType::RGB20, AssetType::RGB21] {
+        let iface_index = contract_type as u32;
+        prefetch_resolver_utxos(iface_index, &mut wallet, &mut resolver, Some(gap_limit)).await;
+        prefetch_resolver_user_utxo_status(iface_index, &mut wallet, &mut resolver, true).await;
+        let mut result =
+            list_balances(&mut wallet, &mut stock, iface_index, &mut resolver, &locked)
+                .map_err(|op| WatcherError::Validation(op.to_string()))?;
+        contracts.append(&mut result);
+    }
+
+    rgb_account
+        .wallets
+        .insert(RGB_DEFAULT_NAME.to_string(), wallet);
+
+    store_stock_account(sk, stock, rgb_account)
+        .await
+        .map_err(WatcherError::IO)?;
+
+    Ok(BalancesResponse { contracts })
+}
+
 pub async fn watcher_address(
     sk: &str,
     name: &str,
@@ -3341,6 +3688,7 @@ pub async fn watcher_address(
         // Prefetch
         let mut resolver = ExplorerResolver {
             explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+            account: resolver_account(sk),
             ..default!()
         };
 
@@ -3381,6 +3729,7 @@ pub async fn watcher_utxo(
 
         let mut resolver = ExplorerResolver {
             explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+            account: resolver_account(sk),
             ..default!()
         };
 
@@ -3458,18 +3807,15 @@ pub async fn watcher_next_utxo(
 
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
+    let gap_limit = watcher_gap_limit(sk, name).await?;
 
-    prefetch_resolver_utxos(
-        iface_index,
-        &mut wallet,
-        &mut resolver,
-        Some(RGB_DEFAULT_FETCH_LIMIT),
-    )
-    .await;
+    prefetch_resolver_utxos(iface_index, &mut wallet, &mut resolver, Some(gap_limit)).await;
     prefetch_resolver_user_utxo_status(iface_index, &mut wallet, &mut resolver, true).await;
-    sync_wallet(iface_index, &mut wallet, &mut resolver);
+    sync_wallet(iface_index, &mut wallet, &mut resolver)
+        .map_err(|op| WatcherError::Explorer(op.to_string()))?;
 
     let utxo = match next_utxo(iface_index, wallet.clone(), &mut resolver)
         .map_err(|op| WatcherError::Validation(op.to_string()))?
@@ -3512,17 +3858,14 @@ pub async fn watcher_unspent_utxos(
 
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
+    let gap_limit = watcher_gap_limit(sk, name).await?;
 
-    sync_wallet(iface_index, &mut wallet, &mut resolver);
-    prefetch_resolver_utxos(
-        iface_index,
-        &mut wallet,
-        &mut resolver,
-        Some(RGB_DEFAULT_FETCH_LIMIT),
-    )
-    .await;
+    sync_wallet(iface_index, &mut wallet, &mut resolver)
+        .map_err(|op| WatcherError::Explorer(op.to_string()))?;
+    prefetch_resolver_utxos(iface_index, &mut wallet, &mut resolver, Some(gap_limit)).await;
     prefetch_resolver_user_utxo_status(iface_index, &mut wallet, &mut resolver, true).await;
 
     let utxos: HashSet<UtxoResponse> = next_utxos(iface_index, wallet.clone(), &mut resolver)
@@ -3544,6 +3887,707 @@ pub async fn watcher_unspent_utxos(
     })
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, Display, From, Error)]
+#[display(doc_comments)]
+pub enum ProvisionError {
+    /// Some request data is missing. {0:?}
+    Validation(BTreeMap<String, String>),
+    /// I/O or connectivity error. {0}
+    IO(RgbPersistenceError),
+    /// Watcher is required for this operation.
+    NoWatcher,
+    /// Interface {0} does not support colorable UTXOs.
+    WrongIface(String),
+    /// Occurs an error in derive step. {0}
+    Derive(String),
+    /// Occurs an error in funding step. {0}
+    Funding(String),
+}
+
+fn provision_iface_index(iface: &str) -> Result<u32, ProvisionError> {
+    match iface {
+        "RGB20" => Ok(AssetType::RGB20 as u32),
+        "RGB21" => Ok(AssetType::RGB21 as u32),
+        _ => Err(ProvisionError::WrongIface(iface.to_string())),
+    }
+}
+
+pub async fn provision_utxos(
+    sk: &str,
+    request: RgbProvisionRequest,
+) -> Result<RgbProvisionResponse, ProvisionError> {
+    if let Err(err) = request.validate(&RGBContext::default()) {
+        let errors = err
+            .iter()
+            .map(|(f, e)| (f.to_string(), e.to_string()))
+            .collect();
+        return Err(ProvisionError::Validation(errors));
+    }
+
+    let RgbProvisionRequest {
+        name,
+        descriptor,
+        change_descriptor,
+        outputs,
+        fee_rate,
+    } = request;
+
+    let mut rgb_account = retrieve_account(sk).await.map_err(ProvisionError::IO)?;
+    let mut wallet = match rgb_account.wallets.get(&name) {
+        Some(wallet) => wallet.to_owned(),
+        _ => return Err(ProvisionError::NoWatcher),
+    };
+
+    let mut pools = vec![];
+    for output in outputs {
+        let iface_index = provision_iface_index(&output.iface)?;
+        pools.push((iface_index, RgbUtxoPool::new(output.count, output.amount)));
+    }
+
+    let resp = internal_provision_utxos(
+        sk,
+        &mut wallet,
+        &descriptor,
+        &change_descriptor,
+        pools,
+        fee_rate,
+    )
+    .await?;
+
+    rgb_account.wallets.insert(name, wallet);
+    store_account(sk, rgb_account)
+        .await
+        .map_err(ProvisionError::IO)?;
+
+    Ok(resp)
+}
+
+pub async fn set_utxo_pool(
+    sk: &str,
+    request: RgbUtxoPoolRequest,
+) -> Result<RgbUtxoPoolResponse, ProvisionError> {
+    if let Err(err) = request.validate(&RGBContext::default()) {
+        let errors = err
+            .iter()
+            .map(|(f, e)| (f.to_string(), e.to_string()))
+            .collect();
+        return Err(ProvisionError::Validation(errors));
+    }
+
+    let RgbUtxoPoolRequest {
+        name,
+        iface,
+        min_free,
+        amount,
+    } = request;
+
+    let iface_index = provision_iface_index(&iface)?;
+    let rgb_account = retrieve_account(sk).await.map_err(ProvisionError::IO)?;
+    if !rgb_account.wallets.contains_key(&name) {
+        return Err(ProvisionError::NoWatcher);
+    }
+
+    let mut rgb_watchers = retrieve_watchers(sk).await.map_err(ProvisionError::IO)?;
+    let settings = rgb_watchers.watchers.entry(name.clone()).or_default();
+    settings
+        .pools
+        .insert(iface_index, RgbUtxoPool::new(min_free, amount));
+
+    let pools = settings
+        .pools
+        .iter()
+        .map(|(index, pool)| (format!("RGB{index}"), pool.to_string()))
+        .collect();
+
+    store_watchers(sk, rgb_watchers)
+        .await
+        .map_err(ProvisionError::IO)?;
+
+    Ok(RgbUtxoPoolResponse { name, pools })
+}
+
+pub async fn top_up_utxos(
+    sk: &str,
+    request: RgbTopUpRequest,
+) -> Result<RgbProvisionResponse, ProvisionError> {
+    if let Err(err) = request.validate(&RGBContext::default()) {
+        let errors = err
+            .iter()
+            .map(|(f, e)| (f.to_string(), e.to_string()))
+            .collect();
+        return Err(ProvisionError::Validation(errors));
+    }
+
+    let RgbTopUpRequest {
+        name,
+        descriptor,
+        change_descriptor,
+        fee_rate,
+    } = request;
+
+    let rgb_watchers = retrieve_watchers(sk).await.map_err(ProvisionError::IO)?;
+    let settings = rgb_watchers
+        .watchers
+        .get(&name)
+        .cloned()
+        .unwrap_or_default();
+    if settings.pools.is_empty() {
+        return Ok(RgbProvisionResponse::default());
+    }
+
+    let (mut stock, mut rgb_account) = retrieve_stock_account(sk)
+        .await
+        .map_err(ProvisionError::IO)?;
+    let mut wallet = match rgb_account.wallets.get(&name) {
+        Some(wallet) => wallet.to_owned(),
+        _ => return Err(ProvisionError::NoWatcher),
+    };
+
+    let mut resolver = ExplorerResolver {
+        explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
+        ..default!()
+    };
+
+    let gap_limit = settings.gap_limit();
+    for contract_type in [AssetType::RGB20, AssetType::RGB21] {
+        let iface_index = contract_type as u32;
+        prefetch_resolver_utxos(iface_index, &mut wallet, &mut resolver, Some(gap_limit)).await;
+        prefetch_resolver_user_utxo_status(iface_index, &mut wallet, &mut resolver, false).await;
+    }
+
+    let mut pools = vec![];
+    for (iface_index, pool) in settings.pools {
+        let free = list_free_utxos(iface_index, &mut wallet, &mut stock, &mut resolver)
+            .map_err(|op| ProvisionError::Derive(op.to_string()))?
+            .len();
+        let deficit = pool.deficit(free);
+        if deficit > 0 {
+            pools.push((iface_index, RgbUtxoPool::new(deficit, pool.amount)));
+        }
+    }
+
+    let resp = if pools.is_empty() {
+        RgbProvisionResponse::default()
+    } else {
+        internal_provision_utxos(
+            sk,
+            &mut wallet,
+            &descriptor,
+            &change_descriptor,
+            pools,
+            fee_rate,
+        )
+        .await?
+    };
+
+    rgb_account.wallets.insert(name, wallet);
+    store_stock_account(sk, stock, rgb_account)
+        .await
+        .map_err(ProvisionError::IO)?;
+
+    Ok(resp)
+}
+
+async fn internal_provision_utxos(
+    sk: &str,
+    wallet: &mut RgbWallet,
+    descriptor: &SecretString,
+    change_descriptor: &SecretString,
+    pools: Vec<(u32, RgbUtxoPool)>,
+    fee_rate: Option<f32>,
+) -> Result<RgbProvisionResponse, ProvisionError> {
+    let network = NETWORK.read().await.to_string();
+    let network =
+        Network::from_str(&network).map_err(|op| ProvisionError::Derive(op.to_string()))?;
+    let network = AddressNetwork::from(network);
+
+    let mut outputs: BTreeMap<u32, Vec<u64>> = BTreeMap::new();
+    for (iface_index, pool) in pools {
+        let amounts = outputs.entry(iface_index).or_default();
+        amounts.extend(vec![pool.amount; pool.min_free as usize]);
+    }
+
+    let mut invoices = vec![];
+    let mut terminals = vec![];
+    for (iface_index, amounts) in outputs {
+        let addresses = next_addresses(iface_index, amounts.len() as u32, wallet.clone(), network)
+            .map_err(|op| ProvisionError::Derive(op.to_string()))?;
+        for (address_terminal, amount) in addresses.into_iter().zip(amounts) {
+            let address = Address::from_str(&address_terminal.address.to_string())
+                .map_err(|op| ProvisionError::Derive(op.to_string()))?;
+            terminals.push((address.script_pubkey(), address_terminal.terminal, amount));
+            invoices.push(SatsInvoice { address, amount });
+        }
+    }
+
+    let btc_wallet = get_wallet(descriptor, Some(change_descriptor))
+        .await
+        .map_err(|op| ProvisionError::Funding(op.to_string()))?;
+    let fee_rate = fee_rate.map(FeeRate::from_sat_per_vb);
+    // the reserved and colored UTXOs are excluded by default
+    let details = create_transaction(sk, invoices, &btc_wallet, fee_rate, None)
+        .await
+        .map_err(|op| ProvisionError::Funding(op.to_string()))?;
+
+    let txid = details.txid;
+    let bp_txid =
+        bp::Txid::from_str(&txid.to_hex()).map_err(|op| ProvisionError::Funding(op.to_string()))?;
+    info!(format!("Provision txid: {txid}"));
+    let tx = details
+        .transaction
+        .ok_or_else(|| ProvisionError::Funding(format!("Transaction {txid} is unknown")))?;
+
+    // the outputs are found by script, the wallet may reorder them or add a change
+    let mut utxos = vec![];
+    let mut reserved = vec![];
+    let mut used = HashSet::new();
+    for (script, terminal, amount) in terminals {
+        let (vout, _) = tx
+            .output
+            .iter()
+            .enumerate()
+            .find(|(vout, output)| {
+                !used.contains(vout) && output.script_pubkey == script && output.value == amount
+            })
+            .ok_or_else(|| {
+                ProvisionError::Funding(format!(
+                    "Transaction {txid} has no output of {amount} sats"
+                ))
+            })?;
+        used.insert(vout);
+
+        let outpoint = Outpoint::new(bp_txid, vout as u32);
+        save_new_utxo(outpoint, amount, terminal, wallet);
+        utxos.push(UtxoResponse::with(outpoint, amount, MiningStatus::Mempool));
+        reserved.push(OutPoint::new(txid, vout as u32));
+    }
+    reserve_utxos(sk, reserved)
+        .await
+        .map_err(ProvisionError::IO)?;
+
+    Ok(RgbProvisionResponse {
+        txid: Some(txid.to_string()),
+        utxos,
+    })
+}
+
+/// Reserve outpoints for RGB usage, the BTC spends of the account leave them
+pub async fn reserve_utxos(sk: &str, outpoints: Vec<OutPoint>) -> Result<(), RgbPersistenceError> {
+    let mut reserved = retrieve_reserved_utxos(sk).await?;
+    reserved
+        .utxos
+        .extend(outpoints.iter().map(|outpoint| outpoint.to_string()));
+    store_reserved_utxos(sk, reserved).await
+}
+
+pub async fn release_utxos(sk: &str, outpoints: Vec<OutPoint>) -> Result<(), RgbPersistenceError> {
+    let mut reserved = retrieve_reserved_utxos(sk).await?;
+    for outpoint in outpoints {
+        reserved.utxos.remove(&outpoint.to_string());
+    }
+    store_reserved_utxos(sk, reserved).await
+}
+
+pub async fn list_reserved_utxos(sk: &str) -> Result<Vec<OutPoint>, RgbPersistenceError> {
+    let reserved = retrieve_reserved_utxos(sk).await?;
+    Ok(reserved
+        .utxos
+        .iter()
+        .filter_map(|outpoint| OutPoint::from_str(outpoint).ok())
+        .collect())
+}
+
+/// Coin control of a BTC spend of the account, excluding the UTXOs reserved
+/// for RGB and the unspent ones of the account carrying allocations
+pub async fn colored_coin_control(
+    sk: &str,
+    coin_control: Option<CoinControl>,
+) -> Result<CoinControl, WatcherError> {
+    let (mut stock, rgb_account) = retrieve_stock_account(sk).await.map_err(WatcherError::IO)?;
+
+    let mut coin_control = coin_control.unwrap_or_default();
+    let reserved = list_reserved_utxos(sk).await.map_err(WatcherError::IO)?;
+    coin_control.exclude.extend(reserved);
+
+    let mut resolver = ExplorerResolver {
+        explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
+        ..default!()
+    };
+    // the synced wallets are not stored, a BTC spend only reads them
+    for (name, mut wallet) in rgb_account.wallets {
+        let gap_limit = watcher_gap_limit(sk, &name).await?;
+        for contract_type in [AssetType::RGB20, AssetType::RGB21] {
+            let iface_index = contract_type as u32;
+            prefetch_resolver_utxos(iface_index, &mut wallet, &mut resolver, Some(gap_limit)).await;
+            prefetch_resolver_user_utxo_status(iface_index, &mut wallet, &mut resolver, false)
+                .await;
+            let details = list_allocations(&mut wallet, &mut stock, iface_index, &mut resolver)
+                .map_err(|op| WatcherError::Validation(op.to_string()))?;
+            for allocation in details
+                .into_iter()
+                .flat_map(|detail| detail.allocations)
+                .filter(|allocation| allocation.is_mine && !allocation.is_spent)
+            {
+                let outpoint = OutPoint::from_str(&allocation.utxo)
+                    .map_err(|op| WatcherError::Validation(op.to_string()))?;
+                coin_control.exclude.push(outpoint);
+            }
+        }
+    }
+    Ok(coin_control)
+}
+
+async fn watcher_gap_limit(sk: &str, name: &str) -> Result<u32, WatcherError> {
+    let rgb_watchers = retrieve_watchers(sk).await.map_err(WatcherError::IO)?;
+    let gap_limit = match rgb_watchers.watchers.get(name) {
+        Some(settings) => settings.gap_limit(),
+        _ => RGB_DEFAULT_FETCH_LIMIT,
+    };
+    Ok(gap_limit)
+}
+
+pub async fn rescan_watcher(
+    sk: &str,
+    request: RgbRescanRequest,
+) -> Result<RgbRescanResponse, WatcherError> {
+    if let Err(err) = request.validate(&RGBContext::default()) {
+        return Err(WatcherError::Validation(err.to_string()));
+    }
+
+    let RgbRescanRequest {
+        name,
+        gap_limit,
+        birthday,
+    } = request;
+
+    let mut rgb_account = retrieve_account(sk).await.map_err(WatcherError::IO)?;
+    let mut wallet = match rgb_account.wallets.get(&name) {
+        Some(wallet) => wallet.to_owned(),
+        _ => return Err(WatcherError::NoWatcher),
+    };
+
+    let mut rgb_watchers = retrieve_watchers(sk).await.map_err(WatcherError::IO)?;
+    let settings = rgb_watchers.watchers.entry(name.clone()).or_default();
+    if gap_limit.is_some() {
+        settings.gap_limit = gap_limit;
+    }
+    if birthday.is_some() {
+        settings.birthday = birthday;
+    }
+
+    let gap_limit = settings.gap_limit();
+    let birthday = settings.birthday;
+
+    let mut resolver = ExplorerResolver {
+        explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
+        ..default!()
+    };
+
+    clear_rescan_progress(sk, &name).await;
+
+    let mut progress = vec![];
+    for contract_type in [AssetType::RGB20, AssetType::RGB21] {
+        let iface_index = contract_type as u32;
+        let iface_progress = rescan_wallet(
+            sk,
+            &name,
+            iface_index,
+            &mut wallet,
+            &mut resolver,
+            gap_limit,
+            birthday,
+        )
+        .await
+        .map_err(|op| WatcherError::Validation(op.to_string()))?;
+        progress.push(iface_progress);
+    }
+
+    rgb_account.wallets.insert(name.clone(), wallet);
+    store_account(sk, rgb_account)
+        .await
+        .map_err(WatcherError::IO)?;
+
+    store_watchers(sk, rgb_watchers)
+        .await
+        .map_err(WatcherError::IO)?;
+
+    Ok(RgbRescanResponse {
+        name,
+        gap_limit,
+        progress,
+    })
+}
+
+pub async fn watcher_rescan_status(
+    sk: &str,
+    name: &str,
+) -> Result<RgbRescanResponse, WatcherError> {
+    let gap_limit = watcher_gap_limit(sk, name).await?;
+    let progress = get_rescan_progress(sk, name).await;
+
+    Ok(RgbRescanResponse {
+        name: name.to_string(),
+        gap_limit,
+        progress,
+    })
+}
+
+/// Backup of the files of the account of `sk`, unencrypted
+async fn account_backup(
+    sk: &str,
+    network: &str,
+    created_at: i64,
+    encrypted_descriptors: Option<String>,
+) -> Result<RgbBackup, BackupError> {
+    let (stock, rgb_account, rgb_transfers) = retrieve_stock_account_transfers(sk)
+        .await
+        .map_err(BackupError::IO)?;
+    let rgb_offers = retrieve_offers(sk).await.map_err(BackupError::IO)?;
+    let rgb_bids = retrieve_bids(sk).await.map_err(BackupError::IO)?;
+    let rgb_watchers = retrieve_watchers(sk).await.map_err(BackupError::IO)?;
+    let rgb_anchors = retrieve_anchors(sk).await.map_err(BackupError::IO)?;
+    let rgb_reserved = retrieve_reserved_utxos(sk).await.map_err(BackupError::IO)?;
+    let rgb_locked = retrieve_locked_utxos(sk).await.map_err(BackupError::IO)?;
+    let labels = retrieve_labels(sk).await.map_err(BackupError::IO)?;
+
+    // components keep the version of their carbonado files
+    let mut backup = RgbBackup::new(network, created_at, encrypted_descriptors);
+    backup.insert_stock(&stock)?;
+    backup.insert(ASSETS_WALLETS, &rgb_account, &RGB_ACCOUNT_VERSION)?;
+    backup.insert(ASSETS_TRANSFERS, &rgb_transfers, &RGB_TRANSFER_VERSION)?;
+    backup.insert(ASSETS_OFFERS, &rgb_offers, &RGB_STRICT_TYPE_VERSION)?;
+    backup.insert(ASSETS_BIDS, &rgb_bids, &RGB_STRICT_TYPE_VERSION)?;
+    backup.insert(ASSETS_WATCHERS, &rgb_watchers, &RGB_STRICT_TYPE_VERSION)?;
+    backup.insert(ASSETS_ANCHORS, &rgb_anchors, &RGB_STRICT_TYPE_VERSION)?;
+    backup.insert(ASSETS_RESERVED, &rgb_reserved, &RGB_STRICT_TYPE_VERSION)?;
+    backup.insert(ACCOUNT_LOCKED, &rgb_locked, &RGB_STRICT_TYPE_VERSION)?;
+    backup.insert_labels(&labels);
+    Ok(backup)
+}
+
+pub async fn export_backup(
+    sk: &str,
+    request: RgbBackupRequest,
+) -> Result<RgbBackupResponse, BackupError> {
+    let RgbBackupRequest {
+        password,
+        encrypted_descriptors,
+    } = request;
+
+    let network = get_network().await;
+    let created_at = chrono::Utc::now().timestamp();
+    let backup = account_backup(
+        sk,
+        &network,
+        created_at,
+        encrypted_descriptors.map(|descriptors| descriptors.0.clone()),
+    )
+    .await?;
+
+    let backup = encrypt_backup(&backup, &password)?;
+    Ok(RgbBackupResponse { backup, created_at })
+}
+
+/// Store a component of a backup, migrated to the current model
+async fn store_backup_component(
+    sk: &str,
+    backup: &RgbBackup,
+    key: &str,
+) -> Result<(), BackupError> {
+    match key {
+        ASSETS_STOCK => store_rgb_stock(sk, backup.stock()?).await,
+        ASSETS_WALLETS => store_account(sk, backup.account()?).await,
+        ASSETS_TRANSFERS => store_transfers(sk, backup.transfers()?).await,
+        ASSETS_OFFERS => store_offers(sk, backup.get_model(key, PersistedModel::Offers)?).await,
+        ASSETS_BIDS => store_bids(sk, backup.get_model(key, PersistedModel::Bids)?).await,
+        ASSETS_WATCHERS => store_watchers(sk, backup.get(key)?).await,
+        ASSETS_ANCHORS => store_anchors(sk, backup.get(key)?).await,
+        ASSETS_RESERVED => store_reserved_utxos(sk, backup.get(key)?).await,
+        ACCOUNT_LOCKED => store_locked_utxos(sk, backup.get(key)?).await,
+        ACCOUNT_LABELS => store_labels(sk, backup.labels()?).await,
+        _ => Ok(()),
+    }
+    .map_err(BackupError::IO)
+}
+
+/// Write back the components of the account replaced by a failed restore
+async fn rollback_backup(
+    sk: &str,
+    previous: &RgbBackup,
+    written: &[&str],
+    err: RgbPersistenceError,
+) -> BackupError {
+    let mut failed = vec![];
+    for key in written.iter().rev() {
+        if let Err(err) = store_backup_component(sk, previous, key).await {
+            warn!(format!(
+                "{key} not restored after a failed backup restore: {err}"
+            ));
+            failed.push(key.to_string());
+        }
+    }
+
+    if failed.is_empty() {
+        BackupError::IO(err)
+    } else {
+        BackupError::Rollback(err.to_string(), failed.join(", "))
+    }
+}
+
+pub async fn restore_backup(
+    sk: &str,
+    request: RgbRestoreRequest,
+) -> Result<RgbRestoreResponse, BackupError> {
+    let RgbRestoreRequest { password, backup } = request;
+    let backup = decrypt_backup(&backup, &password)?;
+
+    let network = get_network().await;
+    if backup.network != network {
+        return Err(BackupError::WrongNetwork(backup.network, network));
+    }
+    check_lib_ids(&backup.lib_ids)?;
+
+    // Every component is decoded (and migrated) before anything is stored
+    let stock = backup.stock()?;
+    let rgb_account = backup.account()?;
+    let rgb_transfers = backup.transfers()?;
+    let rgb_offers: RgbOffers = backup.get_model(ASSETS_OFFERS, PersistedModel::Offers)?;
+    let rgb_bids: RgbBids = backup.get_model(ASSETS_BIDS, PersistedModel::Bids)?;
+    let rgb_watchers: RgbWatchers = backup.get(ASSETS_WATCHERS)?;
+    let rgb_anchors: RgbAnchors = backup.get(ASSETS_ANCHORS)?;
+    let rgb_reserved: RgbReservedUtxos = backup.get(ASSETS_RESERVED)?;
+    let rgb_locked: RgbLockedUtxos = backup.get(ACCOUNT_LOCKED)?;
+    let labels = backup.labels()?;
+
+    let contracts = stock
+        .contract_ids()
+        .map(|contract_ids| contract_ids.len())
+        .unwrap_or_default();
+    let names: Vec<String> = rgb_account.wallets.keys().cloned().collect();
+
+    // The current files are kept to roll back a restore failing midway, an
+    // account that cannot be read has nothing worth keeping
+    let previous = match account_backup(sk, &network, 0, None).await {
+        Ok(previous) => Some(previous),
+        Err(err) => {
+            warn!(format!(
+                "account not readable, restored without rollback: {err}"
+            ));
+            None
+        }
+    };
+
+    // a component is rolled back from its first write, which can be partial
+    let mut written = vec![];
+    let stored = async {
+        written.push(ASSETS_STOCK);
+        store_rgb_stock(sk, stock).await?;
+        written.push(ASSETS_WALLETS);
+        store_account(sk, rgb_account).await?;
+        written.push(ASSETS_TRANSFERS);
+        store_transfers(sk, rgb_transfers).await?;
+        written.push(ASSETS_OFFERS);
+        store_offers(sk, rgb_offers).await?;
+        written.push(ASSETS_BIDS);
+        store_bids(sk, rgb_bids).await?;
+        written.push(ASSETS_WATCHERS);
+        store_watchers(sk, rgb_watchers).await?;
+        written.push(ASSETS_ANCHORS);
+        store_anchors(sk, rgb_anchors).await?;
+        written.push(ASSETS_RESERVED);
+        store_reserved_utxos(sk, rgb_reserved).await?;
+        written.push(ACCOUNT_LOCKED);
+        store_locked_utxos(sk, rgb_locked).await?;
+        written.push(ACCOUNT_LABELS);
+        store_labels(sk, labels).await
+    }
+    .await;
+
+    if let Err(err) = stored {
+        return Err(match previous {
+            Some(previous) => rollback_backup(sk, &previous, &written, err).await,
+            None => BackupError::IO(err),
+        });
+    }
+
+    // The utxos of the backup can be spent since
+    let mut watchers = vec![];
+    for name in names {
+        let request = RgbRescanRequest {
+            name: name.clone(),
+            gap_limit: None,
+            birthday: None,
+        };
+        match rescan_watcher(sk, request).await {
+            Ok(_) => watchers.push(name),
+            Err(err) => warn!(format!("watcher {name} not synchronized: {err}")),
+        }
+    }
+
+    Ok(RgbRestoreResponse {
+        bmc_version: backup.bmc_version,
+        created_at: backup.created_at,
+        contracts,
+        watchers,
+        encrypted_descriptors: backup.encrypted_descriptors.map(SecretString),
+    })
+}
+
+/// Files of the account that [`migrate_account`] would upgrade
+pub async fn migration_plan(sk: &str) -> Result<RgbMigrationResponse, MigrationError> {
+    let storage = storage_backend()
+        .await
+        .map_err(|op| MigrationError::Backend(op.to_string()))?;
+    let migrations = plan_account_files(storage.as_ref(), sk).await?;
+
+    Ok(RgbMigrationResponse {
+        migrations,
+        dry_run: true,
+    })
+}
+
+/// Upgrade all the files of the account to the current version of their model
+pub async fn migrate_account(sk: &str) -> Result<RgbMigrationResponse, MigrationError> {
+    let storage = storage_backend()
+        .await
+        .map_err(|op| MigrationError::Backend(op.to_string()))?;
+    let migrations = migrate_account_files(storage.as_ref(), sk).await?;
+
+    Ok(RgbMigrationResponse {
+        migrations,
+        dry_run: false,
+    })
+}
+
+/// Exchange the changes of the account, transfers, offers and bids with a device
+pub async fn sync_documents(
+    sk: &str,
+    request: RgbSyncRequest,
+) -> Result<RgbSyncResponse, SyncError> {
+    sync_account_documents(sk, request).await
+}
+
+/// Publish the changes of a document to nostr relays, for the other devices of the owner
+pub async fn publish_sync_document(
+    sk: &str,
+    relays: &[String],
+    document: RgbSyncDocument,
+) -> Result<String, SyncError> {
+    publish_sync_event(sk, relays, &document).await
+}
+
+/// Apply the changes published to nostr relays by the other devices of the owner
+pub async fn sync_documents_from_relays(
+    sk: &str,
+    relays: &[String],
+    since: u64,
+) -> Result<RgbSyncResponse, SyncError> {
+    sync_account_from_relays(sk, relays, since).await
+}
+
 pub async fn clear_stock(sk: &str) {
     store_rgb_stock(sk, Stock::default())
         .await
@@ -3631,7 +4675,7 @@ pub async fn inspect_contract(
                     .map_err(|_| TransferError::NoIface)?;
 
                 if let Ok(contract_iface) = stock.contract_iface(contract_id, iface.iface_id()) {
-                    sync_wallet(contract_index, &mut fetch_wallet, &mut resolver);
+                    sync_wallet(contract_index, &mut fetch_wallet, &mut resolver)?;
                     prefetch_resolver_allocations(contract_iface, &mut resolver, true).await;
                     prefetch_resolver_utxos(
                         contract_index,
@@ -3655,6 +4699,7 @@ pub async fn inspect_contract(
 pub async fn read_contract(sk: &str, contract_id: &str) -> Result<ContractResponse> {
     let mut resolver = ExplorerResolver {
         explorer_url: BITCOIN_EXPLORER_API.read().await.to_string(),
+        account: resolver_account(sk),
         ..default!()
     };
 
@@ -3674,7 +4719,7 @@ pub async fn read_contract(sk: &str, contract_id: &str) -> Result<ContractRespon
                     .map_err(|_| TransferError::NoIface)?;
 
                 if let Ok(contract_iface) = stock.contract_iface(contract_id, iface.iface_id()) {
-                    sync_wallet(contract_index, &mut fetch_wallet, &mut resolver);
+                    sync_wallet(contract_index, &mut fetch_wallet, &mut resolver)?;
                     prefetch_resolver_allocations(contract_iface, &mut resolver, true).await;
                     prefetch_resolver_utxos(
                         contract_index,
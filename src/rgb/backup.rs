@@ -0,0 +1,317 @@
+use std::collections::BTreeMap;
+
+use amplify::confinement::{Confined, U32};
+use argon2::{Algorithm, Argon2, Params, Version};
+use postcard::{from_bytes, to_allocvec};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rgbstd::{
+    interface::{LIB_ID_RGB20, LIB_ID_RGB21, LIB_ID_RGB25},
+    persistence::Stock,
+    stl::{LIB_ID_RGB, LIB_ID_RGB_CONTRACT, LIB_ID_RGB_STD},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_encrypt::{
+    serialize::impls::BincodeSerializer, shared_key::SharedKey, traits::SerdeEncryptSharedKey,
+    AsSharedKey, EncryptedMessage,
+};
+use strict_encoding::{StrictDeserialize, StrictSerialize};
+use zeroize::Zeroize;
+
+use crate::{
+    constants::storage_keys::{ACCOUNT_LABELS, ASSETS_STOCK, ASSETS_TRANSFERS, ASSETS_WALLETS},
+    labels::Labels,
+    rgb::{
+        cambria::{ModelVersion, RgbAccountVersions, RgbtransferVersions},
+        constants::RGB_STRICT_TYPE_VERSION,
+        fs::RgbPersistenceError,
+        migration::{chain, version_of, PersistedModel},
+        structs::{RgbAccountV1, RgbTransfersV1},
+    },
+    structs::SecretString,
+};
+
+const BACKUP_MAGIC: [u8; 4] = *b"BMBK";
+const BACKUP_SALT_LEN: usize = 16;
+pub const BACKUP_VERSION: u8 = 1;
+
+/// bitmask-core versions of the RGB library ids, updated by `build.rs`
+const RGB_LIB_IDS: &str = include_str!("../../RGB_LIB_IDs.toml");
+
+#[derive(Debug, Clone, Eq, PartialEq, Display, From, Error)]
+#[display(doc_comments)]
+pub enum BackupError {
+    /// This is not a bitmask backup.
+    WrongFormat,
+    /// Backup version {0} is not supported.
+    UnsupportedVersion(u8),
+    /// Wrong password, or the backup was modified.
+    WrongPassword,
+    /// Backup encryption causes error. {0}
+    Encrypt(String),
+    /// Backup of {0} cannot be restored in {1}.
+    WrongNetwork(String, String),
+    /// Library {0} {1} is unknown.
+    UnknownLibId(String, String),
+    /// Library {0} of bitmask-core {1} is not compatible, assets must be reissued.
+    IncompatibleLibId(String, String),
+    /// Component '{0}' causes error. {1}
+    WrongComponent(String, String),
+    /// I/O or connectivity error. {0}
+    IO(RgbPersistenceError),
+    /// Restore causes error ({0}), and {1} cannot be rolled back.
+    Rollback(String, String),
+}
+
+/// Component of a backup, encoded like its carbonado file
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RgbBackupFile {
+    pub data: Vec<u8>,
+    pub version: [u8; 8],
+}
+
+/// Everything needed to restore an account, encrypted by [`encrypt_backup`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RgbBackup {
+    /// bitmask-core version which made the backup
+    pub bmc_version: String,
+    pub network: String,
+    pub created_at: i64,
+    /// RGB library ids, by name (`LIB_ID_RGB`, `LIB_ID_RGB_STD`...)
+    pub lib_ids: BTreeMap<String, String>,
+    /// Components, by storage key
+    pub files: BTreeMap<String, RgbBackupFile>,
+    /// Encrypted descriptors of the bitcoin wallet
+    pub encrypted_descriptors: Option<String>,
+}
+
+impl SerdeEncryptSharedKey for RgbBackup {
+    type S = BincodeSerializer<Self>;
+}
+
+impl RgbBackup {
+    pub fn new(network: &str, created_at: i64, encrypted_descriptors: Option<String>) -> Self {
+        Self {
+            bmc_version: env!("CARGO_PKG_VERSION").to_owned(),
+            network: network.to_owned(),
+            created_at,
+            lib_ids: current_lib_ids(),
+            files: BTreeMap::new(),
+            encrypted_descriptors,
+        }
+    }
+
+    fn file(&self, key: &str) -> Option<&RgbBackupFile> {
+        self.files.get(key)
+    }
+
+    fn wrong_component(key: &str, err: impl ToString) -> BackupError {
+        BackupError::WrongComponent(key.to_owned(), err.to_string())
+    }
+
+    /// Add a postcard-encoded component, in the `version` of its carbonado file
+    pub fn insert<T: Serialize>(
+        &mut self,
+        key: &str,
+        value: &T,
+        version: &[u8],
+    ) -> Result<(), BackupError> {
+        let data = to_allocvec(value).map_err(|op| Self::wrong_component(key, op))?;
+        self.files.insert(
+            key.to_owned(),
+            RgbBackupFile {
+                data,
+                version: version_of(Some(version)),
+            },
+        );
+        Ok(())
+    }
+
+    /// Get a postcard-encoded component, the default value when missing
+    pub fn get<T: DeserializeOwned + Default>(&self, key: &str) -> Result<T, BackupError> {
+        match self.file(key) {
+            Some(file) => from_bytes(&file.data).map_err(|op| Self::wrong_component(key, op)),
+            None => Ok(T::default()),
+        }
+    }
+
+    /// Get a component migrated to the current version of its model, see [`chain`]
+    pub fn get_model<T: DeserializeOwned + Default>(
+        &self,
+        key: &str,
+        model: PersistedModel,
+    ) -> Result<T, BackupError> {
+        match self.file(key) {
+            Some(file) => chain(model)
+                .decode(&file.data, Some(&file.version))
+                .map_err(|op| Self::wrong_component(key, op)),
+            None => Ok(T::default()),
+        }
+    }
+
+    pub fn insert_stock(&mut self, stock: &Stock) -> Result<(), BackupError> {
+        let data = stock
+            .to_strict_serialized::<U32>()
+            .map_err(|op| Self::wrong_component(ASSETS_STOCK, op))?;
+        self.files.insert(
+            ASSETS_STOCK.to_owned(),
+            RgbBackupFile {
+                data: data.to_vec(),
+                version: RGB_STRICT_TYPE_VERSION,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn stock(&self) -> Result<Stock, BackupError> {
+        let Some(file) = self.file(ASSETS_STOCK) else {
+            return Ok(Stock::default());
+        };
+
+        let confined = Confined::try_from_iter(file.data.clone())
+            .map_err(|op| Self::wrong_component(ASSETS_STOCK, op))?;
+        Stock::from_strict_serialized::<U32>(confined)
+            .map_err(|op| Self::wrong_component(ASSETS_STOCK, op))
+    }
+
+    /// Account migrated to the current model
+    pub fn account(&self) -> Result<RgbAccountV1, BackupError> {
+        match self.file(ASSETS_WALLETS) {
+            Some(file) => RgbAccountVersions::from_bytes(file.data.clone(), file.version)
+                .map_err(|op| Self::wrong_component(ASSETS_WALLETS, op)),
+            None => Ok(RgbAccountV1::default()),
+        }
+    }
+
+    /// Transfers migrated to the current model
+    pub fn transfers(&self) -> Result<RgbTransfersV1, BackupError> {
+        match self.file(ASSETS_TRANSFERS) {
+            Some(file) => RgbtransferVersions::from_bytes(file.data.clone(), file.version)
+                .map_err(|op| Self::wrong_component(ASSETS_TRANSFERS, op)),
+            None => Ok(RgbTransfersV1::default()),
+        }
+    }
+
+    pub fn insert_labels(&mut self, labels: &Labels) {
+        self.files.insert(
+            ACCOUNT_LABELS.to_owned(),
+            RgbBackupFile {
+                data: labels.to_jsonl().into_bytes(),
+                version: RGB_STRICT_TYPE_VERSION,
+            },
+        );
+    }
+
+    pub fn labels(&self) -> Result<Labels, BackupError> {
+        let Some(file) = self.file(ACCOUNT_LABELS) else {
+            return Ok(Labels::default());
+        };
+
+        let data = String::from_utf8(file.data.clone())
+            .map_err(|op| Self::wrong_component(ACCOUNT_LABELS, op))?;
+        Labels::from_jsonl(&data).map_err(|op| Self::wrong_component(ACCOUNT_LABELS, op))
+    }
+}
+
+pub fn current_lib_ids() -> BTreeMap<String, String> {
+    [
+        ("LIB_ID_RGB", LIB_ID_RGB),
+        ("LIB_ID_RGB_CONTRACT", LIB_ID_RGB_CONTRACT),
+        ("LIB_ID_RGB20", LIB_ID_RGB20),
+        ("LIB_ID_RGB21", LIB_ID_RGB21),
+        ("LIB_ID_RGB25", LIB_ID_RGB25),
+        ("LIB_ID_RGB_STD", LIB_ID_RGB_STD),
+    ]
+    .into_iter()
+    .map(|(name, lib_id)| (name.to_owned(), lib_id.to_owned()))
+    .collect()
+}
+
+/// bitmask-core version which introduced a library id, from `RGB_LIB_IDs.toml`
+pub fn lib_id_version(name: &str, lib_id: &str) -> Option<String> {
+    let mut section = "";
+    for line in RGB_LIB_IDS.lines().map(str::trim) {
+        if let Some(header) = line.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+            section = header;
+        } else if section == name {
+            if let Some((id, version)) = line.split_once(" = ") {
+                if id.trim_matches('"') == lib_id {
+                    return Some(version.trim_matches('"').to_owned());
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Check the RGB libraries of a backup against the ones of this build
+///
+/// Only a different `LIB_ID_RGB` is consensus-breaking, the other libraries
+/// only need to be known.
+pub fn check_lib_ids(lib_ids: &BTreeMap<String, String>) -> Result<(), BackupError> {
+    let current = current_lib_ids();
+    for (name, lib_id) in lib_ids {
+        if current.get(name) == Some(lib_id) {
+            continue;
+        }
+
+        let Some(version) = lib_id_version(name, lib_id) else {
+            return Err(BackupError::UnknownLibId(name.clone(), lib_id.clone()));
+        };
+        if name == "LIB_ID_RGB" {
+            return Err(BackupError::IncompatibleLibId(name.clone(), version));
+        }
+    }
+    Ok(())
+}
+
+fn backup_key(password: &SecretString, salt: &[u8]) -> Result<[u8; 32], BackupError> {
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::default())
+        .hash_password_into(password.0.as_bytes(), salt, &mut key)
+        .map_err(|op| BackupError::Encrypt(op.to_string()))?;
+    Ok(key)
+}
+
+/// Encrypt a backup with a password, in hex
+///
+/// The archive is the magic number, the version, the Argon2id salt and the
+/// backup encrypted with XChaCha20-Poly1305.
+pub fn encrypt_backup(backup: &RgbBackup, password: &SecretString) -> Result<String, BackupError> {
+    let mut salt = [0u8; BACKUP_SALT_LEN];
+    StdRng::from_entropy().fill(&mut salt);
+
+    let mut key = backup_key(password, &salt)?;
+    let encrypted = backup
+        .encrypt(&SharedKey::from_array(key))
+        .map_err(|op| BackupError::Encrypt(op.to_string()));
+    key.zeroize();
+
+    let mut archive = BACKUP_MAGIC.to_vec();
+    archive.push(BACKUP_VERSION);
+    archive.extend(salt);
+    archive.extend(encrypted?.serialize());
+    Ok(hex::encode(archive))
+}
+
+pub fn decrypt_backup(archive: &str, password: &SecretString) -> Result<RgbBackup, BackupError> {
+    let archive = hex::decode(archive).map_err(|_| BackupError::WrongFormat)?;
+    let header_len = BACKUP_MAGIC.len() + 1 + BACKUP_SALT_LEN;
+    if archive.len() <= header_len || archive[..BACKUP_MAGIC.len()] != BACKUP_MAGIC {
+        return Err(BackupError::WrongFormat);
+    }
+
+    let version = archive[BACKUP_MAGIC.len()];
+    if version != BACKUP_VERSION {
+        return Err(BackupError::UnsupportedVersion(version));
+    }
+
+    let salt = &archive[BACKUP_MAGIC.len() + 1..header_len];
+    let encrypted = EncryptedMessage::deserialize(archive[header_len..].to_vec())
+        .map_err(|_| BackupError::WrongFormat)?;
+
+    let mut key = backup_key(password, salt)?;
+    let backup = RgbBackup::decrypt_owned(&encrypted, &SharedKey::from_array(key))
+        .map_err(|_| BackupError::WrongPassword);
+    key.zeroize();
+    backup
+}
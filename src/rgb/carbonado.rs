@@ -1,28 +1,39 @@
 use amplify::confinement::{Confined, U32};
 use anyhow::Result;
-use autosurgeon::{hydrate, reconcile};
+use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile};
 use postcard::{from_bytes, to_allocvec};
 use rgbstd::{persistence::Stock, stl::LIB_ID_RGB};
 use strict_encoding::{StrictDeserialize, StrictSerialize};
 
 use crate::{
     carbonado::{
-        auctions_retrieve, auctions_store, marketplace_retrieve, marketplace_store, retrieve, store,
+        auctions_allow, auctions_retrieve, auctions_store, error::CarbonadoError,
+        marketplace_retrieve, marketplace_store,
     },
+    chain::ResolverCache,
+    labels::Labels,
     rgb::{
         cambria::{ModelVersion, RgbAccountVersions, RgbtransferVersions},
-        constants::RGB_STRICT_TYPE_VERSION,
+        chunks::{chunk_id, remember_manifest, stored_manifest, ChunkManifest},
+        constants::{
+            RGB_ACCOUNT_VERSION, RGB_STOCK_MANIFEST_VERSION, RGB_STRICT_TYPE_VERSION,
+            RGB_TRANSFER_VERSION,
+        },
         crdt::{
-            LocalRgbAccount, LocalRgbAuctions, LocalRgbOfferBid, LocalRgbOffers, RawRgbAccount,
+            merge_document, read_document, remember_document, LocalRgbAccount, LocalRgbAuctions,
+            LocalRgbOfferBid, LocalRgbOffers, MergedDocument, RawRgbAccount, RawRgbBids,
+            RawRgbOffers, RawRgbTransfers,
+        },
+        migration::{chain, PersistedModel},
+        storage::{remove, retrieve, store, StorageBackendError},
+        structs::{
+            RgbAccountV1, RgbAnchors, RgbLockedUtxos, RgbReservedUtxos, RgbTransfersV1, RgbWatchers,
         },
-        structs::{RgbAccountV1, RgbTransfersV1},
         swap::{RgbAuctionSwaps, RgbBidSwap, RgbBids, RgbOffers, RgbPublicSwaps},
     },
+    warn,
 };
 
-const RGB_ACCOUNT_VERSION: [u8; 3] = *b"v10";
-const RGB_TRANSFER_VERSION: [u8; 3] = *b"v10";
-
 #[derive(Debug, Clone, Eq, PartialEq, Display, From, Error)]
 #[display(doc_comments)]
 pub enum StorageError {
@@ -54,9 +65,37 @@ pub enum StorageError {
     CarbonadoWrite(String, String),
     /// Reconcile '{0}' causes error. {1}
     Reconcile(String, String),
+    /// Chunk '{1}' of '{0}' is missing or corrupted.
+    ChunkRetrieve(String, String),
+    /// File '{0}' was changed by another device, retrieve it again before writing.
+    Conflict(String),
+}
+
+/// Conflicts are surfaced for the caller to merge or retry
+fn write_error(name: &str, err: StorageBackendError) -> StorageError {
+    match err {
+        StorageBackendError::Carbonado(CarbonadoError::Conflict(_)) => {
+            StorageError::Conflict(name.to_string())
+        }
+        err => StorageError::CarbonadoWrite(name.to_string(), err.to_string()),
+    }
 }
 
 // User Carbonado Operations
+
+/// Store the stock in content-defined chunks, only the new chunks are written
+///
+/// The file itself holds the manifest of the chunks, written last so readers
+/// never see a partial stock. The chunks the new manifest drops are removed
+/// once it is written.
+///
+/// The stock is not split by contract: `Stock` can only be strict-encoded and
+/// decoded whole, it has no way to write or load a single contract. So the
+/// stock is still serialized whole on every call, only the writes shrink to
+/// the chunks around the changes, and [`retrieve_stock`] reads every chunk.
+///
+/// Fails with [`StorageError::Conflict`] when the stored manifest is not the
+/// one this process retrieved or stored last.
 pub async fn store_stock(sk: &str, name: &str, stock: &Stock) -> Result<(), StorageError> {
     let data = stock
         .to_strict_serialized::<U32>()
@@ -66,29 +105,102 @@ pub async fn store_stock(sk: &str, name: &str, stock: &Stock) -> Result<(), Stor
         .to_hex()
         .to_lowercase();
 
+    let (manifest, chunks) = ChunkManifest::with(&data);
+    // Without a known manifest every chunk is written, and none removed
+    let previous = match stored_manifest(sk, &hashed_name) {
+        Some(known) => {
+            let stored = retrieve_manifest(sk, name, &hashed_name)
+                .await?
+                .unwrap_or_default();
+            if stored != known && !stored.chunks.is_empty() {
+                return Err(StorageError::Conflict(name.to_string()));
+            }
+            stored
+        }
+        None => ChunkManifest::default(),
+    };
+    if previous == manifest {
+        return Ok(());
+    }
+
+    for (id, chunk) in manifest.chunks.iter().zip(chunks) {
+        if previous.contains(id) {
+            continue;
+        }
+        // chunks are content-addressed, a concurrent write has the same content
+        store(
+            sk,
+            &format!("{hashed_name}-{id}.c15"),
+            chunk,
+            true,
+            Some(RGB_STRICT_TYPE_VERSION.to_vec()),
+        )
+        .await
+        .map_err(|op| write_error(name, op))?;
+    }
+
+    let data = to_allocvec(&manifest)
+        .map_err(|op| StorageError::SerializeWrite(name.to_string(), op.to_string()))?;
     store(
         sk,
         &format!("{hashed_name}.c15"),
         &data,
         false,
-        Some(RGB_STRICT_TYPE_VERSION.to_vec()),
+        Some(RGB_STOCK_MANIFEST_VERSION.to_vec()),
     )
     .await
-    .map_err(|op| StorageError::CarbonadoWrite(name.to_string(), op.to_string()))
+    .map_err(|op| write_error(name, op))?;
+
+    for id in previous.chunks.iter().filter(|id| !manifest.contains(id)) {
+        // an orphaned chunk only wastes space, the stock is already written
+        if let Err(err) = remove(sk, &format!("{hashed_name}-{id}.c15")).await {
+            warn!(format!("Chunk {id} of {name} is not removed: {err}"));
+        }
+    }
+
+    remember_manifest(sk, &hashed_name, manifest);
+    Ok(())
+}
+
+/// Manifest of a stock stored in chunks, none for stocks stored as a single file
+async fn retrieve_manifest(
+    sk: &str,
+    name: &str,
+    hashed_name: &str,
+) -> Result<Option<ChunkManifest>, StorageError> {
+    let (data, metadata) = retrieve(sk, &format!("{hashed_name}.c15"), vec![])
+        .await
+        .map_err(|op| StorageError::CarbonadoRetrieve(name.to_string(), op.to_string()))?;
+    if metadata.as_deref() != Some(&RGB_STOCK_MANIFEST_VERSION[..]) {
+        return Ok(None);
+    }
+
+    let manifest = from_bytes(&data)
+        .map_err(|op| StorageError::SerializeRetrieve(name.to_string(), op.to_string()))?;
+    Ok(Some(manifest))
 }
 
+/// Store the account, merged with the changes made by other devices
 pub async fn store_wallets(
     sk: &str,
     name: &str,
     rgb_wallets: &RgbAccountV1,
 ) -> Result<(), StorageError> {
-    let data = to_allocvec(rgb_wallets)
-        .map_err(|op| StorageError::StrictWrite(name.to_string(), op.to_string()))?;
-
     let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
         .to_hex()
         .to_lowercase();
 
+    let merged = cdrt_merge_document(
+        sk,
+        name,
+        &hashed_name,
+        RawRgbAccount::from(rgb_wallets.clone()),
+    )
+    .await?;
+    let rgb_wallets = RgbAccountV1::from(merged.value.clone());
+    let data = to_allocvec(&rgb_wallets)
+        .map_err(|op| StorageError::StrictWrite(name.to_string(), op.to_string()))?;
+
     store(
         sk,
         &format!("{hashed_name}.c15"),
@@ -97,21 +209,33 @@ pub async fn store_wallets(
         Some(RGB_ACCOUNT_VERSION.to_vec()),
     )
     .await
-    .map_err(|op| StorageError::CarbonadoWrite(name.to_string(), op.to_string()))
+    .map_err(|op| write_error(name, op))?;
+
+    cdrt_store_document(sk, name, &hashed_name, merged).await
 }
 
+/// Store the transfers, merged with the transfers saved by other devices
 pub async fn store_transfers(
     sk: &str,
     name: &str,
     rgb_transfers: &RgbTransfersV1,
 ) -> Result<(), StorageError> {
-    let data = to_allocvec(rgb_transfers)
-        .map_err(|op| StorageError::StrictWrite(name.to_string(), op.to_string()))?;
-
     let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
         .to_hex()
         .to_lowercase();
 
+    let merged = cdrt_merge_document(
+        sk,
+        name,
+        &hashed_name,
+        RawRgbTransfers::from(rgb_transfers.clone()),
+    )
+    .await?;
+    let rgb_transfers = RgbTransfersV1::try_from(merged.value.clone())
+        .map_err(|op| StorageError::MergeWrite(name.to_string(), op.to_string()))?;
+    let data = to_allocvec(&rgb_transfers)
+        .map_err(|op| StorageError::StrictWrite(name.to_string(), op.to_string()))?;
+
     store(
         sk,
         &format!("{hashed_name}.c15"),
@@ -120,21 +244,58 @@ pub async fn store_transfers(
         Some(RGB_TRANSFER_VERSION.to_vec()),
     )
     .await
-    .map_err(|op| StorageError::CarbonadoWrite(name.to_string(), op.to_string()))
+    .map_err(|op| write_error(name, op))?;
+
+    cdrt_store_document(sk, name, &hashed_name, merged).await
 }
 
+/// Store the offers, merged with the offers saved by other devices
 pub async fn store_offers(
     sk: &str,
     name: &str,
     rgb_offers: &RgbOffers,
 ) -> Result<(), StorageError> {
-    let data = to_allocvec(rgb_offers)
+    let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase();
+
+    let merged = cdrt_merge_document(
+        sk,
+        name,
+        &hashed_name,
+        RawRgbOffers::from(rgb_offers.clone()),
+    )
+    .await?;
+    let rgb_offers = RgbOffers::try_from(merged.value.clone())
+        .map_err(|op| StorageError::MergeWrite(name.to_string(), op.to_string()))?;
+    let data = to_allocvec(&rgb_offers)
         .map_err(|op| StorageError::StrictWrite(name.to_string(), op.to_string()))?;
 
+    store(
+        sk,
+        &format!("{hashed_name}.c15"),
+        &data,
+        true,
+        Some(RGB_STRICT_TYPE_VERSION.to_vec()),
+    )
+    .await
+    .map_err(|op| write_error(name, op))?;
+
+    cdrt_store_document(sk, name, &hashed_name, merged).await
+}
+
+/// Store the bids, merged with the bids saved by other devices
+pub async fn store_bids(sk: &str, name: &str, rgb_bids: &RgbBids) -> Result<(), StorageError> {
     let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
         .to_hex()
         .to_lowercase();
 
+    let merged =
+        cdrt_merge_document(sk, name, &hashed_name, RawRgbBids::from(rgb_bids.clone())).await?;
+    let rgb_bids = RgbBids::from(merged.value.clone());
+    let data = to_allocvec(&rgb_bids)
+        .map_err(|op| StorageError::StrictWrite(name.to_string(), op.to_string()))?;
+
     store(
         sk,
         &format!("{hashed_name}.c15"),
@@ -143,17 +304,134 @@ pub async fn store_offers(
         Some(RGB_STRICT_TYPE_VERSION.to_vec()),
     )
     .await
-    .map_err(|op| StorageError::CarbonadoWrite(name.to_string(), op.to_string()))
+    .map_err(|op| write_error(name, op))?;
+
+    cdrt_store_document(sk, name, &hashed_name, merged).await
 }
 
-pub async fn store_bids(sk: &str, name: &str, rgb_bids: &RgbBids) -> Result<(), StorageError> {
-    let data = to_allocvec(rgb_bids)
+pub async fn store_watchers(
+    sk: &str,
+    name: &str,
+    rgb_watchers: &RgbWatchers,
+) -> Result<(), StorageError> {
+    let data = to_allocvec(rgb_watchers)
         .map_err(|op| StorageError::StrictWrite(name.to_string(), op.to_string()))?;
 
     let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
         .to_hex()
         .to_lowercase();
 
+    store(
+        sk,
+        &format!("{hashed_name}.c15"),
+        &data,
+        false,
+        Some(RGB_STRICT_TYPE_VERSION.to_vec()),
+    )
+    .await
+    .map_err(|op| write_error(name, op))
+}
+
+pub async fn store_reserved_utxos(
+    sk: &str,
+    name: &str,
+    rgb_reserved: &RgbReservedUtxos,
+) -> Result<(), StorageError> {
+    let data = to_allocvec(rgb_reserved)
+        .map_err(|op| StorageError::StrictWrite(name.to_string(), op.to_string()))?;
+
+    let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase();
+
+    store(
+        sk,
+        &format!("{hashed_name}.c15"),
+        &data,
+        false,
+        Some(RGB_STRICT_TYPE_VERSION.to_vec()),
+    )
+    .await
+    .map_err(|op| write_error(name, op))
+}
+
+pub async fn store_locked_utxos(
+    sk: &str,
+    name: &str,
+    rgb_locked: &RgbLockedUtxos,
+) -> Result<(), StorageError> {
+    let data = to_allocvec(rgb_locked)
+        .map_err(|op| StorageError::StrictWrite(name.to_string(), op.to_string()))?;
+
+    let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase();
+
+    store(
+        sk,
+        &format!("{hashed_name}.c15"),
+        &data,
+        false,
+        Some(RGB_STRICT_TYPE_VERSION.to_vec()),
+    )
+    .await
+    .map_err(|op| write_error(name, op))
+}
+
+pub async fn store_anchors(
+    sk: &str,
+    name: &str,
+    rgb_anchors: &RgbAnchors,
+) -> Result<(), StorageError> {
+    let data = to_allocvec(rgb_anchors)
+        .map_err(|op| StorageError::StrictWrite(name.to_string(), op.to_string()))?;
+
+    let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase();
+
+    store(
+        sk,
+        &format!("{hashed_name}.c15"),
+        &data,
+        false,
+        Some(RGB_STRICT_TYPE_VERSION.to_vec()),
+    )
+    .await
+    .map_err(|op| write_error(name, op))
+}
+
+pub async fn store_labels(sk: &str, name: &str, labels: &Labels) -> Result<(), StorageError> {
+    let data = labels.to_jsonl().into_bytes();
+
+    let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase();
+
+    store(
+        sk,
+        &format!("{hashed_name}.c15"),
+        &data,
+        false,
+        Some(RGB_STRICT_TYPE_VERSION.to_vec()),
+    )
+    .await
+    .map_err(|op| write_error(name, op))
+}
+
+pub async fn store_resolver_cache(
+    sk: &str,
+    name: &str,
+    cache: &ResolverCache,
+) -> Result<(), StorageError> {
+    let data = serde_json::to_vec(cache)
+        .map_err(|op| StorageError::StrictWrite(name.to_string(), op.to_string()))?;
+
+    let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase();
+
+    // the cache only saves lookups, so the last write wins
     store(
         sk,
         &format!("{hashed_name}.c15"),
@@ -162,18 +440,46 @@ pub async fn store_bids(sk: &str, name: &str, rgb_bids: &RgbBids) -> Result<(),
         Some(RGB_STRICT_TYPE_VERSION.to_vec()),
     )
     .await
-    .map_err(|op| StorageError::CarbonadoWrite(name.to_string(), op.to_string()))
+    .map_err(|op| write_error(name, op))
 }
 
+/// Retrieve the whole stock, from its chunks or from a single file
+///
+/// Every chunk is read and verified, the stock is not loaded lazily, see [`store_stock`].
 pub async fn retrieve_stock(sk: &str, name: &str) -> Result<Stock, StorageError> {
     let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
         .to_hex()
         .to_lowercase();
 
-    let (data, _) = retrieve(sk, &format!("{hashed_name}.c15"), vec![])
+    let (data, metadata) = retrieve(sk, &format!("{hashed_name}.c15"), vec![])
         .await
         .map_err(|op| StorageError::CarbonadoRetrieve(name.to_string(), op.to_string()))?;
 
+    // Stocks stored before the chunks are a single strict-encoded file
+    let data = if metadata.as_deref() == Some(&RGB_STOCK_MANIFEST_VERSION[..]) {
+        let manifest: ChunkManifest = from_bytes(&data)
+            .map_err(|op| StorageError::SerializeRetrieve(name.to_string(), op.to_string()))?;
+
+        let mut stock_data = Vec::with_capacity(manifest.len as usize);
+        for id in &manifest.chunks {
+            let (chunk, _) = retrieve(sk, &format!("{hashed_name}-{id}.c15"), vec![])
+                .await
+                .map_err(|op| StorageError::CarbonadoRetrieve(name.to_string(), op.to_string()))?;
+            if chunk.is_empty() || chunk_id(&chunk) != *id {
+                return Err(StorageError::ChunkRetrieve(
+                    name.to_string(),
+                    id.to_string(),
+                ));
+            }
+            stock_data.extend(chunk);
+        }
+
+        remember_manifest(sk, &hashed_name, manifest);
+        stock_data
+    } else {
+        data
+    };
+
     if data.is_empty() {
         Ok(Stock::default())
     } else {
@@ -194,6 +500,7 @@ pub async fn retrieve_wallets(sk: &str, name: &str) -> Result<RgbAccountV1, Stor
     let (data, metadata) = retrieve(sk, &format!("{hashed_name}.c15"), vec![])
         .await
         .map_err(|op| StorageError::CarbonadoRetrieve(name.to_string(), op.to_string()))?;
+    cdrt_read_document(sk, name, &hashed_name).await?;
 
     if data.is_empty() {
         Ok(RgbAccountV1::default())
@@ -217,6 +524,7 @@ pub async fn retrieve_transfers(sk: &str, name: &str) -> Result<RgbTransfersV1,
     let (data, metadata) = retrieve(sk, &format!("{hashed_name}.c15"), vec![])
         .await
         .map_err(|op| StorageError::CarbonadoRetrieve(name.to_string(), op.to_string()))?;
+    cdrt_read_document(sk, name, &hashed_name).await?;
 
     if data.is_empty() {
         Ok(RgbTransfersV1::default())
@@ -237,14 +545,16 @@ pub async fn retrieve_offers(sk: &str, name: &str) -> Result<RgbOffers, StorageE
         .to_hex()
         .to_lowercase();
 
-    let (data, _) = retrieve(sk, &format!("{hashed_name}.c15"), vec![])
+    let (data, metadata) = retrieve(sk, &format!("{hashed_name}.c15"), vec![])
         .await
         .map_err(|op| StorageError::CarbonadoRetrieve(name.to_string(), op.to_string()))?;
+    cdrt_read_document(sk, name, &hashed_name).await?;
 
     if data.is_empty() {
         Ok(RgbOffers::default())
     } else {
-        let rgb_offers = from_bytes(&data)
+        let rgb_offers = chain(PersistedModel::Offers)
+            .decode(&data, metadata.as_deref())
             .map_err(|op| StorageError::StrictRetrieve(name.to_string(), op.to_string()))?;
         Ok(rgb_offers)
     }
@@ -255,20 +565,188 @@ pub async fn retrieve_bids(sk: &str, name: &str) -> Result<RgbBids, StorageError
         .to_hex()
         .to_lowercase();
 
-    let (data, _) = retrieve(sk, &format!("{hashed_name}.c15"), vec![])
+    let (data, metadata) = retrieve(sk, &format!("{hashed_name}.c15"), vec![])
         .await
         .map_err(|op| StorageError::CarbonadoRetrieve(name.to_string(), op.to_string()))?;
+    cdrt_read_document(sk, name, &hashed_name).await?;
 
     if data.is_empty() {
         Ok(RgbBids::default())
     } else {
-        let rgb_bids = from_bytes(&data)
+        let rgb_bids = chain(PersistedModel::Bids)
+            .decode(&data, metadata.as_deref())
             .map_err(|op| StorageError::StrictRetrieve(name.to_string(), op.to_string()))?;
         Ok(rgb_bids)
     }
 }
 
+pub async fn retrieve_watchers(sk: &str, name: &str) -> Result<RgbWatchers, StorageError> {
+    let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase();
+
+    let (data, _) = retrieve(sk, &format!("{hashed_name}.c15"), vec![])
+        .await
+        .map_err(|op| StorageError::CarbonadoRetrieve(name.to_string(), op.to_string()))?;
+
+    if data.is_empty() {
+        Ok(RgbWatchers::default())
+    } else {
+        let rgb_watchers = from_bytes(&data)
+            .map_err(|op| StorageError::StrictRetrieve(name.to_string(), op.to_string()))?;
+        Ok(rgb_watchers)
+    }
+}
+
+pub async fn retrieve_reserved_utxos(
+    sk: &str,
+    name: &str,
+) -> Result<RgbReservedUtxos, StorageError> {
+    let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase();
+
+    let (data, _) = retrieve(sk, &format!("{hashed_name}.c15"), vec![])
+        .await
+        .map_err(|op| StorageError::CarbonadoRetrieve(name.to_string(), op.to_string()))?;
+
+    if data.is_empty() {
+        Ok(RgbReservedUtxos::default())
+    } else {
+        let rgb_reserved = from_bytes(&data)
+            .map_err(|op| StorageError::StrictRetrieve(name.to_string(), op.to_string()))?;
+        Ok(rgb_reserved)
+    }
+}
+
+pub async fn retrieve_locked_utxos(sk: &str, name: &str) -> Result<RgbLockedUtxos, StorageError> {
+    let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase();
+
+    let (data, _) = retrieve(sk, &format!("{hashed_name}.c15"), vec![])
+        .await
+        .map_err(|op| StorageError::CarbonadoRetrieve(name.to_string(), op.to_string()))?;
+
+    if data.is_empty() {
+        Ok(RgbLockedUtxos::default())
+    } else {
+        let rgb_locked = from_bytes(&data)
+            .map_err(|op| StorageError::StrictRetrieve(name.to_string(), op.to_string()))?;
+        Ok(rgb_locked)
+    }
+}
+
+pub async fn retrieve_anchors(sk: &str, name: &str) -> Result<RgbAnchors, StorageError> {
+    let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase();
+
+    let (data, _) = retrieve(sk, &format!("{hashed_name}.c15"), vec![])
+        .await
+        .map_err(|op| StorageError::CarbonadoRetrieve(name.to_string(), op.to_string()))?;
+
+    if data.is_empty() {
+        Ok(RgbAnchors::default())
+    } else {
+        let rgb_anchors = from_bytes(&data)
+            .map_err(|op| StorageError::StrictRetrieve(name.to_string(), op.to_string()))?;
+        Ok(rgb_anchors)
+    }
+}
+
+pub async fn retrieve_labels(sk: &str, name: &str) -> Result<Labels, StorageError> {
+    let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase();
+
+    let (data, _) = retrieve(sk, &format!("{hashed_name}.c15"), vec![])
+        .await
+        .map_err(|op| StorageError::CarbonadoRetrieve(name.to_string(), op.to_string()))?;
+
+    if data.is_empty() {
+        Ok(Labels::default())
+    } else {
+        let data = String::from_utf8(data)
+            .map_err(|op| StorageError::StrictRetrieve(name.to_string(), op.to_string()))?;
+        let labels = Labels::from_jsonl(&data)
+            .map_err(|op| StorageError::StrictRetrieve(name.to_string(), op.to_string()))?;
+        Ok(labels)
+    }
+}
+
+pub async fn retrieve_resolver_cache(sk: &str, name: &str) -> Result<ResolverCache, StorageError> {
+    let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase();
+
+    let (data, _) = retrieve(sk, &format!("{hashed_name}.c15"), vec![])
+        .await
+        .map_err(|op| StorageError::CarbonadoRetrieve(name.to_string(), op.to_string()))?;
+
+    if data.is_empty() {
+        Ok(ResolverCache::default())
+    } else {
+        let cache = serde_json::from_slice(&data)
+            .map_err(|op| StorageError::StrictRetrieve(name.to_string(), op.to_string()))?;
+        Ok(cache)
+    }
+}
+
 // CDRT Operations
+
+/// Keep the automerge document of a model, the base of the next merge of its changes
+async fn cdrt_read_document(sk: &str, name: &str, hashed_name: &str) -> Result<(), StorageError> {
+    let (document, _) = retrieve(sk, &format!("{hashed_name}-doc.c15"), vec![])
+        .await
+        .map_err(|op| StorageError::ForkRead(name.to_string(), op.to_string()))?;
+    remember_document(sk, hashed_name, document);
+    Ok(())
+}
+
+/// Merge the changes made to a model since it was read with the latest document
+async fn cdrt_merge_document<T>(
+    sk: &str,
+    name: &str,
+    hashed_name: &str,
+    local: T,
+) -> Result<MergedDocument<T>, StorageError>
+where
+    T: Reconcile + Hydrate + Default,
+{
+    let base = read_document(sk, hashed_name).unwrap_or_default();
+    let (latest, _) = retrieve(sk, &format!("{hashed_name}-doc.c15"), vec![])
+        .await
+        .map_err(|op| StorageError::ForkRead(name.to_string(), op.to_string()))?;
+
+    merge_document(&base, &latest, local)
+        .map_err(|op| StorageError::MergeWrite(name.to_string(), op.to_string()))
+}
+
+/// Store the merged document, after the model itself
+///
+/// A document older than the model only means the next merge re-applies
+/// some records, while a newer one would remove the missing records.
+async fn cdrt_store_document<T>(
+    sk: &str,
+    name: &str,
+    hashed_name: &str,
+    merged: MergedDocument<T>,
+) -> Result<(), StorageError> {
+    store(
+        sk,
+        &format!("{hashed_name}-doc.c15"),
+        &merged.document,
+        false,
+        Some(RGB_STRICT_TYPE_VERSION.to_vec()),
+    )
+    .await
+    .map_err(|op| write_error(name, op))?;
+
+    remember_document(sk, hashed_name, merged.local);
+    Ok(())
+}
+
 pub async fn cdrt_store_wallets(sk: &str, name: &str, changes: &[u8]) -> Result<(), StorageError> {
     let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
         .to_hex()
@@ -309,7 +787,7 @@ pub async fn cdrt_store_wallets(sk: &str, name: &str, changes: &[u8]) -> Result<
         Some(RGB_ACCOUNT_VERSION.to_vec()),
     )
     .await
-    .map_err(|op| StorageError::CarbonadoWrite(name.to_string(), op.to_string()))?;
+    .map_err(|op| write_error(name, op))?;
 
     Ok(())
 }
@@ -356,7 +834,7 @@ pub async fn cdrt_retrieve_wallets(sk: &str, name: &str) -> Result<LocalRgbAccou
             Some(RGB_ACCOUNT_VERSION.to_vec()),
         )
         .await
-        .map_err(|op| StorageError::CarbonadoWrite(name.to_string(), op.to_string()))?;
+        .map_err(|op| write_error(name, op))?;
 
         Ok(LocalRgbAccount {
             version: fork_version.save(),
@@ -373,7 +851,7 @@ pub async fn retrieve_public_offers(name: &str) -> Result<LocalRgbOffers, Storag
     let main_name = &format!("{hashed_name}.c15");
     let original_name = &format!("{hashed_name}-diff.c15");
 
-    let (data, _) = marketplace_retrieve(main_name)
+    let (data, metadata) = marketplace_retrieve(main_name)
         .await
         .map_err(|op| StorageError::CarbonadoRetrieve(name.to_string(), op.to_string()))?;
     if data.is_empty() {
@@ -383,7 +861,8 @@ pub async fn retrieve_public_offers(name: &str) -> Result<LocalRgbOffers, Storag
         })
     } else {
         let mut original_version = automerge::AutoCommit::new();
-        let rgb_offers: RgbPublicSwaps = from_bytes(&data)
+        let rgb_offers: RgbPublicSwaps = chain(PersistedModel::PublicOffers)
+            .decode(&data, metadata.as_deref())
             .map_err(|op| StorageError::StrictRetrieve(name.to_string(), op.to_string()))?;
 
         reconcile(&mut original_version, rgb_offers.clone())
@@ -409,46 +888,80 @@ pub async fn retrieve_public_offers(name: &str) -> Result<LocalRgbOffers, Storag
 pub async fn retrieve_auctions_offers(
     bundle_id: &str,
     name: &str,
+) -> Result<LocalRgbAuctions, StorageError> {
+    Ok(read_auctions_offers(bundle_id, name)
+        .await?
+        .unwrap_or_else(|| LocalRgbAuctions {
+            version: automerge::AutoCommit::new().save(),
+            rgb_offers: RgbAuctionSwaps::default(),
+        }))
+}
+
+/// Retrieve the offers of an auction to change them
+///
+/// The retrieved version is stored with the signature of `sk`, so
+/// [`store_auction_offers`] can merge the changes with it.
+pub async fn fork_auctions_offers(
+    sk: &str,
+    bundle_id: &str,
+    name: &str,
 ) -> Result<LocalRgbAuctions, StorageError> {
     let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
         .to_hex()
         .to_lowercase();
 
-    let main_name = &format!("{hashed_name}.c15");
     let original_name = &format!("{hashed_name}-diff.c15");
 
-    let (data, _) = auctions_retrieve(bundle_id, main_name)
-        .await
-        .map_err(|op| StorageError::CarbonadoRetrieve(name.to_string(), op.to_string()))?;
-    if data.is_empty() {
-        Ok(LocalRgbAuctions {
+    match read_auctions_offers(bundle_id, name).await? {
+        Some(local) => {
+            auctions_store(
+                sk,
+                bundle_id,
+                original_name,
+                &local.version,
+                Some(RGB_STRICT_TYPE_VERSION.to_vec()),
+            )
+            .await
+            .map_err(|op| StorageError::CarbonadoWrite(name.to_string(), op.to_string()))?;
+            Ok(local)
+        }
+        None => Ok(LocalRgbAuctions {
             version: automerge::AutoCommit::new().save(),
             rgb_offers: RgbAuctionSwaps::default(),
-        })
-    } else {
-        let mut original_version = automerge::AutoCommit::new();
-        let rgb_offers: RgbAuctionSwaps = from_bytes(&data)
-            .map_err(|op| StorageError::StrictRetrieve(name.to_string(), op.to_string()))?;
+        }),
+    }
+}
 
-        reconcile(&mut original_version, rgb_offers.clone())
-            .map_err(|op| StorageError::Reconcile(name.to_string(), op.to_string()))?;
+async fn read_auctions_offers(
+    bundle_id: &str,
+    name: &str,
+) -> Result<Option<LocalRgbAuctions>, StorageError> {
+    let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase();
 
-        let mut fork_version = original_version.fork();
+    let main_name = &format!("{hashed_name}.c15");
 
-        auctions_store(
-            bundle_id,
-            original_name,
-            &fork_version.save(),
-            Some(RGB_STRICT_TYPE_VERSION.to_vec()),
-        )
+    let (data, metadata) = auctions_retrieve(bundle_id, main_name)
         .await
-        .map_err(|op| StorageError::CarbonadoWrite(name.to_string(), op.to_string()))?;
-
-        Ok(LocalRgbAuctions {
-            version: fork_version.save(),
-            rgb_offers,
-        })
+        .map_err(|op| StorageError::CarbonadoRetrieve(name.to_string(), op.to_string()))?;
+    if data.is_empty() {
+        return Ok(None);
     }
+
+    let mut original_version = automerge::AutoCommit::new();
+    let rgb_offers: RgbAuctionSwaps = chain(PersistedModel::Auctions)
+        .decode(&data, metadata.as_deref())
+        .map_err(|op| StorageError::StrictRetrieve(name.to_string(), op.to_string()))?;
+
+    reconcile(&mut original_version, rgb_offers.clone())
+        .map_err(|op| StorageError::Reconcile(name.to_string(), op.to_string()))?;
+
+    let mut fork_version = original_version.fork();
+    Ok(Some(LocalRgbAuctions {
+        version: fork_version.save(),
+        rgb_offers,
+    }))
 }
 
 pub async fn store_public_offers(name: &str, changes: &[u8]) -> Result<(), StorageError> {
@@ -486,6 +999,7 @@ pub async fn store_public_offers(name: &str, changes: &[u8]) -> Result<(), Stora
 }
 
 pub async fn store_auction_offers(
+    sk: &str,
     bundle_id: &str,
     name: &str,
     changes: &[u8],
@@ -516,6 +1030,7 @@ pub async fn store_auction_offers(
         .map_err(|op| StorageError::StrictWrite(name.to_string(), op.to_string()))?;
 
     auctions_store(
+        sk,
         bundle_id,
         main_name,
         &data,
@@ -527,6 +1042,17 @@ pub async fn store_auction_offers(
     Ok(())
 }
 
+/// Allow `bidders` to write to the auction of the seller `sk`
+pub async fn store_auction_bidders(
+    sk: &str,
+    bundle_id: &str,
+    bidders: &[String],
+) -> Result<(), StorageError> {
+    auctions_allow(sk, bundle_id, bidders)
+        .await
+        .map_err(|op| StorageError::CarbonadoWrite(bundle_id.to_string(), op.to_string()))
+}
+
 pub async fn retrieve_swap_offer_bid(
     sk: &str,
     name: &str,
@@ -565,11 +1091,11 @@ pub async fn retrieve_swap_offer_bid(
             sk,
             original_name,
             &fork_version.save(),
-            false,
+            true,
             Some(RGB_STRICT_TYPE_VERSION.to_vec()),
         )
         .await
-        .map_err(|op| StorageError::CarbonadoWrite(name.to_string(), op.to_string()))?;
+        .map_err(|op| write_error(name, op))?;
 
         Ok(LocalRgbOfferBid {
             version: fork_version.save(),
@@ -622,7 +1148,7 @@ pub async fn store_swap_offer_bid(
         Some(RGB_STRICT_TYPE_VERSION.to_vec()),
     )
     .await
-    .map_err(|op| StorageError::CarbonadoWrite(name.to_string(), op.to_string()))?;
+    .map_err(|op| write_error(name, op))?;
 
     Ok(())
 }
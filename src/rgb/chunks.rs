@@ -0,0 +1,107 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+/// Chunks are cut after this size, when the content allows it
+pub const CHUNK_MIN_SIZE: usize = 16 * 1024;
+/// Chunks are always cut at this size
+pub const CHUNK_MAX_SIZE: usize = 256 * 1024;
+/// Cut points are ~64 KiB apart on average
+const CHUNK_MASK: u64 = (1 << 16) - 1;
+
+const fn gear_table() -> [u64; 256] {
+    // splitmix64, the table only needs to look random
+    let mut table = [0u64; 256];
+    let mut seed: u64 = 0;
+    let mut i = 0;
+    while i < 256 {
+        seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+static GEAR: [u64; 256] = gear_table();
+
+/// Split data at content-defined cut points
+///
+/// Cut points only depend on the last 64 bytes, so an edit (e.g. a new
+/// consignment in the stock) only changes the chunks around it.
+pub fn split_chunks(data: &[u8]) -> Vec<&[u8]> {
+    let mut chunks = vec![];
+    let mut start = 0;
+    let mut hash: u64 = 0;
+    for (pos, byte) in data.iter().enumerate() {
+        hash = (hash << 1).wrapping_add(GEAR[*byte as usize]);
+        let size = pos + 1 - start;
+        if (size >= CHUNK_MIN_SIZE && hash & CHUNK_MASK == 0) || size >= CHUNK_MAX_SIZE {
+            chunks.push(&data[start..=pos]);
+            start = pos + 1;
+            hash = 0;
+        }
+    }
+
+    if start < data.len() {
+        chunks.push(&data[start..]);
+    }
+    chunks
+}
+
+pub fn chunk_id(chunk: &[u8]) -> String {
+    blake3::hash(chunk).to_hex().to_lowercase()
+}
+
+/// Chunks of a file stored in pieces, in order
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkManifest {
+    /// Blake3 hashes of the chunks
+    pub chunks: Vec<String>,
+    pub len: u64,
+}
+
+impl ChunkManifest {
+    pub fn with(data: &[u8]) -> (Self, Vec<&[u8]>) {
+        let chunks = split_chunks(data);
+        let manifest = Self {
+            chunks: chunks.iter().map(|chunk| chunk_id(chunk)).collect(),
+            len: data.len() as u64,
+        };
+        (manifest, chunks)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.chunks.iter().any(|chunk| chunk == id)
+    }
+}
+
+/// Last manifest stored or retrieved by this process, by owner and file
+///
+/// It is the base of the next write, which checks the stored manifest is
+/// still this one before skipping or removing its chunks.
+static STORED_MANIFESTS: Lazy<Mutex<BTreeMap<String, ChunkManifest>>> = Lazy::new(Default::default);
+
+fn manifest_key(sk: &str, name: &str) -> String {
+    blake3::hash(format!("{sk}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase()
+}
+
+pub fn stored_manifest(sk: &str, name: &str) -> Option<ChunkManifest> {
+    STORED_MANIFESTS
+        .lock()
+        .expect("stored manifests poisoned")
+        .get(&manifest_key(sk, name))
+        .cloned()
+}
+
+pub fn remember_manifest(sk: &str, name: &str, manifest: ChunkManifest) {
+    STORED_MANIFESTS
+        .lock()
+        .expect("stored manifests poisoned")
+        .insert(manifest_key(sk, name), manifest);
+}
@@ -5,8 +5,13 @@ pub const RGB_PSBT_TAPRET: &str = "TAPRET";
 pub const RGB_DEFAULT_NAME: &str = "default";
 pub const RGB_OLDEST_VERSION: [u8; 8] = [0; 8];
 pub const RGB_STRICT_TYPE_VERSION: [u8; 8] = *b"rgbst161";
+pub const RGB_STOCK_MANIFEST_VERSION: [u8; 8] = *b"rgbsm001";
+pub const RGB_ACCOUNT_VERSION: [u8; 3] = *b"v10";
+pub const RGB_TRANSFER_VERSION: [u8; 3] = *b"v10";
 pub const RGB_DEFAULT_FETCH_LIMIT: u32 = 10;
 pub const BITCOIN_DEFAULT_FETCH_LIMIT: u32 = 20;
+/// Confirmations after which an anchor is not watched for reorganizations anymore
+pub const RGB_ANCHOR_FINAL_DEPTH: u32 = 100;
 pub const RGB20_DERIVATION_INDEX: u32 = 20;
 pub const RGB21_DERIVATION_INDEX: u32 = 21;
 
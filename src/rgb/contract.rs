@@ -350,6 +350,7 @@ where
             armored: "".to_string(),
         },
         meta,
+        label: None,
     };
 
     Ok(resp)
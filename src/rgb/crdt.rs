@@ -1,18 +1,21 @@
 use amplify::hex::ToHex;
-use autosurgeon::{Hydrate, Reconcile};
+use automerge::{transaction::CommitOptions, ActorId, AutoCommit};
+use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile};
 use bitcoin::OutPoint;
 use bitcoin_30::bip32::ExtendedPubKey;
 use bp::{dbc::tapret::TapretCommitment, Outpoint};
+use once_cell::sync::Lazy;
 use rgb::{DeriveInfo, RgbDescr, RgbWallet, Tapret, TerminalPath, Utxo};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::{BTreeMap, HashMap},
     str::FromStr,
+    sync::Mutex,
 };
 
 use crate::rgb::{
-    structs::{RgbAccountV0, RgbAccountV1},
-    swap::{RgbAuctionSwaps, RgbBidSwap, RgbPublicSwaps},
+    structs::{RgbAccountV0, RgbAccountV1, RgbTransferV1, RgbTransfersV1},
+    swap::{RgbAuctionSwaps, RgbBid, RgbBidSwap, RgbBids, RgbOffer, RgbOffers, RgbPublicSwaps},
 };
 
 #[derive(Debug, Clone, Eq, PartialEq, Display, From, Error)]
@@ -20,6 +23,16 @@ use crate::rgb::{
 pub enum RgbMergeError {
     /// Invalid Tapret Wallet Format
     NoTapret,
+    /// Document load causes error. {0}
+    Load(String),
+    /// Reconcile causes error. {0}
+    Reconcile(String),
+    /// Merge causes error. {0}
+    Merge(String),
+    /// Hydrate causes error. {0}
+    Hydrate(String),
+    /// Document value is invalid. {0}
+    Invalid(String),
 }
 
 #[derive(PartialEq, Serialize, Deserialize, Debug, Clone, Reconcile, Hydrate, Default, Display)]
@@ -169,9 +182,15 @@ impl From<RawUtxo> for Utxo {
         let outpoint = OutPoint::from_str(&raw_utxo.outpoint).expect("invalid outpoint parse");
         let txid = bp::Txid::from_str(&outpoint.txid.to_hex()).expect("invalid txid");
 
+        // utxos are stored with the block of their transaction, 0 while unconfirmed
+        let status = match raw_utxo.block {
+            0 => rgb::MiningStatus::Mempool,
+            block => rgb::MiningStatus::Blockchain(block),
+        };
+
         Self {
             outpoint: Outpoint::new(txid, outpoint.vout),
-            status: rgb::MiningStatus::Mempool,
+            status,
             amount: raw_utxo.amount,
             derivation: derive_info,
         }
@@ -327,3 +346,322 @@ pub struct LocalRgbOfferBid {
     pub version: Vec<u8>,
     pub rgb_bid: RgbBidSwap,
 }
+
+/// Transfers by `{contract_id}/{consig_id}/{tx_id}/{sender}`, so the
+/// transfers saved by two devices at the same time are both kept
+#[derive(PartialEq, Serialize, Deserialize, Debug, Clone, Reconcile, Hydrate, Default)]
+pub struct RawRgbTransfers {
+    pub transfers: BTreeMap<String, RawRgbTransfer>,
+}
+
+#[derive(PartialEq, Serialize, Deserialize, Debug, Clone, Reconcile, Hydrate, Default)]
+pub struct RawRgbTransfer {
+    pub contract_id: String,
+    /// Position of the transfer in the transfers of the contract
+    pub position: u64,
+    pub consig_id: String,
+    pub tx_id: String,
+    pub iface: String,
+    pub consig: String,
+    pub sender: bool,
+    pub rbf: bool,
+    pub utxos: Vec<String>,
+    pub beneficiaries: Vec<String>,
+}
+
+impl From<RgbTransfersV1> for RawRgbTransfers {
+    fn from(rgb_transfers: RgbTransfersV1) -> Self {
+        let mut transfers = BTreeMap::new();
+        for (contract_id, items) in rgb_transfers.transfers {
+            for (position, transfer) in items.into_iter().enumerate() {
+                let RgbTransferV1 {
+                    consig_id,
+                    tx_id,
+                    iface,
+                    consig,
+                    sender,
+                    rbf,
+                    utxos,
+                    beneficiaries,
+                } = transfer;
+
+                let key = format!("{contract_id}/{consig_id}/{tx_id}/{sender}");
+                transfers.insert(
+                    key,
+                    RawRgbTransfer {
+                        contract_id: contract_id.clone(),
+                        position: position as u64,
+                        consig_id,
+                        tx_id: tx_id.to_string(),
+                        iface,
+                        consig,
+                        sender,
+                        rbf,
+                        utxos,
+                        beneficiaries,
+                    },
+                );
+            }
+        }
+        Self { transfers }
+    }
+}
+
+impl TryFrom<RawRgbTransfers> for RgbTransfersV1 {
+    type Error = RgbMergeError;
+
+    fn try_from(raw_transfers: RawRgbTransfers) -> Result<Self, Self::Error> {
+        let mut raw_items: Vec<_> = raw_transfers.transfers.into_iter().collect();
+        raw_items.sort_by(|(a_key, a), (b_key, b)| (a.position, a_key).cmp(&(b.position, b_key)));
+
+        let mut transfers: BTreeMap<String, Vec<RgbTransferV1>> = BTreeMap::new();
+        for (_, raw_transfer) in raw_items {
+            let RawRgbTransfer {
+                contract_id,
+                consig_id,
+                tx_id,
+                iface,
+                consig,
+                sender,
+                rbf,
+                utxos,
+                beneficiaries,
+                ..
+            } = raw_transfer;
+            let tx_id = bp::Txid::from_str(&tx_id)
+                .map_err(|_| RgbMergeError::Invalid(format!("transfer txid '{tx_id}'")))?;
+
+            transfers
+                .entry(contract_id)
+                .or_default()
+                .push(RgbTransferV1 {
+                    consig_id,
+                    tx_id,
+                    iface,
+                    consig,
+                    sender,
+                    rbf,
+                    utxos,
+                    beneficiaries,
+                });
+        }
+        Ok(Self { transfers })
+    }
+}
+
+/// Offers by `offer_id` and their bids by `{offer_id}/{bid_id}`
+#[derive(Serialize, Deserialize, Debug, Clone, Reconcile, Hydrate, Default)]
+pub struct RawRgbOffers {
+    pub offers: BTreeMap<String, RawRgbOffer>,
+    pub bids: BTreeMap<String, RgbBid>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Reconcile, Hydrate, Default)]
+pub struct RawRgbOffer {
+    pub contract_id: String,
+    /// Position of the offer in the offers of the contract
+    pub position: u64,
+    pub offer: RgbOffer,
+}
+
+impl From<RgbOffers> for RawRgbOffers {
+    fn from(rgb_offers: RgbOffers) -> Self {
+        let mut offers = BTreeMap::new();
+        for (contract_id, items) in rgb_offers.offers {
+            for (position, offer) in items.into_iter().enumerate() {
+                offers.insert(
+                    offer.offer_id.clone(),
+                    RawRgbOffer {
+                        contract_id: contract_id.clone(),
+                        position: position as u64,
+                        offer,
+                    },
+                );
+            }
+        }
+
+        let mut bids = BTreeMap::new();
+        for (offer_id, items) in rgb_offers.bids {
+            for (bid_id, bid) in items {
+                bids.insert(format!("{offer_id}/{bid_id}"), bid);
+            }
+        }
+        Self { offers, bids }
+    }
+}
+
+impl TryFrom<RawRgbOffers> for RgbOffers {
+    type Error = RgbMergeError;
+
+    fn try_from(raw_offers: RawRgbOffers) -> Result<Self, Self::Error> {
+        let mut raw_items: Vec<_> = raw_offers.offers.into_iter().collect();
+        raw_items.sort_by(|(a_key, a), (b_key, b)| (a.position, a_key).cmp(&(b.position, b_key)));
+
+        let mut rgb_offers = RgbOffers::default();
+        for (
+            _,
+            RawRgbOffer {
+                contract_id, offer, ..
+            },
+        ) in raw_items
+        {
+            rgb_offers
+                .offers
+                .entry(contract_id)
+                .or_default()
+                .push(offer);
+        }
+
+        for (key, bid) in raw_offers.bids {
+            let (offer_id, bid_id) = key
+                .split_once('/')
+                .ok_or_else(|| RgbMergeError::Invalid(format!("bid key '{key}'")))?;
+            rgb_offers
+                .bids
+                .entry(offer_id.to_string())
+                .or_default()
+                .insert(bid_id.to_string(), bid);
+        }
+        Ok(rgb_offers)
+    }
+}
+
+/// Bids by `bid_id`
+#[derive(Serialize, Deserialize, Debug, Clone, Reconcile, Hydrate, Default)]
+pub struct RawRgbBids {
+    pub bids: BTreeMap<String, RawRgbBid>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Reconcile, Hydrate, Default)]
+pub struct RawRgbBid {
+    pub contract_id: String,
+    /// Position of the bid in the bids of the contract
+    pub position: u64,
+    pub bid: RgbBid,
+}
+
+impl From<RgbBids> for RawRgbBids {
+    fn from(rgb_bids: RgbBids) -> Self {
+        let mut bids = BTreeMap::new();
+        for (contract_id, items) in rgb_bids.bids {
+            for (position, bid) in items.into_iter().enumerate() {
+                bids.insert(
+                    bid.bid_id.clone(),
+                    RawRgbBid {
+                        contract_id: contract_id.clone(),
+                        position: position as u64,
+                        bid,
+                    },
+                );
+            }
+        }
+        Self { bids }
+    }
+}
+
+impl From<RawRgbBids> for RgbBids {
+    fn from(raw_bids: RawRgbBids) -> Self {
+        let mut raw_items: Vec<_> = raw_bids.bids.into_iter().collect();
+        raw_items.sort_by(|(a_key, a), (b_key, b)| (a.position, a_key).cmp(&(b.position, b_key)));
+
+        let mut rgb_bids = RgbBids::default();
+        for (
+            _,
+            RawRgbBid {
+                contract_id, bid, ..
+            },
+        ) in raw_items
+        {
+            rgb_bids.bids.entry(contract_id).or_default().push(bid);
+        }
+        rgb_bids
+    }
+}
+
+/// Actor of the first change of the documents, so every device starts with the same change
+const RGB_INIT_ACTOR: &[u8] = b"bitmask-init";
+
+/// Empty document of a model, the same on every device
+///
+/// The first change is committed by a fixed actor at a fixed time, so it has
+/// the same hash everywhere. The next changes get an actor of the device, or
+/// two devices would write different changes with the same actor and sequence.
+pub fn init_document<T: Reconcile + Default>() -> Result<AutoCommit, RgbMergeError> {
+    let mut doc = AutoCommit::new().with_actor(ActorId::from(RGB_INIT_ACTOR));
+    reconcile(&mut doc, T::default()).map_err(|op| RgbMergeError::Reconcile(op.to_string()))?;
+    doc.commit_with(CommitOptions::default().with_time(0));
+    doc.set_actor(ActorId::random());
+    Ok(doc)
+}
+
+fn load_document<T: Reconcile + Default>(bytes: &[u8]) -> Result<AutoCommit, RgbMergeError> {
+    if bytes.is_empty() {
+        init_document::<T>()
+    } else {
+        AutoCommit::load(bytes).map_err(|op| RgbMergeError::Load(op.to_string()))
+    }
+}
+
+/// Document of a model after a merge, see [`merge_document`]
+#[derive(Debug, Clone)]
+pub struct MergedDocument<T> {
+    /// Latest document, with the changes of the device
+    pub document: Vec<u8>,
+    /// Document of the device, the base of its next changes
+    pub local: Vec<u8>,
+    pub value: T,
+}
+
+/// Merge the value of a model changed by a device into its latest document
+///
+/// `base` is the document the device read the value from, and `latest` the
+/// stored document, which other devices may have changed since. Only the
+/// difference between `base` and `local` is applied, so the records added by
+/// other devices are kept.
+pub fn merge_document<T>(
+    base: &[u8],
+    latest: &[u8],
+    local: T,
+) -> Result<MergedDocument<T>, RgbMergeError>
+where
+    T: Reconcile + Hydrate + Default,
+{
+    let mut changes = load_document::<T>(base)?;
+    reconcile(&mut changes, local).map_err(|op| RgbMergeError::Reconcile(op.to_string()))?;
+
+    let mut document = load_document::<T>(latest)?;
+    document
+        .merge(&mut changes)
+        .map_err(|op| RgbMergeError::Merge(op.to_string()))?;
+
+    let value: T = hydrate(&document).map_err(|op| RgbMergeError::Hydrate(op.to_string()))?;
+    Ok(MergedDocument {
+        document: document.save(),
+        local: changes.save(),
+        value,
+    })
+}
+
+/// Documents read by this process, by owner and file, the base of the next merge
+static READ_DOCUMENTS: Lazy<Mutex<BTreeMap<String, Vec<u8>>>> = Lazy::new(Default::default);
+
+fn document_key(sk: &str, name: &str) -> String {
+    blake3::hash(format!("{sk}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase()
+}
+
+pub fn read_document(sk: &str, name: &str) -> Option<Vec<u8>> {
+    READ_DOCUMENTS
+        .lock()
+        .expect("read documents poisoned")
+        .get(&document_key(sk, name))
+        .cloned()
+}
+
+pub fn remember_document(sk: &str, name: &str, document: Vec<u8>) {
+    READ_DOCUMENTS
+        .lock()
+        .expect("read documents poisoned")
+        .insert(document_key(sk, name), document);
+}
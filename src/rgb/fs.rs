@@ -1,28 +1,46 @@
 use rgbstd::persistence::Stock;
 
 use crate::constants::storage_keys::{
-    ASSETS_BIDS, ASSETS_OFFERS, ASSETS_STOCK, ASSETS_TRANSFERS, ASSETS_WALLETS, MARKETPLACE_OFFERS,
+    ACCOUNT_LABELS, ACCOUNT_LOCKED, ASSETS_ANCHORS, ASSETS_BIDS, ASSETS_OFFERS, ASSETS_RESERVED,
+    ASSETS_STOCK, ASSETS_TRANSFERS, ASSETS_WALLETS, ASSETS_WATCHERS, MARKETPLACE_OFFERS,
+    RESOLVER_CACHE,
 };
 use crate::rgb::{
     carbonado::{
         cdrt_retrieve_wallets, cdrt_store_wallets,
+        fork_auctions_offers as fork_rgb_auctions_offers, retrieve_anchors as retrieve_rgb_anchors,
         retrieve_auctions_offers as retrieve_rgb_auctions_offers,
-        retrieve_bids as retrieve_rgb_bids, retrieve_offers as retrieve_rgb_offers,
-        retrieve_public_offers as retrieve_rgb_public_offers, retrieve_stock as retrieve_rgb_stock,
+        retrieve_bids as retrieve_rgb_bids, retrieve_labels as retrieve_rgb_labels,
+        retrieve_locked_utxos as retrieve_rgb_locked_utxos, retrieve_offers as retrieve_rgb_offers,
+        retrieve_public_offers as retrieve_rgb_public_offers,
+        retrieve_reserved_utxos as retrieve_rgb_reserved_utxos,
+        retrieve_stock as retrieve_rgb_stock,
         retrieve_swap_offer_bid as retrieve_rgb_swap_offer_bid,
         retrieve_transfers as retrieve_rgb_transfers, retrieve_wallets,
+        retrieve_watchers as retrieve_rgb_watchers, store_anchors as store_rgb_anchors,
+        store_auction_bidders as store_rgb_auction_bidders,
         store_auction_offers as store_rgb_auction_offers, store_bids as store_rgb_bids,
+        store_labels as store_rgb_labels, store_locked_utxos as store_rgb_locked_utxos,
         store_offers as store_rgb_offers, store_public_offers as store_rgb_public_offers,
-        store_stock as store_rgb_stock, store_swap_offer_bid,
-        store_transfers as store_rgb_transfer, store_wallets,
+        store_reserved_utxos as store_rgb_reserved_utxos, store_stock as store_rgb_stock,
+        store_swap_offer_bid, store_transfers as store_rgb_transfer, store_wallets,
+        store_watchers as store_rgb_watchers,
     },
     crdt::LocalRgbAccount,
     crdt::{LocalRgbOfferBid, LocalRgbOffers},
-    structs::{RgbAccountV1, RgbTransfersV1},
+    structs::{
+        RgbAccountV1, RgbAnchors, RgbLockedUtxos, RgbReservedUtxos, RgbTransfersV1, RgbWatchers,
+    },
     swap::{RgbBids, RgbOffers},
 };
 
 use super::crdt::LocalRgbAuctions;
+use crate::chain::ResolverCache;
+use crate::labels::Labels;
+use crate::rgb::carbonado::{
+    retrieve_resolver_cache as retrieve_rgb_resolver_cache,
+    store_resolver_cache as store_rgb_resolver_cache,
+};
 
 #[derive(Debug, Clone, Eq, PartialEq, Display, From, Error)]
 #[display(doc_comments)]
@@ -45,6 +63,18 @@ pub enum RgbPersistenceError {
     RetrievePublicOffers(String),
     // Retrieve Auction Offers Error. {0}
     RetrieveAuctionOffers(String),
+    // Retrieve Watchers Settings Error. {0}
+    RetrieveRgbWatchers(String),
+    // Retrieve Labels Error. {0}
+    RetrieveLabels(String),
+    // Retrieve Anchors Error. {0}
+    RetrieveRgbAnchors(String),
+    // Retrieve Reserved UTXOs Error. {0}
+    RetrieveRgbReservedUtxos(String),
+    // Retrieve Locked UTXOs Error. {0}
+    RetrieveRgbLockedUtxos(String),
+    // Retrieve Resolver Cache Error. {0}
+    RetrieveResolverCache(String),
     // Store Stock Error. {0}
     WriteStock(String),
     // Store RgbAccountV1 Error. {0}
@@ -63,6 +93,18 @@ pub enum RgbPersistenceError {
     WriteRgbAuctionOffers(String),
     // Store Swap Bid Error. {0}
     WriteSwapBids(String),
+    // Store Watchers Settings Error. {0}
+    WriteRgbWatchers(String),
+    // Store Labels Error. {0}
+    WriteLabels(String),
+    // Store Anchors Error. {0}
+    WriteRgbAnchors(String),
+    // Store Reserved UTXOs Error. {0}
+    WriteRgbReservedUtxos(String),
+    // Store Locked UTXOs Error. {0}
+    WriteRgbLockedUtxos(String),
+    // Store Resolver Cache Error. {0}
+    WriteResolverCache(String),
 }
 
 pub async fn retrieve_stock(sk: &str) -> Result<Stock, RgbPersistenceError> {
@@ -116,6 +158,18 @@ pub async fn retrieve_auctions_offers(
     Ok(stock)
 }
 
+pub async fn fork_auctions_offers(
+    sk: &str,
+    bundle_id: &str,
+    name: &str,
+) -> Result<LocalRgbAuctions, RgbPersistenceError> {
+    let stock = fork_rgb_auctions_offers(sk, bundle_id, name)
+        .await
+        .map_err(|op| RgbPersistenceError::RetrieveAuctionOffers(op.to_string()))?;
+
+    Ok(stock)
+}
+
 pub async fn retrieve_swap_offer_bid(
     sk: &str,
     name: &str,
@@ -144,6 +198,55 @@ pub async fn retrieve_bids(sk: &str) -> Result<RgbBids, RgbPersistenceError> {
     Ok(bids)
 }
 
+pub async fn retrieve_watchers(sk: &str) -> Result<RgbWatchers, RgbPersistenceError> {
+    let watchers = retrieve_rgb_watchers(sk, ASSETS_WATCHERS)
+        .await
+        .map_err(|op| RgbPersistenceError::RetrieveRgbWatchers(op.to_string()))?;
+
+    Ok(watchers)
+}
+
+pub async fn retrieve_anchors(sk: &str) -> Result<RgbAnchors, RgbPersistenceError> {
+    let anchors = retrieve_rgb_anchors(sk, ASSETS_ANCHORS)
+        .await
+        .map_err(|op| RgbPersistenceError::RetrieveRgbAnchors(op.to_string()))?;
+
+    Ok(anchors)
+}
+
+pub async fn retrieve_reserved_utxos(sk: &str) -> Result<RgbReservedUtxos, RgbPersistenceError> {
+    let reserved = retrieve_rgb_reserved_utxos(sk, ASSETS_RESERVED)
+        .await
+        .map_err(|op| RgbPersistenceError::RetrieveRgbReservedUtxos(op.to_string()))?;
+
+    Ok(reserved)
+}
+
+pub async fn retrieve_locked_utxos(sk: &str) -> Result<RgbLockedUtxos, RgbPersistenceError> {
+    let locked = retrieve_rgb_locked_utxos(sk, ACCOUNT_LOCKED)
+        .await
+        .map_err(|op| RgbPersistenceError::RetrieveRgbLockedUtxos(op.to_string()))?;
+
+    Ok(locked)
+}
+
+pub async fn retrieve_labels(sk: &str) -> Result<Labels, RgbPersistenceError> {
+    let labels = retrieve_rgb_labels(sk, ACCOUNT_LABELS)
+        .await
+        .map_err(|op| RgbPersistenceError::RetrieveLabels(op.to_string()))?;
+
+    Ok(labels)
+}
+
+/// The resolver cache is kept with the account, encrypted like its other files
+pub async fn retrieve_resolver_cache(sk: &str) -> Result<ResolverCache, RgbPersistenceError> {
+    let cache = retrieve_rgb_resolver_cache(sk, RESOLVER_CACHE)
+        .await
+        .map_err(|op| RgbPersistenceError::RetrieveResolverCache(op.to_string()))?;
+
+    Ok(cache)
+}
+
 pub async fn retrieve_stock_account(
     sk: &str,
 ) -> Result<(Stock, RgbAccountV1), RgbPersistenceError> {
@@ -205,6 +308,54 @@ pub async fn store_bids(sk: &str, rgb_bids: RgbBids) -> Result<(), RgbPersistenc
         .map_err(|op| RgbPersistenceError::WriteRgbBids(op.to_string()))
 }
 
+pub async fn store_watchers(
+    sk: &str,
+    rgb_watchers: RgbWatchers,
+) -> Result<(), RgbPersistenceError> {
+    store_rgb_watchers(sk, ASSETS_WATCHERS, &rgb_watchers)
+        .await
+        .map_err(|op| RgbPersistenceError::WriteRgbWatchers(op.to_string()))
+}
+
+pub async fn store_anchors(sk: &str, anchors: RgbAnchors) -> Result<(), RgbPersistenceError> {
+    store_rgb_anchors(sk, ASSETS_ANCHORS, &anchors)
+        .await
+        .map_err(|op| RgbPersistenceError::WriteRgbAnchors(op.to_string()))
+}
+
+pub async fn store_reserved_utxos(
+    sk: &str,
+    reserved: RgbReservedUtxos,
+) -> Result<(), RgbPersistenceError> {
+    store_rgb_reserved_utxos(sk, ASSETS_RESERVED, &reserved)
+        .await
+        .map_err(|op| RgbPersistenceError::WriteRgbReservedUtxos(op.to_string()))
+}
+
+pub async fn store_locked_utxos(
+    sk: &str,
+    locked: RgbLockedUtxos,
+) -> Result<(), RgbPersistenceError> {
+    store_rgb_locked_utxos(sk, ACCOUNT_LOCKED, &locked)
+        .await
+        .map_err(|op| RgbPersistenceError::WriteRgbLockedUtxos(op.to_string()))
+}
+
+pub async fn store_labels(sk: &str, labels: Labels) -> Result<(), RgbPersistenceError> {
+    store_rgb_labels(sk, ACCOUNT_LABELS, &labels)
+        .await
+        .map_err(|op| RgbPersistenceError::WriteLabels(op.to_string()))
+}
+
+pub async fn store_resolver_cache(
+    sk: &str,
+    cache: ResolverCache,
+) -> Result<(), RgbPersistenceError> {
+    store_rgb_resolver_cache(sk, RESOLVER_CACHE, &cache)
+        .await
+        .map_err(|op| RgbPersistenceError::WriteResolverCache(op.to_string()))
+}
+
 pub async fn store_swap_bids(
     sk: &str,
     name: &str,
@@ -223,11 +374,22 @@ pub async fn store_public_offers(changes: Vec<u8>) -> Result<(), RgbPersistenceE
 }
 
 pub async fn store_auction_offers(
+    sk: &str,
     bundle_id: &str,
     name: &str,
     changes: Vec<u8>,
 ) -> Result<(), RgbPersistenceError> {
-    store_rgb_auction_offers(bundle_id, name, &changes)
+    store_rgb_auction_offers(sk, bundle_id, name, &changes)
+        .await
+        .map_err(|op| RgbPersistenceError::WriteRgbAuctionOffers(op.to_string()))
+}
+
+pub async fn store_auction_bidders(
+    sk: &str,
+    bundle_id: &str,
+    bidders: &[String],
+) -> Result<(), RgbPersistenceError> {
+    store_rgb_auction_bidders(sk, bundle_id, bidders)
         .await
         .map_err(|op| RgbPersistenceError::WriteRgbAuctionOffers(op.to_string()))
 }
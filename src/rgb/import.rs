@@ -20,8 +20,22 @@ use crate::structs::AssetType;
 
 #[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
 #[display(doc_comments)]
-// TODO: Complete errors
-pub enum ImportContractError {}
+pub enum ImportContractError {
+    /// The contract is not a valid armored contract (reason: {0})
+    WrongArmored(String),
+    /// The contract is not valid bech32m or hexadecimal data (reason: {0})
+    WrongEncoding(String),
+    /// The contract is not valid strict encoded data (reason: {0})
+    WrongStrictData(String),
+    /// The contract interface cannot be imported (reason: {0})
+    WrongIface(String),
+    /// The contract {0} contains failures {1:?}
+    ContractInvalid(String, Vec<String>),
+    /// The contract {0} cannot be imported (reason: {1})
+    NoImport(String, String),
+    /// The stock cannot be read (reason: {0})
+    Stock(String),
+}
 
 pub fn import_contract<R>(
     contract: &str,
@@ -34,59 +48,74 @@ where
     R::Error: 'static,
 {
     let contract = if contract.starts_with("-----BEGIN RGB CONTRACT-----") {
-        contract_from_armored(contract)
+        contract_from_armored(contract)?
     } else {
-        contract_from_other_formats(contract, Some(asset_type), Some(stock))
+        contract_from_other_formats(contract, Some(asset_type), Some(stock))?
     };
 
     let contract_id = contract.contract_id();
-    let contract = contract.validate(resolver).expect("invalid contract state");
+    let contract = contract.validate(resolver).map_err(|consig| {
+        ImportContractError::ContractInvalid(
+            contract_id.to_string(),
+            consig
+                .into_validation_status()
+                .unwrap_or_default()
+                .failures
+                .iter()
+                .map(|failure| failure.to_string())
+                .collect(),
+        )
+    })?;
 
     if !stock
         .contract_ids()
-        .expect("contract_ids from stock")
+        .map_err(|err| ImportContractError::Stock(err.to_string()))?
         .contains(&contract_id)
     {
         stock
             .import_contract(contract.clone(), resolver)
-            .expect("import contract failed");
+            .map_err(|err| {
+                ImportContractError::NoImport(contract_id.to_string(), err.to_string())
+            })?;
     };
 
     Ok(contract)
 }
 
-pub fn contract_from_armored(contract: &str) -> Contract {
-    Bindle::<Contract>::from_str(contract)
-        .expect("invalid serialized contract/genesis (base58 format)")
-        .unbindle()
+pub fn contract_from_armored(contract: &str) -> Result<Contract, ImportContractError> {
+    let contract = Bindle::<Contract>::from_str(contract)
+        .map_err(|err| ImportContractError::WrongArmored(err.to_string()))?;
+
+    Ok(contract.unbindle())
 }
 
 pub fn contract_from_other_formats(
     contract: &str,
     asset_type: Option<AssetType>,
     stock: Option<&mut Stock>,
-) -> Contract {
+) -> Result<Contract, ImportContractError> {
     let serialized = if contract.starts_with("rgb1") {
         let (_, serialized, _) =
-            decode(contract).expect("invalid serialized contract/genesis (bech32m format)");
+            decode(contract).map_err(|err| ImportContractError::WrongEncoding(err.to_string()))?;
         Vec::<u8>::from_base32(&serialized)
-            .expect("invalid hexadecimal contract/genesis (bech32m format)")
+            .map_err(|err| ImportContractError::WrongEncoding(err.to_string()))?
     } else {
-        Vec::<u8>::from_hex(contract).expect("invalid hexadecimal contract/genesis")
+        Vec::<u8>::from_hex(contract)
+            .map_err(|err| ImportContractError::WrongEncoding(err.to_string()))?
     };
 
     let confined: Confined<Vec<u8>, 0, { U32 }> =
         Confined::try_from_iter(serialized.iter().copied())
-            .expect("invalid strict serialized data");
+            .map_err(|err| ImportContractError::WrongStrictData(err.to_string()))?;
 
     match asset_type {
         Some(asset_type) => match Genesis::from_strict_serialized::<{ U32 }>(confined.clone()) {
             Ok(genesis) => contract_from_genesis(genesis, asset_type, stock),
             Err(_) => Contract::from_strict_serialized::<{ U32 }>(confined)
-                .expect("invalid strict contract data"),
+                .map_err(|err| ImportContractError::WrongStrictData(err.to_string())),
         },
         None => Contract::from_strict_serialized::<{ U32 }>(confined)
-            .expect("invalid strict contract data"),
+            .map_err(|err| ImportContractError::WrongStrictData(err.to_string())),
     }
 }
 
@@ -94,7 +123,7 @@ pub fn contract_from_genesis(
     genesis: Genesis,
     asset_type: AssetType,
     stock: Option<&mut Stock>,
-) -> Contract {
+) -> Result<Contract, ImportContractError> {
     let (schema, iface, iimpl) = match asset_type {
         AssetType::RGB20 => (nia_schema(), rgb20(), nia_rgb20()),
         AssetType::RGB21 => (uda_schema(), rgb21(), uda_rgb21()),
@@ -104,13 +133,13 @@ pub fn contract_from_genesis(
     if let Some(stock) = stock {
         stock
             .import_iface(iface.clone())
-            .expect("import iface failed");
+            .map_err(|err| ImportContractError::WrongIface(err.to_string()))?;
     }
     let mut contract = Contract::new(schema, genesis);
     contract
         .ifaces
         .insert(iface.iface_id(), IfacePair::with(iface, iimpl))
-        .expect("import iface pair failed");
+        .map_err(|err| ImportContractError::WrongIface(err.to_string()))?;
 
-    contract
+    Ok(contract)
 }
@@ -0,0 +1,332 @@
+use postcard::{from_bytes, to_allocvec};
+use rgbstd::stl::LIB_ID_RGB;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    constants::storage_keys::{
+        ASSETS_BIDS, ASSETS_OFFERS, ASSETS_STOCK, ASSETS_TRANSFERS, ASSETS_WALLETS,
+    },
+    rgb::{
+        constants::{
+            RGB_ACCOUNT_VERSION, RGB_OLDEST_VERSION, RGB_STOCK_MANIFEST_VERSION,
+            RGB_STRICT_TYPE_VERSION, RGB_TRANSFER_VERSION,
+        },
+        storage::StorageBackend,
+        structs::{RgbAccountV0, RgbAccountV1, RgbTransfersV0, RgbTransfersV1},
+    },
+    structs::RgbModelMigration,
+};
+
+#[derive(Debug, Clone, Eq, PartialEq, Display, From, Error)]
+#[display(doc_comments)]
+pub enum MigrationError {
+    /// Storage backend causes error. {0}
+    Backend(String),
+    /// Model {0} has no upgrade from version '{1}'.
+    NoUpgrade(PersistedModel, String),
+    /// Upgrade of {0} from version '{1}' causes error. {2}
+    Upgrade(PersistedModel, String, String),
+    /// Decode of {0} causes error. {1}
+    Decode(PersistedModel, String),
+    /// File '{0}' retrieve causes error. {1}
+    Retrieve(String, String),
+    /// File '{0}' write causes error, the migrated files were restored. {1}
+    Write(String, String),
+    /// Files {0} stay upgraded after a failed migration. {1}
+    Restore(String, String),
+}
+
+/// Models persisted by bitmask-core, each one with its own version chain
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PersistedModel {
+    #[display("account")]
+    Account,
+    #[display("transfers")]
+    Transfers,
+    #[display("offers")]
+    Offers,
+    #[display("bids")]
+    Bids,
+    #[display("public_offers")]
+    PublicOffers,
+    #[display("auctions")]
+    Auctions,
+    #[display("stock")]
+    Stock,
+}
+
+/// Files of an account, by model
+pub const ACCOUNT_FILES: [(PersistedModel, &str); 5] = [
+    (PersistedModel::Stock, ASSETS_STOCK),
+    (PersistedModel::Account, ASSETS_WALLETS),
+    (PersistedModel::Transfers, ASSETS_TRANSFERS),
+    (PersistedModel::Offers, ASSETS_OFFERS),
+    (PersistedModel::Bids, ASSETS_BIDS),
+];
+
+/// Upgrade of an encoded model to the next version, without side effects
+pub type Upgrade = fn(&[u8]) -> Result<Vec<u8>, String>;
+
+#[derive(Clone, Copy, Debug)]
+pub struct VersionStep {
+    pub from: [u8; 8],
+    pub to: [u8; 8],
+    pub upgrade: Upgrade,
+}
+
+/// Versions of a model, from the oldest one to `current`
+#[derive(Clone, Debug)]
+pub struct VersionChain {
+    pub model: PersistedModel,
+    pub current: [u8; 8],
+    pub steps: Vec<VersionStep>,
+}
+
+impl VersionChain {
+    /// Upgrades from `version` to the current version, none when it's up to date
+    pub fn path(&self, version: [u8; 8]) -> Result<Vec<VersionStep>, MigrationError> {
+        let mut path = vec![];
+        let mut from = version;
+        while from != self.current {
+            // a chain with a cycle never reaches the current version
+            let step = self
+                .steps
+                .iter()
+                .find(|step| step.from == from)
+                .filter(|_| path.len() < self.steps.len())
+                .ok_or_else(|| MigrationError::NoUpgrade(self.model, version_name(version)))?;
+            from = step.to;
+            path.push(*step);
+        }
+        Ok(path)
+    }
+
+    /// Upgrade a model encoded in `version` to the current version
+    pub fn migrate(&self, data: &[u8], version: [u8; 8]) -> Result<Vec<u8>, MigrationError> {
+        self.path(version)?
+            .into_iter()
+            .try_fold(data.to_vec(), |data, step| {
+                (step.upgrade)(&data).map_err(|err| {
+                    MigrationError::Upgrade(self.model, version_name(step.from), err)
+                })
+            })
+    }
+
+    /// Decode a model encoded in any version of the chain
+    pub fn decode<T: DeserializeOwned>(
+        &self,
+        data: &[u8],
+        metadata: Option<&[u8]>,
+    ) -> Result<T, MigrationError> {
+        let data = self.migrate(data, version_of(metadata))?;
+        from_bytes(&data).map_err(|op| MigrationError::Decode(self.model, op.to_string()))
+    }
+}
+
+/// Version of a file from its metadata, files without metadata are the oldest version
+pub fn version_of(metadata: Option<&[u8]>) -> [u8; 8] {
+    let mut version = RGB_OLDEST_VERSION;
+    if let Some(metadata) = metadata {
+        let len = metadata.len().min(version.len());
+        version[..len].copy_from_slice(&metadata[..len]);
+    }
+    version
+}
+
+pub fn version_name(version: [u8; 8]) -> String {
+    String::from_utf8_lossy(&version)
+        .trim_matches(char::from(0))
+        .to_string()
+}
+
+/// The encoding is the same, only the version of the file changes
+fn relabel(data: &[u8]) -> Result<Vec<u8>, String> {
+    Ok(data.to_vec())
+}
+
+fn convert<P, N>(data: &[u8]) -> Result<Vec<u8>, String>
+where
+    P: DeserializeOwned,
+    N: Serialize + From<P>,
+{
+    let previous: P = from_bytes(data).map_err(|op| op.to_string())?;
+    to_allocvec(&N::from(previous)).map_err(|op| op.to_string())
+}
+
+/// Version chain of a model
+pub fn chain(model: PersistedModel) -> VersionChain {
+    let account_version = version_of(Some(&RGB_ACCOUNT_VERSION));
+    let transfer_version = version_of(Some(&RGB_TRANSFER_VERSION));
+
+    // files written before the metadata are strict-type files
+    let unlabeled = VersionStep {
+        from: RGB_OLDEST_VERSION,
+        to: RGB_STRICT_TYPE_VERSION,
+        upgrade: relabel,
+    };
+
+    let (current, steps) = match model {
+        PersistedModel::Account => (
+            account_version,
+            vec![
+                unlabeled,
+                VersionStep {
+                    from: RGB_STRICT_TYPE_VERSION,
+                    to: account_version,
+                    upgrade: convert::<RgbAccountV0, RgbAccountV1>,
+                },
+            ],
+        ),
+        PersistedModel::Transfers => (
+            transfer_version,
+            vec![
+                unlabeled,
+                VersionStep {
+                    from: RGB_STRICT_TYPE_VERSION,
+                    to: transfer_version,
+                    upgrade: convert::<RgbTransfersV0, RgbTransfersV1>,
+                },
+            ],
+        ),
+        PersistedModel::Offers
+        | PersistedModel::Bids
+        | PersistedModel::PublicOffers
+        | PersistedModel::Auctions
+        | PersistedModel::Stock => (RGB_STRICT_TYPE_VERSION, vec![unlabeled]),
+    };
+
+    VersionChain {
+        model,
+        current,
+        steps,
+    }
+}
+
+/// Version of a stored file, in the chain of its model
+fn stored_version(model: PersistedModel, metadata: Option<&[u8]>) -> [u8; 8] {
+    // the chunks of a stock manifest are always written in the current version
+    if model == PersistedModel::Stock && metadata == Some(&RGB_STOCK_MANIFEST_VERSION[..]) {
+        return RGB_STRICT_TYPE_VERSION;
+    }
+    version_of(metadata)
+}
+
+struct PendingFile {
+    name: String,
+    data: Vec<u8>,
+    metadata: Option<Vec<u8>>,
+    upgraded: Vec<u8>,
+    current: [u8; 8],
+    migration: RgbModelMigration,
+}
+
+/// Files of the account of `sk` in an older version, already upgraded in memory
+async fn pending_files(
+    storage: &dyn StorageBackend,
+    sk: &str,
+) -> Result<Vec<PendingFile>, MigrationError> {
+    let mut pending = vec![];
+    for (model, file) in ACCOUNT_FILES {
+        let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{file}").as_bytes())
+            .to_hex()
+            .to_lowercase();
+        let name = format!("{hashed_name}.c15");
+
+        let (data, metadata) = storage
+            .retrieve(sk, &name, vec![])
+            .await
+            .map_err(|op| MigrationError::Retrieve(file.to_string(), op.to_string()))?;
+        if data.is_empty() {
+            continue;
+        }
+
+        let chain = chain(model);
+        let version = stored_version(model, metadata.as_deref());
+        let steps = chain.path(version)?;
+        if steps.is_empty() {
+            continue;
+        }
+
+        let upgraded = chain.migrate(&data, version)?;
+        pending.push(PendingFile {
+            name,
+            data,
+            metadata,
+            upgraded,
+            current: chain.current,
+            migration: RgbModelMigration {
+                model: model.to_string(),
+                file: file.to_string(),
+                from: version_name(version),
+                to: version_name(chain.current),
+                steps: steps.len(),
+            },
+        });
+    }
+    Ok(pending)
+}
+
+/// Files of the account of `sk` that [`migrate_account_files`] would upgrade
+pub async fn plan_account_files(
+    storage: &dyn StorageBackend,
+    sk: &str,
+) -> Result<Vec<RgbModelMigration>, MigrationError> {
+    let pending = pending_files(storage, sk).await?;
+    Ok(pending.into_iter().map(|file| file.migration).collect())
+}
+
+/// Upgrade all the files of the account of `sk`
+///
+/// Every file is upgraded in memory before the first write. When a write
+/// fails, the files already written are stored again in their previous
+/// version. The storage has no atomic rename, so this rollback can fail
+/// too: [`MigrationError::Restore`] names the files left upgraded.
+pub async fn migrate_account_files(
+    storage: &dyn StorageBackend,
+    sk: &str,
+) -> Result<Vec<RgbModelMigration>, MigrationError> {
+    let pending = pending_files(storage, sk).await?;
+
+    for (index, file) in pending.iter().enumerate() {
+        let written = storage
+            .store(
+                sk,
+                &file.name,
+                &file.upgraded,
+                false,
+                Some(file.current.to_vec()),
+            )
+            .await;
+
+        if let Err(err) = written {
+            let mut failed = vec![];
+            for previous in pending[..index].iter().rev() {
+                let restored = storage
+                    .store(
+                        sk,
+                        &previous.name,
+                        &previous.data,
+                        true,
+                        previous.metadata.clone(),
+                    )
+                    .await;
+                if let Err(op) = restored {
+                    failed.push(format!("{} ({op})", previous.migration.file));
+                }
+            }
+
+            if !failed.is_empty() {
+                return Err(MigrationError::Restore(
+                    failed.join(", "),
+                    format!("File '{}' write causes error. {err}", file.migration.file),
+                ));
+            }
+            return Err(MigrationError::Write(
+                file.migration.file.clone(),
+                err.to_string(),
+            ));
+        }
+    }
+
+    Ok(pending.into_iter().map(|file| file.migration).collect())
+}
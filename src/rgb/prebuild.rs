@@ -142,7 +142,8 @@ pub async fn prebuild_transfer_asset(
         .map_err(|_| TransferError::NoContract)?;
 
     let contract_index = contract_index as u32;
-    sync_wallet(contract_index, rgb_wallet, resolver);
+    sync_wallet(contract_index, rgb_wallet, resolver)
+        .map_err(|op| TransferError::Explorer(op.to_string()))?;
     prefetch_resolver_utxos(
         contract_index,
         rgb_wallet,
@@ -262,7 +263,8 @@ pub async fn prebuild_transfer_asset(
 
     let bitcoin_indexes = [0, 1];
     for bitcoin_index in bitcoin_indexes {
-        sync_wallet(bitcoin_index, rgb_wallet, resolver);
+        sync_wallet(bitcoin_index, rgb_wallet, resolver)
+            .map_err(|op| TransferError::Explorer(op.to_string()))?;
         prefetch_resolver_utxos(
             bitcoin_index,
             rgb_wallet,
@@ -472,7 +474,8 @@ pub async fn prebuild_seller_swap(
         .map_err(|_| RgbSwapError::NoContract)?;
 
     let contract_index = contract_index as u32;
-    sync_wallet(contract_index, rgb_wallet, resolver);
+    sync_wallet(contract_index, rgb_wallet, resolver)
+        .map_err(|op| RgbSwapError::Explorer(op.to_string()))?;
     prefetch_resolver_utxos(
         contract_index,
         rgb_wallet,
@@ -598,7 +601,8 @@ pub async fn prebuild_seller_swap(
 
             let bitcoin_indexes = [0, 1];
             for bitcoin_index in bitcoin_indexes {
-                sync_wallet(bitcoin_index, rgb_wallet, resolver);
+                sync_wallet(bitcoin_index, rgb_wallet, resolver)
+                    .map_err(|op| RgbSwapError::Explorer(op.to_string()))?;
                 prefetch_resolver_utxos(
                     bitcoin_index,
                     rgb_wallet,
@@ -728,7 +732,8 @@ pub async fn prebuild_buyer_swap(
     ];
     for derive_type in derive_indexes {
         let derive_index = derive_type.clone() as u32;
-        sync_wallet(derive_index, rgb_wallet, resolver);
+        sync_wallet(derive_index, rgb_wallet, resolver)
+            .map_err(|op| RgbSwapError::Explorer(op.to_string()))?;
         prefetch_resolver_utxos(
             derive_index,
             rgb_wallet,
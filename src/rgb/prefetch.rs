@@ -1,445 +1,269 @@
 #![allow(unused_imports)]
 #![allow(unused_variables)]
-use crate::rgb::resolvers::ExplorerResolver;
-use crate::structs::UtxoSpentStatus;
-use crate::structs::{AssetType, MediaInfo, TxStatus};
-use amplify::{
-    confinement::Confined,
-    hex::{FromHex, ToHex},
+use crate::chain::{chain_backend_with_explorer, ChainBackend};
+use crate::rgb::resolvers::{
+    chain_spent_status, mined_witness_ord, script_utxos, to_bp_tx, ExplorerResolver,
 };
-use bdk::blockchain::EsploraBlockchain;
-
-#[cfg(target_arch = "wasm32")]
-use bdk::esplora_client::{AsyncClient, Tx as ExplorerTX};
-
-use bech32::{decode, FromBase32};
-use bitcoin::hashes::{sha256, Hash};
+use crate::structs::{AssetType, TxStatus};
+use crate::warn;
+use amplify::hex::{FromHex, ToHex};
 use bitcoin::{OutPoint, Script, Txid};
 use bitcoin_30::ScriptBuf;
 use bitcoin_scripts::{
     address::{AddressCompat, AddressNetwork},
     PubkeyScript,
 };
-use bp::{LockTime, Outpoint, SeqNo, Tx, TxIn, TxOut, TxVer, Txid as BpTxid, VarIntArray, Witness};
-use reqwest::StatusCode;
-use rgb::{DeriveInfo, MiningStatus, RgbWallet, SpkDescriptor, Utxo};
-use rgbstd::containers::Contract;
+use bp::{Outpoint, Txid as BpTxid};
+use rgb::{DeriveInfo, MiningStatus, RgbWallet, Utxo};
+use rgbstd::contract::WitnessOrd;
 use rgbstd::interface::ContractIface;
-use std::collections::HashMap;
-use std::f32::consts::E;
-use std::{collections::BTreeMap, str::FromStr};
-use strict_encoding::StrictDeserialize;
-use wallet::onchain::ResolveTx;
-
-use crate::rgb::resolvers::ExploreClientExtError;
-use crate::rgb::structs::MediaMetadata;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Range,
+    str::FromStr,
+};
 
-#[cfg(not(target_arch = "wasm32"))]
-pub async fn prefetch_resolver_rgb(
-    contract: &str,
-    explorer: &mut ExplorerResolver,
-    asset_type: Option<AssetType>,
-) {
+/// Backend of the prefetch, selected by `BITCOIN_BACKEND`
+///
+/// Prefetch failures are not fatal, the resolver reports the missing data
+async fn explorer_backend(explorer: &ExplorerResolver) -> Option<Box<dyn ChainBackend>> {
+    match chain_backend_with_explorer(&explorer.explorer_url, &explorer.account).await {
+        Ok(backend) => Some(backend),
+        Err(err) => {
+            warn!(format!("prefetch skipped: {err}"));
+            None
+        }
+    }
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-pub async fn prefetch_resolver_import_rgb(
-    contract: &str,
-    asset_type: AssetType,
+async fn prefetch_anchor_tx(
+    backend: &dyn ChainBackend,
+    txid: BpTxid,
     explorer: &mut ExplorerResolver,
+    with_height: bool,
 ) {
-}
+    let transaction_id = bitcoin::Txid::from_str(&txid.to_hex()).expect("invalid transaction ID");
 
-#[cfg(not(target_arch = "wasm32"))]
-pub async fn prefetch_resolver_psbt(input_utxo: &str, explorer: &mut ExplorerResolver) {}
+    match backend.get_tx(&transaction_id).await {
+        Ok(Some(tx)) => {
+            if with_height {
+                prefetch_tx_height(backend, txid, explorer).await;
+            }
+            explorer.bp_txs.insert(txid, to_bp_tx(tx.clone()));
+            explorer.txs.insert(transaction_id, tx);
+        }
+        Ok(None) => {}
+        Err(err) => warn!(format!("prefetch of {transaction_id} failed: {err}")),
+    }
+}
 
-#[cfg(not(target_arch = "wasm32"))]
-pub async fn prefetch_resolver_user_utxo_status(
-    iface_index: u32,
-    wallet: &mut RgbWallet,
+async fn prefetch_tx_height(
+    backend: &dyn ChainBackend,
+    txid: BpTxid,
     explorer: &mut ExplorerResolver,
-    with_block_height: bool,
 ) {
+    let transaction_id =
+        &bitcoin::Txid::from_str(&txid.to_hex()).expect("invalid transaction id parse");
+
+    let status = match backend.get_tx_status(transaction_id).await {
+        Ok(TxStatus::Block(height)) => match mined_witness_ord(*transaction_id, height) {
+            Ok(status) => status,
+            Err(err) => {
+                warn!(format!("prefetch of {transaction_id} height failed: {err}"));
+                return;
+            }
+        },
+        Ok(_) => WitnessOrd::OffChain,
+        Err(err) => {
+            warn!(format!("prefetch of {transaction_id} height failed: {err}"));
+            return;
+        }
+    };
+    explorer.tx_height.insert(txid, status);
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-pub async fn prefetch_resolver_allocations(
-    contract_iface: ContractIface,
+async fn prefetch_spent_status(
+    backend: &dyn ChainBackend,
+    outpoints: Vec<Outpoint>,
     explorer: &mut ExplorerResolver,
     with_block_height: bool,
 ) {
-}
+    for utxo in outpoints {
+        let txid = bitcoin::Txid::from_str(&utxo.txid.to_hex()).expect("invalid outpoint format");
+        let utxo_status =
+            chain_spent_status(backend, txid, utxo.vout.to_u32().into(), with_block_height).await;
 
-#[cfg(not(target_arch = "wasm32"))]
-pub async fn prefetch_resolver_utxos(
-    iface_index: u32,
-    wallet: &mut RgbWallet,
-    explorer: &mut ExplorerResolver,
-    limit: Option<u32>,
-) {
+        explorer.utxos_spent.push(utxo_status);
+    }
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-pub async fn prefetch_resolver_txs(txids: Vec<Txid>, explorer: &mut ExplorerResolver) {}
-
-#[cfg(not(target_arch = "wasm32"))]
-pub async fn prefetch_resolver_waddress(
-    address: &str,
-    wallet: &mut RgbWallet,
-    explorer: &mut ExplorerResolver,
-    limit: Option<u32>,
-) {
-}
+/// Merge the found UTXOs with the wallet ones, updating those in the mempool
+fn merge_utxos(new_utxos: BTreeSet<Utxo>, wallet: &mut RgbWallet) -> Vec<Utxo> {
+    let mut updated = vec![];
+    for mut new_utxo in new_utxos {
+        if let Some(current_utxo) = wallet
+            .utxos
+            .clone()
+            .into_iter()
+            .find(|u| u.outpoint == new_utxo.outpoint)
+        {
+            if current_utxo.status == MiningStatus::Mempool {
+                wallet.utxos.remove(&current_utxo);
+                updated.push(current_utxo.clone());
 
-#[cfg(not(target_arch = "wasm32"))]
-pub async fn prefetch_resolver_wutxo(
-    utxo: &str,
-    network: AddressNetwork,
-    wallet: &mut RgbWallet,
-    explorer: &mut ExplorerResolver,
-    limit: Option<u32>,
-) {
+                new_utxo.derivation = current_utxo.derivation;
+                wallet.utxos.insert(new_utxo.clone());
+                updated.push(new_utxo);
+            }
+        } else {
+            wallet.utxos.insert(new_utxo.clone());
+            updated.push(new_utxo);
+        }
+    }
+    updated
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-pub async fn prefetch_resolver_tx_height(txid: bp::Txid, explorer: &mut ExplorerResolver) {}
-
-#[cfg(target_arch = "wasm32")]
 pub async fn prefetch_resolver_rgb(
     contract: &str,
     explorer: &mut ExplorerResolver,
     asset_type: Option<AssetType>,
 ) {
-    use crate::rgb::import::{contract_from_armored, contract_from_other_formats};
     use crate::rgb::prebuild::prebuild_extract_transfer;
-    use amplify::confinement::U32;
-    use rgbstd::contract::Genesis;
 
-    let esplora_client: EsploraBlockchain =
-        EsploraBlockchain::new(&explorer.explorer_url, 1).with_concurrency(6);
-    let contract = prebuild_extract_transfer(contract).expect("invalid transfer");
+    let Some(backend) = explorer_backend(explorer).await else {
+        return;
+    };
+    // Invalid transfers are reported by the accept itself
+    let Ok(contract) = prebuild_extract_transfer(contract) else {
+        return;
+    };
     let contract = contract.transfer.unbindle();
 
     for anchor_bundle in contract.bundles {
-        let transaction_id = &bitcoin::Txid::from_str(&anchor_bundle.anchor.txid.to_hex())
-            .expect("invalid transaction ID");
-
-        let tx_raw = esplora_client
-            .get_tx(transaction_id)
-            .await
-            .expect("service unavailable");
-
-        if let Some(tx) = tx_raw {
-            let txid =
-                rgbstd::Txid::from_hex(&transaction_id.to_hex()).expect("invalid transaction id");
-            prefetch_resolver_tx_height(txid, explorer).await;
-            let new_tx = Tx {
-                version: TxVer::from_consensus_i32(tx.clone().version),
-                inputs: VarIntArray::try_from_iter(tx.clone().input.into_iter().map(|txin| {
-                    TxIn {
-                        prev_output: Outpoint::new(
-                            BpTxid::from_str(&txin.previous_output.txid.to_hex())
-                                .expect("invalid transaction ID"),
-                            txin.previous_output.vout,
-                        ),
-                        sig_script: txin.script_sig.to_bytes().into(),
-                        sequence: SeqNo::from_consensus_u32(txin.sequence.to_consensus_u32()),
-                        witness: Witness::from_consensus_stack(txin.witness.to_vec()),
-                    }
-                }))
-                .expect("consensus-invalid transaction"),
-                outputs: VarIntArray::try_from_iter(tx.clone().output.into_iter().map(|txout| {
-                    TxOut {
-                        value: txout.value.into(),
-                        script_pubkey: txout.script_pubkey.to_bytes().into(),
-                    }
-                }))
-                .expect("consensus-invalid transaction"),
-                lock_time: LockTime::from_consensus_u32(tx.lock_time.0),
-            };
-
-            explorer.txs.insert(tx.txid(), tx);
-            explorer.bp_txs.insert(anchor_bundle.anchor.txid, new_tx);
-        }
+        prefetch_anchor_tx(backend.as_ref(), anchor_bundle.anchor.txid, explorer, true).await;
     }
 }
 
-#[cfg(target_arch = "wasm32")]
 pub async fn prefetch_resolver_import_rgb(
     contract: &str,
     asset_type: AssetType,
     explorer: &mut ExplorerResolver,
 ) {
     use crate::rgb::import::{contract_from_armored, contract_from_other_formats};
-    use amplify::confinement::U32;
-    use rgbstd::contract::Genesis;
 
-    let esplora_client: EsploraBlockchain =
-        EsploraBlockchain::new(&explorer.explorer_url, 1).with_concurrency(6);
+    let Some(backend) = explorer_backend(explorer).await else {
+        return;
+    };
     let contract = if contract.starts_with("-----BEGIN RGB CONTRACT-----") {
         contract_from_armored(contract)
     } else {
         contract_from_other_formats(contract, Some(asset_type), None)
     };
-
-    let contract = contract.validate(explorer).expect("invalid contract state");
+    // Invalid contracts are reported by the import itself
+    let Ok(contract) = contract else {
+        return;
+    };
 
     for anchor_bundle in contract.bundles {
-        let transaction_id = &bitcoin::Txid::from_str(&anchor_bundle.anchor.txid.to_hex())
-            .expect("invalid transaction ID");
-
-        let tx_raw = esplora_client
-            .get_tx(transaction_id)
-            .await
-            .expect("service unavailable");
-
-        if let Some(tx) = tx_raw {
-            let new_tx = Tx {
-                version: TxVer::from_consensus_i32(tx.clone().version),
-                inputs: VarIntArray::try_from_iter(tx.clone().input.into_iter().map(|txin| {
-                    TxIn {
-                        prev_output: Outpoint::new(
-                            BpTxid::from_str(&txin.previous_output.txid.to_hex())
-                                .expect("invalid transaction ID"),
-                            txin.previous_output.vout,
-                        ),
-                        sig_script: txin.script_sig.to_bytes().into(),
-                        sequence: SeqNo::from_consensus_u32(txin.sequence.to_consensus_u32()),
-                        witness: Witness::from_consensus_stack(txin.witness.to_vec()),
-                    }
-                }))
-                .expect("consensus-invalid transaction"),
-                outputs: VarIntArray::try_from_iter(tx.clone().output.into_iter().map(|txout| {
-                    TxOut {
-                        value: txout.value.into(),
-                        script_pubkey: txout.script_pubkey.to_bytes().into(),
-                    }
-                }))
-                .expect("consensus-invalid transaction"),
-                lock_time: LockTime::from_consensus_u32(tx.lock_time.0),
-            };
-
-            explorer.txs.insert(tx.txid(), tx);
-            explorer.bp_txs.insert(anchor_bundle.anchor.txid, new_tx);
-        }
+        prefetch_anchor_tx(backend.as_ref(), anchor_bundle.anchor.txid, explorer, false).await;
     }
 }
 
-#[cfg(target_arch = "wasm32")]
 pub async fn prefetch_resolver_psbt(input_utxo: &str, explorer: &mut ExplorerResolver) {
-    let esplora_client: EsploraBlockchain =
-        EsploraBlockchain::new(&explorer.explorer_url, 1).with_concurrency(6);
-
-    let outpoint: OutPoint = input_utxo.parse().expect("invalid outpoint format");
-    let txid = outpoint.txid;
-    if let Some(tx) = esplora_client
-        .get_tx(&txid)
-        .await
-        .expect("service unavailable")
-    {
-        explorer.txs.insert(txid, tx);
-    }
+    // Invalid outpoints are reported by the PSBT builder
+    let Ok(outpoint) = input_utxo.parse::<OutPoint>() else {
+        return;
+    };
+    prefetch_resolver_txs(vec![outpoint.txid], explorer).await;
 }
 
-#[cfg(target_arch = "wasm32")]
 pub async fn prefetch_resolver_user_utxo_status(
     iface_index: u32,
     wallet: &mut RgbWallet,
     explorer: &mut ExplorerResolver,
     with_block_height: bool,
 ) {
-    let esplora_client = EsploraBlockchain::new(&explorer.explorer_url, 1)
-        .with_concurrency(6)
-        .clone();
-    let utxos: Vec<Utxo> = wallet
+    let Some(backend) = explorer_backend(explorer).await else {
+        return;
+    };
+    let utxos: Vec<Outpoint> = wallet
         .utxos
-        .clone()
-        .into_iter()
+        .iter()
         .filter(|utxo| utxo.derivation.terminal.app == iface_index)
+        .map(|utxo| utxo.outpoint)
         .collect();
 
-    if !utxos.is_empty() {
-        for utxo in utxos {
-            let txid = bitcoin::Txid::from_str(&utxo.outpoint.txid.to_hex())
-                .expect("invalid outpoint format");
-
-            let block_h = match ExploreAsyncExt::get_full_tx(&esplora_client, &txid).await {
-                Ok(full_tx) => {
-                    if full_tx.status.confirmed {
-                        TxStatus::Block(full_tx.status.block_height.unwrap_or_default())
-                    } else {
-                        TxStatus::Mempool
-                    }
-                }
-                Err(err) => TxStatus::Error(err.to_string()),
-            };
-
-            let (is_spent, utxo_status) = match esplora_client
-                .clone()
-                .get_output_status(&txid, utxo.outpoint.vout.to_u32().into())
-                .await
-            {
-                Ok(output_status) => match output_status {
-                    Some(output_status) => {
-                        let status = if !output_status.spent && output_status.txid.is_none() {
-                            TxStatus::NotFound
-                        } else {
-                            match output_status.status {
-                                Some(utxo_status) => {
-                                    if utxo_status.confirmed {
-                                        TxStatus::Block(
-                                            utxo_status.block_height.unwrap_or_default(),
-                                        )
-                                    } else {
-                                        TxStatus::Mempool
-                                    }
-                                }
-                                None => TxStatus::NotFound,
-                            }
-                        };
-                        (output_status.spent, status)
-                    }
-                    None => (
-                        false,
-                        TxStatus::Error(
-                            format!(
-                                "The utxo {txid}:{} does not exists",
-                                utxo.outpoint.vout.to_u32()
-                            )
-                            .to_string(),
-                        ),
-                    ),
-                },
-                Err(err) => (false, TxStatus::Error(err.to_string())),
-            };
-
-            let utxo_status = UtxoSpentStatus {
-                utxo: format!("{}:{}", utxo.outpoint.txid, utxo.outpoint.vout.to_u32()),
-                is_spent,
-                block_height: block_h,
-                spent_height: utxo_status,
-            };
-
-            explorer.utxos_spent.push(utxo_status);
-        }
-    }
+    // The block height of the user UTXOs is always required by the wallet
+    prefetch_spent_status(backend.as_ref(), utxos, explorer, true).await;
 }
 
-#[cfg(target_arch = "wasm32")]
 pub async fn prefetch_resolver_utxos(
     iface_index: u32,
     wallet: &mut RgbWallet,
     explorer: &mut ExplorerResolver,
     limit: Option<u32>,
 ) {
-    let esplora_client: EsploraBlockchain =
-        EsploraBlockchain::new(&explorer.explorer_url, 1).with_concurrency(6);
-
     let index = 0;
     let mut step = 100;
     if let Some(limit) = limit {
         step = limit;
     }
 
-    let scripts = wallet.descr.derive(iface_index, index..step);
+    prefetch_resolver_utxos_range(iface_index, index..step, wallet, explorer).await;
+}
+
+pub async fn prefetch_resolver_utxos_range(
+    iface_index: u32,
+    range: Range<u32>,
+    wallet: &mut RgbWallet,
+    explorer: &mut ExplorerResolver,
+) {
+    let Some(backend) = explorer_backend(explorer).await else {
+        return;
+    };
+
+    let scripts = wallet.descr.derive(iface_index, range);
     let mut new_utxos = bset![];
     for (derive, script) in scripts {
         // TODO: Remove that after bitcoin v.30 full compatibility
         let script_compatible =
             Script::from_str(&script.as_script().to_hex_string()).expect("invalid script");
 
-        let mut related_txs = esplora_client
-            .scripthash_txs(&script_compatible, None)
-            .await
-            .expect("Service unavailable");
-        let n_confirmed = related_txs.iter().filter(|tx| tx.status.confirmed).count();
-        // esplora pages on 25 confirmed transactions. If there are 25 or more we
-        // keep requesting to see if there's more.
-        if n_confirmed >= 25 {
-            loop {
-                let new_related_txs = esplora_client
-                    .scripthash_txs(&script_compatible, Some(related_txs.last().unwrap().txid))
-                    .await
-                    .expect("Service unavailable");
-                let n = new_related_txs.len();
-                related_txs.extend(new_related_txs);
-                // we've reached the end
-                if n < 25 {
-                    break;
-                }
+        match backend.get_script_txs(&script_compatible).await {
+            Ok(related_txs) => {
+                new_utxos.extend(script_utxos(&derive, &script_compatible, &related_txs))
             }
+            Err(err) => warn!(format!("prefetch of wallet utxos failed: {err}")),
         }
-
-        related_txs.into_iter().for_each(|tx| {
-            for (index, vout) in tx.vout.iter().enumerate() {
-                if vout.scriptpubkey != script_compatible {
-                    continue;
-                }
-
-                let status = match tx.status.block_height {
-                    Some(height) => MiningStatus::Blockchain(height),
-                    _ => MiningStatus::Mempool,
-                };
-                let outpoint = Outpoint::new(
-                    bp::Txid::from_str(&tx.txid.to_hex()).expect("invalid outpoint parse"),
-                    index as u32,
-                );
-                let new_utxo = Utxo {
-                    outpoint,
-                    status,
-                    amount: vout.value,
-                    derivation: derive.clone(),
-                };
-                new_utxos.insert(new_utxo);
-            }
-        });
     }
 
-    for mut new_utxo in new_utxos {
-        if let Some(current_utxo) = wallet
-            .utxos
-            .clone()
-            .into_iter()
-            .find(|u| u.outpoint == new_utxo.outpoint)
-        {
-            if current_utxo.status == MiningStatus::Mempool {
-                wallet.utxos.remove(&current_utxo.clone());
-                explorer.utxos.insert(current_utxo.clone());
-
-                new_utxo.derivation = current_utxo.derivation;
-                wallet.utxos.insert(new_utxo.clone());
-                explorer.utxos.insert(new_utxo);
-            }
-        } else {
-            wallet.utxos.insert(new_utxo.clone());
-            explorer.utxos.insert(new_utxo);
-        }
-    }
+    explorer.utxos.extend(merge_utxos(new_utxos, wallet));
 }
 
-#[cfg(target_arch = "wasm32")]
 pub async fn prefetch_resolver_txs(txids: Vec<Txid>, explorer: &mut ExplorerResolver) {
-    let esplora_client = EsploraBlockchain::new(&explorer.explorer_url, 1).with_concurrency(6);
+    let Some(backend) = explorer_backend(explorer).await else {
+        return;
+    };
     for txid in txids {
-        if let Some(tx) = esplora_client
-            .get_tx(&txid)
-            .await
-            .expect("service unavailable")
-        {
-            explorer.txs.insert(txid, tx);
+        match backend.get_tx(&txid).await {
+            Ok(Some(tx)) => {
+                explorer.txs.insert(txid, tx);
+            }
+            Ok(None) => {}
+            Err(err) => warn!(format!("prefetch of {txid} failed: {err}")),
         }
     }
 }
 
-#[cfg(target_arch = "wasm32")]
 pub async fn prefetch_resolver_waddress(
     address: &str,
     wallet: &mut RgbWallet,
     explorer: &mut ExplorerResolver,
     limit: Option<u32>,
 ) {
-    let esplora_client: EsploraBlockchain =
-        EsploraBlockchain::new(&explorer.explorer_url, 1).with_concurrency(6);
+    let Some(backend) = explorer_backend(explorer).await else {
+        return;
+    };
 
     let index = 0;
     let mut step = 100;
@@ -447,7 +271,10 @@ pub async fn prefetch_resolver_waddress(
         step = limit;
     }
 
-    let sc = AddressCompat::from_str(address).expect("invalid address");
+    // Invalid addresses are reported by `register_address`
+    let Ok(sc) = AddressCompat::from_str(address) else {
+        return;
+    };
     let script = ScriptBuf::from_hex(&sc.script_pubkey().to_hex()).expect("invalid script");
 
     let mut scripts: BTreeMap<DeriveInfo, ScriptBuf> = BTreeMap::new();
@@ -456,71 +283,18 @@ pub async fn prefetch_resolver_waddress(
         scripts.append(&mut wallet.descr.derive(app, index..step));
     }
 
-    let script = scripts.into_iter().find(|(_, sc)| sc.eq(&script));
-    if let Some((d, sc)) = script {
-        let mut scripts = BTreeMap::new();
-        scripts.insert(d, sc);
-
-        let mut new_utxos = bset![];
-        for (derive, script) in scripts {
-            // TODO: Remove that after bitcoin v.30 full compatibility
-            let script_compatible =
-                Script::from_str(&script.to_hex_string()).expect("invalid script");
-            let txs = match esplora_client
-                .scripthash_txs(&script_compatible, none!())
-                .await
-            {
-                Ok(txs) => txs,
-                _ => vec![],
-            };
-
-            txs.into_iter().for_each(|tx| {
-                let index = tx
-                    .vout
-                    .clone()
-                    .into_iter()
-                    .position(|txout| txout.scriptpubkey == script_compatible);
-                if let Some(index) = index {
-                    let status = match tx.status.block_height {
-                        Some(height) => MiningStatus::Blockchain(height),
-                        _ => MiningStatus::Mempool,
-                    };
-                    let outpoint = Outpoint::new(
-                        bp::Txid::from_str(&tx.txid.to_hex()).expect("invalid transactionID parse"),
-                        index as u32,
-                    );
-                    let new_utxo = Utxo {
-                        outpoint,
-                        status,
-                        amount: tx.vout[index].value,
-                        derivation: derive.clone(),
-                    };
-                    new_utxos.insert(new_utxo);
-                }
-            });
-        }
+    if let Some((derive, script)) = scripts.into_iter().find(|(_, sc)| sc.eq(&script)) {
+        // TODO: Remove that after bitcoin v.30 full compatibility
+        let script_compatible = Script::from_str(&script.to_hex_string()).expect("invalid script");
+        let txs = backend
+            .get_script_txs(&script_compatible)
+            .await
+            .unwrap_or_default();
 
-        for mut new_utxo in new_utxos {
-            if let Some(current_utxo) = wallet
-                .utxos
-                .clone()
-                .into_iter()
-                .find(|u| u.outpoint == new_utxo.outpoint)
-            {
-                if current_utxo.status == MiningStatus::Mempool {
-                    wallet.utxos.remove(&current_utxo);
-
-                    new_utxo.derivation = current_utxo.derivation;
-                    wallet.utxos.insert(new_utxo);
-                }
-            } else {
-                wallet.utxos.insert(new_utxo);
-            }
-        }
+        merge_utxos(script_utxos(&derive, &script_compatible, &txs), wallet);
     }
 }
 
-#[cfg(target_arch = "wasm32")]
 pub async fn prefetch_resolver_wutxo(
     utxo: &str,
     network: AddressNetwork,
@@ -528,17 +302,16 @@ pub async fn prefetch_resolver_wutxo(
     explorer: &mut ExplorerResolver,
     limit: Option<u32>,
 ) {
-    let esplora_client: EsploraBlockchain =
-        EsploraBlockchain::new(&explorer.explorer_url, 1).with_concurrency(6);
-
-    let outpoint = OutPoint::from_str(utxo).expect("invalid outpoint");
+    // Invalid outpoints are reported by `register_utxo`
+    let Ok(outpoint) = OutPoint::from_str(utxo) else {
+        return;
+    };
+    let Some(backend) = explorer_backend(explorer).await else {
+        return;
+    };
 
-    if let Some(tx) = esplora_client
-        .get_tx(&outpoint.txid)
-        .await
-        .expect("service unavailable")
-    {
-        if let Some(vout) = tx.output.to_vec().get(outpoint.vout as usize) {
+    if let Ok(Some(tx)) = backend.get_tx(&outpoint.txid).await {
+        if let Some(vout) = tx.output.get(outpoint.vout as usize) {
             let sc = Script::from_str(&vout.script_pubkey.to_hex()).expect("invalid script");
             let pub_script = PubkeyScript::from(sc);
             if let Some(address) = AddressCompat::from_script(&pub_script, network) {
@@ -548,43 +321,23 @@ pub async fn prefetch_resolver_wutxo(
     }
 }
 
-#[cfg(target_arch = "wasm32")]
-pub async fn prefetch_resolver_tx_height(txid: rgbstd::Txid, explorer: &mut ExplorerResolver) {
-    use rgbstd::contract::{WitnessHeight, WitnessOrd};
-
-    let esplora_client: EsploraBlockchain =
-        EsploraBlockchain::new(&explorer.explorer_url, 1).with_concurrency(6);
-
-    let transaction_id =
-        &bitcoin::Txid::from_str(&txid.to_hex()).expect("invalid transaction id parse");
-
-    let tx = esplora_client
-        .get_tx_status(transaction_id)
-        .await
-        .expect("service unavailable");
-
-    if let Some(tx) = tx {
-        let status = match tx.block_height {
-            Some(height) => WitnessOrd::OnChain(WitnessHeight::new(height).unwrap()),
-            _ => WitnessOrd::OffChain,
-        };
-        explorer.tx_height.insert(txid, status);
-    } else {
-        explorer.tx_height.insert(txid, WitnessOrd::OffChain);
-    }
+pub async fn prefetch_resolver_tx_height(txid: BpTxid, explorer: &mut ExplorerResolver) {
+    let Some(backend) = explorer_backend(explorer).await else {
+        return;
+    };
+    prefetch_tx_height(backend.as_ref(), txid, explorer).await;
 }
 
-#[cfg(target_arch = "wasm32")]
 pub async fn prefetch_resolver_allocations(
     contract_iface: ContractIface,
     explorer: &mut ExplorerResolver,
     with_block_height: bool,
 ) {
-    let esplora_client: EsploraBlockchain =
-        EsploraBlockchain::new(&explorer.explorer_url, 1).with_concurrency(6);
+    let Some(backend) = explorer_backend(explorer).await else {
+        return;
+    };
 
     let mut contract_utxos = vec![];
-
     for owned in &contract_iface.iface.assignments {
         if let Ok(allocations) = contract_iface.fungible(owned.name.clone(), &None) {
             for allocation in allocations {
@@ -599,123 +352,24 @@ pub async fn prefetch_resolver_allocations(
         }
     }
 
-    if !contract_utxos.is_empty() {
-        for utxo in contract_utxos {
-            let txid =
-                bitcoin::Txid::from_str(&utxo.txid.to_hex()).expect("invalid outpoint format");
-
-            let block_h = if with_block_height {
-                match ExploreAsyncExt::get_full_tx(&esplora_client, &txid).await {
-                    Ok(full_tx) => {
-                        if full_tx.status.confirmed {
-                            TxStatus::Block(full_tx.status.block_height.unwrap_or_default())
-                        } else {
-                            TxStatus::Mempool
-                        }
-                    }
-                    Err(err) => TxStatus::Error(err.to_string()),
-                }
-            } else {
-                TxStatus::NotFound
-            };
-
-            let (is_spent, utxo_status) = match esplora_client
-                .get_output_status(&txid, utxo.vout.to_u32().into())
-                .await
-            {
-                Ok(output_status) => match output_status {
-                    Some(output_status) => {
-                        let status = if !output_status.spent && output_status.txid.is_none() {
-                            TxStatus::NotFound
-                        } else {
-                            match output_status.status {
-                                Some(utxo_status) => {
-                                    if utxo_status.confirmed {
-                                        TxStatus::Block(
-                                            utxo_status.block_height.unwrap_or_default(),
-                                        )
-                                    } else {
-                                        TxStatus::Mempool
-                                    }
-                                }
-                                None => TxStatus::NotFound,
-                            }
-                        };
-                        (output_status.spent, status)
-                    }
-                    None => (
-                        false,
-                        TxStatus::Error(
-                            format!("The utxo {txid}:{} does not exists", utxo.vout.to_u32())
-                                .to_string(),
-                        ),
-                    ),
-                },
-                Err(err) => (false, TxStatus::Error(err.to_string())),
-            };
-
-            let utxo_status = UtxoSpentStatus {
-                utxo: format!("{}:{}", utxo.txid, utxo.vout.to_u32()),
-                is_spent,
-                block_height: block_h,
-                spent_height: utxo_status,
-            };
-
-            explorer.utxos_spent.push(utxo_status);
-        }
-    }
+    prefetch_spent_status(
+        backend.as_ref(),
+        contract_utxos,
+        explorer,
+        with_block_height,
+    )
+    .await;
 }
 
 pub async fn prefetch_resolver_txs_status(txids: Vec<Txid>, explorer: &mut ExplorerResolver) {
-    let esplora_client = EsploraBlockchain::new(&explorer.explorer_url, 1).with_concurrency(6);
+    let Some(backend) = explorer_backend(explorer).await else {
+        return;
+    };
     for txid in txids {
-        let tx_resp = esplora_client.get_tx_status(&txid).await;
-        if tx_resp.is_ok() {
-            let mut status = TxStatus::NotFound;
-            let tx_resp = tx_resp.unwrap_or_default();
-            if let Some(tx_status) = tx_resp {
-                if tx_status.confirmed {
-                    status = TxStatus::Block(tx_status.block_height.unwrap_or_default());
-                } else {
-                    status = TxStatus::Mempool;
-                }
-            }
-            explorer.txs_status.insert(txid, status);
-        } else {
-            let err = match tx_resp.err() {
-                Some(err) => err.to_string(),
-                None => "unknown explorer error".to_string(),
-            };
-
-            let err = TxStatus::Error(err);
-            explorer.txs_status.insert(txid, err);
-        }
-    }
-}
-
-#[cfg(target_arch = "wasm32")]
-struct ExploreAsyncExt {}
-
-#[cfg(target_arch = "wasm32")]
-impl ExploreAsyncExt {
-    pub async fn get_full_tx(
-        client: &AsyncClient,
-        txid: &bitcoin::Txid,
-    ) -> Result<ExplorerTX, ExploreClientExtError> {
-        let resp = client
-            .client()
-            .get(&format!("{}/tx/{}", client.url(), txid))
-            .send()
-            .await
-            .expect("unavailable esplora server");
-
-        if let StatusCode::NOT_FOUND = resp.status() {
-            return Err(ExploreClientExtError::NotFound);
-        }
-
-        Ok(resp
-            .json::<ExplorerTX>()
-            .await
-            .expect("Invalid json parse in FullTx"))
+        let status = match backend.get_tx_status(&txid).await {
+            Ok(status) => status,
+            Err(err) => TxStatus::Error(err.to_string()),
+        };
+        explorer.txs_status.insert(txid, status);
     }
 }
@@ -0,0 +1,255 @@
+use std::{collections::BTreeSet, str::FromStr, sync::Mutex};
+
+use amplify::hex::ToHex;
+use bitcoin::BlockHash;
+use bp::Txid;
+use once_cell::sync::Lazy;
+use rgb::MiningStatus;
+use rgbstd::{resolvers::ResolveHeight, validation::ResolveTx};
+
+use crate::{
+    chain::{ChainBackend, ChainError},
+    rgb::{
+        constants::RGB_ANCHOR_FINAL_DEPTH,
+        prebuild::prebuild_extract_transfer,
+        structs::{ContractAmount, RgbAccountV1, RgbAnchor, RgbAnchors, RgbTransfersV1},
+    },
+    structs::{
+        AllocationDetail, AllocationValue, BatchRgbTransferItem, ContractResponse, RgbReorgEvent,
+        TxStatus,
+    },
+};
+
+/// Hook called for each reorganized anchor
+pub type ReorgHook = Box<dyn Fn(&RgbReorgEvent) + Send + Sync>;
+
+static REORG_HOOKS: Lazy<Mutex<Vec<ReorgHook>>> = Lazy::new(Default::default);
+
+/// Register a hook notified of the reorganized anchors (e.g. to hold an order)
+pub fn on_reorg(hook: ReorgHook) {
+    REORG_HOOKS.lock().expect("reorg hooks poisoned").push(hook);
+}
+
+pub fn notify_reorg(event: &RgbReorgEvent) {
+    for hook in REORG_HOOKS.lock().expect("reorg hooks poisoned").iter() {
+        hook(event);
+    }
+}
+
+/// Anchor compared with the current chain
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnchorCheck {
+    /// The block of the witness transaction is still in the chain
+    Unchanged,
+    /// The witness transaction was mined again, in another block
+    Moved(u32, BlockHash),
+    /// The witness transaction is back in the mempool, or was dropped
+    Unconfirmed(TxStatus),
+}
+
+/// Start watching the witness transactions of the accepted transfers
+pub async fn track_anchors<B: ChainBackend + ?Sized>(
+    backend: &B,
+    anchors: &mut RgbAnchors,
+    transfers: &RgbTransfersV1,
+    items: &[BatchRgbTransferItem],
+) -> Result<(), ChainError> {
+    for item in items.iter().filter(|item| item.is_accept) {
+        let TxStatus::Block(height) = item.status else {
+            continue;
+        };
+
+        let Some(transfer) = transfers
+            .transfers
+            .get(&item.contract_id)
+            .and_then(|activities| {
+                activities
+                    .iter()
+                    .find(|activity| activity.tx_id.to_hex() == item.txid)
+            })
+        else {
+            continue;
+        };
+
+        if let Some(block_hash) = backend.get_block_hash(height).await? {
+            // the witness of a pending transfer was mined again
+            anchors.pending.remove(&transfer.consig_id);
+            anchors.anchors.insert(
+                transfer.consig_id.clone(),
+                RgbAnchor {
+                    contract_id: item.contract_id.clone(),
+                    transfer: transfer.clone(),
+                    height,
+                    block_hash: block_hash.to_string(),
+                },
+            );
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn check_anchor<B: ChainBackend + ?Sized>(
+    backend: &B,
+    anchor: &RgbAnchor,
+) -> Result<AnchorCheck, ChainError> {
+    let block_hash = backend.get_block_hash(anchor.height).await?;
+    if block_hash.map(|hash| hash.to_string()).as_ref() == Some(&anchor.block_hash) {
+        return Ok(AnchorCheck::Unchanged);
+    }
+
+    // The cached status is the one of the old chain
+    let txid =
+        bitcoin::Txid::from_str(&anchor.transfer.tx_id.to_hex()).expect("invalid transaction id");
+    backend.forget(&txid);
+
+    let status = backend.get_tx_status(&txid).await?;
+    if let TxStatus::Block(height) = status {
+        if let Some(block_hash) = backend.get_block_hash(height).await? {
+            return Ok(AnchorCheck::Moved(height, block_hash));
+        }
+    }
+
+    Ok(AnchorCheck::Unconfirmed(status))
+}
+
+/// Apply the check of an anchor to the models of the account
+///
+/// Returns the status of the witness transaction when it was reorganized.
+/// Transfers whose witness left the chain are moved to the pending anchors,
+/// and verified again, so they are accepted once the witness is mined.
+pub fn apply_anchor_check(
+    anchors: &mut RgbAnchors,
+    rgb_account: &mut RgbAccountV1,
+    rgb_transfers: &mut RgbTransfersV1,
+    consig_id: &str,
+    check: AnchorCheck,
+) -> Option<TxStatus> {
+    let anchor = anchors.anchors.get(consig_id)?.clone();
+    match check {
+        AnchorCheck::Unchanged => None,
+        AnchorCheck::Moved(height, block_hash) => {
+            set_witness_status(rgb_account, &anchor.transfer.tx_id, Some(height));
+            anchors.anchors.insert(
+                consig_id.to_string(),
+                RgbAnchor {
+                    height,
+                    block_hash: block_hash.to_string(),
+                    ..anchor
+                },
+            );
+            Some(TxStatus::Block(height))
+        }
+        AnchorCheck::Unconfirmed(status) => {
+            set_witness_status(rgb_account, &anchor.transfer.tx_id, None);
+            anchors.anchors.remove(consig_id);
+
+            let transfers = rgb_transfers
+                .transfers
+                .entry(anchor.contract_id.clone())
+                .or_default();
+            if !transfers
+                .iter()
+                .any(|transfer| transfer.consig_id == anchor.transfer.consig_id)
+            {
+                transfers.push(anchor.transfer.clone());
+            }
+
+            anchors.pending.insert(consig_id.to_string(), anchor);
+            Some(status)
+        }
+    }
+}
+
+/// Stop watching the anchors buried deep enough to not be reorganized anymore,
+/// returns the number of pruned anchors
+pub fn prune_anchors(anchors: &mut RgbAnchors, tip: u32) -> usize {
+    let watched = anchors.anchors.len();
+    anchors
+        .anchors
+        .retain(|_, anchor| tip.saturating_sub(anchor.height) + 1 < RGB_ANCHOR_FINAL_DEPTH);
+    watched - anchors.anchors.len()
+}
+
+/// Witness transactions of the pending transfers of a contract
+pub fn pending_witnesses(anchors: &RgbAnchors, contract_id: &str) -> BTreeSet<String> {
+    anchors
+        .pending
+        .values()
+        .filter(|anchor| anchor.contract_id == contract_id)
+        .map(|anchor| anchor.transfer.tx_id.to_hex())
+        .collect()
+}
+
+/// Mark the allocations created by pending witnesses, returns the value of
+/// the ones counted in the balance
+pub fn mark_pending_allocations(
+    allocations: &mut [AllocationDetail],
+    witnesses: &BTreeSet<String>,
+) -> u64 {
+    let mut pending = 0;
+    for allocation in allocations.iter_mut() {
+        let txid = allocation.utxo.split(':').next().unwrap_or_default();
+        if !witnesses.contains(txid) {
+            continue;
+        }
+
+        allocation.is_pending = true;
+        if allocation.is_mine && !allocation.is_spent {
+            pending += match allocation.value {
+                AllocationValue::Value(value) => value,
+                AllocationValue::UDA(_) => 1,
+            };
+        }
+    }
+    pending
+}
+
+/// The stock keeps the state of the transfers whose witness left the chain,
+/// so their allocations are marked pending and left out of the balance
+pub fn mark_pending_contract(anchors: &RgbAnchors, contract: &mut ContractResponse) {
+    let witnesses = pending_witnesses(anchors, &contract.contract_id);
+    if witnesses.is_empty() {
+        return;
+    }
+
+    let pending = mark_pending_allocations(&mut contract.allocations, &witnesses);
+    contract.balance = contract.balance.saturating_sub(pending);
+    let balance_normalized = ContractAmount::new(contract.balance, contract.precision).to_string();
+    if let Ok(balance_normalized) = f64::from_str(&balance_normalized) {
+        contract.balance_normalized = balance_normalized;
+    }
+}
+
+/// Update the mining status of the wallet UTXOs created by a witness transaction
+pub fn set_witness_status(rgb_account: &mut RgbAccountV1, txid: &Txid, height: Option<u32>) {
+    for wallet in rgb_account.wallets.values_mut() {
+        let utxos: Vec<_> = wallet
+            .utxos
+            .iter()
+            .filter(|utxo| utxo.outpoint.txid == *txid)
+            .cloned()
+            .collect();
+
+        for mut utxo in utxos {
+            wallet.utxos.remove(&utxo);
+            utxo.status = match height {
+                Some(height) => MiningStatus::Blockchain(height),
+                None => MiningStatus::Mempool,
+            };
+            wallet.utxos.insert(utxo);
+        }
+    }
+}
+
+/// Validate a consignment again, against the current chain
+pub fn revalidate_transfer<R>(consig: &str, resolver: &mut R) -> bool
+where
+    R: ResolveHeight + ResolveTx,
+    R::Error: 'static,
+{
+    match prebuild_extract_transfer(consig) {
+        Ok(extracted) => extracted.transfer.unbindle().validate(resolver).is_ok(),
+        Err(_) => false,
+    }
+}
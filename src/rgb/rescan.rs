@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+
+use once_cell::sync::Lazy;
+use rgb::RgbWallet;
+use tokio::sync::RwLock;
+
+use crate::{
+    info,
+    rgb::{
+        prefetch::prefetch_resolver_utxos_range, resolvers::ExplorerResolver, wallet::scan_wallet,
+    },
+    structs::RgbRescanProgress,
+};
+
+/// Progress of the running (or last) rescans, by watcher
+static RESCAN_PROGRESS: Lazy<RwLock<BTreeMap<String, Vec<RgbRescanProgress>>>> =
+    Lazy::new(Default::default);
+
+fn rescan_key(sk: &str, name: &str) -> String {
+    blake3::hash(format!("{sk}-{name}").as_bytes())
+        .to_hex()
+        .to_string()
+}
+
+pub async fn update_rescan_progress(sk: &str, name: &str, progress: RgbRescanProgress) {
+    let mut rescans = RESCAN_PROGRESS.write().await;
+    let current = rescans.entry(rescan_key(sk, name)).or_default();
+    current.retain(|item| item.iface != progress.iface);
+    current.push(progress);
+}
+
+pub async fn clear_rescan_progress(sk: &str, name: &str) {
+    let mut rescans = RESCAN_PROGRESS.write().await;
+    rescans.remove(&rescan_key(sk, name));
+}
+
+pub async fn get_rescan_progress(sk: &str, name: &str) -> Vec<RgbRescanProgress> {
+    let rescans = RESCAN_PROGRESS.read().await;
+    rescans
+        .get(&rescan_key(sk, name))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Scan an interface branch from index 0 until `gap_limit` consecutive
+/// unused addresses are found
+#[allow(clippy::too_many_arguments)]
+pub async fn rescan_wallet(
+    sk: &str,
+    name: &str,
+    iface_index: u32,
+    wallet: &mut RgbWallet,
+    resolver: &mut ExplorerResolver,
+    gap_limit: u32,
+    birthday: Option<u32>,
+) -> Result<RgbRescanProgress, anyhow::Error> {
+    let mut progress = RgbRescanProgress {
+        iface: format!("RGB{iface_index}"),
+        ..default!()
+    };
+
+    let mut start = 0;
+    while !progress.done {
+        let range = start..start + gap_limit;
+        prefetch_resolver_utxos_range(iface_index, range.clone(), wallet, resolver).await;
+        match scan_wallet(iface_index, range.clone(), wallet, resolver, birthday)? {
+            Some(index) => {
+                progress.last_used = Some(index);
+                start = index + 1;
+            }
+            None => progress.done = true,
+        }
+
+        progress.scanned = range.end;
+        progress.utxos = wallet
+            .utxos
+            .iter()
+            .filter(|utxo| utxo.derivation.terminal.app == iface_index)
+            .count();
+
+        info!(format!(
+            "Rescan {}: {} addresses scanned",
+            progress.iface, progress.scanned
+        ));
+        update_rescan_progress(sk, name, progress.clone()).await;
+    }
+
+    Ok(progress)
+}
@@ -4,13 +4,21 @@ use std::{
     collections::{BTreeMap, BTreeSet, HashMap},
     convert::Infallible,
     str::FromStr,
+    time::Duration,
 };
 
 use amplify::hex::ToHex;
 use bp::{LockTime, Outpoint, SeqNo, Tx, TxIn, TxOut, TxVer, Txid, VarIntArray, Witness};
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::chain::{blocking_chain_backend, with_resolver_cache, OutputStatus, ResolverCache};
 #[cfg(not(target_arch = "wasm32"))]
 use esplora_block::{BlockingClient, Tx as ExplorerTX};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::{
+    runtime::{Handle, RuntimeFlavor},
+    task::block_in_place,
+};
 
 use rgb::{prelude::DeriveInfo, MiningStatus, TerminalPath, Utxo};
 use rgbstd::{
@@ -20,11 +28,58 @@ use rgbstd::{
 };
 use wallet::onchain::{ResolveTx, TxResolverError};
 
-use crate::structs::{TxStatus, UtxoSpentStatus};
+use crate::{
+    chain::{block_on_runtime, ChainBackend, ChainError, ScriptTx},
+    constants::{BITCOIN_EXPLORER_API_FALLBACK, EXPLORER_MAX_RETRIES, EXPLORER_RETRY_BACKOFF_MS},
+    structs::{TxStatus, UtxoSpentStatus},
+};
+
+#[derive(Clone, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum ExplorerResolverError {
+    /// No explorer answered after {0} attempts (last error: {1})
+    Unavailable(u32, String),
+    /// The blockchain backend cannot be used (reason: {0})
+    Backend(String),
+}
+
+/// Retries of each explorer call, with exponential backoff
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on each attempt
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// Read from `EXPLORER_MAX_RETRIES` and `EXPLORER_RETRY_BACKOFF_MS`
+    fn default() -> Self {
+        let max_retries = EXPLORER_MAX_RETRIES
+            .try_read()
+            .ok()
+            .and_then(|retries| retries.parse().ok())
+            .unwrap_or(2);
+        let backoff_ms = EXPLORER_RETRY_BACKOFF_MS
+            .try_read()
+            .ok()
+            .and_then(|backoff| backoff.parse().ok())
+            .unwrap_or(250);
+
+        Self {
+            max_retries,
+            backoff: Duration::from_millis(backoff_ms),
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct ExplorerResolver {
     pub explorer_url: String,
+    /// Explorers tried, in order, when `explorer_url` fails
+    pub fallback_urls: Vec<String>,
+    pub retry: RetryPolicy,
+    /// Account whose resolver cache is used, see [`crate::chain::resolver_account`]
+    pub account: String,
     // Prefetch Data (wasm32)
     pub utxos: BTreeSet<Utxo>,
     pub utxos_spent: Vec<UtxoSpentStatus>,
@@ -46,27 +101,30 @@ impl rgb::Resolver for ExplorerResolver {
         use esplora_block::FromHex;
         use std::collections::HashSet;
 
-        let mut utxos = bset![];
-        let explorer_client = esplora_block::Builder::new(&self.explorer_url)
-            .build_blocking()
-            .expect("service unavailable");
+        if let Some(mut resolver) = chain_resolver(&self.account).map_err(|err| err.to_string())? {
+            return resolver.resolve_utxo(scripts);
+        }
 
+        let mut utxos = bset![];
         for (derive, script) in scripts {
             // TODO: Remove that after bitcoin v.30 full compatibility
             let script_compatible =
                 Script::from_hex(&script.as_script().to_hex_string()).expect("invalid script");
 
-            let mut related_txs = explorer_client
-                .scripthash_txs(&script_compatible, None)
-                .expect("Service unavailable");
+            let mut related_txs = self
+                .with_explorer(|client| client.scripthash_txs(&script_compatible, None))
+                .map_err(|err| err.to_string())?;
             let n_confirmed = related_txs.iter().filter(|tx| tx.status.confirmed).count();
             // esplora pages on 25 confirmed transactions. If there are 25 or more we
             // keep requesting to see if there's more.
             if n_confirmed >= 25 {
                 loop {
-                    let new_related_txs = explorer_client
-                        .scripthash_txs(&script_compatible, Some(related_txs.last().unwrap().txid))
-                        .expect("Service unavailable");
+                    let last_seen = related_txs.last().map(|tx| tx.txid);
+                    let new_related_txs = self
+                        .with_explorer(|client| {
+                            client.scripthash_txs(&script_compatible, last_seen)
+                        })
+                        .map_err(|err| err.to_string())?;
                     let n = new_related_txs.len();
                     related_txs.extend(new_related_txs);
                     // we've reached the end
@@ -113,107 +171,118 @@ impl rgb::Resolver for ExplorerResolver {
 }
 
 impl ResolveTx for ExplorerResolver {
-    #[cfg(not(target_arch = "wasm32"))]
     fn resolve_tx(
         &self,
         txid: bitcoin::Txid,
     ) -> Result<bitcoin::Transaction, wallet::onchain::TxResolverError> {
-        let explorer_client = esplora_block::Builder::new(&self.explorer_url)
-            .build_blocking()
-            .expect("service unavailable");
-
-        match explorer_client.get_tx(&txid).expect("service unavailable") {
-            Some(tx) => Ok(tx),
-            _ => Err(TxResolverError { txid, err: none!() }),
+        if let Some(tx) = self.txs.get(&txid) {
+            return Ok(tx.to_owned());
         }
-    }
 
-    #[cfg(target_arch = "wasm32")]
-    fn resolve_tx(
-        &self,
-        txid: bitcoin::Txid,
-    ) -> Result<bitcoin::Transaction, wallet::onchain::TxResolverError> {
-        match self.txs.get(&txid) {
-            Some(tx) => Ok(tx.to_owned()),
-            _ => Err(TxResolverError { txid, err: none!() }),
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let unavailable = || TxResolverError { txid, err: none!() };
+            if let Some(resolver) = chain_resolver(&self.account).map_err(|_| unavailable())? {
+                return ResolveTx::resolve_tx(&resolver, txid);
+            }
+
+            if let Some(tx) = self.explorer_tx(&txid).map_err(|_| unavailable())? {
+                return Ok(tx);
+            }
         }
+
+        Err(TxResolverError { txid, err: none!() })
     }
 }
 
 impl ResolveHeight for ExplorerResolver {
     type Error = TxResolverError;
-    #[cfg(not(target_arch = "wasm32"))]
+
     fn resolve_height(&mut self, txid: Txid) -> Result<WitnessOrd, Self::Error> {
-        let esplora_client = esplora_block::Builder::new(&self.explorer_url)
-            .build_blocking()
-            .expect("service unavailable");
-        let transaction_id =
-            &bitcoin::Txid::from_str(&txid.to_hex()).expect("invalid transaction id parse");
-        let tx = esplora_client
-            .get_tx_status(transaction_id)
-            .expect("service unavailable");
-
-        let status = match tx.block_height {
-            Some(height) => WitnessOrd::OnChain(WitnessHeight::new(height).unwrap()),
-            _ => WitnessOrd::OffChain,
-        };
+        if let Some(status) = self.tx_height.get(&txid) {
+            return Ok(*status);
+        }
 
-        Ok(status)
-    }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let transaction_id =
+                bitcoin::Txid::from_str(&txid.to_hex()).expect("invalid transaction id parse");
+            let unavailable = || TxResolverError {
+                txid: transaction_id,
+                err: none!(),
+            };
+
+            if let Some(mut resolver) = chain_resolver(&self.account).map_err(|_| unavailable())? {
+                return resolver.resolve_height(txid);
+            }
+
+            let status = self
+                .explorer_mining_status(&transaction_id)
+                .map_err(|_| unavailable())?;
+
+            if let TxStatus::Block(height) = status {
+                return mined_witness_ord(transaction_id, height)
+                    .map_err(|err| height_error(transaction_id, err));
+            }
+        }
 
-    #[cfg(target_arch = "wasm32")]
-    fn resolve_height(&mut self, txid: Txid) -> Result<WitnessOrd, Self::Error> {
         Ok(WitnessOrd::OffChain)
     }
 }
 
+/// Convert a rust-bitcoin transaction into a BP transaction
+pub fn to_bp_tx(tx: bitcoin::Transaction) -> Tx {
+    Tx {
+        version: TxVer::from_consensus_i32(tx.version),
+        inputs: VarIntArray::try_from_iter(tx.input.into_iter().map(|txin| {
+            TxIn {
+                prev_output: Outpoint::new(
+                    Txid::from_str(&txin.previous_output.txid.to_hex())
+                        .expect("invalid transaction id parse"),
+                    txin.previous_output.vout,
+                ),
+                sig_script: txin.script_sig.to_bytes().into(),
+                sequence: SeqNo::from_consensus_u32(txin.sequence.to_consensus_u32()),
+                witness: Witness::from_consensus_stack(txin.witness.to_vec()),
+            }
+        }))
+        .expect("consensus-invalid transaction"),
+        outputs: VarIntArray::try_from_iter(tx.output.into_iter().map(|txout| TxOut {
+            value: txout.value.into(),
+            script_pubkey: txout.script_pubkey.to_bytes().into(),
+        }))
+        .expect("consensus-invalid transaction"),
+        lock_time: LockTime::from_consensus_u32(tx.lock_time.0),
+    }
+}
+
 // TODO: Review after migrate to rust-bitcoin v0.30
 impl ResolveCommiment for ExplorerResolver {
-    #[cfg(not(target_arch = "wasm32"))]
     fn resolve_tx(&self, txid: Txid) -> Result<Tx, rgbstd::validation::TxResolverError> {
-        let explorer_client = esplora_block::Builder::new(&self.explorer_url)
-            .build_blocking()
-            .expect("service unavailable");
-
-        let transaction_id =
-            &bitcoin::Txid::from_str(&txid.to_hex()).expect("invalid transaction id parse");
-        let tx = explorer_client
-            .get_tx(transaction_id)
-            .expect("service unavailable");
-
-        match tx {
-            Some(tx) => Ok(Tx {
-                version: TxVer::from_consensus_i32(tx.version),
-                inputs: VarIntArray::try_from_iter(tx.input.into_iter().map(|txin| {
-                    TxIn {
-                        prev_output: Outpoint::new(
-                            Txid::from_str(&txin.previous_output.txid.to_hex())
-                                .expect("invalid transaction id parse"),
-                            txin.previous_output.vout,
-                        ),
-                        sig_script: txin.script_sig.to_bytes().into(),
-                        sequence: SeqNo::from_consensus_u32(txin.sequence.to_consensus_u32()),
-                        witness: Witness::from_consensus_stack(txin.witness.to_vec()),
-                    }
-                }))
-                .expect("consensus-invalid transaction"),
-                outputs: VarIntArray::try_from_iter(tx.output.into_iter().map(|txout| TxOut {
-                    value: txout.value.into(),
-                    script_pubkey: txout.script_pubkey.to_bytes().into(),
-                }))
-                .expect("consensus-invalid transaction"),
-                lock_time: LockTime::from_consensus_u32(tx.lock_time.0),
-            }),
-            _ => Err(rgbstd::validation::TxResolverError::Unknown(txid)),
+        if let Some(tx) = self.bp_txs.get(&txid) {
+            return Ok(tx.clone());
         }
-    }
 
-    #[cfg(target_arch = "wasm32")]
-    fn resolve_tx(&self, txid: Txid) -> Result<Tx, rgbstd::validation::TxResolverError> {
-        match self.bp_txs.get(&txid) {
-            Some(tx) => Ok(tx.clone()),
-            _ => Err(rgbstd::validation::TxResolverError::Unknown(txid)),
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let unavailable = |err: String| rgbstd::validation::TxResolverError::Other(txid, err);
+            if let Some(resolver) =
+                chain_resolver(&self.account).map_err(|err| unavailable(err.to_string()))?
+            {
+                return ResolveCommiment::resolve_tx(&resolver, txid);
+            }
+
+            let transaction_id =
+                bitcoin::Txid::from_str(&txid.to_hex()).expect("invalid transaction id parse");
+            if let Some(tx) = self
+                .explorer_tx(&transaction_id)
+                .map_err(|err| unavailable(err.to_string()))?
+            {
+                return Ok(to_bp_tx(tx));
+            }
         }
+
+        Err(rgbstd::validation::TxResolverError::Unknown(txid))
     }
 }
 
@@ -222,6 +291,32 @@ impl ResolveCommiment for ExplorerResolver {
 pub enum SpendResolverError {
     /// transaction {0} is not mined
     Unknown(bitcoin::Txid),
+    /// The blockchain backend cannot be used (reason: {0})
+    Backend(String),
+}
+
+#[derive(Clone, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum HeightResolverError {
+    /// transaction {0} is mined at height {1}, which is not a valid witness height
+    InvalidHeight(bitcoin::Txid, u32),
+}
+
+/// Witness order of a transaction mined at `height`
+pub fn mined_witness_ord(
+    txid: bitcoin::Txid,
+    height: u32,
+) -> Result<WitnessOrd, HeightResolverError> {
+    WitnessHeight::new(height)
+        .map(WitnessOrd::OnChain)
+        .ok_or(HeightResolverError::InvalidHeight(txid, height))
+}
+
+fn height_error(txid: bitcoin::Txid, err: HeightResolverError) -> TxResolverError {
+    TxResolverError {
+        txid,
+        err: Some(Box::new(err)),
+    }
 }
 
 pub trait ResolveSpent {
@@ -235,53 +330,229 @@ pub trait ResolveSpent {
     ) -> Result<UtxoSpentStatus, Self::Error>;
 }
 
-impl ResolveSpent for ExplorerResolver {
-    type Error = SpendResolverError;
-    #[cfg(not(target_arch = "wasm32"))]
-    fn resolve_spent_status(
-        &mut self,
+/// Resolver of the backend selected by `BITCOIN_BACKEND`, `None` for Esplora
+#[cfg(not(target_arch = "wasm32"))]
+fn chain_resolver(
+    account: &str,
+) -> Result<Option<ChainResolver<Box<dyn ChainBackend>>>, ChainError> {
+    Ok(blocking_chain_backend(account)?.map(ChainResolver::new))
+}
+
+impl ExplorerResolver {
+    /// Explorers in failover order: `explorer_url`, `fallback_urls` and
+    /// the ones of `BITCOIN_EXPLORER_API_FALLBACK`
+    pub fn explorer_urls(&self) -> Vec<String> {
+        let mut urls = vec![self.explorer_url.clone()];
+        urls.extend(self.fallback_urls.iter().cloned());
+        if let Ok(fallback) = BITCOIN_EXPLORER_API_FALLBACK.try_read() {
+            urls.extend(
+                fallback
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|url| !url.is_empty())
+                    .map(str::to_owned),
+            );
+        }
+
+        let mut unique = vec![];
+        for url in urls {
+            if !unique.contains(&url) {
+                unique.push(url);
+            }
+        }
+        unique
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ExplorerResolver {
+    /// Run an explorer call, retrying with backoff before moving to the next explorer
+    fn with_explorer<T>(
+        &self,
+        call: impl Fn(&BlockingClient) -> Result<T, esplora_block::Error>,
+    ) -> Result<T, ExplorerResolverError> {
+        // the explorer client blocks, so run it (and the backoff sleeps) off the runtime worker
+        match Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                block_in_place(|| {
+                    self.try_explorers(call, |backoff| handle.block_on(tokio::time::sleep(backoff)))
+                })
+            }
+            _ => self.try_explorers(call, std::thread::sleep),
+        }
+    }
+
+    fn try_explorers<T>(
+        &self,
+        call: impl Fn(&BlockingClient) -> Result<T, esplora_block::Error>,
+        sleep: impl Fn(Duration),
+    ) -> Result<T, ExplorerResolverError> {
+        let mut attempts = 0;
+        let mut last_err = String::new();
+        for url in self.explorer_urls() {
+            let client = match esplora_block::Builder::new(&url).build_blocking() {
+                Ok(client) => client,
+                Err(err) => {
+                    last_err = format!("{url}: {err}");
+                    continue;
+                }
+            };
+
+            let mut backoff = self.retry.backoff;
+            for retry in 0..=self.retry.max_retries {
+                attempts += 1;
+                match call(&client) {
+                    Ok(value) => return Ok(value),
+                    Err(err) => last_err = format!("{url}: {err}"),
+                }
+
+                if retry < self.retry.max_retries {
+                    sleep(backoff);
+                    backoff *= 2;
+                }
+            }
+        }
+
+        Err(ExplorerResolverError::Unavailable(attempts, last_err))
+    }
+
+    fn cache<T>(&self, f: impl FnOnce(&mut ResolverCache) -> T) -> T {
+        with_resolver_cache(&self.account, f)
+    }
+
+    /// Ask the tip again once it expired, the cached statuses are only final under it
+    fn refresh_cache_tip(&self, now: i64) {
+        let tip = self.cache(|cache| cache.tip(now));
+        if tip.is_some() {
+            return;
+        }
+
+        // without a tip the cached confirmations are shallow, so the error is not fatal
+        if let Ok(height) = self.with_explorer(|client| client.get_height()) {
+            self.cache(|cache| cache.set_tip(height, now));
+        }
+    }
+
+    /// Get a transaction from the resolver cache, or from the explorers
+    fn explorer_tx(
+        &self,
+        txid: &bitcoin::Txid,
+    ) -> Result<Option<bitcoin::Transaction>, ExplorerResolverError> {
+        let cached = self.cache(|cache| cache.tx(txid));
+        if cached.is_some() {
+            return Ok(cached);
+        }
+
+        let tx = self.with_explorer(|client| client.get_tx(txid))?;
+        if let Some(tx) = &tx {
+            let now = chrono::Utc::now().timestamp();
+            self.cache(|cache| cache.insert_tx(tx, now));
+        }
+        Ok(tx)
+    }
+
+    /// Get the mining status of a transaction from the resolver cache, or from the explorers
+    fn explorer_mining_status(
+        &self,
+        txid: &bitcoin::Txid,
+    ) -> Result<TxStatus, ExplorerResolverError> {
+        let now = chrono::Utc::now().timestamp();
+        self.refresh_cache_tip(now);
+        let cached = self.cache(|cache| cache.tx_status(txid, now));
+        if let Some(status) = cached {
+            return Ok(status);
+        }
+
+        let status = self.with_explorer(|client| client.get_tx_status(txid))?;
+        let status = match status.block_height {
+            Some(height) if status.confirmed => TxStatus::Block(height),
+            _ => TxStatus::Mempool,
+        };
+        self.cache(|cache| cache.insert_tx_status(txid, status.clone(), now));
+        Ok(status)
+    }
+
+    /// Get the spending status of an output from the resolver cache, or from the explorers
+    fn explorer_output_status(
+        &self,
+        txid: &bitcoin::Txid,
+        index: u64,
+    ) -> Result<Option<OutputStatus>, ExplorerResolverError> {
+        let now = chrono::Utc::now().timestamp();
+        self.refresh_cache_tip(now);
+        let cached = self.cache(|cache| cache.output_status(txid, index as u32, now));
+        if cached.is_some() {
+            return Ok(cached);
+        }
+
+        let Some(output_status) =
+            self.with_explorer(|client| client.get_output_status(txid, index))?
+        else {
+            return Ok(None);
+        };
+        let status = if !output_status.spent && output_status.txid.is_none() {
+            TxStatus::NotFound
+        } else {
+            match output_status.status {
+                Some(utxo_status) if utxo_status.confirmed => {
+                    TxStatus::Block(utxo_status.block_height.unwrap_or_default())
+                }
+                Some(_) => TxStatus::Mempool,
+                None => TxStatus::NotFound,
+            }
+        };
+        let status = OutputStatus {
+            spent: output_status.spent,
+            txid: output_status.txid,
+            status,
+        };
+        self.cache(|cache| cache.insert_output_status(txid, index as u32, status.clone(), now));
+        Ok(Some(status))
+    }
+
+    fn explorer_tx_status(&self, txid: bitcoin::Txid) -> Result<TxStatus, ResolverTxStatusError> {
+        match chain_resolver(&self.account) {
+            Ok(Some(mut resolver)) => return resolver.resolve_tx_status(txid),
+            Ok(None) => {}
+            Err(err) => return Ok(TxStatus::Error(err.to_string())),
+        }
+
+        let status = match self.explorer_mining_status(&txid) {
+            Ok(status) => status,
+            Err(err) => TxStatus::Error(err.to_string()),
+        };
+        Ok(status)
+    }
+
+    fn explorer_spent_status(
+        &self,
         txid: bitcoin::Txid,
         index: u64,
         block_height: bool,
-    ) -> Result<UtxoSpentStatus, Self::Error> {
-        let explorer_client = esplora_block::Builder::new(&self.explorer_url)
-            .build_blocking()
-            .expect("service unavailable");
+    ) -> Result<UtxoSpentStatus, SpendResolverError> {
+        if let Some(backend) = blocking_chain_backend(&self.account)
+            .map_err(|err| SpendResolverError::Backend(err.to_string()))?
+        {
+            return Ok(block_on_runtime(chain_spent_status(
+                &backend,
+                txid,
+                index,
+                block_height,
+            )));
+        }
 
         let block_h = if block_height {
-            match explorer_client.get_full_tx(&txid) {
-                Ok(full_tx) => {
-                    if full_tx.status.confirmed {
-                        TxStatus::Block(full_tx.status.block_height.unwrap_or_default())
-                    } else {
-                        TxStatus::Mempool
-                    }
-                }
+            match self.explorer_mining_status(&txid) {
+                Ok(status) => status,
                 Err(err) => TxStatus::Error(err.to_string()),
             }
         } else {
             TxStatus::NotFound
         };
 
-        let (is_spent, utxo_status) = match explorer_client.get_output_status(&txid, index) {
+        let (is_spent, utxo_status) = match self.explorer_output_status(&txid, index) {
             Ok(output_status) => match output_status {
-                Some(output_status) => {
-                    let status = if !output_status.spent && output_status.txid.is_none() {
-                        TxStatus::NotFound
-                    } else {
-                        match output_status.status {
-                            Some(utxo_status) => {
-                                if utxo_status.confirmed {
-                                    TxStatus::Block(utxo_status.block_height.unwrap_or_default())
-                                } else {
-                                    TxStatus::Mempool
-                                }
-                            }
-                            None => TxStatus::NotFound,
-                        }
-                    };
-                    (output_status.spent, status)
-                }
+                Some(output_status) => (output_status.spent, output_status.status),
                 None => (
                     false,
                     TxStatus::Error(format!("The utxo {txid}:{index} does not exists").to_string()),
@@ -290,17 +561,18 @@ impl ResolveSpent for ExplorerResolver {
             Err(err) => (false, TxStatus::Error(err.to_string())),
         };
 
-        let utxo_status = UtxoSpentStatus {
+        Ok(UtxoSpentStatus {
             utxo: format!("{txid}:{index}"),
             is_spent,
             block_height: block_h,
             spent_height: utxo_status,
-        };
-
-        Ok(utxo_status)
+        })
     }
+}
+
+impl ResolveSpent for ExplorerResolver {
+    type Error = SpendResolverError;
 
-    #[cfg(target_arch = "wasm32")]
     fn resolve_spent_status(
         &mut self,
         txid: bitcoin::Txid,
@@ -309,19 +581,23 @@ impl ResolveSpent for ExplorerResolver {
     ) -> Result<UtxoSpentStatus, Self::Error> {
         if let Some(utxo) = self
             .utxos_spent
-            .clone()
-            .into_iter()
+            .iter()
             .find(|x| x.utxo == format!("{}:{}", txid.to_hex(), index))
         {
-            Ok(utxo)
-        } else {
-            Ok(UtxoSpentStatus {
-                utxo: format!("{}:{}", txid.to_hex(), index),
-                is_spent: false,
-                block_height: TxStatus::NotFound,
-                spent_height: TxStatus::NotFound,
-            })
+            return Ok(utxo.clone());
         }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let utxo_status = self.explorer_spent_status(txid, index, block_height)?;
+        #[cfg(target_arch = "wasm32")]
+        let utxo_status = UtxoSpentStatus {
+            utxo: format!("{}:{}", txid.to_hex(), index),
+            is_spent: false,
+            block_height: TxStatus::NotFound,
+            spent_height: TxStatus::NotFound,
+        };
+
+        Ok(utxo_status)
     }
 }
 
@@ -337,15 +613,22 @@ pub trait ResolveTxStatus {
     fn resolve_tx_status(&mut self, txid: bitcoin::Txid) -> Result<TxStatus, Self::Error>;
 }
 
+#[cfg(target_arch = "wasm32")]
+impl ExplorerResolver {
+    fn explorer_tx_status(&self, _txid: bitcoin::Txid) -> Result<TxStatus, ResolverTxStatusError> {
+        Err(ResolverTxStatusError::Unknown)
+    }
+}
+
 impl ResolveTxStatus for ExplorerResolver {
     type Error = ResolverTxStatusError;
 
     fn resolve_tx_status(&mut self, txid: bitcoin::Txid) -> Result<TxStatus, Self::Error> {
         if let Some(status) = self.txs_status.get(&txid) {
-            Ok(status.clone())
-        } else {
-            Err(ResolverTxStatusError::Unknown)
+            return Ok(status.clone());
         }
+
+        self.explorer_tx_status(txid)
     }
 }
 
@@ -378,3 +661,190 @@ impl ExploreClientExt for BlockingClient {
         }
     }
 }
+
+/// Wallet outputs paying to `script` in the related transactions
+pub fn script_utxos(
+    derive: &DeriveInfo,
+    script: &bitcoin::Script,
+    txs: &[ScriptTx],
+) -> BTreeSet<Utxo> {
+    let mut utxos = bset![];
+    for script_tx in txs {
+        let txid = Txid::from_str(&script_tx.tx.txid().to_hex()).expect("invalid outpoint parse");
+
+        for (index, vout) in script_tx.tx.output.iter().enumerate() {
+            if &vout.script_pubkey != script {
+                continue;
+            }
+
+            let status = match script_tx.status {
+                TxStatus::Block(height) => MiningStatus::Blockchain(height),
+                _ => MiningStatus::Mempool,
+            };
+            utxos.insert(Utxo {
+                outpoint: Outpoint::new(txid, index as u32),
+                status,
+                amount: vout.value,
+                derivation: derive.clone(),
+            });
+        }
+    }
+    utxos
+}
+
+/// Spent status of an output, as stored in [`ExplorerResolver::utxos_spent`]
+pub async fn chain_spent_status<B: ChainBackend + ?Sized>(
+    backend: &B,
+    txid: bitcoin::Txid,
+    index: u64,
+    block_height: bool,
+) -> UtxoSpentStatus {
+    let block_h = if block_height {
+        match backend.get_tx_status(&txid).await {
+            Ok(status) => status,
+            Err(err) => TxStatus::Error(err.to_string()),
+        }
+    } else {
+        TxStatus::NotFound
+    };
+
+    let (is_spent, spent_height) = match backend.get_output_status(&txid, index as u32).await {
+        Ok(Some(output_status)) => (output_status.spent, output_status.status),
+        Ok(None) => (
+            false,
+            TxStatus::Error(format!("The utxo {txid}:{index} does not exists").to_string()),
+        ),
+        Err(err) => (false, TxStatus::Error(err.to_string())),
+    };
+
+    UtxoSpentStatus {
+        utxo: format!("{txid}:{index}"),
+        is_spent,
+        block_height: block_h,
+        spent_height,
+    }
+}
+
+/// Resolver querying a [`ChainBackend`] directly, without prefetch
+///
+/// The RGB resolvers are synchronous, so the backend futures are run with
+/// [`block_on_runtime`]. Use it only with backends that don't need an async
+/// runtime (Electrum, Bitcoin Core and the mock); Esplora is resolved with
+/// [`ExplorerResolver`] and the prefetch functions.
+pub struct ChainResolver<B: ChainBackend> {
+    pub backend: B,
+}
+
+impl<B: ChainBackend> ChainResolver<B> {
+    pub fn new(backend: B) -> Self {
+        Self { backend }
+    }
+}
+
+/// Resolver backed by an Electrum server (electrs, Fulcrum, ElectrumX)
+#[cfg(not(target_arch = "wasm32"))]
+pub type ElectrumResolver = ChainResolver<crate::chain::ElectrumBackend>;
+
+/// Resolver backed by a Bitcoin Core node (requires `txindex=1`)
+#[cfg(not(target_arch = "wasm32"))]
+pub type BitcoindResolver = ChainResolver<crate::chain::BitcoindBackend>;
+
+/// Resolver backed by the in-memory chain
+pub type MockResolver = ChainResolver<crate::chain::MockBackend>;
+
+impl<B: ChainBackend> rgb::Resolver for ChainResolver<B> {
+    fn resolve_utxo<'s>(
+        &mut self,
+        scripts: BTreeMap<DeriveInfo, bitcoin_30::ScriptBuf>,
+    ) -> Result<BTreeSet<rgb::prelude::Utxo>, String> {
+        use bitcoin::Script;
+
+        let mut utxos = bset![];
+        for (derive, script) in scripts {
+            // TODO: Remove that after bitcoin v.30 full compatibility
+            let script_compatible =
+                Script::from_str(&script.as_script().to_hex_string()).expect("invalid script");
+
+            let txs = block_on_runtime(self.backend.get_script_txs(&script_compatible))
+                .map_err(|err| err.to_string())?;
+            utxos.extend(script_utxos(&derive, &script_compatible, &txs));
+        }
+        Ok(utxos)
+    }
+}
+
+impl<B: ChainBackend> ResolveTx for ChainResolver<B> {
+    fn resolve_tx(
+        &self,
+        txid: bitcoin::Txid,
+    ) -> Result<bitcoin::Transaction, wallet::onchain::TxResolverError> {
+        match block_on_runtime(self.backend.get_tx(&txid)) {
+            Ok(Some(tx)) => Ok(tx),
+            _ => Err(TxResolverError { txid, err: none!() }),
+        }
+    }
+}
+
+impl<B: ChainBackend> ResolveHeight for ChainResolver<B> {
+    type Error = TxResolverError;
+
+    fn resolve_height(&mut self, txid: Txid) -> Result<WitnessOrd, Self::Error> {
+        let transaction_id =
+            bitcoin::Txid::from_str(&txid.to_hex()).expect("invalid transaction id parse");
+
+        let status = match block_on_runtime(self.backend.get_tx_status(&transaction_id)) {
+            Ok(TxStatus::Block(height)) => mined_witness_ord(transaction_id, height)
+                .map_err(|err| height_error(transaction_id, err))?,
+            Ok(_) => WitnessOrd::OffChain,
+            Err(_) => {
+                return Err(TxResolverError {
+                    txid: transaction_id,
+                    err: none!(),
+                })
+            }
+        };
+
+        Ok(status)
+    }
+}
+
+impl<B: ChainBackend> ResolveCommiment for ChainResolver<B> {
+    fn resolve_tx(&self, txid: Txid) -> Result<Tx, rgbstd::validation::TxResolverError> {
+        let transaction_id =
+            bitcoin::Txid::from_str(&txid.to_hex()).expect("invalid transaction id parse");
+
+        match block_on_runtime(self.backend.get_tx(&transaction_id)) {
+            Ok(Some(tx)) => Ok(to_bp_tx(tx)),
+            _ => Err(rgbstd::validation::TxResolverError::Unknown(txid)),
+        }
+    }
+}
+
+impl<B: ChainBackend> ResolveSpent for ChainResolver<B> {
+    type Error = SpendResolverError;
+
+    fn resolve_spent_status(
+        &mut self,
+        txid: bitcoin::Txid,
+        index: u64,
+        block_height: bool,
+    ) -> Result<UtxoSpentStatus, Self::Error> {
+        Ok(block_on_runtime(chain_spent_status(
+            &self.backend,
+            txid,
+            index,
+            block_height,
+        )))
+    }
+}
+
+impl<B: ChainBackend> ResolveTxStatus for ChainResolver<B> {
+    type Error = ResolverTxStatusError;
+
+    fn resolve_tx_status(&mut self, txid: bitcoin::Txid) -> Result<TxStatus, Self::Error> {
+        match block_on_runtime(self.backend.get_tx_status(&txid)) {
+            Ok(status) => Ok(status),
+            Err(err) => Ok(TxStatus::Error(err.to_string())),
+        }
+    }
+}
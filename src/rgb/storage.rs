@@ -0,0 +1,388 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use amplify::hex::ToHex;
+use async_trait::async_trait;
+use bitcoin_30::secp256k1::{PublicKey, SecretKey};
+use once_cell::sync::Lazy;
+use thiserror::Error;
+
+use crate::{
+    carbonado::{error::CarbonadoError, retrieve as carbonado_retrieve, store as carbonado_store},
+    constants::{NETWORK, STORAGE_BACKEND},
+    rgb::constants::RGB_STRICT_TYPE_VERSION,
+    util::MaybeSendSync,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::carbonado::remove as carbonado_remove;
+
+#[derive(Error, Debug)]
+pub enum StorageBackendError {
+    /// Carbonado error
+    #[error("Carbonado error: {0}")]
+    Carbonado(#[from] CarbonadoError),
+    /// Sled error
+    #[error("Sled error: {0}")]
+    Sled(String),
+    /// Backend not available in this target
+    #[error("Storage backend {0} is not supported")]
+    UnsupportedBackend(String),
+    /// Metadata doesn't fit in the carbonado header
+    #[error("Metadata of {0} bytes is longer than the {METADATA_LEN} bytes of the header")]
+    MetadataTooLong(usize),
+}
+
+/// Length of the metadata in a carbonado header
+const METADATA_LEN: usize = 8;
+
+/// Storage of the RGB files (stocks, accounts, transfers...) under
+/// `rgb::carbonado`, by owner and name
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait StorageBackend: MaybeSendSync {
+    /// Store a file of the owner of `sk`
+    async fn store(
+        &self,
+        sk: &str,
+        name: &str,
+        input: &[u8],
+        force: bool,
+        metadata: Option<Vec<u8>>,
+    ) -> Result<(), StorageBackendError>;
+
+    /// Retrieve a file and its metadata, empty when it doesn't exist
+    ///
+    /// Alternative names are only accepted for strict-encoded files.
+    async fn retrieve(
+        &self,
+        sk: &str,
+        name: &str,
+        alt_names: Vec<&String>,
+    ) -> Result<(Vec<u8>, Option<Vec<u8>>), StorageBackendError>;
+
+    /// Remove a file of the owner of `sk`, removing a missing file succeeds
+    async fn remove(&self, sk: &str, name: &str) -> Result<(), StorageBackendError>;
+}
+
+/// Carbonado server in the browser, local carbonado files in bitmaskd
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CarbonadoStorage;
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl StorageBackend for CarbonadoStorage {
+    async fn store(
+        &self,
+        sk: &str,
+        name: &str,
+        input: &[u8],
+        force: bool,
+        metadata: Option<Vec<u8>>,
+    ) -> Result<(), StorageBackendError> {
+        carbonado_store(sk, name, input, force, metadata).await?;
+        Ok(())
+    }
+
+    async fn retrieve(
+        &self,
+        sk: &str,
+        name: &str,
+        alt_names: Vec<&String>,
+    ) -> Result<(Vec<u8>, Option<Vec<u8>>), StorageBackendError> {
+        Ok(carbonado_retrieve(sk, name, alt_names).await?)
+    }
+
+    /// The carbonado server has no removal, so the browser keeps the file
+    async fn remove(&self, sk: &str, name: &str) -> Result<(), StorageBackendError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        carbonado_remove(sk, name).await?;
+        #[cfg(target_arch = "wasm32")]
+        let _ = (sk, name);
+        Ok(())
+    }
+}
+
+/// Key of a file in the key-value backends, namespaced by owner and network
+async fn file_key(pk: &str, name: &str) -> String {
+    let network = NETWORK.read().await.to_string();
+    let networks = ["bitcoin", "testnet", "signet", "regtest"];
+    if networks.into_iter().any(|x| name.contains(x)) {
+        format!("{pk}/{name}")
+    } else {
+        format!("{pk}/{network}-{name}")
+    }
+}
+
+fn owner_keys(sk: &str) -> Result<(Vec<u8>, PublicKey), StorageBackendError> {
+    let sk = hex::decode(sk).map_err(CarbonadoError::from)?;
+    let secret_key = SecretKey::from_slice(&sk).map_err(CarbonadoError::from)?;
+    let public_key = PublicKey::from_secret_key_global(&secret_key);
+    Ok((sk, public_key))
+}
+
+/// Key and content of a file, encrypted for its owner in the carbonado format
+async fn encode_file(
+    sk: &str,
+    name: &str,
+    input: &[u8],
+    metadata: Option<Vec<u8>>,
+) -> Result<(String, Vec<u8>), StorageBackendError> {
+    let level = 15;
+    let (sk, public_key) = owner_keys(sk)?;
+
+    let mut meta: Option<[u8; METADATA_LEN]> = default!();
+    if let Some(metadata) = metadata {
+        if metadata.len() > METADATA_LEN {
+            return Err(StorageBackendError::MetadataTooLong(metadata.len()));
+        }
+        let mut inner: [u8; METADATA_LEN] = default!();
+        inner[..metadata.len()].copy_from_slice(&metadata);
+        meta = Some(inner);
+    }
+
+    let (body, _encode_info) =
+        carbonado::file::encode(&sk, Some(&public_key.serialize()), input, level, meta)
+            .map_err(CarbonadoError::from)?;
+    Ok((file_key(&public_key.to_hex(), name).await, body))
+}
+
+/// Decrypt the first file found in a key-value backend, see [`StorageBackend::retrieve`]
+async fn decode_file<F>(
+    sk: &str,
+    name: &str,
+    alt_names: Vec<&String>,
+    get: F,
+) -> Result<(Vec<u8>, Option<Vec<u8>>), StorageBackendError>
+where
+    F: Fn(&str) -> Result<Option<Vec<u8>>, StorageBackendError>,
+{
+    let (sk, public_key) = owner_keys(sk)?;
+    let pk = public_key.to_hex();
+
+    if let Some(bytes) = get(&file_key(&pk, name).await)? {
+        let (header, decoded) =
+            carbonado::file::decode(&sk, &bytes).map_err(CarbonadoError::from)?;
+        return Ok((decoded, header.metadata.map(|m| m.to_vec())));
+    }
+
+    // Check alternative names
+    for alt_name in alt_names {
+        if let Some(bytes) = get(&file_key(&pk, alt_name).await)? {
+            let (header, decoded) =
+                carbonado::file::decode(&sk, &bytes).map_err(CarbonadoError::from)?;
+            if header.metadata == Some(RGB_STRICT_TYPE_VERSION) {
+                return Ok((decoded, header.metadata.map(|m| m.to_vec())));
+            }
+        }
+    }
+
+    Ok((Vec::new(), None))
+}
+
+/// Encrypted files kept in memory, for tests and ephemeral sessions
+///
+/// Clones share the same files.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStorage {
+    files: Arc<Mutex<BTreeMap<String, Vec<u8>>>>,
+}
+
+impl MemoryStorage {
+    pub fn len(&self) -> usize {
+        self.files.lock().expect("memory storage poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.files.lock().expect("memory storage poisoned").clear();
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl StorageBackend for MemoryStorage {
+    async fn store(
+        &self,
+        sk: &str,
+        name: &str,
+        input: &[u8],
+        _force: bool,
+        metadata: Option<Vec<u8>>,
+    ) -> Result<(), StorageBackendError> {
+        let (key, body) = encode_file(sk, name, input, metadata).await?;
+        self.files
+            .lock()
+            .expect("memory storage poisoned")
+            .insert(key, body);
+        Ok(())
+    }
+
+    async fn retrieve(
+        &self,
+        sk: &str,
+        name: &str,
+        alt_names: Vec<&String>,
+    ) -> Result<(Vec<u8>, Option<Vec<u8>>), StorageBackendError> {
+        decode_file(sk, name, alt_names, |key| {
+            Ok(self
+                .files
+                .lock()
+                .expect("memory storage poisoned")
+                .get(key)
+                .cloned())
+        })
+        .await
+    }
+
+    async fn remove(&self, sk: &str, name: &str) -> Result<(), StorageBackendError> {
+        let (_, public_key) = owner_keys(sk)?;
+        let key = file_key(&public_key.to_hex(), name).await;
+        self.files
+            .lock()
+            .expect("memory storage poisoned")
+            .remove(&key);
+        Ok(())
+    }
+}
+
+/// Encrypted files in a local sled database, for desktop and server deployments
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Debug)]
+pub struct SledStorage {
+    db: sled::Db,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SledStorage {
+    pub fn open(path: &std::path::Path) -> Result<Self, StorageBackendError> {
+        let db = sled::Config::default()
+            .path(path)
+            .open()
+            .map_err(|err| StorageBackendError::Sled(err.to_string()))?;
+        Ok(Self { db })
+    }
+
+    /// Database removed when the last clone is dropped
+    pub fn temporary() -> Result<Self, StorageBackendError> {
+        let db = sled::Config::default()
+            .temporary(true)
+            .open()
+            .map_err(|err| StorageBackendError::Sled(err.to_string()))?;
+        Ok(Self { db })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl StorageBackend for SledStorage {
+    async fn store(
+        &self,
+        sk: &str,
+        name: &str,
+        input: &[u8],
+        _force: bool,
+        metadata: Option<Vec<u8>>,
+    ) -> Result<(), StorageBackendError> {
+        let (key, body) = encode_file(sk, name, input, metadata).await?;
+        self.db
+            .insert(key.as_bytes(), body)
+            .map_err(|err| StorageBackendError::Sled(err.to_string()))?;
+        self.db
+            .flush_async()
+            .await
+            .map_err(|err| StorageBackendError::Sled(err.to_string()))?;
+        Ok(())
+    }
+
+    async fn retrieve(
+        &self,
+        sk: &str,
+        name: &str,
+        alt_names: Vec<&String>,
+    ) -> Result<(Vec<u8>, Option<Vec<u8>>), StorageBackendError> {
+        decode_file(sk, name, alt_names, |key| {
+            self.db
+                .get(key.as_bytes())
+                .map(|value| value.map(|value| value.to_vec()))
+                .map_err(|err| StorageBackendError::Sled(err.to_string()))
+        })
+        .await
+    }
+
+    async fn remove(&self, sk: &str, name: &str) -> Result<(), StorageBackendError> {
+        let (_, public_key) = owner_keys(sk)?;
+        let key = file_key(&public_key.to_hex(), name).await;
+        self.db
+            .remove(key.as_bytes())
+            .map_err(|err| StorageBackendError::Sled(err.to_string()))?;
+        self.db
+            .flush_async()
+            .await
+            .map_err(|err| StorageBackendError::Sled(err.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Files shared by all the `memory` backends
+pub static MEMORY_STORAGE: Lazy<MemoryStorage> = Lazy::new(MemoryStorage::default);
+
+/// Database shared by all the `sled` backends, in `$CARBONADO_DIR/rgb_sled_kv`
+#[cfg(not(target_arch = "wasm32"))]
+static SLED_STORAGE: Lazy<Result<SledStorage, String>> = Lazy::new(|| {
+    let dir = std::env::var("CARBONADO_DIR").unwrap_or("/tmp/bitmaskd/carbonado".to_owned());
+    SledStorage::open(&std::path::Path::new(&dir).join("rgb_sled_kv"))
+        .map_err(|err| err.to_string())
+});
+
+// the storages of bitmaskd are held across `.await` by `Send` handlers
+#[cfg(not(target_arch = "wasm32"))]
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync + ?Sized>() {}
+    assert_send_sync::<dyn StorageBackend>();
+};
+
+/// Backend selected by `STORAGE_BACKEND` ("carbonado", "sled" or "memory")
+pub async fn storage_backend() -> Result<Box<dyn StorageBackend>, StorageBackendError> {
+    let backend = STORAGE_BACKEND.read().await.clone();
+    match backend.as_str() {
+        "carbonado" => Ok(Box::new(CarbonadoStorage)),
+        "memory" => Ok(Box::new(MEMORY_STORAGE.clone())),
+        #[cfg(not(target_arch = "wasm32"))]
+        "sled" => match &*SLED_STORAGE {
+            Ok(storage) => Ok(Box::new(storage.clone())),
+            Err(err) => Err(StorageBackendError::Sled(err.clone())),
+        },
+        _ => Err(StorageBackendError::UnsupportedBackend(backend)),
+    }
+}
+
+pub async fn store(
+    sk: &str,
+    name: &str,
+    input: &[u8],
+    force: bool,
+    metadata: Option<Vec<u8>>,
+) -> Result<(), StorageBackendError> {
+    storage_backend()
+        .await?
+        .store(sk, name, input, force, metadata)
+        .await
+}
+
+pub async fn retrieve(
+    sk: &str,
+    name: &str,
+    alt_names: Vec<&String>,
+) -> Result<(Vec<u8>, Option<Vec<u8>>), StorageBackendError> {
+    storage_backend().await?.retrieve(sk, name, alt_names).await
+}
+
+pub async fn remove(sk: &str, name: &str) -> Result<(), StorageBackendError> {
+    storage_backend().await?.remove(sk, name).await
+}
@@ -6,13 +6,15 @@ use core::fmt::Display;
 use rgb::{RgbWallet, TerminalPath};
 use std::{
     cmp::Ordering,
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
     str::FromStr,
 };
 
 use rgbstd::containers::{Bindle, Transfer};
 use serde::{Deserialize, Serialize};
 
+use crate::rgb::constants::RGB_DEFAULT_FETCH_LIMIT;
+
 pub type RgbAccountV0 = RgbAccount;
 pub type RgbTransferV0 = RgbTransfer;
 pub type RgbTransfersV0 = RgbTransfers;
@@ -307,6 +309,61 @@ pub struct RgbAccountV1 {
     pub invoices: Vec<String>,
 }
 
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Default)]
+pub struct RgbWatchers {
+    pub watchers: BTreeMap<String, RgbWatcherSettings>,
+}
+
+/// Outpoints provisioned for RGB usage, the BTC wallet of the account never
+/// spends them
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Default)]
+pub struct RgbReservedUtxos {
+    pub utxos: BTreeSet<String>,
+}
+
+/// Outpoints locked by the user, the BTC wallet of the account doesn't spend
+/// them until they are unlocked
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Default)]
+pub struct RgbLockedUtxos {
+    pub utxos: BTreeSet<String>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Default)]
+pub struct RgbWatcherSettings {
+    /// Colorable UTXO pools (by derivation index)
+    pub pools: BTreeMap<u32, RgbUtxoPool>,
+    /// Number of consecutive unused addresses before stop scanning
+    pub gap_limit: Option<u32>,
+    /// Block height of the first wallet transaction
+    pub birthday: Option<u32>,
+}
+
+impl RgbWatcherSettings {
+    pub fn gap_limit(&self) -> u32 {
+        self.gap_limit.unwrap_or(RGB_DEFAULT_FETCH_LIMIT)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, Default, Display)]
+#[display("{min_free}x{amount}")]
+pub struct RgbUtxoPool {
+    /// Minimum number of free (uncolored) UTXOs
+    pub min_free: u32,
+    /// Amount (in sats) of each new UTXO
+    pub amount: u64,
+}
+
+impl RgbUtxoPool {
+    pub fn new(min_free: u32, amount: u64) -> Self {
+        Self { min_free, amount }
+    }
+
+    /// Number of UTXOs required to refill the pool
+    pub fn deficit(&self, free: usize) -> u32 {
+        self.min_free.saturating_sub(free as u32)
+    }
+}
+
 #[derive(
     Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize, Default, Display,
 )]
@@ -397,6 +454,24 @@ impl Default for RgbTransferV1 {
     }
 }
 
+/// Accepted transfer, with the block its witness transaction was mined in
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RgbAnchor {
+    pub contract_id: String,
+    pub transfer: RgbTransferV1,
+    pub height: u32,
+    pub block_hash: String,
+}
+
+/// Anchors watched for chain reorganizations, by consignment ID
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Default)]
+pub struct RgbAnchors {
+    pub anchors: BTreeMap<String, RgbAnchor>,
+    /// Anchors whose witness transaction left the chain, their state in the
+    /// stock is pending until the witness is mined again
+    pub pending: BTreeMap<String, RgbAnchor>,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct RgbExtractTransfer {
     pub consig_id: String,
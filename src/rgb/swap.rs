@@ -33,8 +33,9 @@ use crate::{
         constants::LIB_NAME_BITMASK,
         crdt::{LocalRgbAuctions, LocalRgbOfferBid, LocalRgbOffers},
         fs::{
-            retrieve_auctions_offers, retrieve_public_offers, retrieve_swap_offer_bid,
-            store_auction_offers, store_public_offers, store_swap_bids, RgbPersistenceError,
+            fork_auctions_offers, retrieve_auctions_offers, retrieve_public_offers,
+            retrieve_swap_offer_bid, store_auction_bidders, store_auction_offers,
+            store_public_offers, store_swap_bids, RgbPersistenceError,
         },
     },
     structs::PsbtFeeRequest,
@@ -1058,8 +1059,13 @@ pub async fn publish_swap_bid(
     Ok(())
 }
 
+/// Publish the offers of an auction, signed by its seller
+///
+/// Only the seller and the `bidders` it allows can write to the auction.
 pub async fn publish_auction_offers(
+    sk: &str,
     strategy: RgbAuctionStrategy,
+    bidders: Vec<String>,
     new_offers: Vec<RgbOfferSwap>,
 ) -> Result<(), RgbOfferErrors> {
     let RgbOfferSwap { bundle_id, .. } = new_offers[0].clone();
@@ -1069,7 +1075,7 @@ pub async fn publish_auction_offers(
     let LocalRgbAuctions {
         mut rgb_offers,
         version,
-    } = retrieve_auctions_offers(&bundle_id, &file_name)
+    } = fork_auctions_offers(sk, &bundle_id, &file_name)
         .await
         .map_err(RgbOfferErrors::IO)?;
 
@@ -1082,7 +1088,10 @@ pub async fn publish_auction_offers(
     reconcile(&mut current_version, rgb_offers.clone())
         .map_err(|op| RgbOfferErrors::AutoMerge(op.to_string()))?;
 
-    store_auction_offers(&bundle_id, &file_name, current_version.save())
+    store_auction_offers(sk, &bundle_id, &file_name, current_version.save())
+        .await
+        .map_err(RgbOfferErrors::IO)?;
+    store_auction_bidders(sk, &bundle_id, &bidders)
         .await
         .map_err(RgbOfferErrors::IO)?;
 
@@ -1090,7 +1099,8 @@ pub async fn publish_auction_offers(
     Ok(())
 }
 
-pub async fn publish_auction_bid(new_bid: RgbBidSwap) -> Result<(), RgbOfferErrors> {
+/// Publish a bid to an auction, signed by the bidder
+pub async fn publish_auction_bid(sk: &str, new_bid: RgbBidSwap) -> Result<(), RgbOfferErrors> {
     let RgbBidSwap { offer_id, .. } = new_bid.clone();
     let RgbOfferSwap { bundle_id, .. } = get_public_offer(offer_id.clone()).await?;
     let bundle_id = bundle_id.unwrap_or_default();
@@ -1099,7 +1109,7 @@ pub async fn publish_auction_bid(new_bid: RgbBidSwap) -> Result<(), RgbOfferErro
     let LocalRgbAuctions {
         mut rgb_offers,
         version,
-    } = retrieve_auctions_offers(&bundle_id, &file_name)
+    } = fork_auctions_offers(sk, &bundle_id, &file_name)
         .await
         .map_err(RgbOfferErrors::IO)?;
 
@@ -1110,7 +1120,7 @@ pub async fn publish_auction_bid(new_bid: RgbBidSwap) -> Result<(), RgbOfferErro
     reconcile(&mut local_copy, rgb_offers)
         .map_err(|op| RgbOfferErrors::AutoMerge(op.to_string()))?;
 
-    store_auction_offers(&bundle_id, &file_name, local_copy.save())
+    store_auction_offers(sk, &bundle_id, &file_name, local_copy.save())
         .await
         .map_err(RgbOfferErrors::IO)?;
 
@@ -0,0 +1,553 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+use automerge::{AutoCommit, Change, ChangeHash, ObjId, ObjType, ReadDoc, Value, ROOT};
+use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile};
+use nostr_sdk::prelude::{
+    nip04, Client, Event, EventBuilder, Filter, FromSkStr, Keys, Kind, Tag, Timestamp,
+};
+use postcard::to_allocvec;
+use rgbstd::stl::LIB_ID_RGB;
+
+use crate::{
+    constants::storage_keys::{ASSETS_BIDS, ASSETS_OFFERS, ASSETS_TRANSFERS, ASSETS_WALLETS},
+    rgb::{
+        constants::{RGB_ACCOUNT_VERSION, RGB_STRICT_TYPE_VERSION, RGB_TRANSFER_VERSION},
+        crdt::{init_document, RawRgbAccount, RawRgbBids, RawRgbOffers, RawRgbTransfers},
+        migration::{chain, PersistedModel},
+        storage::{retrieve, store},
+        structs::{RgbAccountV1, RgbTransfersV1},
+        swap::{RgbBids, RgbOffers},
+    },
+    structs::{RgbSyncConflict, RgbSyncDocument, RgbSyncRequest, RgbSyncResponse},
+};
+
+#[derive(Debug, Clone, Eq, PartialEq, Display, From, Error)]
+#[display(doc_comments)]
+pub enum SyncError {
+    /// Model '{0}' is not synced.
+    UnknownModel(String),
+    /// Head '{0}' is not a change hash.
+    WrongHead(String),
+    /// Change cannot be decoded. {0}
+    WrongChange(String),
+    /// Document of {0} causes error. {1}
+    Document(PersistedModel, String),
+    /// File '{0}' retrieve causes error. {1}
+    Retrieve(String, String),
+    /// File '{0}' write causes error. {1}
+    Write(String, String),
+    /// Nostr event causes error. {0}
+    Nostr(String),
+}
+
+/// Kind of the nostr events with the changes of a document, sent by a device to the other ones
+pub const SYNC_EVENT_KIND: u64 = 7078;
+
+/// Time to wait for the stored events of the relays
+const SYNC_RELAY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Model stored next to an automerge document, see `rgb::carbonado::store_transfers`
+pub trait SyncedModel: Reconcile + Hydrate + Default {
+    const MODEL: PersistedModel;
+
+    /// Decode the stored model, in any version of its chain
+    fn decode(data: &[u8], metadata: Option<&[u8]>) -> Result<Self, String>;
+
+    fn encode(self) -> Result<Vec<u8>, String>;
+
+    fn version() -> Vec<u8>;
+}
+
+impl SyncedModel for RawRgbAccount {
+    const MODEL: PersistedModel = PersistedModel::Account;
+
+    fn decode(data: &[u8], metadata: Option<&[u8]>) -> Result<Self, String> {
+        let rgb_account: RgbAccountV1 = chain(Self::MODEL)
+            .decode(data, metadata)
+            .map_err(|op| op.to_string())?;
+        Ok(RawRgbAccount::from(rgb_account))
+    }
+
+    fn encode(self) -> Result<Vec<u8>, String> {
+        to_allocvec(&RgbAccountV1::from(self)).map_err(|op| op.to_string())
+    }
+
+    fn version() -> Vec<u8> {
+        RGB_ACCOUNT_VERSION.to_vec()
+    }
+}
+
+impl SyncedModel for RawRgbTransfers {
+    const MODEL: PersistedModel = PersistedModel::Transfers;
+
+    fn decode(data: &[u8], metadata: Option<&[u8]>) -> Result<Self, String> {
+        let rgb_transfers: RgbTransfersV1 = chain(Self::MODEL)
+            .decode(data, metadata)
+            .map_err(|op| op.to_string())?;
+        Ok(RawRgbTransfers::from(rgb_transfers))
+    }
+
+    fn encode(self) -> Result<Vec<u8>, String> {
+        let rgb_transfers = RgbTransfersV1::try_from(self).map_err(|op| op.to_string())?;
+        to_allocvec(&rgb_transfers).map_err(|op| op.to_string())
+    }
+
+    fn version() -> Vec<u8> {
+        RGB_TRANSFER_VERSION.to_vec()
+    }
+}
+
+impl SyncedModel for RawRgbOffers {
+    const MODEL: PersistedModel = PersistedModel::Offers;
+
+    fn decode(data: &[u8], metadata: Option<&[u8]>) -> Result<Self, String> {
+        let rgb_offers: RgbOffers = chain(Self::MODEL)
+            .decode(data, metadata)
+            .map_err(|op| op.to_string())?;
+        Ok(RawRgbOffers::from(rgb_offers))
+    }
+
+    fn encode(self) -> Result<Vec<u8>, String> {
+        let rgb_offers = RgbOffers::try_from(self).map_err(|op| op.to_string())?;
+        to_allocvec(&rgb_offers).map_err(|op| op.to_string())
+    }
+
+    fn version() -> Vec<u8> {
+        RGB_STRICT_TYPE_VERSION.to_vec()
+    }
+}
+
+impl SyncedModel for RawRgbBids {
+    const MODEL: PersistedModel = PersistedModel::Bids;
+
+    fn decode(data: &[u8], metadata: Option<&[u8]>) -> Result<Self, String> {
+        let rgb_bids: RgbBids = chain(Self::MODEL)
+            .decode(data, metadata)
+            .map_err(|op| op.to_string())?;
+        Ok(RawRgbBids::from(rgb_bids))
+    }
+
+    fn encode(self) -> Result<Vec<u8>, String> {
+        to_allocvec(&RgbBids::from(self)).map_err(|op| op.to_string())
+    }
+
+    fn version() -> Vec<u8> {
+        RGB_STRICT_TYPE_VERSION.to_vec()
+    }
+}
+
+/// Files of an account synced between devices, by model
+pub const SYNC_FILES: [(PersistedModel, &str); 4] = [
+    (PersistedModel::Account, ASSETS_WALLETS),
+    (PersistedModel::Transfers, ASSETS_TRANSFERS),
+    (PersistedModel::Offers, ASSETS_OFFERS),
+    (PersistedModel::Bids, ASSETS_BIDS),
+];
+
+pub fn heads(doc: &mut AutoCommit) -> Vec<String> {
+    doc.get_heads()
+        .into_iter()
+        .map(|hash| hex::encode(hash.0))
+        .collect()
+}
+
+/// Changes of a document which are not ancestors of `heads`, in hex
+pub fn changes_since(doc: &mut AutoCommit, heads: &[String]) -> Result<Vec<String>, SyncError> {
+    let mut known = vec![];
+    for head in heads {
+        let hash = hex::decode(head)
+            .ok()
+            .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+            .ok_or(SyncError::WrongHead(head.clone()))?;
+        known.push(ChangeHash(hash));
+    }
+
+    Ok(doc
+        .get_changes(&known)
+        .into_iter()
+        .map(|change| hex::encode(change.raw_bytes()))
+        .collect())
+}
+
+/// Apply the changes of another device, returns the number of new changes
+pub fn apply_changes(doc: &mut AutoCommit, changes: &[String]) -> Result<usize, SyncError> {
+    let mut new_changes = vec![];
+    for change in changes {
+        let bytes = hex::decode(change).map_err(|op| SyncError::WrongChange(op.to_string()))?;
+        let change =
+            Change::from_bytes(bytes).map_err(|op| SyncError::WrongChange(op.to_string()))?;
+        if doc.get_change_by_hash(&change.hash()).is_none() {
+            new_changes.push(change);
+        }
+    }
+
+    let applied = new_changes.len();
+    doc.apply_changes(new_changes)
+        .map_err(|op| SyncError::WrongChange(op.to_string()))?;
+    Ok(applied)
+}
+
+fn value_name(value: &Value) -> String {
+    match value {
+        Value::Scalar(scalar) => scalar.to_string(),
+        Value::Object(obj_type) => format!("{obj_type:?}").to_lowercase(),
+    }
+}
+
+/// Fields of the maps of a document written by two devices at the same time
+fn field_conflicts(
+    model: PersistedModel,
+    doc: &AutoCommit,
+    obj: &ObjId,
+    path: &str,
+    conflicts: &mut Vec<RgbSyncConflict>,
+) {
+    for key in doc.keys(obj) {
+        let values = doc.get_all(obj, key.as_str()).unwrap_or_default();
+        let path = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{path}/{key}")
+        };
+
+        if values.len() > 1 {
+            conflicts.push(RgbSyncConflict {
+                model: model.to_string(),
+                path,
+                values: values.iter().map(|(value, _)| value_name(value)).collect(),
+            });
+        } else if let Some((Value::Object(ObjType::Map), child)) = values.into_iter().next() {
+            field_conflicts(model, doc, &child, &path, conflicts);
+        }
+    }
+}
+
+/// UTXOs registered on more than one terminal, e.g. by two devices
+pub fn utxo_conflicts(rgb_account: &RawRgbAccount) -> Vec<RgbSyncConflict> {
+    let mut terminals: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+    for (name, wallet) in &rgb_account.wallets {
+        for utxo in &wallet.utxos {
+            terminals
+                .entry(&utxo.outpoint)
+                .or_default()
+                .insert(format!("{name}/{}", utxo.terminal));
+        }
+    }
+
+    terminals
+        .into_iter()
+        .filter(|(_, terminals)| terminals.len() > 1)
+        .map(|(outpoint, terminals)| RgbSyncConflict {
+            model: PersistedModel::Account.to_string(),
+            path: format!("utxos/{outpoint}"),
+            values: terminals.into_iter().collect(),
+        })
+        .collect()
+}
+
+/// Conflicts of a document, the fields written concurrently and the UTXOs of the account
+pub fn conflicts(
+    model: PersistedModel,
+    doc: &AutoCommit,
+) -> Result<Vec<RgbSyncConflict>, SyncError> {
+    let mut conflicts = vec![];
+    field_conflicts(model, doc, &ROOT, "", &mut conflicts);
+
+    if model == PersistedModel::Account {
+        let rgb_account: RawRgbAccount =
+            hydrate(doc).map_err(|op| SyncError::Document(model, op.to_string()))?;
+        conflicts.extend(utxo_conflicts(&rgb_account));
+    }
+    Ok(conflicts)
+}
+
+/// Stored document of a model, see [`load_document`]
+struct StoredDocument {
+    doc: AutoCommit,
+    /// The document was reconciled from the model, and must be stored
+    seeded: bool,
+    /// The stored model is behind the document, e.g. after a sync which
+    /// failed to write it, and must be written again
+    outdated: bool,
+}
+
+/// Load the stored document of a model
+///
+/// Models stored before their document are reconciled into a new document.
+/// The model is retrieved first, so both writes of the sync are conditional
+/// on the versions read here.
+async fn load_document<T: SyncedModel>(
+    sk: &str,
+    file: &str,
+    hashed_name: &str,
+) -> Result<StoredDocument, SyncError> {
+    let (data, metadata) = retrieve(sk, &format!("{hashed_name}.c15"), vec![])
+        .await
+        .map_err(|op| SyncError::Retrieve(file.to_string(), op.to_string()))?;
+    let model = match data.is_empty() {
+        true => None,
+        false => Some(
+            T::decode(&data, metadata.as_deref())
+                .map_err(|op| SyncError::Document(T::MODEL, op))?,
+        ),
+    };
+
+    let (bytes, _) = retrieve(sk, &format!("{hashed_name}-doc.c15"), vec![])
+        .await
+        .map_err(|op| SyncError::Retrieve(file.to_string(), op.to_string()))?;
+    if !bytes.is_empty() {
+        let mut doc =
+            AutoCommit::load(&bytes).map_err(|op| SyncError::Document(T::MODEL, op.to_string()))?;
+        let outdated = match model {
+            Some(model) => {
+                // the model has every record of the document when reconciling it changes nothing
+                let mut fork = doc.fork();
+                reconcile(&mut fork, model)
+                    .map_err(|op| SyncError::Document(T::MODEL, op.to_string()))?;
+                fork.get_heads() != doc.get_heads()
+            }
+            None => true,
+        };
+        return Ok(StoredDocument {
+            doc,
+            seeded: false,
+            outdated,
+        });
+    }
+
+    let mut doc =
+        init_document::<T>().map_err(|op| SyncError::Document(T::MODEL, op.to_string()))?;
+    let seeded = model.is_some();
+    if let Some(model) = model {
+        reconcile(&mut doc, model).map_err(|op| SyncError::Document(T::MODEL, op.to_string()))?;
+    }
+    Ok(StoredDocument {
+        doc,
+        seeded,
+        outdated: false,
+    })
+}
+
+async fn sync_document<T: SyncedModel>(
+    sk: &str,
+    file: &str,
+    device: RgbSyncDocument,
+) -> Result<(RgbSyncDocument, usize, Vec<RgbSyncConflict>), SyncError> {
+    let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{file}").as_bytes())
+        .to_hex()
+        .to_lowercase();
+
+    let StoredDocument {
+        mut doc,
+        seeded,
+        outdated,
+    } = load_document::<T>(sk, file, &hashed_name).await?;
+    let missing = changes_since(&mut doc, &device.heads)?;
+    let applied = apply_changes(&mut doc, &device.changes)?;
+
+    // The document is written first: a model left behind it is written by the next sync
+    if applied > 0 || seeded {
+        store(
+            sk,
+            &format!("{hashed_name}-doc.c15"),
+            &doc.save(),
+            false,
+            Some(RGB_STRICT_TYPE_VERSION.to_vec()),
+        )
+        .await
+        .map_err(|op| SyncError::Write(file.to_string(), op.to_string()))?;
+    }
+
+    if applied > 0 || outdated {
+        let value: T = hydrate(&doc).map_err(|op| SyncError::Document(T::MODEL, op.to_string()))?;
+        let data = value
+            .encode()
+            .map_err(|op| SyncError::Write(file.to_string(), op))?;
+        store(
+            sk,
+            &format!("{hashed_name}.c15"),
+            &data,
+            false,
+            Some(T::version()),
+        )
+        .await
+        .map_err(|op| SyncError::Write(file.to_string(), op.to_string()))?;
+    }
+
+    let conflicts = conflicts(T::MODEL, &doc)?;
+    let document = RgbSyncDocument {
+        model: T::MODEL.to_string(),
+        heads: heads(&mut doc),
+        changes: missing,
+    };
+    Ok((document, applied, conflicts))
+}
+
+/// Exchange the changes of the documents of an account with a device
+///
+/// The device sends the heads of its documents with its new changes, and
+/// receives the changes it doesn't have yet. Documents missing in the
+/// request are sent in full.
+pub async fn sync_account_documents(
+    sk: &str,
+    request: RgbSyncRequest,
+) -> Result<RgbSyncResponse, SyncError> {
+    let mut devices: BTreeMap<String, RgbSyncDocument> = BTreeMap::new();
+    for document in request.documents {
+        if !SYNC_FILES
+            .iter()
+            .any(|(model, _)| model.to_string() == document.model)
+        {
+            return Err(SyncError::UnknownModel(document.model));
+        }
+        devices.insert(document.model.clone(), document);
+    }
+
+    let mut response = RgbSyncResponse::default();
+    for (model, file) in SYNC_FILES {
+        let device = devices.remove(&model.to_string()).unwrap_or_default();
+        let (document, applied, conflicts) = match model {
+            PersistedModel::Account => sync_document::<RawRgbAccount>(sk, file, device).await?,
+            PersistedModel::Transfers => sync_document::<RawRgbTransfers>(sk, file, device).await?,
+            PersistedModel::Offers => sync_document::<RawRgbOffers>(sk, file, device).await?,
+            PersistedModel::Bids => sync_document::<RawRgbBids>(sk, file, device).await?,
+            model => return Err(SyncError::UnknownModel(model.to_string())),
+        };
+
+        response.documents.push(document);
+        response.applied += applied;
+        response.conflicts.extend(conflicts);
+    }
+    Ok(response)
+}
+
+/// Nostr event with the changes of a document, encrypted for the devices of the owner
+pub fn sync_event(sk: &str, document: &RgbSyncDocument) -> Result<Event, SyncError> {
+    let keys = Keys::from_sk_str(sk).map_err(|op| SyncError::Nostr(op.to_string()))?;
+    let secret_key = keys
+        .secret_key()
+        .map_err(|op| SyncError::Nostr(op.to_string()))?;
+    let content = serde_json::to_string(document).map_err(|op| SyncError::Nostr(op.to_string()))?;
+    let content = nip04::encrypt(&secret_key, &keys.public_key(), content)
+        .map_err(|op| SyncError::Nostr(op.to_string()))?;
+
+    EventBuilder::new(
+        Kind::Custom(SYNC_EVENT_KIND),
+        content,
+        &[Tag::Hashtag(document.model.clone())],
+    )
+    .to_event(&keys)
+    .map_err(|op| SyncError::Nostr(op.to_string()))
+}
+
+/// Changes of a document sent by another device of the owner, see [`sync_event`]
+pub fn read_sync_event(sk: &str, event: &Event) -> Result<RgbSyncDocument, SyncError> {
+    let keys = Keys::from_sk_str(sk).map_err(|op| SyncError::Nostr(op.to_string()))?;
+    event
+        .verify()
+        .map_err(|op| SyncError::Nostr(op.to_string()))?;
+    if event.kind != Kind::Custom(SYNC_EVENT_KIND) || event.pubkey != keys.public_key() {
+        return Err(SyncError::Nostr(format!(
+            "event {} is not a sync event of the owner",
+            event.id
+        )));
+    }
+
+    let secret_key = keys
+        .secret_key()
+        .map_err(|op| SyncError::Nostr(op.to_string()))?;
+    let content = nip04::decrypt(&secret_key, &keys.public_key(), &event.content)
+        .map_err(|op| SyncError::Nostr(op.to_string()))?;
+    serde_json::from_str(&content).map_err(|op| SyncError::Nostr(op.to_string()))
+}
+
+async fn relay_client(keys: &Keys, relays: &[String]) -> Result<Client, SyncError> {
+    let client = Client::new(keys);
+    for relay in relays {
+        #[cfg(not(target_arch = "wasm32"))]
+        let added = client.add_relay(relay.as_str(), None).await;
+        #[cfg(target_arch = "wasm32")]
+        let added = client.add_relay(relay.as_str()).await;
+        added.map_err(|op| SyncError::Nostr(op.to_string()))?;
+    }
+    client.connect().await;
+    Ok(client)
+}
+
+/// Publish the changes of a document to the relays, for the other devices of the owner
+///
+/// Returns the id of the published event.
+pub async fn publish_sync_event(
+    sk: &str,
+    relays: &[String],
+    document: &RgbSyncDocument,
+) -> Result<String, SyncError> {
+    let keys = Keys::from_sk_str(sk).map_err(|op| SyncError::Nostr(op.to_string()))?;
+    let event = sync_event(sk, document)?;
+
+    let client = relay_client(&keys, relays).await?;
+    let sent = client.send_event(event).await;
+    client.disconnect().await.ok();
+
+    let event_id = sent.map_err(|op| SyncError::Nostr(op.to_string()))?;
+    Ok(event_id.to_hex())
+}
+
+/// Changes of the documents published to the relays by the devices of the
+/// owner since `since`, a unix timestamp, merged by model
+///
+/// Events which cannot be read are skipped, relays may store anything.
+pub async fn fetch_sync_events(
+    sk: &str,
+    relays: &[String],
+    since: u64,
+) -> Result<Vec<RgbSyncDocument>, SyncError> {
+    let keys = Keys::from_sk_str(sk).map_err(|op| SyncError::Nostr(op.to_string()))?;
+    let filter = Filter::new()
+        .author(keys.public_key())
+        .kind(Kind::Custom(SYNC_EVENT_KIND))
+        .since(Timestamp::from(since));
+
+    let client = relay_client(&keys, relays).await?;
+    let events = client
+        .get_events_of(vec![filter], Some(SYNC_RELAY_TIMEOUT))
+        .await;
+    client.disconnect().await.ok();
+
+    let mut events = events.map_err(|op| SyncError::Nostr(op.to_string()))?;
+    events.sort_by_key(|event| event.created_at);
+
+    let mut documents: BTreeMap<String, RgbSyncDocument> = BTreeMap::new();
+    for event in events {
+        let Ok(received) = read_sync_event(sk, &event) else {
+            continue;
+        };
+
+        let document = documents
+            .entry(received.model.clone())
+            .or_insert_with(|| RgbSyncDocument {
+                model: received.model.clone(),
+                ..Default::default()
+            });
+        document.heads = received.heads;
+        for change in received.changes {
+            if !document.changes.contains(&change) {
+                document.changes.push(change);
+            }
+        }
+    }
+    Ok(documents.into_values().collect())
+}
+
+/// Apply the changes published to the relays since `since` to the documents of an account
+pub async fn sync_account_from_relays(
+    sk: &str,
+    relays: &[String],
+    since: u64,
+) -> Result<RgbSyncResponse, SyncError> {
+    let documents = fetch_sync_events(sk, relays, since).await?;
+    sync_account_documents(sk, RgbSyncRequest { documents }).await
+}
@@ -1,5 +1,6 @@
 use std::{
     collections::{BTreeMap, HashMap},
+    ops::Range,
     str::FromStr,
 };
 
@@ -11,7 +12,11 @@ use bitcoin_scripts::{
     address::{AddressCompat, AddressNetwork},
     PubkeyScript,
 };
-use rgb::{DeriveInfo, MiningStatus, Resolver, RgbDescr, RgbWallet, SpkDescriptor, Tapret, Utxo};
+use bp::Outpoint;
+use rgb::{
+    DeriveInfo, MiningStatus, Resolver, RgbDescr, RgbWallet, SpkDescriptor, Tapret, TerminalPath,
+    Utxo,
+};
 use rgbstd::{
     contract::ContractId,
     persistence::{Inventory, Stash, Stock},
@@ -21,9 +26,10 @@ use strict_encoding::tn;
 
 use crate::{
     debug,
-    rgb::{resolvers::ResolveSpent, structs::AddressTerminal},
+    rgb::{contract::export_boilerplate, resolvers::ResolveSpent, structs::AddressTerminal},
     structs::{
-        AllocationDetail, AllocationValue, TxStatus, UDAPosition, UtxoSpentStatus, WatcherDetail,
+        AllocationDetail, AllocationValue, ContractBalance, TxStatus, UDAPosition, UtxoSpentStatus,
+        WatcherDetail,
     },
 };
 
@@ -143,6 +149,85 @@ pub fn next_address(
     Ok(addresses[addresses.len() - 1].clone())
 }
 
+pub fn next_addresses(
+    iface_index: u32,
+    count: u32,
+    wallet: RgbWallet,
+    network: AddressNetwork,
+) -> Result<Vec<AddressTerminal>, anyhow::Error> {
+    let max = wallet
+        .utxos
+        .into_iter()
+        .filter(|utxo| utxo.derivation.terminal.app == iface_index)
+        .map(|utxo| utxo.derivation.terminal.index)
+        .max();
+
+    let next_index = match max {
+        Some(max) => max + 1,
+        None => 0,
+    };
+
+    let scripts = wallet
+        .descr
+        .derive(iface_index, next_index..next_index + count);
+    let addresses: Vec<AddressTerminal> = scripts
+        .into_iter()
+        .map(|(d, sb)| {
+            let sc = Script::from_str(&sb.to_hex_string()).expect("invalid script data");
+            let address =
+                AddressCompat::from_script(&sc.into(), network).expect("invalid address data");
+            let terminal = d.terminal;
+            AddressTerminal { address, terminal }
+        })
+        .collect();
+
+    debug!(format!("RGB Addresses: {addresses:?}"));
+    Ok(addresses)
+}
+
+pub fn save_new_utxo(
+    outpoint: Outpoint,
+    amount: u64,
+    terminal: TerminalPath,
+    wallet: &mut RgbWallet,
+) {
+    wallet.utxos.insert(Utxo {
+        amount,
+        outpoint,
+        status: MiningStatus::Mempool,
+        derivation: DeriveInfo::with(terminal.app, terminal.index, None),
+    });
+}
+
+pub fn list_free_utxos<T>(
+    iface_index: u32,
+    wallet: &mut RgbWallet,
+    stock: &mut Stock,
+    resolver: &mut T,
+) -> Result<Vec<Utxo>, anyhow::Error>
+where
+    T: ResolveSpent + Resolver,
+{
+    let mut colored = vec![];
+    for index in [20, 21] {
+        for detail in list_allocations(wallet, stock, index, resolver)? {
+            colored.extend(
+                detail
+                    .allocations
+                    .into_iter()
+                    .filter(|allocation| allocation.is_mine && !allocation.is_spent)
+                    .map(|allocation| allocation.utxo),
+            );
+        }
+    }
+
+    let utxos = next_utxos(iface_index, wallet.clone(), resolver)?
+        .into_iter()
+        .filter(|utxo| !colored.contains(&utxo.outpoint.to_string()))
+        .collect();
+    Ok(utxos)
+}
+
 pub fn next_utxo(
     iface_index: u32,
     wallet: RgbWallet,
@@ -166,7 +251,7 @@ pub fn next_utxo(
         let index = utxo.outpoint.vout.into_u32();
         let utxo_status = resolver
             .resolve_spent_status(txid, index.into(), true)
-            .expect("unavailable service");
+            .map_err(|op| anyhow!(op))?;
         if !utxo_status.is_spent && !utxo_status.clone().is_invalid_state() {
             match utxo_status.block_height {
                 TxStatus::Mempool => {
@@ -210,7 +295,7 @@ pub fn next_utxos(
         let index = utxo.outpoint.vout.into_u32();
         let utxo_status = resolver
             .resolve_spent_status(txid, index.into(), true)
-            .expect("unavailable service");
+            .map_err(|op| anyhow!(op))?;
         if !utxo_status.is_spent && !utxo_status.is_invalid_state() && !next_utxo.contains(&utxo) {
             next_utxo.push(utxo);
         }
@@ -218,14 +303,18 @@ pub fn next_utxos(
     Ok(next_utxo)
 }
 
-pub fn sync_wallet(iface_index: u32, wallet: &mut RgbWallet, resolver: &mut impl Resolver) {
+pub fn sync_wallet(
+    iface_index: u32,
+    wallet: &mut RgbWallet,
+    resolver: &mut impl Resolver,
+) -> Result<(), anyhow::Error> {
     let step = 20;
     let index = 0;
 
     let new_scripts = wallet.descr.derive(iface_index, index..step);
     let new_utxos = resolver
         .resolve_utxo(new_scripts)
-        .expect("service unavalible");
+        .map_err(|op| anyhow!(op))?;
 
     for mut new_utxo in new_utxos {
         if let Some(current_utxo) = wallet
@@ -244,6 +333,53 @@ pub fn sync_wallet(iface_index: u32, wallet: &mut RgbWallet, resolver: &mut impl
             wallet.utxos.insert(new_utxo);
         }
     }
+
+    Ok(())
+}
+
+/// Scan a derivation range and register the UTXOs found
+///
+/// Returns the highest used index in the range (if any).
+pub fn scan_wallet(
+    iface_index: u32,
+    range: Range<u32>,
+    wallet: &mut RgbWallet,
+    resolver: &mut impl Resolver,
+    birthday: Option<u32>,
+) -> Result<Option<u32>, anyhow::Error> {
+    let scripts = wallet.descr.derive(iface_index, range.clone());
+    let new_utxos = resolver.resolve_utxo(scripts).map_err(|op| anyhow!(op))?;
+
+    let mut last_used = None;
+    for new_utxo in new_utxos {
+        let terminal = new_utxo.derivation.terminal;
+        if terminal.app != iface_index || !range.contains(&terminal.index) {
+            continue;
+        }
+
+        last_used = last_used.max(Some(terminal.index));
+        if let (Some(birthday), MiningStatus::Blockchain(height)) = (birthday, &new_utxo.status) {
+            if *height < birthday {
+                continue;
+            }
+        }
+
+        if let Some(current_utxo) = wallet
+            .utxos
+            .clone()
+            .into_iter()
+            .find(|u| u.outpoint == new_utxo.outpoint)
+        {
+            if current_utxo.status == MiningStatus::Mempool {
+                wallet.utxos.remove(&current_utxo);
+                wallet.utxos.insert(new_utxo);
+            }
+        } else {
+            wallet.utxos.insert(new_utxo);
+        }
+    }
+
+    Ok(last_used)
 }
 
 pub fn register_address<T>(
@@ -262,8 +398,8 @@ where
         step = limit;
     }
 
-    let sc = AddressCompat::from_str(address).expect("invalid address");
-    let script = ScriptBuf::from_hex(&sc.script_pubkey().to_hex()).expect("invalid script");
+    let sc = AddressCompat::from_str(address)?;
+    let script = ScriptBuf::from_hex(&sc.script_pubkey().to_hex())?;
 
     let mut scripts: BTreeMap<DeriveInfo, ScriptBuf> = BTreeMap::new();
     for app in asset_indexes {
@@ -276,7 +412,7 @@ where
         let mut scripts = BTreeMap::new();
         scripts.insert(d.clone(), sc);
 
-        let new_utxos = &resolver.resolve_utxo(scripts).expect("service unavalible");
+        let new_utxos = &resolver.resolve_utxo(scripts).map_err(|op| anyhow!(op))?;
         for utxo in new_utxos {
             wallet.utxos.insert(utxo.to_owned());
         }
@@ -297,8 +433,8 @@ pub fn register_utxo<T>(
 where
     T: ResolveTx + Resolver,
 {
-    let outpoint = OutPoint::from_str(utxo).expect("invalid outpoint");
-    let txid = bp::Txid::from_str(&outpoint.txid.to_hex()).expect("invalid txid");
+    let outpoint = OutPoint::from_str(utxo)?;
+    let txid = bp::Txid::from_str(&outpoint.txid.to_hex())?;
 
     let mut utxos = vec![];
     if let Ok(tx) = resolver.resolve_tx(txid) {
@@ -307,8 +443,7 @@ where
             let pub_script = PubkeyScript::from(sc);
             if let Some(address) = AddressCompat::from_script(&pub_script, network) {
                 utxos =
-                    register_address(&address.to_string(), asset_indexes, wallet, resolver, limit)
-                        .expect("invalid utxos");
+                    register_address(&address.to_string(), asset_indexes, wallet, resolver, limit)?;
             }
         }
     }
@@ -330,7 +465,7 @@ where
         _ => "Contract",
     };
 
-    sync_wallet(iface_index, wallet, resolver);
+    sync_wallet(iface_index, wallet, resolver)?;
     let mut details = vec![];
     for contract_id in stock.contract_ids()? {
         let iface = stock.iface_by_name(&tn!(iface_name))?;
@@ -362,6 +497,8 @@ where
                                 ),
                                 is_mine: true,
                                 is_spent,
+                                is_pending: false,
+                                label: None,
                             });
                         } else {
                             owners.push(AllocationDetail {
@@ -370,6 +507,8 @@ where
                                 derivation: default!(),
                                 is_mine: false,
                                 is_spent,
+                                is_pending: false,
+                                label: None,
                             });
                         }
                     }
@@ -401,6 +540,8 @@ where
                                 ),
                                 is_mine: true,
                                 is_spent,
+                                is_pending: false,
+                                label: None,
                             });
                         } else {
                             owners.push(AllocationDetail {
@@ -409,6 +550,8 @@ where
                                 derivation: default!(),
                                 is_mine: false,
                                 is_spent,
+                                is_pending: false,
+                                label: None,
                             });
                         }
                     }
@@ -424,6 +567,85 @@ where
     Ok(details)
 }
 
+pub fn list_balances<T>(
+    wallet: &mut RgbWallet,
+    stock: &mut Stock,
+    iface_index: u32,
+    resolver: &mut T,
+    locked: &[String],
+) -> Result<Vec<ContractBalance>, anyhow::Error>
+where
+    T: ResolveSpent + Resolver,
+{
+    let iface = match iface_index {
+        20 => "RGB20",
+        21 => "RGB21",
+        _ => "Contract",
+    };
+
+    let mut balances = vec![];
+    for detail in list_allocations(wallet, stock, iface_index, resolver)? {
+        let mut balance = ContractBalance {
+            contract_id: detail.contract_id.clone(),
+            iface: iface.to_string(),
+            ..default!()
+        };
+
+        for allocation in detail.allocations.into_iter().filter(|a| a.is_mine) {
+            let outpoint = OutPoint::from_str(&allocation.utxo)?;
+            let utxo_status = resolver
+                .resolve_spent_status(outpoint.txid, outpoint.vout.into(), true)
+                .map_err(|op| anyhow!(op.to_string()))?;
+
+            if utxo_status.clone().is_invalid_state() {
+                continue;
+            }
+
+            let UtxoSpentStatus {
+                is_spent,
+                block_height,
+                spent_height,
+                ..
+            } = utxo_status;
+
+            match (is_spent, block_height, spent_height) {
+                (false, TxStatus::Block(_), _) => {
+                    balance.confirmed.add(&allocation.value);
+                    if locked.contains(&allocation.utxo) {
+                        balance.locked.add(&allocation.value);
+                    } else {
+                        balance.spendable.add(&allocation.value);
+                    }
+                }
+                (false, _, _) => balance.pending_incoming.add(&allocation.value),
+                (true, _, TxStatus::Mempool) => balance.pending_outgoing.add(&allocation.value),
+                _ => {}
+            }
+        }
+
+        let precision = match ContractId::from_str(&detail.contract_id) {
+            Ok(contract_id) if iface_index == 20 => export_boilerplate(contract_id, stock)
+                .map(|boilerplate| boilerplate.precision)
+                .unwrap_or_default(),
+            _ => 0,
+        };
+
+        for amount in [
+            &mut balance.confirmed,
+            &mut balance.pending_incoming,
+            &mut balance.pending_outgoing,
+            &mut balance.spendable,
+            &mut balance.locked,
+        ] {
+            amount.format(precision);
+        }
+
+        balances.push(balance);
+    }
+
+    Ok(balances)
+}
+
 pub fn contract_allocations<T>(
     contract_id: ContractId,
     iface_index: u32,
@@ -443,7 +665,7 @@ where
 
     let mut owners = vec![];
     if let Ok(contract) = stock.contract_iface(contract_id, iface.iface_id()) {
-        sync_wallet(iface_index, wallet, resolver);
+        sync_wallet(iface_index, wallet, resolver)?;
         for owned in &contract.iface.assignments {
             if let Ok(allocations) = contract.fungible(owned.name.clone(), &None) {
                 for allocation in allocations {
@@ -469,6 +691,8 @@ where
                             ),
                             is_mine: true,
                             is_spent,
+                            is_pending: false,
+                            label: None,
                         });
                     } else {
                         owners.push(AllocationDetail {
@@ -477,6 +701,8 @@ where
                             derivation: default!(),
                             is_mine: false,
                             is_spent,
+                            is_pending: false,
+                            label: None,
                         });
                     }
                 }
@@ -506,6 +732,8 @@ where
                             ),
                             is_mine: true,
                             is_spent,
+                            is_pending: false,
+                            label: None,
                         });
                     } else {
                         owners.push(AllocationDetail {
@@ -514,6 +742,8 @@ where
                             derivation: default!(),
                             is_mine: false,
                             is_spent,
+                            is_pending: false,
+                            label: None,
                         });
                     }
                 }
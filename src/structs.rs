@@ -8,12 +8,12 @@ use std::collections::{BTreeMap, HashMap};
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
 pub use bdk::{Balance, BlockTime, TransactionDetails};
-pub use bitcoin::{util::address::Address, Txid};
+pub use bitcoin::{util::address::Address, OutPoint, Txid};
 use rgbstd::interface::rgb21::Allocation as AllocationUDA;
 
 use crate::{
     rgb::{
-        structs::MediaMetadata,
+        structs::{ContractAmount, MediaMetadata},
         swap::{PublicRgbBid, RgbAuctionStrategy, RgbBid, RgbOffer, RgbOfferSwap, RgbSwapStrategy},
     },
     validators::{
@@ -42,6 +42,8 @@ pub struct WalletTransaction {
     pub confirmation_time: Option<BlockTime>,
     pub vsize: usize,
     pub fee_rate: f32,
+    #[serde(default)]
+    pub label: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -496,6 +498,9 @@ pub struct ContractResponse {
     pub genesis: GenesisFormats,
     /// contract metadata (only RGB21/UDA)
     pub meta: Option<ContractMediaDetail>,
+    /// Label (BIP-329)
+    #[serde(default)]
+    pub label: Option<String>,
 }
 
 #[deprecated(
@@ -1079,15 +1084,33 @@ pub struct WatcherRequest {
     #[garde(ascii)]
     #[garde(length(min = 1, max = 32))]
     pub name: String,
-    /// The xpub will be watch
+    /// The xpub or output descriptor will be watch
+    /// (ex. xpub, [fp/86h/1h/0h]xpub/* or tr([fp/86h/1h/0h]xpub/20/*))
     #[garde(ascii)]
-    #[garde(length(min = 1, max = 64))]
+    #[garde(length(min = 1, max = 1024))]
     pub xpub: String,
     /// Force recreate
     #[garde(skip)]
     pub force: bool,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+#[derive(Validate)]
+#[garde(context(RGBContext))]
+pub struct WatchersRequest {
+    /// The watcher name (next accounts are suffixed by position, ex. name-1)
+    #[garde(ascii)]
+    #[garde(length(min = 1, max = 29))]
+    pub name: String,
+    /// The output descriptors (one per account) will be watch
+    #[garde(length(min = 1, max = 100))]
+    pub descriptors: Vec<String>,
+    /// Force recreate
+    #[garde(skip)]
+    pub force: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct WatcherResponse {
@@ -1097,6 +1120,13 @@ pub struct WatcherResponse {
     pub migrate: bool,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchersResponse {
+    /// The watchers created
+    pub watchers: Vec<WatcherResponse>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct WatcherDetailResponse {
@@ -1104,6 +1134,59 @@ pub struct WatcherDetailResponse {
     pub contracts: Vec<WatcherDetail>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BalancesResponse {
+    /// Balances by contract
+    pub contracts: Vec<ContractBalance>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractBalance {
+    /// Contract ID
+    pub contract_id: String,
+    /// Contract Interface
+    pub iface: String,
+    /// Confirmed and unspent allocations
+    pub confirmed: BalanceAmount,
+    /// Received allocations waiting confirmation
+    pub pending_incoming: BalanceAmount,
+    /// Sent allocations waiting confirmation
+    pub pending_outgoing: BalanceAmount,
+    /// Confirmed allocations available to spend
+    pub spendable: BalanceAmount,
+    /// Confirmed allocations locked by coin control
+    pub locked: BalanceAmount,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceAmount {
+    /// Raw value (RGB20) or number of token positions (RGB21)
+    pub value: u64,
+    /// Value formatted with the contract precision (ex. 100.00)
+    pub amount: String,
+    /// Token positions (only RGB21)
+    pub positions: Vec<UDAPosition>,
+}
+
+impl BalanceAmount {
+    pub fn add(&mut self, value: &AllocationValue) {
+        match value {
+            AllocationValue::Value(value) => self.value += value,
+            AllocationValue::UDA(position) => {
+                self.value += 1;
+                self.positions.push(position.clone());
+            }
+        }
+    }
+
+    pub fn format(&mut self, precision: u8) {
+        self.amount = ContractAmount::new(self.value, precision).to_string();
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct WatcherDetail {
@@ -1126,6 +1209,12 @@ pub struct AllocationDetail {
     pub is_mine: bool,
     /// Allocation spent?
     pub is_spent: bool,
+    /// Witness transaction left the chain after a reorganization?
+    #[serde(default)]
+    pub is_pending: bool,
+    /// Label (BIP-329)
+    #[serde(default)]
+    pub label: Option<String>,
 }
 
 #[derive(Eq, Ord, PartialEq, PartialOrd, Serialize, Deserialize, Debug, Clone, Display)]
@@ -1219,6 +1308,210 @@ pub struct WatcherUtxoResponse {
     pub utxos: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+#[derive(Validate)]
+#[garde(context(RGBContext))]
+pub struct RgbProvisionRequest {
+    /// The watcher name
+    #[garde(ascii)]
+    #[garde(length(min = 1, max = 32))]
+    pub name: String,
+    /// Bitcoin Descriptor (used to fund the UTXOs)
+    // TODO: Check secure way to validate xpriv desc
+    #[garde(skip)]
+    pub descriptor: SecretString,
+    /// Bitcoin Change Descriptor
+    #[garde(skip)]
+    pub change_descriptor: SecretString,
+    /// UTXOs to be created (by interface)
+    #[garde(dive)]
+    #[garde(length(min = 1, max = 10))]
+    pub outputs: Vec<RgbProvisionOutput>,
+    /// Fee rate (in sats/vbyte)
+    #[garde(skip)]
+    pub fee_rate: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+#[derive(Validate)]
+#[garde(context(RGBContext))]
+pub struct RgbProvisionOutput {
+    /// The contract interface (RGB20 or RGB21)
+    #[garde(ascii)]
+    #[garde(length(min = 5, max = 5))]
+    pub iface: String,
+    /// Amount (in sats) of each UTXO
+    #[garde(range(min = 546, max = u64::MAX))]
+    pub amount: u64,
+    /// Number of UTXOs
+    #[garde(range(min = 1, max = 100))]
+    pub count: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RgbProvisionResponse {
+    /// The funding transaction id
+    pub txid: Option<String>,
+    /// The new colorable UTXOs
+    pub utxos: Vec<UtxoResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+#[derive(Validate)]
+#[garde(context(RGBContext))]
+pub struct RgbUtxoPoolRequest {
+    /// The watcher name
+    #[garde(ascii)]
+    #[garde(length(min = 1, max = 32))]
+    pub name: String,
+    /// The contract interface (RGB20 or RGB21)
+    #[garde(ascii)]
+    #[garde(length(min = 5, max = 5))]
+    pub iface: String,
+    /// Minimum number of free UTXOs
+    #[garde(range(min = 0, max = 100))]
+    pub min_free: u32,
+    /// Amount (in sats) of each new UTXO
+    #[garde(range(min = 546, max = u64::MAX))]
+    pub amount: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RgbUtxoPoolResponse {
+    /// The watcher name
+    pub name: String,
+    /// Pool settings by interface (ex. RGB20 => 5x1000)
+    pub pools: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+#[derive(Validate)]
+#[garde(context(RGBContext))]
+pub struct RgbTopUpRequest {
+    /// The watcher name
+    #[garde(ascii)]
+    #[garde(length(min = 1, max = 32))]
+    pub name: String,
+    /// Bitcoin Descriptor (used to fund the UTXOs)
+    #[garde(skip)]
+    pub descriptor: SecretString,
+    /// Bitcoin Change Descriptor
+    #[garde(skip)]
+    pub change_descriptor: SecretString,
+    /// Fee rate (in sats/vbyte)
+    #[garde(skip)]
+    pub fee_rate: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+#[derive(Validate)]
+#[garde(context(RGBContext))]
+pub struct RgbRescanRequest {
+    /// The watcher name
+    #[garde(ascii)]
+    #[garde(length(min = 1, max = 32))]
+    pub name: String,
+    /// Number of consecutive unused addresses before stop scanning
+    #[garde(range(min = 1, max = 1000))]
+    pub gap_limit: Option<u32>,
+    /// Block height of the first wallet transaction
+    #[garde(skip)]
+    pub birthday: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RgbRescanProgress {
+    /// The contract interface (RGB20 or RGB21)
+    pub iface: String,
+    /// Number of addresses scanned
+    pub scanned: u32,
+    /// Highest used derivation index
+    pub last_used: Option<u32>,
+    /// Number of UTXOs registered
+    pub utxos: usize,
+    /// Scan finished?
+    pub done: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RgbRescanResponse {
+    /// The watcher name
+    pub name: String,
+    /// Gap limit used
+    pub gap_limit: u32,
+    /// Progress by interface
+    pub progress: Vec<RgbRescanProgress>,
+}
+
+/// BIP-329 label type (`contract` and `consignment` are RGB extensions)
+#[derive(
+    Eq, Ord, PartialEq, PartialOrd, Hash, Serialize, Deserialize, Clone, Copy, Debug, Display,
+)]
+#[serde(rename_all = "lowercase")]
+#[display(lowercase)]
+pub enum LabelType {
+    Tx,
+    Addr,
+    Pubkey,
+    Input,
+    Output,
+    Xpub,
+    Contract,
+    Consignment,
+}
+
+/// BIP-329 label record
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    /// The label type
+    #[serde(rename = "type")]
+    pub ty: LabelType,
+    /// The reference (txid, address, outpoint, contract id, etc.)
+    #[serde(rename = "ref")]
+    pub reference: String,
+    /// The label
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// The key origin (ex. tr([fp/86h/1h/0h]))
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    /// The output can be spent? (only outputs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spendable: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelsRequest {
+    /// Labels to be created or updated
+    pub labels: Vec<Label>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelsResponse {
+    /// Current labels
+    pub labels: Vec<Label>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinControl {
+    /// Outpoints that must be spent
+    pub include: Vec<OutPoint>,
+    /// Outpoints that must not be spent
+    pub exclude: Vec<OutPoint>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SatsInvoice {
@@ -1266,6 +1559,16 @@ pub struct FileMetadata {
     pub metadata: [u8; 8],
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileVersion {
+    /// Blake3 hash of the encoded file
+    pub version: String,
+    /// Unix time the version was replaced
+    pub created_at: i64,
+    pub bytes: u64,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct RgbTransfersResponse {
@@ -1280,6 +1583,8 @@ pub struct RgbTransferDetail {
     pub status: TxStatus,
     #[serde(rename = "type")]
     pub ty: TransferType,
+    #[serde(default)]
+    pub label: Option<String>,
 }
 
 #[derive(
@@ -1299,6 +1604,20 @@ pub enum TxStatus {
     Block(u32),
 }
 
+/// Hits and misses of the resolver cache since the process started
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolverCacheMetrics {
+    pub tx_hits: u64,
+    pub tx_misses: u64,
+    pub status_hits: u64,
+    pub status_misses: u64,
+    pub output_hits: u64,
+    pub output_misses: u64,
+    /// Entries currently cached (transactions, statuses and outputs)
+    pub entries: u64,
+}
+
 #[derive(Eq, Ord, PartialEq, PartialOrd, Serialize, Deserialize, Clone, Debug, Display)]
 #[serde(rename_all = "camelCase")]
 pub enum TransferType {
@@ -1336,6 +1655,129 @@ pub struct BatchRgbTransferItem {
     pub txid: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RgbReorgEvent {
+    pub contract_id: String,
+    pub consig_id: String,
+    pub txid: String,
+    /// Height the witness transaction was mined at before the reorganization
+    pub height: u32,
+    /// Status of the witness transaction in the current chain
+    pub status: TxStatus,
+    /// The consignment still validates against the current chain
+    pub is_valid: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RgbReorgResponse {
+    /// Height of the chain tip
+    pub tip: u32,
+    /// Anchors reorganized since the last check
+    pub reorgs: Vec<RgbReorgEvent>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RgbBackupRequest {
+    /// Password of the backup
+    pub password: SecretString,
+    /// Encrypted descriptors of the bitcoin wallet, kept in the backup
+    pub encrypted_descriptors: Option<SecretString>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RgbBackupResponse {
+    /// Encrypted backup, in hex
+    pub backup: String,
+    pub created_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RgbRestoreRequest {
+    /// Password of the backup
+    pub password: SecretString,
+    /// Encrypted backup, in hex
+    pub backup: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RgbRestoreResponse {
+    /// bitmask-core version which made the backup
+    pub bmc_version: String,
+    pub created_at: i64,
+    /// Number of contracts in the restored stock
+    pub contracts: usize,
+    /// Watchers synchronized after the restore
+    pub watchers: Vec<String>,
+    /// Encrypted descriptors of the bitcoin wallet
+    pub encrypted_descriptors: Option<SecretString>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RgbModelMigration {
+    /// Persisted model, e.g. `account` or `offers`
+    pub model: String,
+    /// Name of the file of the model
+    pub file: String,
+    /// Version of the stored file, empty for unlabeled files
+    pub from: String,
+    pub to: String,
+    /// Upgrades applied from `from` to `to`
+    pub steps: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RgbMigrationResponse {
+    /// Files upgraded, or that would be upgraded in a dry-run
+    pub migrations: Vec<RgbModelMigration>,
+    pub dry_run: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RgbSyncDocument {
+    /// Synced model: `account`, `transfers`, `offers` or `bids`
+    pub model: String,
+    /// Heads of the automerge document, in hex
+    pub heads: Vec<String>,
+    /// Automerge changes, in hex
+    pub changes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RgbSyncRequest {
+    /// Heads of the documents of the device, with its new changes
+    pub documents: Vec<RgbSyncDocument>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RgbSyncResponse {
+    /// Heads of the synced documents, with the changes missing in the device
+    pub documents: Vec<RgbSyncDocument>,
+    /// Changes of the device which were not synced yet
+    pub applied: usize,
+    pub conflicts: Vec<RgbSyncConflict>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RgbSyncConflict {
+    pub model: String,
+    /// Conflicting field, e.g. `bids/{bid_id}/bid/bitcoin_amount` or `utxos/{outpoint}`
+    pub path: String,
+    /// Values written by each device
+    pub values: Vec<String>,
+}
+
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Hash, Serialize, Deserialize, Debug, Display)]
 #[display("{utxo}:{is_spent}")]
 pub struct UtxoSpentStatus {
@@ -1370,6 +1812,11 @@ pub struct RgbAuctionOfferRequest {
 
     #[garde(dive)]
     pub fee: Option<PsbtFeeRequest>,
+
+    /// Nostr keys of the bidders allowed to write to the auction
+    #[garde(skip)]
+    #[serde(default)]
+    pub bidders: Vec<String>,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, Display, Default, Validate)]
@@ -63,6 +63,18 @@ macro_rules! trace {
     };
 }
 
+/// `Send + Sync` on native targets, where bitmaskd handlers hold the backends
+/// and storages across `.await`; the wasm32 HTTP clients are not thread-safe
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSendSync: Send + Sync {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send + Sync + ?Sized> MaybeSendSync for T {}
+
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSendSync {}
+#[cfg(target_arch = "wasm32")]
+impl<T: ?Sized> MaybeSendSync for T {}
+
 #[cfg(target_arch = "wasm32")]
 pub async fn post_json<T: Serialize>(url: &str, body: &T) -> Result<(String, u16)> {
     let client = reqwest::Client::new();
@@ -7,11 +7,14 @@ use wasm_bindgen_futures::{future_to_promise, JsFuture};
 
 use crate::rgb::structs::ContractAmount;
 use crate::structs::{
-    AcceptRequest, FullIssueRequest, FullRgbTransferRequest, ImportRequest, InvoiceRequest,
-    IssueMediaRequest, IssueRequest, MediaRequest, PsbtRequest, PublishPsbtRequest, ReIssueRequest,
-    RgbAuctionBidRequest, RgbAuctionOfferRequest, RgbBidRequest, RgbOfferRequest,
-    RgbOfferUpdateRequest, RgbRemoveTransferRequest, RgbSaveTransferRequest, RgbSwapRequest,
-    RgbTransferRequest, SecretString, SignPsbtRequest, WatcherRequest,
+    AcceptRequest, CoinControl, FullIssueRequest, FullRgbTransferRequest, ImportRequest,
+    InvoiceRequest, IssueMediaRequest, IssueRequest, LabelType, LabelsRequest, MediaRequest,
+    OutPoint, PsbtRequest, PublishPsbtRequest, ReIssueRequest, RgbAuctionBidRequest,
+    RgbAuctionOfferRequest, RgbBackupRequest, RgbBidRequest, RgbOfferRequest,
+    RgbOfferUpdateRequest, RgbProvisionRequest, RgbRemoveTransferRequest, RgbRescanRequest,
+    RgbRestoreRequest, RgbSaveTransferRequest, RgbSwapRequest, RgbSyncDocument, RgbSyncRequest,
+    RgbTopUpRequest, RgbTransferRequest, RgbUtxoPoolRequest, SecretString, SignPsbtRequest,
+    WatcherRequest, WatchersRequest,
 };
 
 pub fn set_panic_hook() {
@@ -273,16 +276,26 @@ pub mod bitcoin {
         destination: String,
         amount: u64,
         fee_rate: Option<f32>,
+        nostr_hex_sk: String,
+        coin_control: JsValue,
     ) -> Promise {
         set_panic_hook();
 
         future_to_promise(async move {
+            let coin_control: Option<CoinControl> =
+                match serde_wasm_bindgen::from_value(coin_control) {
+                    Ok(coin_control) => coin_control,
+                    Err(err) => return Err(JsValue::from_string(err.to_string())),
+                };
+
             match crate::bitcoin::send_sats(
+                &nostr_hex_sk,
                 &SecretString(descriptor),
                 &SecretString(change_descriptor),
                 &destination,
                 amount,
                 fee_rate,
+                coin_control,
             )
             .await
             {
@@ -301,11 +314,13 @@ pub mod bitcoin {
         asset_address: String,
         uda_address: String,
         fee_rate: Option<f32>,
+        nostr_hex_sk: String,
     ) -> Promise {
         set_panic_hook();
 
         future_to_promise(async move {
             match crate::bitcoin::fund_vault(
+                &nostr_hex_sk,
                 &SecretString(descriptor),
                 &SecretString(change_descriptor),
                 &asset_address,
@@ -344,23 +359,76 @@ pub mod bitcoin {
         })
     }
 
+    #[wasm_bindgen]
+    pub fn lock_utxos(nostr_hex_sk: String, outpoints: JsValue) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let outpoints: Vec<OutPoint> = match serde_wasm_bindgen::from_value(outpoints) {
+                Ok(outpoints) => outpoints,
+                Err(err) => return Err(JsValue::from_string(err.to_string())),
+            };
+            if let Err(err) = crate::bitcoin::lock_utxos(&nostr_hex_sk, outpoints).await {
+                return Err(JsValue::from_string(err.to_string()));
+            }
+
+            match crate::bitcoin::list_locked_utxos(&nostr_hex_sk).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn unlock_utxos(nostr_hex_sk: String, outpoints: JsValue) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let outpoints: Vec<OutPoint> = match serde_wasm_bindgen::from_value(outpoints) {
+                Ok(outpoints) => outpoints,
+                Err(err) => return Err(JsValue::from_string(err.to_string())),
+            };
+            if let Err(err) = crate::bitcoin::unlock_utxos(&nostr_hex_sk, outpoints).await {
+                return Err(JsValue::from_string(err.to_string()));
+            }
+
+            match crate::bitcoin::list_locked_utxos(&nostr_hex_sk).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
     #[wasm_bindgen]
     pub fn drain_wallet(
         destination: String,
         descriptor: String,
         change_descriptor: Option<String>,
         fee_rate: Option<f32>,
+        nostr_hex_sk: String,
+        coin_control: JsValue,
     ) -> Promise {
         set_panic_hook();
 
         future_to_promise(async move {
             let change_descriptor = change_descriptor.map(SecretString);
+            let coin_control: Option<CoinControl> =
+                match serde_wasm_bindgen::from_value(coin_control) {
+                    Ok(coin_control) => coin_control,
+                    Err(err) => return Err(JsValue::from_string(err.to_string())),
+                };
 
             match crate::bitcoin::drain_wallet(
+                &nostr_hex_sk,
                 &destination,
                 &SecretString(descriptor),
                 change_descriptor.as_ref(),
                 fee_rate,
+                coin_control,
             )
             .await
             {
@@ -607,6 +675,20 @@ pub mod rgb {
         })
     }
 
+    #[wasm_bindgen]
+    pub fn check_reorgs(nostr_hex_sk: String) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            match crate::rgb::check_reorgs(&nostr_hex_sk).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
     #[wasm_bindgen]
     pub fn get_contract(nostr_hex_sk: String, contract_id: String) -> Promise {
         set_panic_hook();
@@ -662,6 +744,19 @@ pub mod rgb {
         })
     }
 
+    #[wasm_bindgen]
+    pub fn resolver_cache_metrics() -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let result = crate::chain::resolver_cache_metrics();
+
+            Ok(JsValue::from_string(
+                serde_json::to_string(&result).unwrap(),
+            ))
+        })
+    }
+
     #[wasm_bindgen]
     pub fn list_all_contracts(nostr_hex_sk: String) -> Promise {
         set_panic_hook();
@@ -734,6 +829,21 @@ pub mod rgb {
         })
     }
 
+    #[wasm_bindgen]
+    pub fn create_watchers(nostr_hex_sk: String, request: JsValue) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let req: WatchersRequest = serde_wasm_bindgen::from_value(request).unwrap();
+            match crate::rgb::create_watchers(&nostr_hex_sk, req).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
     #[wasm_bindgen]
     pub fn watcher_details(nostr_hex_sk: String, name: String) -> Promise {
         set_panic_hook();
@@ -762,6 +872,20 @@ pub mod rgb {
         })
     }
 
+    #[wasm_bindgen]
+    pub fn get_balances(nostr_hex_sk: String) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            match crate::rgb::get_balances(&nostr_hex_sk).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
     #[wasm_bindgen]
     pub fn watcher_address(nostr_hex_sk: String, name: String, address: String) -> Promise {
         set_panic_hook();
@@ -832,6 +956,227 @@ pub mod rgb {
         })
     }
 
+    #[wasm_bindgen]
+    pub fn rescan_watcher(nostr_hex_sk: String, request: JsValue) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let req: RgbRescanRequest = serde_wasm_bindgen::from_value(request).unwrap();
+            match crate::rgb::rescan_watcher(&nostr_hex_sk, req).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn watcher_rescan_status(nostr_hex_sk: String, name: String) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            match crate::rgb::watcher_rescan_status(&nostr_hex_sk, &name).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn export_backup(nostr_hex_sk: String, request: JsValue) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let req: RgbBackupRequest = serde_wasm_bindgen::from_value(request).unwrap();
+            match crate::rgb::export_backup(&nostr_hex_sk, req).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn restore_backup(nostr_hex_sk: String, request: JsValue) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let req: RgbRestoreRequest = serde_wasm_bindgen::from_value(request).unwrap();
+            match crate::rgb::restore_backup(&nostr_hex_sk, req).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn migration_plan(nostr_hex_sk: String) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            match crate::rgb::migration_plan(&nostr_hex_sk).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn migrate_account(nostr_hex_sk: String) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            match crate::rgb::migrate_account(&nostr_hex_sk).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn sync_documents(nostr_hex_sk: String, request: JsValue) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let req: RgbSyncRequest = serde_wasm_bindgen::from_value(request).unwrap();
+            match crate::rgb::sync_documents(&nostr_hex_sk, req).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn publish_sync_document(
+        nostr_hex_sk: String,
+        relays: JsValue,
+        document: JsValue,
+    ) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let relays: Vec<String> = serde_wasm_bindgen::from_value(relays).unwrap();
+            let document: RgbSyncDocument = serde_wasm_bindgen::from_value(document).unwrap();
+            match crate::rgb::publish_sync_document(&nostr_hex_sk, &relays, document).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn sync_documents_from_relays(
+        nostr_hex_sk: String,
+        relays: JsValue,
+        since: u64,
+    ) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let relays: Vec<String> = serde_wasm_bindgen::from_value(relays).unwrap();
+            match crate::rgb::sync_documents_from_relays(&nostr_hex_sk, &relays, since).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn sync_event(nostr_hex_sk: String, document: JsValue) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let document: RgbSyncDocument = serde_wasm_bindgen::from_value(document).unwrap();
+            match crate::rgb::sync::sync_event(&nostr_hex_sk, &document) {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn read_sync_event(nostr_hex_sk: String, event: String) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let result = serde_json::from_str(&event)
+                .map_err(|op| op.to_string())
+                .and_then(|event| {
+                    crate::rgb::sync::read_sync_event(&nostr_hex_sk, &event)
+                        .map_err(|op| op.to_string())
+                });
+            match result {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err)),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn provision_utxos(nostr_hex_sk: String, request: JsValue) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let req: RgbProvisionRequest = serde_wasm_bindgen::from_value(request).unwrap();
+            match crate::rgb::provision_utxos(&nostr_hex_sk, req).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn set_utxo_pool(nostr_hex_sk: String, request: JsValue) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let req: RgbUtxoPoolRequest = serde_wasm_bindgen::from_value(request).unwrap();
+            match crate::rgb::set_utxo_pool(&nostr_hex_sk, req).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn top_up_utxos(nostr_hex_sk: String, request: JsValue) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let req: RgbTopUpRequest = serde_wasm_bindgen::from_value(request).unwrap();
+            match crate::rgb::top_up_utxos(&nostr_hex_sk, req).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
     #[wasm_bindgen]
     pub fn list_transfers(nostr_hex_sk: String, contract_id: String) -> Promise {
         set_panic_hook();
@@ -1355,6 +1700,48 @@ pub mod carbonado {
         })
     }
 
+    #[wasm_bindgen]
+    pub fn store_media(secret_key: String, name: String, data: Vec<u8>) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            match crate::carbonado::store_media(&secret_key, &name, &data).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    /// Bytes `start..end` of a media file, the whole file without a range
+    #[wasm_bindgen]
+    pub fn retrieve_media(
+        secret_key: String,
+        name: String,
+        start: Option<u64>,
+        end: Option<u64>,
+    ) -> Promise {
+        set_panic_hook();
+
+        use js_sys::Uint8Array;
+
+        future_to_promise(async move {
+            let range = match (start, end) {
+                (None, None) => None,
+                (start, end) => Some(start.unwrap_or_default()..end.unwrap_or(u64::MAX)),
+            };
+            match crate::carbonado::retrieve_media(&secret_key, &name, range).await {
+                Ok(result) => {
+                    let array = Uint8Array::new_with_length(result.len() as u32);
+                    array.copy_from(&result);
+                    Ok(JsValue::from(array))
+                }
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
     #[wasm_bindgen]
     pub fn encode_hex(bytes: Vec<u8>) -> String {
         set_panic_hook();
@@ -1415,3 +1802,102 @@ pub mod nostr {
         })
     }
 }
+
+pub mod labels {
+    use super::*;
+
+    #[wasm_bindgen]
+    pub fn list_labels(nostr_hex_sk: String) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            match crate::labels::list_labels(&nostr_hex_sk).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn set_labels(nostr_hex_sk: String, request: JsValue) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let req: LabelsRequest = serde_wasm_bindgen::from_value(request).unwrap();
+            match crate::labels::set_labels(&nostr_hex_sk, req).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn remove_label(nostr_hex_sk: String, ty: JsValue, reference: String) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let ty: LabelType = serde_wasm_bindgen::from_value(ty).unwrap();
+            match crate::labels::remove_label(&nostr_hex_sk, ty, &reference).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn import_labels(nostr_hex_sk: String, jsonl: String) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            match crate::labels::import_labels(&nostr_hex_sk, &jsonl).await {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn export_labels(nostr_hex_sk: String) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            match crate::labels::export_labels(&nostr_hex_sk).await {
+                Ok(result) => Ok(JsValue::from_string(result)),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn get_labeled_wallet_data(
+        nostr_hex_sk: String,
+        descriptor: String,
+        change_descriptor: Option<String>,
+    ) -> Promise {
+        set_panic_hook();
+
+        future_to_promise(async move {
+            let change_descriptor = change_descriptor.map(SecretString);
+            match crate::labels::get_labeled_wallet_data(
+                &nostr_hex_sk,
+                &SecretString(descriptor),
+                change_descriptor.as_ref(),
+            )
+            .await
+            {
+                Ok(result) => Ok(JsValue::from_string(
+                    serde_json::to_string(&result).unwrap(),
+                )),
+                Err(err) => Err(JsValue::from_string(err.to_string())),
+            }
+        })
+    }
+}
@@ -0,0 +1,478 @@
+#![cfg(not(target_arch = "wasm32"))]
+use std::str::FromStr;
+
+use anyhow::Result;
+use bitcoin_30::secp256k1::{PublicKey, SecretKey};
+use bitmask_core::{
+    carbonado::{
+        auth::{allow_auction_bidders, auction_bidders, auction_writer, WriteAuth},
+        error::CarbonadoError,
+        gc::{auction_expired, collect_proxy_files, GcReport},
+        handle_file, history, list_versions,
+        quota::{usage, StorageCategory, StorageLimits},
+        replication::{select_replica, split_endpoints, WriteQuorum},
+        retrieve, retrieve_media, retrieve_version, rollback,
+        segments::{parse_range, SegmentManifest, MEDIA_SEGMENT_SIZE},
+        store, store_media,
+        versions::{
+            file_version, forget_versions, recall_version, version_matches, write_if_matches,
+        },
+    },
+    constants::{get_env, set_env},
+    rgb::swap::{RgbAuctionSwaps, RgbOfferSwap},
+};
+
+const OWNER_SK: &str = "0101010101010101010101010101010101010101010101010101010101010101";
+
+fn encode(input: &[u8]) -> Result<Vec<u8>> {
+    let sk = hex::decode(OWNER_SK)?;
+    let secret_key = SecretKey::from_slice(&sk)?;
+    let pk = PublicKey::from_secret_key_global(&secret_key).serialize();
+    let (body, _) = carbonado::file::encode(&sk, Some(&pk), input, 15, None)?;
+    Ok(body)
+}
+
+#[test]
+fn allow_parse_carbonado_write_quorum() -> Result<()> {
+    assert_eq!(WriteQuorum::from_str("majority")?, WriteQuorum::Majority);
+    assert_eq!(WriteQuorum::from_str("ALL")?, WriteQuorum::All);
+    assert_eq!(WriteQuorum::from_str("2")?, WriteQuorum::Count(2));
+    assert!(WriteQuorum::from_str("0").is_err());
+    assert!(WriteQuorum::from_str("most").is_err());
+
+    assert_eq!(WriteQuorum::Majority.required(1), 1);
+    assert_eq!(WriteQuorum::Majority.required(3), 2);
+    assert_eq!(WriteQuorum::Majority.required(4), 3);
+    assert_eq!(WriteQuorum::All.required(3), 3);
+    assert_eq!(WriteQuorum::Count(5).required(3), 3);
+
+    assert!(WriteQuorum::Majority.check(2, 3).is_ok());
+    assert!(matches!(
+        WriteQuorum::Majority.check(1, 3),
+        Err(CarbonadoError::WriteQuorumFailed(1, 2))
+    ));
+    assert!(matches!(
+        WriteQuorum::Count(1).check(0, 3),
+        Err(CarbonadoError::AllEndpointsFailed)
+    ));
+
+    assert_eq!(
+        split_endpoints("http://a/carbonado, http://b/carbonado/,"),
+        vec!["http://a/carbonado", "http://b/carbonado"]
+    );
+    Ok(())
+}
+
+#[test]
+fn allow_select_carbonado_replica_of_most_endpoints() -> Result<()> {
+    let sk = hex::decode(OWNER_SK)?;
+    let old = encode(&[1; 1_000])?;
+    let new = encode(&[2; 2_000])?;
+    let mut corrupted = encode(&[3; 4_000])?;
+    corrupted[carbonado::file::Header::len() + 64] ^= 0xff;
+
+    // the larger copy is not the newest one, and there is no majority to repair
+    let responses = vec![
+        Some(new.clone()),
+        None,
+        Some(old.clone()),
+        Some(corrupted.clone()),
+        Some(vec![]),
+        Some(old.clone()),
+    ];
+    let replica = select_replica(&sk, &responses, None).expect("no valid replica");
+    assert_eq!(replica.endpoint, 2);
+    assert_eq!(replica.decoded, vec![1; 1_000]);
+    assert_eq!(replica.encoded, old);
+    assert!(replica.lagging.is_empty());
+
+    // the copy known by the process wins a tie
+    let responses = vec![Some(old.clone()), Some(new.clone())];
+    let replica =
+        select_replica(&sk, &responses, Some(&file_version(&new))).expect("no valid replica");
+    assert_eq!(replica.endpoint, 1);
+    assert!(replica.lagging.is_empty());
+
+    // the lagging endpoints are repaired over the copy they returned
+    let responses = vec![
+        Some(new.clone()),
+        Some(corrupted.clone()),
+        Some(new.clone()),
+        None,
+        Some(new.clone()),
+    ];
+    let replica = select_replica(&sk, &responses, None).expect("no valid replica");
+    assert_eq!(replica.endpoint, 0);
+    assert_eq!(
+        replica.lagging,
+        vec![(1, Some(file_version(&corrupted))), (3, None)]
+    );
+
+    assert!(select_replica(&sk, &[None, Some(vec![])], None).is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_authorize_signed_carbonado_writes() -> Result<()> {
+    let sk = hex::decode(OWNER_SK)?;
+    let pk = PublicKey::from_secret_key_global(&SecretKey::from_slice(&sk)?).to_string();
+    let resource = format!("{pk}/bitmask-auth_test.c15");
+    let body = encode(b"signed")?;
+
+    let auth = WriteAuth::sign(OWNER_SK, &resource, &body)?;
+    assert_eq!(auth.pubkey, pk);
+    let headers = auth.headers();
+    let received = WriteAuth::from_headers(|name| {
+        headers
+            .iter()
+            .find(|(header, _)| *header == name)
+            .map(|(_, value)| value.as_str())
+    })?;
+    assert_eq!(received, auth);
+    received.authorize(Some(&pk), &resource, &body).await?;
+
+    // signatures only hold for their file and body
+    assert!(matches!(
+        auth.verify(&format!("{resource}/force"), &body),
+        Err(CarbonadoError::WrongWriteSignature)
+    ));
+    assert!(matches!(
+        auth.verify(&resource, &encode(b"tampered")?),
+        Err(CarbonadoError::WrongWriteSignature)
+    ));
+
+    // and only once
+    assert!(matches!(
+        auth.authorize(Some(&pk), &resource, &body).await,
+        Err(CarbonadoError::ReplayedWriteAuth(_))
+    ));
+
+    let other_sk = "0202020202020202020202020202020202020202020202020202020202020202";
+    let other = WriteAuth::sign(other_sk, &resource, &body)?;
+    assert!(matches!(
+        other.authorize(Some(&pk), &resource, &body).await,
+        Err(CarbonadoError::WrongWriteSigner(_))
+    ));
+    other.authorize(None, &resource, &body).await?;
+
+    let stale = WriteAuth::sign_at(OWNER_SK, &resource, &body, 1_000)?;
+    stale.verify(&resource, &body)?;
+    assert!(matches!(
+        stale.check_fresh(1_000 + 301, 300),
+        Err(CarbonadoError::ExpiredWriteAuth(1_000))
+    ));
+
+    assert!(matches!(
+        WriteAuth::from_headers(|_| None),
+        Err(CarbonadoError::MissingWriteAuth(_))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_authorize_auction_writes_of_allowed_bidders() -> Result<()> {
+    let public_key = |sk: &str| -> Result<PublicKey> {
+        Ok(PublicKey::from_secret_key_global(&SecretKey::from_slice(
+            &hex::decode(sk)?,
+        )?))
+    };
+    let seller = public_key(OWNER_SK)?.to_string();
+    let bidder = public_key("0202020202020202020202020202020202020202020202020202020202020202")?;
+    let stranger = public_key("0303030303030303030303030303030303030303030303030303030303030303")?;
+    // bidders are allowed by their nostr key
+    let bidders = vec![bidder.x_only_public_key().0.to_string()];
+
+    assert_eq!(auction_writer(&seller, &[], &bidder.to_string()), seller);
+    assert_eq!(
+        auction_writer(&seller, &bidders, &bidder.to_string()),
+        bidder.to_string()
+    );
+    assert_eq!(
+        auction_writer(&seller, &bidders, &stranger.to_string()),
+        seller
+    );
+    assert_eq!(auction_writer(&seller, &bidders, &seller), seller);
+    assert_eq!(auction_writer(&seller, &bidders, "not a key"), seller);
+
+    let auction_dir = std::env::temp_dir().join("bitmask-auction_bidders_test");
+    tokio::fs::create_dir_all(&auction_dir).await?;
+    allow_auction_bidders(&auction_dir, &[]).await?;
+    assert!(auction_bidders(&auction_dir).await.is_empty());
+
+    allow_auction_bidders(&auction_dir, &bidders).await?;
+    assert_eq!(auction_bidders(&auction_dir).await, bidders);
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_reject_stale_carbonado_writes() -> Result<()> {
+    let name = "bitmask-occ_test.c15";
+    store(OWNER_SK, name, b"device a", true, None).await?;
+    store(OWNER_SK, name, b"device a, again", false, None).await?;
+
+    // another device writes the file
+    let sk = hex::decode(OWNER_SK)?;
+    let pk = PublicKey::from_secret_key_global(&SecretKey::from_slice(&sk)?).to_string();
+    let filepath = handle_file(&pk, name, 0).await?;
+    let other = encode(b"device b")?;
+    tokio::fs::write(&filepath, &other).await?;
+
+    let stale = store(OWNER_SK, name, b"device a, stale", false, None).await;
+    assert!(matches!(stale, Err(CarbonadoError::Conflict(_))));
+
+    // the write succeeds over the version retrieved last
+    let (data, _) = retrieve(OWNER_SK, name, vec![]).await?;
+    assert_eq!(data, b"device b");
+    store(OWNER_SK, name, b"device a, merged", false, None).await?;
+
+    // forced writes are unconditional
+    tokio::fs::write(&filepath, &other).await?;
+    store(OWNER_SK, name, b"device a, forced", true, None).await?;
+    let (data, _) = retrieve(OWNER_SK, name, vec![]).await?;
+    assert_eq!(data, b"device a, forced");
+
+    assert!(version_matches(Some(&file_version(&other)), Some(&other)));
+    assert!(!version_matches(Some(&file_version(b"old")), Some(&other)));
+    assert!(version_matches(Some(&file_version(b"old")), None));
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_write_carbonado_file_once_per_version() -> Result<()> {
+    let filepath = std::env::temp_dir().join("bitmask-cas_test.c15");
+    let first = encode(b"first")?;
+    write_if_matches(&filepath, None, &first).await?;
+    let expected = file_version(&first);
+
+    // concurrent writes over the same version, only one replaces it
+    let bodies = (0..4_u8)
+        .map(|device| encode(&[device]))
+        .collect::<Result<Vec<_>>>()?;
+    let writes = bodies
+        .iter()
+        .map(|body| write_if_matches(&filepath, Some(&expected), body));
+    let written = futures::future::join_all(writes)
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(written.iter().filter(|written| **written).count(), 1);
+    assert!(bodies.contains(&tokio::fs::read(&filepath).await?));
+
+    // bitmaskd leaves empty files behind, there is nothing to lose
+    tokio::fs::write(&filepath, b"").await?;
+    assert!(write_if_matches(&filepath, Some(&expected), &first).await?);
+
+    // the versions known by the process survive it
+    let sk = "0404040404040404040404040404040404040404040404040404040404040404";
+    let pk =
+        PublicKey::from_secret_key_global(&SecretKey::from_slice(&hex::decode(sk)?)?).to_string();
+    let name = "bitmask-persisted_version_test.c15";
+    store(sk, name, b"stored", true, None).await?;
+    let filepath = handle_file(&pk, name, 0).await?;
+    let file_name = filepath.file_name().unwrap_or_default().to_string_lossy();
+
+    forget_versions(&pk);
+    let stored = tokio::fs::read(&filepath).await?;
+    assert_eq!(
+        recall_version(&filepath, &pk, &file_name).await,
+        Some(file_version(&stored))
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_rollback_carbonado_file() -> Result<()> {
+    // the limit is global, the previous one is restored even when a step fails
+    let previous_limit = get_env("CARBONADO_HISTORY_LIMIT").await;
+    set_env("CARBONADO_HISTORY_LIMIT", "2").await;
+    let result: Result<()> = async {
+        let name = "bitmask-history_test.c15";
+        for content in ["v1", "v2", "v3", "v4"] {
+            store(OWNER_SK, name, content.as_bytes(), true, None).await?;
+        }
+
+        // only the newest previous versions are kept
+        let versions = list_versions(OWNER_SK, name).await?;
+        assert_eq!(versions.len(), 2);
+        let (data, _) = retrieve_version(OWNER_SK, name, &versions[0].version).await?;
+        assert_eq!(data, b"v3");
+        let (data, _) = retrieve_version(OWNER_SK, name, &versions[1].version).await?;
+        assert_eq!(data, b"v2");
+
+        rollback(OWNER_SK, name, &versions[1].version).await?;
+        let (data, _) = retrieve(OWNER_SK, name, vec![]).await?;
+        assert_eq!(data, b"v2");
+
+        // the replaced version can be restored too
+        let versions = list_versions(OWNER_SK, name).await?;
+        let (data, _) = retrieve_version(OWNER_SK, name, &versions[0].version).await?;
+        assert_eq!(data, b"v4");
+
+        // versions are checked by the next writes
+        store(OWNER_SK, name, b"v5", false, None).await?;
+
+        let missing = rollback(OWNER_SK, name, &file_version(b"unknown")).await;
+        assert!(matches!(missing, Err(CarbonadoError::VersionNotFound(_))));
+        Ok(())
+    }
+    .await;
+    set_env("CARBONADO_HISTORY_LIMIT", &previous_limit).await;
+    result
+}
+
+#[test]
+fn allow_parse_http_ranges() {
+    assert_eq!(parse_range("bytes=0-99", 1_000), Some(0..100));
+    assert_eq!(parse_range("bytes=900-", 1_000), Some(900..1_000));
+    assert_eq!(parse_range("bytes=-100", 1_000), Some(900..1_000));
+    assert_eq!(parse_range("bytes=900-5000", 1_000), Some(900..1_000));
+    assert_eq!(parse_range("bytes=1000-", 1_000), None);
+    assert_eq!(parse_range("bytes=0-1,5-9", 1_000), None);
+    assert_eq!(parse_range("items=0-9", 1_000), None);
+
+    let manifest = SegmentManifest {
+        segment_size: 100,
+        len: 250,
+        segments: vec![],
+    };
+    assert_eq!(manifest.segments_for(&(0..250)), 0..3);
+    assert_eq!(manifest.segments_for(&(150..200)), 1..2);
+    assert_eq!(manifest.segments_for(&(199..201)), 1..3);
+    assert_eq!(manifest.segments_for(&(10..10)), 0..0);
+    assert_eq!(manifest.slice(1, &[7; 100], &(150..300)), &[7; 50]);
+}
+
+#[tokio::test]
+async fn allow_retrieve_media_ranges() -> Result<()> {
+    let name = "bitmask-media_test.c15";
+    let media: Vec<u8> = (0..MEDIA_SEGMENT_SIZE * 2 + 1_000)
+        .map(|i| (i % 251) as u8)
+        .collect();
+
+    let manifest = store_media(OWNER_SK, name, &media).await?;
+    assert_eq!(manifest.segments.len(), 3);
+    assert_eq!(manifest.len, media.len() as u64);
+
+    assert_eq!(retrieve_media(OWNER_SK, name, None).await?, media);
+
+    // a range across two segments
+    let start = MEDIA_SEGMENT_SIZE as u64 - 10;
+    let range = start..start + 20;
+    let data = retrieve_media(OWNER_SK, name, Some(range.clone())).await?;
+    assert_eq!(data, &media[range.start as usize..range.end as usize]);
+
+    // segments are verified on their own
+    let sk = hex::decode(OWNER_SK)?;
+    let pk = PublicKey::from_secret_key_global(&SecretKey::from_slice(&sk)?).to_string();
+    let filepath = handle_file(&pk, &SegmentManifest::segment_name(name, 2), 0).await?;
+    tokio::fs::write(&filepath, encode(b"another segment")?).await?;
+
+    let first = retrieve_media(OWNER_SK, name, Some(0..100)).await?;
+    assert_eq!(first, &media[..100]);
+    let last = retrieve_media(OWNER_SK, name, None).await;
+    assert!(matches!(last, Err(CarbonadoError::SegmentCorrupted(2))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_keep_history_of_media_segments() -> Result<()> {
+    let micros = chrono::Utc::now().timestamp_micros();
+    let name = &format!("bitmask-media_history_test-{micros}.c15");
+    let segment = SegmentManifest::segment_name(name, 0);
+
+    store_media(OWNER_SK, name, b"first media").await?;
+    store_media(OWNER_SK, name, b"second media").await?;
+    assert_eq!(retrieve_media(OWNER_SK, name, None).await?, b"second media");
+
+    // the replaced segment is kept like any other stored file
+    let versions = list_versions(OWNER_SK, &segment).await?;
+    assert_eq!(versions.len(), 1);
+    let (data, _) = retrieve_version(OWNER_SK, &segment, &versions[0].version).await?;
+    assert_eq!(data, b"first media");
+    Ok(())
+}
+
+#[test]
+fn allow_check_carbonado_storage_limits() -> Result<()> {
+    let limits = StorageLimits::parse("1000", "stock=600, media=100")?;
+    assert_eq!(limits.files.get(&StorageCategory::Stock), Some(&600));
+    assert!(StorageLimits::parse("1000", "videos=100").is_err());
+    assert!(StorageLimits::parse("a lot", "").is_err());
+
+    limits.check(StorageCategory::Stock, 600, 0, 400)?;
+    assert!(matches!(
+        limits.check(StorageCategory::Media, 101, 0, 0),
+        Err(CarbonadoError::FileTooLarge(_, 100))
+    ));
+    assert!(matches!(
+        limits.check(StorageCategory::Stock, 600, 0, 401),
+        Err(CarbonadoError::QuotaExceeded(1000))
+    ));
+    // overwrites only count the difference
+    limits.check(StorageCategory::Other, 800, 500, 700)?;
+    StorageLimits::parse("0", "")?.check(StorageCategory::Other, u64::MAX, 0, u64::MAX)?;
+
+    assert_eq!(
+        StorageCategory::of("bitcoin-media-segment-2.c15"),
+        StorageCategory::Media
+    );
+    assert_eq!(
+        StorageCategory::of("bitcoin-bitmask-wallet.c15"),
+        StorageCategory::Other
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_count_archived_versions_in_storage_usage() -> Result<()> {
+    let micros = chrono::Utc::now().timestamp_micros();
+    let dir = std::env::temp_dir().join(format!("bitmask-quota_test-{micros}"));
+    tokio::fs::create_dir_all(&dir).await?;
+    let filepath = dir.join("bitmask-quota_test.c15");
+
+    let first = encode(b"first")?;
+    write_if_matches(&filepath, None, &first).await?;
+    assert_eq!(usage(&dir).await?, first.len() as u64);
+
+    // the present version moves to the history on the next write
+    let (archived, _) = history::archive_growth(&filepath).await?;
+    assert_eq!(archived, first.len() as u64);
+
+    let second = encode(b"second")?;
+    write_if_matches(&filepath, None, &second).await?;
+    assert_eq!(usage(&dir).await?, (first.len() + second.len()) as u64);
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_collect_carbonado_garbage() -> Result<()> {
+    let proxy_dir = std::env::temp_dir().join("bitmask-gc_test-proxy");
+    tokio::fs::create_dir_all(&proxy_dir).await?;
+    tokio::fs::write(proxy_dir.join("bitcoin-utxob:orphan"), b"consignment").await?;
+
+    // files are kept while they may still be uploaded
+    let now = chrono::Utc::now().timestamp();
+    let mut report = GcReport::default();
+    collect_proxy_files(&proxy_dir, now, 3600, &mut report).await?;
+    assert_eq!(report.proxy_files, 0);
+
+    collect_proxy_files(&proxy_dir, now + 3601, 3600, &mut report).await?;
+    assert_eq!(report.proxy_files, 1);
+    assert_eq!(report.proxy_bytes, 11);
+    assert!(!proxy_dir.join("bitcoin-utxob:orphan").exists());
+
+    let offer = |expire_at| RgbOfferSwap {
+        expire_at,
+        ..Default::default()
+    };
+    let mut auction = RgbAuctionSwaps {
+        items: vec![offer(Some(now - 10)), offer(Some(now + 10))],
+        ..Default::default()
+    };
+    assert!(!auction_expired(&auction, now));
+    assert!(auction_expired(&auction, now + 11));
+    auction.items.push(offer(None));
+    assert!(!auction_expired(&auction, now + 11));
+    assert!(!auction_expired(&RgbAuctionSwaps::default(), now));
+    Ok(())
+}
@@ -0,0 +1,613 @@
+#![cfg(not(target_arch = "wasm32"))]
+use std::{collections::BTreeMap, str::FromStr, time::Duration};
+
+use async_trait::async_trait;
+use bdk::{
+    database::MemoryDatabase, descriptor::IntoWalletDescriptor, wallet::AddressIndex, SyncOptions,
+};
+use bitcoin::{
+    secp256k1::Secp256k1, BlockHash, Network, OutPoint, PackedLockTime, Script, Sequence,
+    Transaction, TxIn, TxOut, Txid, Witness,
+};
+use bitmask_core::{
+    bitcoin::new_mnemonic,
+    chain::{
+        block_on_runtime, resolver_account, resolver_cache_metrics, resolver_cache_snapshot,
+        BitcoindBackend, CachedBackend, ChainBackend, ChainBlockchain, ChainError, MockBackend,
+        OutputStatus, ResolverCache, ScriptTx,
+    },
+    constants::{
+        RESOLVER_CACHE_FINAL_DEPTH, RESOLVER_CACHE_MAX_TXS, RESOLVER_CACHE_TTL,
+        RESOLVER_CACHE_TX_TTL,
+    },
+    rgb::{
+        check_reorgs,
+        constants::RGB_ANCHOR_FINAL_DEPTH,
+        fs::{retrieve_resolver_cache, store_resolver_cache},
+        reorg::{
+            apply_anchor_check, check_anchor, mark_pending_allocations, pending_witnesses,
+            prune_anchors, track_anchors, AnchorCheck,
+        },
+        resolvers::{MockResolver, ResolveSpent, ResolveTxStatus},
+        structs::{RgbAccountV1, RgbAnchor, RgbAnchors, RgbTransferV1, RgbTransfersV1},
+    },
+    structs::{AllocationDetail, AllocationValue, BatchRgbTransferItem, SecretString, TxStatus},
+};
+use rgb::{prelude::DeriveInfo, MiningStatus, Resolver};
+
+const WALLET_SCRIPT: &str = "0014a3f6c1e3d0f1b2e4c5d6e7f8091a2b3c4d5e6f70";
+
+fn spend(prevout: OutPoint, value: u64) -> Transaction {
+    Transaction {
+        version: 2,
+        lock_time: PackedLockTime(0),
+        input: vec![TxIn {
+            previous_output: prevout,
+            script_sig: Script::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value,
+            script_pubkey: Script::new(),
+        }],
+    }
+}
+
+/// Mock chain answering like Bitcoin Core `scantxoutset`, which only finds
+/// the transactions with unspent outputs
+struct UnspentOnlyBackend(MockBackend);
+
+#[async_trait]
+impl ChainBackend for UnspentOnlyBackend {
+    async fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>, ChainError> {
+        self.0.get_tx(txid).await
+    }
+
+    async fn get_tx_status(&self, txid: &Txid) -> Result<TxStatus, ChainError> {
+        self.0.get_tx_status(txid).await
+    }
+
+    async fn get_output_status(
+        &self,
+        txid: &Txid,
+        vout: u32,
+    ) -> Result<Option<OutputStatus>, ChainError> {
+        self.0.get_output_status(txid, vout).await
+    }
+
+    async fn get_script_txs(&self, script: &Script) -> Result<Vec<ScriptTx>, ChainError> {
+        let mut txs = vec![];
+        for script_tx in self.0.get_script_txs(script).await? {
+            let txid = script_tx.tx.txid();
+            let mut unspent = false;
+            for (vout, output) in script_tx.tx.output.iter().enumerate() {
+                if &output.script_pubkey != script {
+                    continue;
+                }
+                let status = self.0.get_output_status(&txid, vout as u32).await?;
+                unspent |= status.is_some_and(|status| !status.spent);
+            }
+            if unspent {
+                txs.push(script_tx);
+            }
+        }
+        Ok(txs)
+    }
+
+    async fn get_height(&self) -> Result<u32, ChainError> {
+        self.0.get_height().await
+    }
+
+    async fn get_block_hash(&self, height: u32) -> Result<Option<BlockHash>, ChainError> {
+        self.0.get_block_hash(height).await
+    }
+
+    async fn get_block_time(&self, height: u32) -> Result<Option<u64>, ChainError> {
+        self.0.get_block_time(height).await
+    }
+
+    async fn estimate_fee(&self, target: usize) -> Result<f32, ChainError> {
+        self.0.estimate_fee(target).await
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<(), ChainError> {
+        self.0.broadcast(tx).await
+    }
+
+    fn full_history(&self) -> bool {
+        false
+    }
+}
+
+#[tokio::test]
+async fn allow_resolve_with_mock_backend() -> anyhow::Result<()> {
+    let script = Script::from_str(WALLET_SCRIPT)?;
+    let backend = MockBackend::default();
+
+    let funding = backend.send_to(&script, 10_000);
+    backend.mine(101);
+    assert_eq!(backend.get_height().await?, 101);
+
+    let script_txs = backend.get_script_txs(&script).await?;
+    assert_eq!(script_txs.len(), 1);
+    assert_eq!(script_txs[0].status, TxStatus::Block(1));
+
+    let mut resolver = MockResolver::new(backend.clone());
+    let scripts = BTreeMap::from([(
+        DeriveInfo::with(0, 0, None),
+        bitcoin_30::ScriptBuf::from_hex(WALLET_SCRIPT)?,
+    )]);
+    let utxos = resolver.resolve_utxo(scripts).map_err(anyhow::Error::msg)?;
+    assert_eq!(utxos.len(), 1);
+
+    let utxo = utxos.into_iter().next().expect("utxo exists");
+    assert_eq!(utxo.amount, 10_000);
+    assert_eq!(utxo.status, MiningStatus::Blockchain(1));
+
+    let status = resolver.resolve_spent_status(funding, 0, true)?;
+    assert!(!status.is_spent);
+    assert_eq!(status.block_height, TxStatus::Block(1));
+
+    let spending = spend(OutPoint::new(funding, 0), 9_000);
+    backend.broadcast(&spending).await?;
+
+    let status = resolver.resolve_spent_status(funding, 0, false)?;
+    assert!(status.is_spent);
+    assert_eq!(status.spent_height, TxStatus::Mempool);
+    assert_eq!(
+        resolver.resolve_tx_status(spending.txid())?,
+        TxStatus::Mempool
+    );
+
+    let status = resolver.resolve_spent_status(funding, 1, false)?;
+    assert!(matches!(status.spent_height, TxStatus::Error(_)));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_replace_by_fee_in_mock_mempool() -> anyhow::Result<()> {
+    let script = Script::from_str(WALLET_SCRIPT)?;
+    let backend = MockBackend::default();
+
+    let funding = backend.send_to(&script, 10_000);
+    backend.mine(1);
+
+    let original = spend(OutPoint::new(funding, 0), 9_000);
+    backend.broadcast(&original).await?;
+
+    // same fee, rejected
+    let mut same_fee = spend(OutPoint::new(funding, 0), 9_000);
+    same_fee.lock_time = PackedLockTime(1);
+    assert!(backend.broadcast(&same_fee).await.is_err());
+
+    let replacement = spend(OutPoint::new(funding, 0), 8_000);
+    backend.broadcast(&replacement).await?;
+    assert_eq!(backend.mempool(), vec![replacement.txid()]);
+    assert_eq!(
+        backend.get_tx_status(&original.txid()).await?,
+        TxStatus::NotFound
+    );
+
+    backend.mine(1);
+    let output_status = backend
+        .get_output_status(&funding, 0)
+        .await?
+        .expect("output exists");
+    assert!(output_status.spent);
+    assert_eq!(output_status.txid, Some(replacement.txid()));
+    assert_eq!(output_status.status, TxStatus::Block(2));
+
+    // confirmed spends cannot be replaced
+    let double_spend = spend(OutPoint::new(funding, 0), 1_000);
+    assert!(backend.broadcast(&double_spend).await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_reorg_mock_chain() -> anyhow::Result<()> {
+    let script = Script::from_str(WALLET_SCRIPT)?;
+    let backend = MockBackend::default();
+
+    let funding = backend.send_to(&script, 10_000);
+    backend.mine(1);
+    let spending = spend(OutPoint::new(funding, 0), 9_000);
+    backend.broadcast(&spending).await?;
+    let tip = backend.mine(2);
+    assert_eq!(
+        backend.get_tx_status(&spending.txid()).await?,
+        TxStatus::Block(2)
+    );
+
+    backend.reorg(2);
+    assert_eq!(backend.get_height().await?, 1);
+    assert_eq!(backend.mempool(), vec![spending.txid()]);
+    assert_eq!(
+        backend.get_tx_status(&spending.txid()).await?,
+        TxStatus::Mempool
+    );
+
+    backend.drop_tx(&spending.txid());
+    let output_status = backend
+        .get_output_status(&funding, 0)
+        .await?
+        .expect("output exists");
+    assert!(!output_status.spent);
+
+    let new_tip = backend.mine(2);
+    assert_ne!(tip, new_tip);
+    assert_eq!(backend.get_height().await?, 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_sync_wallet_with_mock_backend() -> anyhow::Result<()> {
+    // the backend is injected, other tests keep their own chain
+    let mock = MockBackend::default();
+    let blockchain = ChainBlockchain::new(mock.clone());
+
+    let keys = new_mnemonic(&SecretString("".to_string())).await?;
+    let descriptor = keys
+        .private
+        .btc_descriptor_xprv
+        .into_wallet_descriptor(&Secp256k1::new(), Network::Regtest)?;
+    let wallet = bdk::Wallet::new(descriptor, None, Network::Regtest, MemoryDatabase::new())?;
+    wallet.sync(&blockchain, SyncOptions::default()).await?;
+    assert_eq!(wallet.get_balance()?.confirmed, 0);
+
+    let address = wallet.get_address(AddressIndex::New)?;
+    mock.send_to(&address.script_pubkey(), 100_000);
+
+    wallet.sync(&blockchain, SyncOptions::default()).await?;
+    assert_eq!(wallet.get_balance()?.untrusted_pending, 100_000);
+
+    mock.mine(1);
+    wallet.sync(&blockchain, SyncOptions::default()).await?;
+    assert_eq!(wallet.get_balance()?.confirmed, 100_000);
+    let txs = wallet.list_transactions(false)?;
+    assert_eq!(txs.len(), 1);
+    assert!(txs[0].confirmation_time.is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_keep_spent_history_without_full_history() -> anyhow::Result<()> {
+    let mock = MockBackend::default();
+    let blockchain = ChainBlockchain::new(UnspentOnlyBackend(mock.clone()));
+
+    let keys = new_mnemonic(&SecretString("".to_string())).await?;
+    let descriptor = keys
+        .private
+        .btc_descriptor_xprv
+        .into_wallet_descriptor(&Secp256k1::new(), Network::Regtest)?;
+    let wallet = bdk::Wallet::new(descriptor, None, Network::Regtest, MemoryDatabase::new())?;
+    let address = wallet.get_address(AddressIndex::New)?;
+
+    let funding = mock.send_to(&address.script_pubkey(), 100_000);
+    mock.mine(1);
+    wallet.sync(&blockchain, SyncOptions::default()).await?;
+    assert_eq!(wallet.get_balance()?.confirmed, 100_000);
+
+    // the spending transaction pays out of the wallet, so the backend forgets the funding
+    mock.broadcast(&spend(OutPoint::new(funding, 0), 90_000))
+        .await?;
+    mock.mine(1);
+    wallet.sync(&blockchain, SyncOptions::default()).await?;
+
+    assert_eq!(wallet.get_balance()?.confirmed, 0);
+    let txs = wallet.list_transactions(false)?;
+    assert!(txs.iter().any(|tx| tx.txid == funding));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_cache_resolved_chain_data() -> anyhow::Result<()> {
+    let script = Script::from_str(WALLET_SCRIPT)?;
+    let mock = MockBackend::default();
+    let account = resolver_account("cached_chain_data");
+    let backend = CachedBackend::for_account(mock.clone(), &account);
+
+    let funding = mock.send_to(&script, 10_000);
+    mock.mine(1);
+
+    let before = resolver_cache_metrics();
+    assert_eq!(backend.get_tx_status(&funding).await?, TxStatus::Block(1));
+    assert!(backend.get_tx(&funding).await?.is_some());
+    let output_status = backend
+        .get_output_status(&funding, 0)
+        .await?
+        .expect("output exists");
+    assert!(!output_status.spent);
+
+    // resolved data is not asked again before it expires
+    mock.reorg(1);
+    assert_eq!(backend.get_tx_status(&funding).await?, TxStatus::Block(1));
+    assert!(backend.get_tx(&funding).await?.is_some());
+
+    let after = resolver_cache_metrics();
+    assert_eq!(after.status_misses - before.status_misses, 1);
+    assert_eq!(after.status_hits - before.status_hits, 1);
+    assert_eq!(after.tx_misses - before.tx_misses, 1);
+    assert_eq!(after.tx_hits - before.tx_hits, 1);
+
+    // broadcasts invalidate the outputs they spend
+    mock.mine(1);
+    let spending = spend(OutPoint::new(funding, 0), 9_000);
+    backend.broadcast(&spending).await?;
+    let output_status = backend
+        .get_output_status(&funding, 0)
+        .await?
+        .expect("output exists");
+    assert!(output_status.spent);
+    assert_eq!(output_status.status, TxStatus::Mempool);
+
+    let stored = serde_json::to_string(&resolver_cache_snapshot(&account))?;
+    let mut restored: ResolverCache = serde_json::from_str(&stored)?;
+    assert_eq!(restored.tx(&funding), backend.get_tx(&funding).await?);
+
+    // the data resolved for an account is not stored in the others
+    let mut other = resolver_cache_snapshot(&resolver_account("other_chain_data"));
+    assert_eq!(other.tx(&funding), None);
+
+    Ok(())
+}
+
+#[test]
+fn allow_expire_shallow_confirmations_of_resolver_cache() {
+    let txid = spend(OutPoint::null(), 1_000).txid();
+    let mut cache = ResolverCache::default();
+    cache.set_tip(1, 0);
+    cache.insert_tx_status(&txid, TxStatus::Block(1), 0);
+
+    // a single confirmation can still be reorganized
+    let later = RESOLVER_CACHE_TTL + 1;
+    assert_eq!(cache.tx_status(&txid, later), None);
+
+    cache.set_tip(RESOLVER_CACHE_FINAL_DEPTH, later);
+    assert_eq!(cache.tx_status(&txid, later), Some(TxStatus::Block(1)));
+}
+
+#[test]
+fn allow_expire_old_transactions_of_resolver_cache() {
+    let old = spend(OutPoint::null(), 1_000);
+    let mut cache = ResolverCache::default();
+    cache.insert_tx(&old, 0);
+
+    // the oldest transactions are dropped over the limit
+    for value in 0..RESOLVER_CACHE_MAX_TXS as u64 {
+        cache.insert_tx(&spend(OutPoint::null(), 2_000 + value), 1);
+    }
+    assert_eq!(cache.tx(&old.txid()), None);
+
+    let recent = spend(OutPoint::null(), 1_500);
+    cache.insert_tx(&recent, 2);
+    cache.prune(RESOLVER_CACHE_TX_TTL + 2);
+    assert_eq!(cache.tx(&recent.txid()), None);
+}
+
+#[tokio::test]
+async fn allow_store_resolver_cache_per_account() -> anyhow::Result<()> {
+    const OWNER_SK: &str = "0909090909090909090909090909090909090909090909090909090909090909";
+    const OTHER_SK: &str = "0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a";
+
+    let tx = spend(OutPoint::null(), 2_000);
+    let mut cache = ResolverCache::default();
+    cache.insert_tx(&tx, chrono::Utc::now().timestamp());
+    store_resolver_cache(OWNER_SK, cache).await?;
+
+    let mut stored = retrieve_resolver_cache(OWNER_SK).await?;
+    assert_eq!(stored.tx(&tx.txid()), Some(tx.clone()));
+    let mut other = retrieve_resolver_cache(OTHER_SK).await?;
+    assert_eq!(other.tx(&tx.txid()), None);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_detect_reorg_of_rgb_anchor() -> anyhow::Result<()> {
+    let script = Script::from_str(WALLET_SCRIPT)?;
+    let backend = MockBackend::default();
+
+    let witness = backend.send_to(&script, 10_000);
+    let block_hash = backend.mine(1);
+    assert_eq!(backend.get_block_hash(1).await?, Some(block_hash));
+    assert_eq!(backend.get_block_hash(2).await?, None);
+
+    let mut anchor = RgbAnchor {
+        contract_id: "rgb:contract".to_string(),
+        transfer: RgbTransferV1 {
+            consig_id: "rgb:consig".to_string(),
+            tx_id: bp::Txid::from_str(&witness.to_string())?,
+            ..Default::default()
+        },
+        height: 1,
+        block_hash: block_hash.to_string(),
+    };
+    assert_eq!(
+        check_anchor(&backend, &anchor).await?,
+        AnchorCheck::Unchanged
+    );
+
+    backend.reorg(1);
+    assert_eq!(
+        check_anchor(&backend, &anchor).await?,
+        AnchorCheck::Unconfirmed(TxStatus::Mempool)
+    );
+
+    // the witness is mined again, in another block at the same height
+    backend.send_to(&script, 5_000);
+    let new_block_hash = backend.mine(1);
+    assert_ne!(block_hash, new_block_hash);
+    assert_eq!(
+        check_anchor(&backend, &anchor).await?,
+        AnchorCheck::Moved(1, new_block_hash)
+    );
+
+    anchor.block_hash = new_block_hash.to_string();
+    assert_eq!(
+        check_anchor(&backend, &anchor).await?,
+        AnchorCheck::Unchanged
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_mark_pending_the_transfers_of_reorged_anchors() -> anyhow::Result<()> {
+    let script = Script::from_str(WALLET_SCRIPT)?;
+    let backend = MockBackend::default();
+
+    let witness = backend.send_to(&script, 10_000);
+    backend.mine(1);
+
+    let transfer = RgbTransferV1 {
+        consig_id: "rgb:consig".to_string(),
+        tx_id: bp::Txid::from_str(&witness.to_string())?,
+        ..Default::default()
+    };
+    let mut rgb_transfers = RgbTransfersV1::default();
+    rgb_transfers
+        .transfers
+        .insert("rgb:contract".to_string(), vec![transfer.clone()]);
+    let item = BatchRgbTransferItem {
+        contract_id: "rgb:contract".to_string(),
+        consig_id: transfer.consig_id.clone(),
+        iface: "RGB20".to_string(),
+        status: TxStatus::Block(1),
+        is_accept: true,
+        is_mine: false,
+        txid: witness.to_string(),
+    };
+
+    let mut rgb_anchors = RgbAnchors::default();
+    track_anchors(&backend, &mut rgb_anchors, &rgb_transfers, &[item.clone()]).await?;
+    assert!(rgb_anchors.anchors.contains_key("rgb:consig"));
+
+    // the accepted transfer is not pending anymore
+    let mut rgb_transfers = RgbTransfersV1::default();
+    let mut rgb_account = RgbAccountV1::default();
+    backend.reorg(1);
+    let check = check_anchor(&backend, &rgb_anchors.anchors["rgb:consig"]).await?;
+    let status = apply_anchor_check(
+        &mut rgb_anchors,
+        &mut rgb_account,
+        &mut rgb_transfers,
+        "rgb:consig",
+        check,
+    );
+    assert_eq!(status, Some(TxStatus::Mempool));
+    assert!(rgb_anchors.anchors.is_empty());
+    assert!(rgb_anchors.pending.contains_key("rgb:consig"));
+    assert_eq!(
+        rgb_transfers.transfers["rgb:contract"],
+        vec![transfer.clone()]
+    );
+
+    let witnesses = pending_witnesses(&rgb_anchors, "rgb:contract");
+    let mut allocations = vec![
+        AllocationDetail {
+            utxo: format!("{witness}:0"),
+            value: AllocationValue::Value(600),
+            is_mine: true,
+            ..Default::default()
+        },
+        AllocationDetail {
+            utxo: "9a5d21d4cc15ffa14c6f416396235c082cddb5e227abd863974445709f8e9af0:1".to_string(),
+            value: AllocationValue::Value(400),
+            is_mine: true,
+            ..Default::default()
+        },
+    ];
+    assert_eq!(mark_pending_allocations(&mut allocations, &witnesses), 600);
+    assert!(allocations[0].is_pending);
+    assert!(!allocations[1].is_pending);
+
+    // the witness is mined again and the transfer accepted again
+    backend.mine(1);
+    track_anchors(&backend, &mut rgb_anchors, &rgb_transfers, &[item]).await?;
+    assert!(rgb_anchors.anchors.contains_key("rgb:consig"));
+    assert!(rgb_anchors.pending.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_prune_final_anchors() -> anyhow::Result<()> {
+    let anchor = |height: u32| RgbAnchor {
+        contract_id: "rgb:contract".to_string(),
+        transfer: RgbTransferV1::default(),
+        height,
+        block_hash: String::new(),
+    };
+    let mut rgb_anchors = RgbAnchors::default();
+    rgb_anchors.anchors.insert("old".to_string(), anchor(1));
+    rgb_anchors.anchors.insert("new".to_string(), anchor(50));
+
+    assert_eq!(
+        prune_anchors(&mut rgb_anchors, RGB_ANCHOR_FINAL_DEPTH - 1),
+        0
+    );
+    assert_eq!(prune_anchors(&mut rgb_anchors, RGB_ANCHOR_FINAL_DEPTH), 1);
+    assert!(rgb_anchors.anchors.contains_key("new"));
+    assert!(!rgb_anchors.anchors.contains_key("old"));
+
+    Ok(())
+}
+
+#[test]
+fn allow_check_reorgs_from_send_futures() {
+    // servers run the reorganization checks on multi-threaded runtimes
+    fn assert_send<T: Send>(_: &T) {}
+    assert_send(&check_reorgs("sk"));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn allow_query_bitcoind_off_the_async_workers() -> anyhow::Result<()> {
+    // a node that accepts the connection but never answers
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let backend = BitcoindBackend::new(&url, "bitmask", "bitmask")?;
+
+    // the single worker keeps running while the request is pending
+    tokio::select! {
+        _ = backend.get_height() => panic!("the node never answers"),
+        _ = tokio::time::sleep(Duration::from_millis(50)) => {}
+    }
+
+    // closing the listener resets the pending request
+    drop(listener);
+    Ok(())
+}
+
+#[test]
+fn allow_query_bitcoind_without_runtime() {
+    let backend = BitcoindBackend::new("http://127.0.0.1:1", "", "").expect("valid url");
+    let height = futures::executor::block_on(backend.get_height());
+    assert!(matches!(height, Err(ChainError::Bitcoind(_))));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn allow_block_on_the_runtime_from_sync_code() {
+    // resolvers are synchronous, but run within the runtime of the callers
+    let height = tokio::task::spawn(async {
+        block_on_runtime(async {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            101
+        })
+    })
+    .await
+    .expect("task completes");
+    assert_eq!(height, 101);
+
+    assert_eq!(
+        std::thread::spawn(|| block_on_runtime(async { 102 }))
+            .join()
+            .ok(),
+        Some(102)
+    );
+}
@@ -0,0 +1,57 @@
+#![cfg(not(target_arch = "wasm32"))]
+use bitmask_core::{
+    labels::Labels,
+    structs::{Label, LabelType},
+};
+
+const BIP329_EXAMPLE: &str = r#"{"type":"tx","ref":"f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd","label":"Transaction","origin":"wpkh([d34db33f/84'/0'/0'])"}
+{"type":"addr","ref":"bc1q34aq5drpuwy3wgl9lhup9892qp6svr8ldzyy7c","label":"Address"}
+
+{"type":"output","ref":"f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:1","label":"Output","spendable":false}
+{"type":"contract","ref":"rgb:2bfAmUA-Nr3KMy3b-V1DuqaqR-mNodZ5Nm-SoS1dJCY-tEAnkAk","label":"Fungible"}"#;
+
+#[tokio::test]
+async fn parse_and_export_bip329_labels() -> anyhow::Result<()> {
+    let labels = Labels::from_jsonl(BIP329_EXAMPLE)?;
+    assert_eq!(labels.len(), 4);
+    assert_eq!(
+        labels.label(
+            LabelType::Tx,
+            "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd"
+        ),
+        Some("Transaction".to_string())
+    );
+
+    let output = labels
+        .get(
+            LabelType::Output,
+            "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:1",
+        )
+        .expect("output label");
+    assert_eq!(output.spendable, Some(false));
+
+    let exported = Labels::from_jsonl(&labels.to_jsonl())?;
+    assert_eq!(labels, exported);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn reject_wrong_bip329_labels() -> anyhow::Result<()> {
+    assert!(Labels::from_jsonl(r#"{"type":"unknown","ref":"abc"}"#).is_err());
+    assert!(Labels::from_jsonl(r#"{"type":"tx","ref":"","label":"empty"}"#).is_err());
+    assert!(Labels::from_jsonl(r#"{"type":"tx","ref":"abc","spendable":true}"#).is_err());
+
+    let mut labels = Labels::default();
+    labels.insert(Label {
+        ty: LabelType::Consignment,
+        reference: "consig".to_string(),
+        label: Some("Invoice #1".to_string()),
+        origin: None,
+        spendable: None,
+    })?;
+    assert!(labels.remove(LabelType::Consignment, "consig").is_some());
+    assert!(labels.is_empty());
+
+    Ok(())
+}
@@ -48,11 +48,13 @@ async fn payjoin() -> Result<()> {
     let amount = 1000;
 
     match send_sats(
+        &vault.private.nostr_prv,
         &SecretString(vault.private.btc_descriptor_xprv.clone()),
         &SecretString(vault.private.btc_change_descriptor_xprv.clone()),
         &destination,
         amount,
         Some(1.1),
+        None,
     )
     .await
     {
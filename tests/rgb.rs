@@ -2,12 +2,19 @@ mod rgb {
 
     mod unit {
         mod amount;
+        mod backup;
         mod invoice;
         mod issue;
+        mod migration;
+        mod pool;
         mod psbt;
+        mod resolvers;
         mod stl;
         mod stock;
+        mod storage;
+        mod sync;
         pub mod utils;
+        mod watcher;
     }
 
     mod integration {
@@ -15,6 +22,7 @@ mod rgb {
         // mod collectibles;
         mod accept;
         mod batch;
+        mod bitcoind;
         mod cambria;
         mod collectibles;
         mod crdt;
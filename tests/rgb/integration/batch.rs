@@ -3,6 +3,7 @@
 use std::str::FromStr;
 
 use anyhow::Result;
+use bdk::blockchain::GetTx;
 use bitcoin::Txid;
 use bitmask_core::{
     bitcoin::{get_blockchain, new_mnemonic, sign_and_publish_psbt_file},
@@ -240,7 +241,7 @@ pub async fn create_transfer_rbf() -> Result<()> {
 
     // 6. Check Mempool transaction
     let txid1 = Txid::from_str(&psbt_resp?.txid)?;
-    let explorer = get_blockchain().await;
+    let explorer = get_blockchain().await?;
     let transaction = explorer.get_tx(&txid1).await;
     assert!(transaction.is_ok());
     assert!(transaction?.is_some());
@@ -417,7 +418,7 @@ pub async fn create_batch_transfer() -> Result<()> {
 
     // 7. Check Mempool transaction
     let txid1 = Txid::from_str(&txid)?;
-    let explorer = get_blockchain().await;
+    let explorer = get_blockchain().await?;
     let transaction = explorer.get_tx(&txid1).await;
     assert!(transaction.is_ok());
     assert!(transaction?.is_some());
@@ -620,7 +621,7 @@ pub async fn create_transfer_skipping_invalid_states() -> Result<()> {
 
     // 6. Check Mempool transaction
     let txid1 = Txid::from_str(&psbt_resp?.txid)?;
-    let explorer = get_blockchain().await;
+    let explorer = get_blockchain().await?;
     let transaction = explorer.get_tx(&txid1).await;
     assert!(transaction.is_ok());
     assert!(transaction?.is_some());
@@ -3,6 +3,7 @@
 use std::str::FromStr;
 
 use anyhow::Result;
+use bdk::blockchain::GetTx;
 use bitcoin::Txid;
 use bitmask_core::{
     bitcoin::{get_blockchain, new_mnemonic, sign_and_publish_psbt_file},
@@ -241,7 +242,7 @@ pub async fn create_transfer_rbf() -> Result<()> {
 
     // 6. Check Mempool transaction
     let txid1 = Txid::from_str(&psbt_resp?.txid)?;
-    let explorer = get_blockchain().await;
+    let explorer = get_blockchain().await?;
     let transaction = explorer.get_tx(&txid1).await;
     assert!(transaction.is_ok());
     assert!(transaction?.is_some());
@@ -418,7 +419,7 @@ pub async fn create_batch_transfer() -> Result<()> {
 
     // 7. Check Mempool transaction
     let txid1 = Txid::from_str(&txid)?;
-    let explorer = get_blockchain().await;
+    let explorer = get_blockchain().await?;
     let transaction = explorer.get_tx(&txid1).await;
     assert!(transaction.is_ok());
     assert!(transaction?.is_some());
@@ -0,0 +1,48 @@
+#![cfg(not(target_arch = "wasm32"))]
+use std::{collections::BTreeMap, str::FromStr};
+
+use bitmask_core::{
+    bitcoin::{get_wallet_data, new_mnemonic},
+    chain::BitcoindBackend,
+    rgb::resolvers::{BitcoindResolver, ResolveSpent, ResolveTxStatus},
+    structs::{SecretString, TxStatus},
+};
+use rgb::{prelude::DeriveInfo, MiningStatus, Resolver};
+
+use crate::rgb::integration::utils::send_some_coins;
+
+#[tokio::test]
+async fn allow_resolve_utxos_with_bitcoind() -> anyhow::Result<()> {
+    let keys = new_mnemonic(&SecretString("".to_string())).await?;
+    let wallet_data =
+        get_wallet_data(&SecretString(keys.public.btc_descriptor_xpub.clone()), None).await?;
+
+    send_some_coins(&wallet_data.address, "0.1").await;
+
+    let address = bitcoin_30::Address::from_str(&wallet_data.address)?.assume_checked();
+    let scripts = BTreeMap::from([(DeriveInfo::with(0, 0, None), address.script_pubkey())]);
+
+    let mut resolver = BitcoindResolver::new(BitcoindBackend::new(
+        "http://localhost:18443",
+        "bitmask",
+        "bitmask",
+    )?);
+
+    let utxos = resolver.resolve_utxo(scripts).map_err(anyhow::Error::msg)?;
+    assert_eq!(utxos.len(), 1);
+
+    let utxo = utxos.into_iter().next().expect("utxo exists");
+    assert_eq!(utxo.amount, 10_000_000);
+    assert!(matches!(utxo.status, MiningStatus::Blockchain(_)));
+
+    let txid = bitcoin::Txid::from_str(&utxo.outpoint.txid.to_string())?;
+    let tx_status = resolver.resolve_tx_status(txid)?;
+    assert!(matches!(tx_status, TxStatus::Block(_)));
+
+    let spent_status =
+        resolver.resolve_spent_status(txid, utxo.outpoint.vout.into_u32().into(), true)?;
+    assert!(!spent_status.is_spent);
+    assert!(matches!(spent_status.block_height, TxStatus::Block(_)));
+
+    Ok(())
+}
@@ -3,8 +3,14 @@ use crate::rgb::integration::utils::get_raw_wallet;
 use amplify::confinement::Collection;
 use automerge::AutoCommit;
 use autosurgeon::{hydrate, reconcile};
-use bitmask_core::rgb::crdt::{RawRgbWallet, RawUtxo};
-use rgb::{RgbWallet, Utxo};
+use bitmask_core::rgb::{
+    crdt::{merge_document, RawRgbBids, RawRgbOffers, RawRgbTransfers, RawRgbWallet, RawUtxo},
+    structs::{RgbTransferV1, RgbTransfersV1},
+    swap::{RgbBid, RgbBids, RgbOffer, RgbOffers},
+};
+use bp::Txid;
+use rgb::{MiningStatus, RgbWallet, Utxo};
+use std::{collections::BTreeMap, str::FromStr};
 
 #[tokio::test]
 async fn allow_fork_with_previous_version() -> anyhow::Result<()> {
@@ -75,3 +81,224 @@ async fn allow_fork_without_previous_version() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn allow_keep_block_of_stored_utxos() -> anyhow::Result<()> {
+    let mut raw_utxo = RawUtxo {
+        outpoint: "9a5d21d4cc15ffa14c6f416396235c082cddb5e227abd863974445709f8e9af0:0".to_string(),
+        block: 0,
+        amount: 10000000,
+        terminal: "20:1".to_string(),
+        tweak: None,
+    };
+    assert_eq!(Utxo::from(raw_utxo.clone()).status, MiningStatus::Mempool);
+
+    raw_utxo.block = 120;
+    assert_eq!(Utxo::from(raw_utxo).status, MiningStatus::Blockchain(120));
+
+    Ok(())
+}
+
+fn new_transfer(consig_id: &str) -> RgbTransferV1 {
+    let tx_id =
+        Txid::from_str("9a5d21d4cc15ffa14c6f416396235c082cddb5e227abd863974445709f8e9af0").unwrap();
+    RgbTransferV1::new(
+        consig_id.to_string(),
+        format!("consignment {consig_id}"),
+        "RGB20".to_string(),
+        tx_id,
+        vec![],
+    )
+}
+
+fn consig_ids(transfers: &RgbTransfersV1, contract_id: &str) -> Vec<String> {
+    transfers.transfers[contract_id]
+        .iter()
+        .map(|transfer| transfer.consig_id.clone())
+        .collect()
+}
+
+#[tokio::test]
+async fn allow_merge_concurrent_transfers() -> anyhow::Result<()> {
+    let mut current = RgbTransfersV1::default();
+    current
+        .transfers
+        .insert("contract".to_string(), vec![new_transfer("consig1")]);
+
+    // both devices read the same document
+    let base = merge_document(&[], &[], RawRgbTransfers::from(current.clone()))?.document;
+
+    let mut device_a = current.clone();
+    device_a
+        .transfers
+        .get_mut("contract")
+        .unwrap()
+        .push(new_transfer("consig2"));
+    let merged_a = merge_document(&base, &base, RawRgbTransfers::from(device_a))?;
+
+    let mut device_b = current.clone();
+    device_b
+        .transfers
+        .get_mut("contract")
+        .unwrap()
+        .push(new_transfer("consig3"));
+    let merged_b = merge_document(&base, &merged_a.document, RawRgbTransfers::from(device_b))?;
+
+    let merged = RgbTransfersV1::try_from(merged_b.value)?;
+    let mut ids = consig_ids(&merged, "contract");
+    assert_eq!(ids.remove(0), "consig1");
+    ids.sort();
+    assert_eq!(ids, vec!["consig2", "consig3"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_merge_removed_and_added_transfers() -> anyhow::Result<()> {
+    let mut current = RgbTransfersV1::default();
+    current.transfers.insert(
+        "contract".to_string(),
+        vec![new_transfer("consig1"), new_transfer("consig2")],
+    );
+    let base = merge_document(&[], &[], RawRgbTransfers::from(current.clone()))?.document;
+
+    let mut device_a = current.clone();
+    device_a.transfers.get_mut("contract").unwrap().remove(0);
+    let merged_a = merge_document(&base, &base, RawRgbTransfers::from(device_a))?;
+
+    let mut device_b = current.clone();
+    device_b
+        .transfers
+        .insert("other".to_string(), vec![new_transfer("consig3")]);
+    let merged_b = merge_document(&base, &merged_a.document, RawRgbTransfers::from(device_b))?;
+
+    let merged = RgbTransfersV1::try_from(merged_b.value)?;
+    assert_eq!(consig_ids(&merged, "contract"), vec!["consig2"]);
+    assert_eq!(consig_ids(&merged, "other"), vec!["consig3"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_merge_documents_created_by_each_device() -> anyhow::Result<()> {
+    let mut device_a = RgbTransfersV1::default();
+    device_a
+        .transfers
+        .insert("contract".to_string(), vec![new_transfer("consig1")]);
+    let document_a = merge_document(&[], &[], RawRgbTransfers::from(device_a))?.document;
+
+    let mut device_b = RgbTransfersV1::default();
+    device_b
+        .transfers
+        .insert("contract".to_string(), vec![new_transfer("consig2")]);
+    let document_b = merge_document(&[], &[], RawRgbTransfers::from(device_b))?.document;
+
+    // both documents start with the same change, and their own changes do not collide
+    let mut document = AutoCommit::load(&document_a)?;
+    document.merge(&mut AutoCommit::load(&document_b)?)?;
+    let raw_transfers: RawRgbTransfers = hydrate(&document)?;
+
+    let merged = RgbTransfersV1::try_from(raw_transfers)?;
+    let mut ids = consig_ids(&merged, "contract");
+    ids.sort();
+    assert_eq!(ids, vec!["consig1", "consig2"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn disallow_convert_invalid_documents() -> anyhow::Result<()> {
+    let mut raw_transfers = RawRgbTransfers::from(RgbTransfersV1 {
+        transfers: BTreeMap::from([("contract".to_string(), vec![new_transfer("consig1")])]),
+    });
+    for raw_transfer in raw_transfers.transfers.values_mut() {
+        raw_transfer.tx_id = "invalid".to_string();
+    }
+    assert!(RgbTransfersV1::try_from(raw_transfers).is_err());
+
+    let mut raw_offers = RawRgbOffers::default();
+    raw_offers
+        .bids
+        .insert("invalid".to_string(), RgbBid::default());
+    assert!(RgbOffers::try_from(raw_offers).is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_merge_concurrent_offers_without_document() -> anyhow::Result<()> {
+    let new_offer = |offer_id: &str| RgbOffer {
+        offer_id: offer_id.to_string(),
+        contract_id: "contract".to_string(),
+        ..Default::default()
+    };
+
+    // neither device has read a document yet
+    let mut device_a = RgbOffers::default();
+    device_a
+        .offers
+        .insert("contract".to_string(), vec![new_offer("offer1")]);
+    let merged_a = merge_document(&[], &[], RawRgbOffers::from(device_a))?;
+
+    let mut device_b = RgbOffers::default();
+    device_b
+        .offers
+        .insert("contract".to_string(), vec![new_offer("offer2")]);
+    let bid = RgbBid {
+        bid_id: "bid1".to_string(),
+        offer_id: "offer2".to_string(),
+        ..Default::default()
+    };
+    device_b.bids.insert(
+        "offer2".to_string(),
+        BTreeMap::from([("bid1".to_string(), bid)]),
+    );
+    let merged_b = merge_document(&[], &merged_a.document, RawRgbOffers::from(device_b))?;
+
+    let merged = RgbOffers::try_from(merged_b.value)?;
+    let mut offer_ids: Vec<_> = merged.offers["contract"]
+        .iter()
+        .map(|offer| offer.offer_id.clone())
+        .collect();
+    offer_ids.sort();
+    assert_eq!(offer_ids, vec!["offer1", "offer2"]);
+    assert!(merged.bids["offer2"].contains_key("bid1"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_merge_concurrent_bid_updates() -> anyhow::Result<()> {
+    let new_bid = |bid_id: &str| RgbBid {
+        bid_id: bid_id.to_string(),
+        contract_id: "contract".to_string(),
+        ..Default::default()
+    };
+
+    let mut current = RgbBids::default();
+    current
+        .bids
+        .insert("contract".to_string(), vec![new_bid("bid1")]);
+    let base = merge_document(&[], &[], RawRgbBids::from(current.clone()))?.document;
+
+    let mut device_a = current.clone();
+    device_a.bids.get_mut("contract").unwrap()[0].bitcoin_amount = 1000;
+    let merged_a = merge_document(&base, &base, RawRgbBids::from(device_a))?;
+
+    let mut device_b = current.clone();
+    device_b
+        .bids
+        .get_mut("contract")
+        .unwrap()
+        .push(new_bid("bid2"));
+    let merged_b = merge_document(&base, &merged_a.document, RawRgbBids::from(device_b))?;
+
+    let merged = RgbBids::from(merged_b.value);
+    let bids = &merged.bids["contract"];
+    assert_eq!(bids.len(), 2);
+    assert_eq!(bids[0].bid_id, "bid1");
+    assert_eq!(bids[0].bitcoin_amount, 1000);
+    assert_eq!(bids[1].bid_id, "bid2");
+
+    Ok(())
+}
@@ -39,12 +39,14 @@ pub async fn drain() -> Result<()> {
 
     // 2. Drain sats from original wallet to new wallet
     let drain_wallet_details = drain_wallet(
+        &old_keys.private.nostr_prv,
         &new_wallet_data.address,
         &SecretString(old_keys.private.btc_descriptor_xprv.clone()),
         Some(&SecretString(
             old_keys.private.btc_change_descriptor_xprv.clone(),
         )),
         Some(2.0),
+        None,
     )
     .await?;
 
@@ -166,6 +166,7 @@ async fn create_dustless_transfer_with_fee_rate() -> anyhow::Result<()> {
     sync_wallet(&btc_wallet).await?;
 
     let fund_vault = fund_vault(
+        &issuer_keys.private.nostr_prv,
         &btc_descriptor_xprv,
         &btc_change_descriptor_xprv,
         &assets_address_1,
@@ -1,6 +1,7 @@
 #![cfg(not(target_arch = "wasm32"))]
 use anyhow::Result;
 use bdk::{
+    blockchain::{Blockchain, GetTx},
     database::MemoryDatabase,
     descriptor::IntoWalletDescriptor,
     wallet::{tx_builder::TxOrdering, AddressIndex},
@@ -74,7 +75,7 @@ pub async fn create_simple_rbf_bitcoin_transfer() -> Result<()> {
 
     // 4. Check TX
     let txid1 = Txid::from_str(&psbt_resp?.txid)?;
-    let explorer = get_blockchain().await;
+    let explorer = get_blockchain().await?;
     let transaction = explorer.get_tx(&txid1).await;
     assert!(transaction.is_ok());
     assert!(transaction?.is_some());
@@ -103,7 +104,7 @@ pub async fn create_simple_rbf_bitcoin_transfer() -> Result<()> {
 
     // 7. Check Both TX
     let txid2 = Txid::from_str(&psbt_resp?.txid)?;
-    let explorer = get_blockchain().await;
+    let explorer = get_blockchain().await?;
     let transaction2 = explorer.get_tx(&txid2).await;
     assert!(transaction2.is_ok());
 
@@ -135,7 +136,7 @@ pub async fn create_bdk_rbf_transaction() -> Result<()> {
     // 1. Initial Setup
     let user_keys = new_mnemonic(&SecretString("".to_string())).await?;
 
-    let blockchain = get_blockchain().await;
+    let blockchain = get_blockchain().await?;
     let secp = Secp256k1::new();
     let db = MemoryDatabase::new();
     let descriptor = user_keys
@@ -78,6 +78,7 @@ async fn create_hotswap_swap() -> anyhow::Result<()> {
     sync_wallet(&btc_wallet).await?;
 
     let fund_vault = fund_vault(
+        &seller_keys.private.nostr_prv,
         &btc_descriptor_xprv,
         &btc_change_descriptor_xprv,
         &assets_address_1,
@@ -306,6 +307,7 @@ async fn create_hotswap_swap_for_uda() -> anyhow::Result<()> {
     sync_wallet(&btc_wallet).await?;
 
     let fund_vault = fund_vault(
+        &seller_keys.private.nostr_prv,
         &btc_descriptor_xprv,
         &btc_change_descriptor_xprv,
         &assets_address_1,
@@ -533,6 +535,7 @@ async fn create_p2p_swap() -> anyhow::Result<()> {
     sync_wallet(&btc_wallet).await?;
 
     let fund_vault = fund_vault(
+        &seller_keys.private.nostr_prv,
         &btc_descriptor_xprv,
         &btc_change_descriptor_xprv,
         &assets_address_1,
@@ -778,6 +781,7 @@ async fn create_auction_swap() -> anyhow::Result<()> {
     sync_wallet(&btc_wallet).await?;
 
     let fund_vault = fund_vault(
+        &seller_keys.private.nostr_prv,
         &btc_descriptor_xprv,
         &btc_change_descriptor_xprv,
         &assets_address_1,
@@ -1005,6 +1009,7 @@ async fn create_collectible_auction() -> anyhow::Result<()> {
     sync_wallet(&btc_wallet).await?;
 
     let fund_vault = fund_vault(
+        &alice_keys.private.nostr_prv,
         &btc_descriptor_xprv,
         &btc_change_descriptor_xprv,
         &assets_address_1,
@@ -1230,6 +1235,7 @@ async fn create_airdrop_swap() -> anyhow::Result<()> {
     sync_wallet(&btc_wallet).await?;
 
     let fund_vault = fund_vault(
+        &seller_keys.private.nostr_prv,
         &btc_descriptor_xprv,
         &btc_change_descriptor_xprv,
         &assets_address_1,
@@ -4,6 +4,7 @@ use amplify::bmap;
 use bdk::wallet::AddressIndex;
 use bitmask_core::{
     bitcoin::{get_wallet, get_wallet_data, save_mnemonic, sync_wallet},
+    constants::get_env,
     rgb::{
         crdt::{RawRgbWallet, RawUtxo},
         create_invoice, create_psbt, create_watcher, import, issue_contract,
@@ -21,6 +22,11 @@ use bitmask_core::{
 use std::{collections::HashMap, env, process::Stdio};
 use tokio::process::Command;
 
+#[cfg(feature = "mock-chain")]
+use bitmask_core::chain::MOCK_BACKEND;
+#[cfg(feature = "mock-chain")]
+use std::str::FromStr;
+
 pub const ISSUER_MNEMONIC: &str =
     "ordinary crucial edit settle pencil lion appear unlock left fly century license";
 
@@ -64,8 +70,17 @@ impl UtxoFilter {
     }
 }
 
+/// Whether the tests run on the mock chain, it needs the `mock-chain` feature
+async fn mock_chain() -> bool {
+    cfg!(feature = "mock-chain") && get_env("BITCOIN_BACKEND").await == "mock"
+}
+
 #[allow(dead_code)]
 pub async fn start_node() {
+    if mock_chain().await {
+        return;
+    }
+
     let path = env::current_dir().expect("oh no!");
     let path = path.to_str().expect("oh no!");
     let full_file = format!("{}/tests/scripts/startup_node.sh", path);
@@ -81,6 +96,18 @@ pub async fn start_node() {
 }
 
 pub async fn send_some_coins(address: &str, amount: &str) {
+    #[cfg(feature = "mock-chain")]
+    if mock_chain().await {
+        let script = bitcoin::Address::from_str(address)
+            .expect("invalid address")
+            .script_pubkey();
+        let amount = bitcoin::Amount::from_str_in(amount, bitcoin::Denomination::Bitcoin)
+            .expect("invalid amount");
+        MOCK_BACKEND.send_to(&script, amount.to_sat());
+        MOCK_BACKEND.mine(2);
+        return;
+    }
+
     let path = env::current_dir().expect("oh no!");
     let path = path.to_str().expect("oh no!");
     let full_file = format!("{}/tests/scripts/send_coins.sh", path);
@@ -97,6 +124,12 @@ pub async fn send_some_coins(address: &str, amount: &str) {
 }
 
 pub async fn generate_new_block() {
+    #[cfg(feature = "mock-chain")]
+    if mock_chain().await {
+        MOCK_BACKEND.mine(1);
+        return;
+    }
+
     let path = env::current_dir().expect("oh no!");
     let path = path.to_str().expect("oh no!");
     let full_file = format!("{}/tests/scripts/new_blocks.sh", path);
@@ -113,6 +146,10 @@ pub async fn generate_new_block() {
 
 #[allow(dead_code)]
 pub async fn stop_node() {
+    if mock_chain().await {
+        return;
+    }
+
     let path = env::current_dir().expect("oh no!");
     let path = path.to_str().expect("oh no!");
     let full_file = format!("{}/tests/scripts/stop_node.sh", path);
@@ -1,7 +1,7 @@
 #![cfg(not(target_arch = "wasm32"))]
 use crate::rgb::integration::utils::ISSUER_MNEMONIC;
 use anyhow::{anyhow, Context};
-use bitmask_core::carbonado::{retrieve_metadata, store};
+use bitmask_core::carbonado::{auth::WriteAuth, retrieve_metadata, store};
 use bitmask_core::rgb::constants::RGB_STRICT_TYPE_VERSION;
 use bitmask_core::structs::WatcherRequest;
 use bitmask_core::{
@@ -101,12 +101,17 @@ async fn store_in_server(
     let name = format!("{network}-{name}");
     let hash = blake3::hash(name.as_bytes());
     let url = format!("{endpoint}/carbonado/{pk_hex}/{hash}");
+    let auth = WriteAuth::sign(&hex::encode(&sk), &format!("{pk_hex}/{hash}"), &body)?;
     let client = reqwest::Client::new();
-    let response = client
+    let mut request = client
         .post(&url)
-        .body(body)
         .header("Content-Type", "application/octet-stream")
-        .header("Cache-Control", "no-cache")
+        .header("Cache-Control", "no-cache");
+    for (name, value) in auth.headers() {
+        request = request.header(name, value);
+    }
+    let response = request
+        .body(body)
         .send()
         .await
         .context(format!("Error sending JSON POST request to {url}"))?;
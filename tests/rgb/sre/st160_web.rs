@@ -3,6 +3,7 @@
 #![allow(unused_variables)]
 #![cfg(target_arch = "wasm32")]
 use anyhow::{anyhow, Context};
+use bitmask_core::carbonado::auth::WriteAuth;
 use bitmask_core::rgb::constants::RGB_STRICT_TYPE_VERSION;
 use bitmask_core::structs::FileMetadata;
 use bitmask_core::structs::ReIssueResponse;
@@ -139,12 +140,18 @@ async fn store_in_server(
     let name = format!("{network}-{name}");
     let hash = blake3::hash(name.as_bytes());
     let url = format!("{endpoint}/carbonado/{pk_hex}/{hash}");
+    let auth = WriteAuth::sign(&hex::encode(&sk), &format!("{pk_hex}/{hash}"), &body)
+        .map_err(|err| anyhow!(err.to_string()))?;
     let client = reqwest::Client::new();
-    let response = client
+    let mut request = client
         .post(&url)
-        .body(body)
         .header("Content-Type", "application/octet-stream")
-        .header("Cache-Control", "no-cache")
+        .header("Cache-Control", "no-cache");
+    for (name, value) in auth.headers() {
+        request = request.header(name, value);
+    }
+    let response = request
+        .body(body)
         .send()
         .await
         .context(format!("Error sending JSON POST request to {url}"))?;
@@ -0,0 +1,111 @@
+#![cfg(not(target_arch = "wasm32"))]
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use bitmask_core::{
+    constants::storage_keys::{ASSETS_OFFERS, ASSETS_RESERVED, ASSETS_WATCHERS},
+    rgb::{
+        backup::{
+            check_lib_ids, current_lib_ids, decrypt_backup, encrypt_backup, BackupError, RgbBackup,
+        },
+        constants::RGB_STRICT_TYPE_VERSION,
+        migration::PersistedModel,
+        structs::{RgbReservedUtxos, RgbWatchers},
+        swap::RgbOffers,
+    },
+    structs::SecretString,
+};
+use rgbstd::persistence::{Stash, Stock};
+
+use crate::rgb::unit::utils::create_fake_contract;
+
+#[test]
+fn allow_restore_encrypted_backup() -> Result<()> {
+    let mut stock = Stock::default();
+    create_fake_contract(&mut stock);
+
+    let mut backup = RgbBackup::new("regtest", 1_700_000_000, Some("descriptor".to_string()));
+    backup.insert_stock(&stock)?;
+    backup.insert(
+        ASSETS_WATCHERS,
+        &RgbWatchers::default(),
+        &RGB_STRICT_TYPE_VERSION,
+    )?;
+    let mut reserved = RgbReservedUtxos::default();
+    reserved
+        .utxos
+        .insert("70339a6b27f55105da2d050babc759f046c21c26b7b75e9394bc1d818e50ff52:0".to_string());
+    backup.insert(ASSETS_RESERVED, &reserved, &RGB_STRICT_TYPE_VERSION)?;
+
+    let password = SecretString("correct horse battery staple".to_string());
+    let archive = encrypt_backup(&backup, &password)?;
+    let restored = decrypt_backup(&archive, &password)?;
+    assert_eq!(restored, backup);
+    assert_eq!(
+        restored
+            .stock()?
+            .contract_ids()
+            .expect("invalid contracts state"),
+        stock.contract_ids().expect("invalid contracts state")
+    );
+    assert_eq!(restored.account()?, Default::default());
+    assert_eq!(restored.get::<RgbReservedUtxos>(ASSETS_RESERVED)?, reserved);
+
+    let wrong_password = SecretString("wrong".to_string());
+    assert_eq!(
+        decrypt_backup(&archive, &wrong_password),
+        Err(BackupError::WrongPassword)
+    );
+
+    // the last byte belongs to the authentication tag
+    let mut tampered = hex::decode(&archive)?;
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    assert_eq!(
+        decrypt_backup(&hex::encode(tampered), &password),
+        Err(BackupError::WrongPassword)
+    );
+
+    assert_eq!(
+        decrypt_backup("00", &password),
+        Err(BackupError::WrongFormat)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn allow_check_backup_lib_ids() {
+    assert!(check_lib_ids(&current_lib_ids()).is_ok());
+
+    let mut lib_ids = current_lib_ids();
+    lib_ids.insert(
+        "LIB_ID_RGB_STD".to_string(),
+        "urn:ubideco:stl:unknown".to_string(),
+    );
+    assert!(matches!(
+        check_lib_ids(&lib_ids),
+        Err(BackupError::UnknownLibId(_, _))
+    ));
+
+    assert!(check_lib_ids(&BTreeMap::new()).is_ok());
+}
+
+#[test]
+fn allow_migrate_versioned_backup_components() -> Result<()> {
+    let mut backup = RgbBackup::new("regtest", 1_700_000_000, None);
+    backup.insert(
+        ASSETS_OFFERS,
+        &RgbOffers::default(),
+        &RGB_STRICT_TYPE_VERSION,
+    )?;
+    backup.get_model::<RgbOffers>(ASSETS_OFFERS, PersistedModel::Offers)?;
+
+    // a component in a version its model doesn't know is not restored
+    backup.insert(ASSETS_OFFERS, &RgbOffers::default(), b"v99")?;
+    assert!(matches!(
+        backup.get_model::<RgbOffers>(ASSETS_OFFERS, PersistedModel::Offers),
+        Err(BackupError::WrongComponent(_, _))
+    ));
+    Ok(())
+}
@@ -2,11 +2,22 @@
 use std::collections::HashMap;
 
 use amplify::{confinement::U32, hex::ToHex};
+use bdk::{
+    database::MemoryDatabase, descriptor::IntoWalletDescriptor, wallet::AddressIndex, SignOptions,
+    SyncOptions,
+};
+use bitcoin::{secp256k1::Secp256k1, util::psbt::PartiallySignedTransaction, Network};
 use bitmask_core::{
+    bitcoin::new_mnemonic,
+    chain::{ChainBackend, ChainBlockchain, MockBackend},
     rgb::{
         consignment::NewTransferOptions,
+        issue::issue_contract,
+        psbt::{create_psbt, set_tapret_output, NewPsbtOptions},
+        resolvers::{MockResolver, ResolveTxStatus},
         transfer::{accept_transfer, create_invoice, pay_invoice},
     },
+    structs::{PsbtInputRequest, SecretString, TxStatus},
     util::init_logging,
 };
 use rgbstd::persistence::Stock;
@@ -91,3 +102,121 @@ async fn allow_accept_invoice() -> anyhow::Result<()> {
     assert!(pay_status.is_ok());
     Ok(())
 }
+
+/// RGB wallet of a new mnemonic, synchronized with the mock chain
+async fn mock_rgb_wallet(
+    mock: &MockBackend,
+) -> anyhow::Result<(String, bdk::Wallet<MemoryDatabase>)> {
+    let keys = new_mnemonic(&SecretString("".to_string())).await?;
+    let descriptor = keys
+        .private
+        .rgb_assets_descriptor_xprv
+        .into_wallet_descriptor(&Secp256k1::new(), Network::Regtest)?;
+    let wallet = bdk::Wallet::new(descriptor, None, Network::Regtest, MemoryDatabase::new())?;
+
+    let address = wallet.get_address(AddressIndex::New)?;
+    mock.send_to(&address.script_pubkey(), 100_000);
+    mock.mine(1);
+    wallet
+        .sync(&ChainBlockchain::new(mock.clone()), SyncOptions::default())
+        .await?;
+
+    Ok((keys.public.rgb_assets_descriptor_xpub, wallet))
+}
+
+#[tokio::test]
+async fn allow_issue_and_transfer_on_mock_chain() -> anyhow::Result<()> {
+    init_logging("rgb_invoice=warn");
+
+    // the chain is passed to the wallets and resolvers, no process-wide backend is selected
+    let mock = MockBackend::default();
+    let mut resolver = MockResolver::new(mock.clone());
+
+    let (issuer_desc, issuer_wallet) = mock_rgb_wallet(&mock).await?;
+    let issue_utxo = issuer_wallet.list_unspent()?[0].outpoint;
+    let mut issuer_stock = Stock::default();
+    let contract = issue_contract(
+        "DIBA",
+        "DIBA",
+        "DIBA",
+        2,
+        1_000,
+        "RGB20",
+        &format!("tapret1st:{issue_utxo}"),
+        "regtest",
+        None,
+        &mut resolver,
+        &mut issuer_stock,
+    )?;
+    let contract_id = contract.contract_id();
+
+    let (_, owner_wallet) = mock_rgb_wallet(&mock).await?;
+    let owner_utxo = owner_wallet.list_unspent()?[0].outpoint;
+    let mut owner_stock = Stock::default();
+    owner_stock
+        .import_contract(contract, &mut resolver)
+        .expect("valid contract");
+    let invoice = create_invoice(
+        &contract_id.to_string(),
+        "RGB20",
+        300,
+        &format!("tapret1st:{owner_utxo}"),
+        "regtest",
+        HashMap::new(),
+        &mut owner_stock,
+    )?;
+
+    let (psbt, _) = create_psbt(
+        vec![PsbtInputRequest {
+            descriptor: SecretString(issuer_desc),
+            utxo: issue_utxo.to_string(),
+            utxo_terminal: "/20/0".to_string(),
+            sigh_hash: None,
+            tapret: None,
+        }],
+        vec![],
+        1_000,
+        Some("/20/1".to_string()),
+        None,
+        &resolver,
+        NewPsbtOptions::default(),
+    )?;
+    let pos = (psbt.outputs.len() - 1) as u16;
+    let psbt = set_tapret_output(psbt, pos)?;
+    let (psbt, mut transfers) = pay_invoice(
+        invoice.to_string(),
+        psbt.to_string(),
+        NewTransferOptions::default(),
+        &mut issuer_stock,
+    )?;
+
+    let mut psbt = PartiallySignedTransaction::from(psbt);
+    let sign_options = SignOptions {
+        allow_all_sighashes: true,
+        remove_partial_sigs: false,
+        ..Default::default()
+    };
+    assert!(issuer_wallet.sign(&mut psbt, sign_options)?);
+    let witness = psbt.extract_tx();
+    mock.broadcast(&witness).await?;
+    mock.mine(1);
+    assert_eq!(
+        resolver.resolve_tx_status(witness.txid())?,
+        TxStatus::Block(3)
+    );
+
+    let transfer = transfers.remove(0);
+    let transfer_hex = transfer
+        .to_strict_serialized::<U32>()
+        .expect("valid transfer")
+        .to_hex();
+    accept_transfer(transfer_hex, false, &mut resolver, &mut owner_stock)?;
+
+    // the issue utxo is spent by the witness
+    let output_status = mock
+        .get_output_status(&issue_utxo.txid, issue_utxo.vout)
+        .await?
+        .expect("issue utxo exists");
+    assert_eq!(output_status.txid, Some(witness.txid()));
+    Ok(())
+}
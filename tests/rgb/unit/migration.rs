@@ -0,0 +1,184 @@
+#![cfg(not(target_arch = "wasm32"))]
+use anyhow::Result;
+use async_trait::async_trait;
+use bitmask_core::{
+    constants::storage_keys::{ASSETS_OFFERS, ASSETS_WALLETS},
+    rgb::{
+        constants::{RGB_OLDEST_VERSION, RGB_STRICT_TYPE_VERSION},
+        migration::{
+            chain, migrate_account_files, plan_account_files, version_of, MigrationError,
+            PersistedModel,
+        },
+        storage::{MemoryStorage, StorageBackend, StorageBackendError},
+        structs::{RgbAccountV0, RgbAccountV1},
+        swap::RgbOffers,
+    },
+};
+use postcard::{from_bytes, to_allocvec};
+use rgbstd::stl::LIB_ID_RGB;
+
+const OWNER_SK: &str = "0101010101010101010101010101010101010101010101010101010101010101";
+
+fn file_name(name: &str) -> String {
+    let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase();
+    format!("{hashed_name}.c15")
+}
+
+async fn store_legacy_files(storage: &dyn StorageBackend) -> Result<()> {
+    let account = to_allocvec(&RgbAccountV0::default())?;
+    storage
+        .store(
+            OWNER_SK,
+            &file_name(ASSETS_WALLETS),
+            &account,
+            false,
+            Some(RGB_STRICT_TYPE_VERSION.to_vec()),
+        )
+        .await?;
+
+    let offers = to_allocvec(&RgbOffers::default())?;
+    storage
+        .store(OWNER_SK, &file_name(ASSETS_OFFERS), &offers, false, None)
+        .await?;
+    Ok(())
+}
+
+/// Fails the writes of a file, to check a migration is undone
+struct FailingStorage {
+    inner: MemoryStorage,
+    failing: String,
+    /// Fail the forced writes too, which restore the files
+    fail_restores: bool,
+}
+
+#[async_trait]
+impl StorageBackend for FailingStorage {
+    async fn store(
+        &self,
+        sk: &str,
+        name: &str,
+        input: &[u8],
+        force: bool,
+        metadata: Option<Vec<u8>>,
+    ) -> Result<(), StorageBackendError> {
+        if name == self.failing || (force && self.fail_restores) {
+            return Err(StorageBackendError::Sled("disk full".to_string()));
+        }
+        self.inner.store(sk, name, input, force, metadata).await
+    }
+
+    async fn retrieve(
+        &self,
+        sk: &str,
+        name: &str,
+        alt_names: Vec<&String>,
+    ) -> Result<(Vec<u8>, Option<Vec<u8>>), StorageBackendError> {
+        self.inner.retrieve(sk, name, alt_names).await
+    }
+
+    async fn remove(&self, sk: &str, name: &str) -> Result<(), StorageBackendError> {
+        self.inner.remove(sk, name).await
+    }
+}
+
+#[test]
+fn allow_upgrade_models_through_their_chain() -> Result<()> {
+    let account = chain(PersistedModel::Account);
+    assert_eq!(account.path(RGB_OLDEST_VERSION)?.len(), 2);
+    assert_eq!(account.path(RGB_STRICT_TYPE_VERSION)?.len(), 1);
+    assert!(account.path(account.current)?.is_empty());
+
+    let data = to_allocvec(&RgbAccountV0::default())?;
+    let upgraded = account.migrate(&data, RGB_OLDEST_VERSION)?;
+    assert_eq!(
+        from_bytes::<RgbAccountV1>(&upgraded)?,
+        RgbAccountV1::default()
+    );
+
+    let offers = chain(PersistedModel::Offers);
+    assert_eq!(offers.current, RGB_STRICT_TYPE_VERSION);
+    let data = to_allocvec(&RgbOffers::default())?;
+    let decoded: RgbOffers = offers.decode(&data, None)?;
+    assert!(decoded.offers.is_empty());
+
+    let unknown = version_of(Some(b"v99"));
+    assert_eq!(
+        account.path(unknown).unwrap_err(),
+        MigrationError::NoUpgrade(PersistedModel::Account, "v99".to_string())
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_migrate_account_files() -> Result<()> {
+    let storage = MemoryStorage::default();
+    store_legacy_files(&storage).await?;
+
+    // the dry-run doesn't write anything
+    let plan = plan_account_files(&storage, OWNER_SK).await?;
+    let models: Vec<_> = plan.iter().map(|x| x.model.as_str()).collect();
+    assert_eq!(models, vec!["account", "offers"]);
+    assert_eq!(plan[0].from, "rgbst161");
+    assert_eq!(plan[0].to, "v10");
+    assert_eq!(plan[1].from, "");
+    assert_eq!(plan[1].steps, 1);
+    assert_eq!(plan_account_files(&storage, OWNER_SK).await?, plan);
+
+    let migrated = migrate_account_files(&storage, OWNER_SK).await?;
+    assert_eq!(migrated, plan);
+    assert!(plan_account_files(&storage, OWNER_SK).await?.is_empty());
+
+    let (data, metadata) = storage
+        .retrieve(OWNER_SK, &file_name(ASSETS_WALLETS), vec![])
+        .await?;
+    assert_eq!(from_bytes::<RgbAccountV1>(&data)?, RgbAccountV1::default());
+    assert_eq!(
+        version_of(metadata.as_deref()),
+        chain(PersistedModel::Account).current
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_restore_files_of_failed_migration() -> Result<()> {
+    let storage = FailingStorage {
+        inner: MemoryStorage::default(),
+        failing: file_name(ASSETS_OFFERS),
+        fail_restores: false,
+    };
+    store_legacy_files(&storage.inner).await?;
+    let (account, metadata) = storage
+        .retrieve(OWNER_SK, &file_name(ASSETS_WALLETS), vec![])
+        .await?;
+
+    let err = migrate_account_files(&storage, OWNER_SK).await.unwrap_err();
+    assert!(matches!(err, MigrationError::Write(file, _) if file == ASSETS_OFFERS));
+
+    // the account was upgraded first, and restored
+    let (restored, restored_metadata) = storage
+        .retrieve(OWNER_SK, &file_name(ASSETS_WALLETS), vec![])
+        .await?;
+    assert_eq!(restored, account);
+    assert_eq!(restored_metadata, metadata);
+    assert_eq!(plan_account_files(&storage, OWNER_SK).await?.len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_report_files_not_restored_after_failed_migration() -> Result<()> {
+    let storage = FailingStorage {
+        inner: MemoryStorage::default(),
+        failing: file_name(ASSETS_OFFERS),
+        fail_restores: true,
+    };
+    store_legacy_files(&storage.inner).await?;
+
+    let err = migrate_account_files(&storage, OWNER_SK).await.unwrap_err();
+    assert!(matches!(err, MigrationError::Restore(files, _) if files.starts_with(ASSETS_WALLETS)));
+
+    // the account stays upgraded, only the offers are left
+    assert_eq!(plan_account_files(&storage, OWNER_SK).await?.len(), 1);
+    Ok(())
+}
@@ -0,0 +1,74 @@
+#![cfg(not(target_arch = "wasm32"))]
+use std::str::FromStr;
+
+use bitcoin::OutPoint;
+use bitmask_core::{
+    bitcoin::{list_locked_utxos, lock_utxos, unlock_utxos, unspendable_utxos},
+    rgb::{
+        colored_coin_control, list_reserved_utxos, release_utxos, reserve_utxos,
+        structs::RgbUtxoPool,
+    },
+    structs::CoinControl,
+};
+
+const OWNER_SK: &str = "0606060606060606060606060606060606060606060606060606060606060606";
+const OTHER_SK: &str = "0808080808080808080808080808080808080808080808080808080808080808";
+
+#[tokio::test]
+async fn calculate_utxo_pool_deficit() -> anyhow::Result<()> {
+    let pool = RgbUtxoPool::new(5, 1000);
+    assert_eq!(pool.deficit(0), 5);
+    assert_eq!(pool.deficit(3), 2);
+    assert_eq!(pool.deficit(5), 0);
+    assert_eq!(pool.deficit(8), 0);
+    assert_eq!(pool.to_string(), "5x1000");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_reserve_and_release_utxos_of_the_account() -> anyhow::Result<()> {
+    let outpoint =
+        OutPoint::from_str("5ca6cd1f54c081c8b3a7b4bcc988e55fe3c420ac87512b53a58c55233e15ba4f:1")?;
+
+    reserve_utxos(OWNER_SK, vec![outpoint]).await?;
+    assert!(list_reserved_utxos(OWNER_SK).await?.contains(&outpoint));
+    assert!(!list_reserved_utxos(OTHER_SK).await?.contains(&outpoint));
+
+    let coin_control = colored_coin_control(OWNER_SK, None).await?;
+    assert!(coin_control.exclude.contains(&outpoint));
+
+    release_utxos(OWNER_SK, vec![outpoint]).await?;
+    assert!(!list_reserved_utxos(OWNER_SK).await?.contains(&outpoint));
+    let coin_control = colored_coin_control(OWNER_SK, None).await?;
+    assert!(!coin_control.exclude.contains(&outpoint));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_lock_and_unlock_utxos_of_the_account() -> anyhow::Result<()> {
+    let outpoint =
+        OutPoint::from_str("8f2e1a5a1e4bb4c7b5a0e1a3ef8d6f0c5b2e7d9a4c3b1f0e9d8c7b6a5f4e3d2c:0")?;
+
+    lock_utxos(OWNER_SK, vec![outpoint]).await?;
+    assert!(list_locked_utxos(OWNER_SK).await?.contains(&outpoint));
+    let unspendable = unspendable_utxos(OWNER_SK, &CoinControl::default()).await?;
+    assert!(unspendable.contains(&outpoint));
+    let unspendable = unspendable_utxos(OTHER_SK, &CoinControl::default()).await?;
+    assert!(!unspendable.contains(&outpoint));
+
+    // the caller can still spend them explicitly
+    let coin_control = CoinControl {
+        include: vec![outpoint],
+        ..Default::default()
+    };
+    assert!(!unspendable_utxos(OWNER_SK, &coin_control)
+        .await?
+        .contains(&outpoint));
+
+    unlock_utxos(OWNER_SK, vec![outpoint]).await?;
+    assert!(!list_locked_utxos(OWNER_SK).await?.contains(&outpoint));
+
+    Ok(())
+}
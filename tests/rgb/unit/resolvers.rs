@@ -0,0 +1,83 @@
+#![cfg(not(target_arch = "wasm32"))]
+use std::{str::FromStr, time::Duration};
+
+use anyhow::Result;
+use bitmask_core::{
+    rgb::{
+        import::{import_contract, ImportContractError},
+        resolvers::{mined_witness_ord, ExplorerResolver, HeightResolverError, RetryPolicy},
+    },
+    structs::AssetType,
+};
+use rgbstd::persistence::Stock;
+use wallet::onchain::ResolveTx;
+
+use crate::rgb::unit::utils::DumbResolve;
+
+#[test]
+fn allow_explorer_failover_order() {
+    let resolver = ExplorerResolver {
+        explorer_url: "https://mempool.space/api".to_owned(),
+        fallback_urls: vec![
+            "https://blockstream.info/api".to_owned(),
+            "https://mempool.space/api".to_owned(),
+        ],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        resolver.explorer_urls(),
+        vec![
+            "https://mempool.space/api".to_owned(),
+            "https://blockstream.info/api".to_owned()
+        ]
+    );
+}
+
+#[test]
+fn unavailable_explorers_return_error() -> Result<()> {
+    let resolver = ExplorerResolver {
+        explorer_url: "http://127.0.0.1:9".to_owned(),
+        fallback_urls: vec!["http://127.0.0.1:10".to_owned()],
+        retry: RetryPolicy {
+            max_retries: 1,
+            backoff: Duration::from_millis(1),
+        },
+        ..Default::default()
+    };
+
+    let txid = bitcoin::Txid::from_str(
+        "70339a6b27f55105da2d050babc759f046c21c26b7b75e9394bc1d818e50ff52",
+    )?;
+    assert!(ResolveTx::resolve_tx(&resolver, txid).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn invalid_witness_height_returns_error() -> Result<()> {
+    let txid = bitcoin::Txid::from_str(
+        "70339a6b27f55105da2d050babc759f046c21c26b7b75e9394bc1d818e50ff52",
+    )?;
+    assert!(mined_witness_ord(txid, 800_000).is_ok());
+    assert!(matches!(
+        mined_witness_ord(txid, 0),
+        Err(HeightResolverError::InvalidHeight(_, 0))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn invalid_contract_import_returns_error() {
+    let mut stock = Stock::default();
+    let mut resolver = DumbResolve {};
+
+    let result = import_contract(
+        "not a contract",
+        AssetType::RGB20,
+        &mut stock,
+        &mut resolver,
+    );
+    assert!(matches!(result, Err(ImportContractError::WrongEncoding(_))));
+}
@@ -1,6 +1,18 @@
 #![cfg(not(target_arch = "wasm32"))]
-use bitmask_core::util::init_logging;
-use rgbstd::persistence::{Inventory, Stash, Stock};
+use amplify::confinement::U32;
+use bitmask_core::{
+    rgb::{
+        carbonado::{retrieve_stock, store_stock, StorageError},
+        chunks::{remember_manifest, split_chunks, ChunkManifest, CHUNK_MAX_SIZE},
+        storage::retrieve,
+    },
+    util::init_logging,
+};
+use rgbstd::{
+    persistence::{Inventory, Stash, Stock},
+    stl::LIB_ID_RGB,
+};
+use strict_encoding::StrictSerialize;
 
 use crate::rgb::unit::utils::create_fake_contract;
 
@@ -69,3 +81,79 @@ async fn allow_list_schemas() -> anyhow::Result<()> {
     assert!(!schemas.is_empty());
     Ok(())
 }
+
+#[test]
+fn allow_store_stock_changes_in_few_chunks() {
+    let mut data = vec![0u8; 2 * 1024 * 1024];
+    blake3::Hasher::new()
+        .update(b"stock")
+        .finalize_xof()
+        .fill(&mut data);
+
+    let chunks = split_chunks(&data);
+    assert!(chunks.len() > 1);
+    assert!(chunks.iter().all(|chunk| chunk.len() <= CHUNK_MAX_SIZE));
+    assert_eq!(chunks.concat(), data);
+
+    let (manifest, _) = ChunkManifest::with(&data);
+    let mut changed = data.clone();
+    changed.splice(1024 * 1024..1024 * 1024, [7u8; 100]);
+    let (changed_manifest, _) = ChunkManifest::with(&changed);
+
+    let new_chunks: Vec<_> = changed_manifest
+        .chunks
+        .iter()
+        .filter(|id| !manifest.contains(id))
+        .collect();
+    assert!(!new_chunks.is_empty());
+    assert!(new_chunks.len() <= 3);
+}
+
+#[tokio::test]
+async fn allow_store_and_retrieve_stock_in_chunks() -> anyhow::Result<()> {
+    init_logging("rgb_stock=warn");
+
+    let sk = "0505050505050505050505050505050505050505050505050505050505050505";
+    let name = "bitmask-chunked_stock_test.c15";
+    let hashed_name = blake3::hash(format!("{LIB_ID_RGB}-{name}").as_bytes())
+        .to_hex()
+        .to_lowercase();
+
+    // the manifest of an earlier run is the base of the writes
+    retrieve_stock(sk, name).await?;
+
+    let empty = Stock::default();
+    store_stock(sk, name, &empty).await?;
+    let retrieved = retrieve_stock(sk, name).await?;
+    let empty_data = empty.to_strict_serialized::<U32>()?;
+    assert_eq!(retrieved.to_strict_serialized::<U32>()?, empty_data);
+
+    let mut stock = Stock::default();
+    create_fake_contract(&mut stock);
+    store_stock(sk, name, &stock).await?;
+    let retrieved = retrieve_stock(sk, name).await?;
+    assert_eq!(
+        retrieved.to_strict_serialized::<U32>()?,
+        stock.to_strict_serialized::<U32>()?
+    );
+
+    // the chunks dropped by the new manifest are removed
+    let (empty_manifest, _) = ChunkManifest::with(&empty_data);
+    let (manifest, _) = ChunkManifest::with(&stock.to_strict_serialized::<U32>()?);
+    for id in empty_manifest
+        .chunks
+        .iter()
+        .filter(|id| !manifest.contains(id))
+    {
+        let (chunk, _) = retrieve(sk, &format!("{hashed_name}-{id}.c15"), vec![]).await?;
+        assert!(chunk.is_empty());
+    }
+
+    // a manifest stored by another device is not overwritten
+    remember_manifest(sk, &hashed_name, empty_manifest);
+    assert_eq!(
+        store_stock(sk, name, &empty).await.unwrap_err(),
+        StorageError::Conflict(name.to_string())
+    );
+    Ok(())
+}
@@ -0,0 +1,69 @@
+#![cfg(not(target_arch = "wasm32"))]
+use anyhow::Result;
+use bitmask_core::rgb::{
+    constants::RGB_STRICT_TYPE_VERSION,
+    storage::{MemoryStorage, SledStorage, StorageBackend, StorageBackendError},
+};
+
+const OWNER_SK: &str = "0101010101010101010101010101010101010101010101010101010101010101";
+const OTHER_SK: &str = "0202020202020202020202020202020202020202020202020202020202020202";
+
+async fn check_storage_backend(storage: &dyn StorageBackend) -> Result<()> {
+    let name = "bitmask-storage_test.c15".to_string();
+    storage
+        .store(
+            OWNER_SK,
+            &name,
+            b"rgb stock",
+            false,
+            Some(RGB_STRICT_TYPE_VERSION.to_vec()),
+        )
+        .await?;
+
+    let (data, metadata) = storage.retrieve(OWNER_SK, &name, vec![]).await?;
+    assert_eq!(data, b"rgb stock");
+    assert_eq!(metadata, Some(RGB_STRICT_TYPE_VERSION.to_vec()));
+
+    // files are namespaced by owner
+    let (data, metadata) = storage.retrieve(OTHER_SK, &name, vec![]).await?;
+    assert!(data.is_empty());
+    assert!(metadata.is_none());
+
+    let (data, _) = storage
+        .retrieve(OWNER_SK, "bitmask-missing.c15", vec![&name])
+        .await?;
+    assert_eq!(data, b"rgb stock");
+
+    // the carbonado header only has 8 bytes of metadata
+    let err = storage
+        .store(OWNER_SK, &name, b"rgb stock", true, Some(vec![1; 9]))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, StorageBackendError::MetadataTooLong(9)));
+
+    let removed = "bitmask-removed_test.c15".to_string();
+    storage
+        .store(OWNER_SK, &removed, b"rgb chunk", true, None)
+        .await?;
+    storage.remove(OWNER_SK, &removed).await?;
+    // removing a missing file succeeds
+    storage.remove(OWNER_SK, &removed).await?;
+    let (data, _) = storage.retrieve(OWNER_SK, &removed, vec![]).await?;
+    assert!(data.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_store_in_memory_storage() -> Result<()> {
+    let storage = MemoryStorage::default();
+    check_storage_backend(&storage).await?;
+    assert_eq!(storage.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn allow_store_in_sled_storage() -> Result<()> {
+    let storage = SledStorage::temporary()?;
+    check_storage_backend(&storage).await
+}
@@ -0,0 +1,158 @@
+#![cfg(not(target_arch = "wasm32"))]
+use anyhow::Result;
+use autosurgeon::{hydrate, reconcile};
+use bitmask_core::{
+    rgb::{
+        crdt::{init_document, RawRgbAccount, RawRgbWallet, RawUtxo},
+        migration::PersistedModel,
+        sync::{
+            apply_changes, changes_since, conflicts, heads, read_sync_event, sync_event,
+            utxo_conflicts, SyncError,
+        },
+    },
+    structs::RgbSyncDocument,
+};
+
+const OWNER_SK: &str = "0101010101010101010101010101010101010101010101010101010101010101";
+const OTHER_SK: &str = "0202020202020202020202020202020202020202020202020202020202020202";
+
+fn raw_wallet(xpub: &str, outpoint: &str, terminal: &str) -> RawRgbWallet {
+    RawRgbWallet {
+        xpub: xpub.to_string(),
+        utxos: vec![RawUtxo {
+            outpoint: outpoint.to_string(),
+            block: 0,
+            amount: 10000000,
+            terminal: terminal.to_string(),
+            tweak: None,
+        }],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn allow_exchange_changes_by_heads() -> Result<()> {
+    let mut device_a = init_document::<RawRgbAccount>()?;
+    let mut device_b = device_a.fork();
+    let known = heads(&mut device_b);
+
+    let mut rgb_account = RawRgbAccount::default();
+    rgb_account.invoices.push("rgb:invoice".to_string());
+    reconcile(&mut device_a, &rgb_account)?;
+
+    let changes = changes_since(&mut device_a, &known)?;
+    assert_eq!(changes.len(), 1);
+    assert_eq!(apply_changes(&mut device_b, &changes)?, 1);
+    assert_eq!(heads(&mut device_b), heads(&mut device_a));
+
+    // the changes already applied are ignored
+    assert_eq!(apply_changes(&mut device_b, &changes)?, 0);
+    assert!(changes_since(&mut device_a, &heads(&mut device_b))?.is_empty());
+
+    let synced: RawRgbAccount = hydrate(&device_b)?;
+    assert_eq!(synced, rgb_account);
+
+    let wrong_head = "00".to_string();
+    assert_eq!(
+        changes_since(&mut device_a, &[wrong_head.clone()]).unwrap_err(),
+        SyncError::WrongHead(wrong_head)
+    );
+    Ok(())
+}
+
+#[test]
+fn allow_exchange_changes_of_devices_initialized_apart() -> Result<()> {
+    let mut device_a = init_document::<RawRgbAccount>()?;
+    let mut device_b = init_document::<RawRgbAccount>()?;
+    let init = heads(&mut device_a);
+    assert_eq!(init, heads(&mut device_b));
+
+    let mut account_a = RawRgbAccount::default();
+    account_a.invoices.push("rgb:invoice_a".to_string());
+    reconcile(&mut device_a, &account_a)?;
+
+    let mut account_b = RawRgbAccount::default();
+    account_b.hidden_contracts.push("contract_b".to_string());
+    reconcile(&mut device_b, &account_b)?;
+
+    let changes_a = changes_since(&mut device_a, &init)?;
+    let changes_b = changes_since(&mut device_b, &init)?;
+    assert_eq!(changes_a.len(), 1);
+    assert_eq!(changes_b.len(), 1);
+    assert_eq!(apply_changes(&mut device_a, &changes_b)?, 1);
+    assert_eq!(apply_changes(&mut device_b, &changes_a)?, 1);
+    assert_eq!(heads(&mut device_a), heads(&mut device_b));
+
+    let synced: RawRgbAccount = hydrate(&device_b)?;
+    assert_eq!(synced.invoices, vec!["rgb:invoice_a"]);
+    assert_eq!(synced.hidden_contracts, vec!["contract_b"]);
+    Ok(())
+}
+
+#[test]
+fn allow_report_conflicts_of_concurrent_edits() -> Result<()> {
+    let outpoint = "9a5d21d4cc15ffa14c6f416396235c082cddb5e227abd863974445709f8e9af0:0";
+    let mut device_a = init_document::<RawRgbAccount>()?;
+    let mut device_b = device_a.fork();
+
+    let mut account_a = RawRgbAccount::default();
+    account_a.wallets.insert(
+        "default".to_string(),
+        raw_wallet("xpub_a", outpoint, "20:1"),
+    );
+    reconcile(&mut device_a, &account_a)?;
+
+    let mut account_b = RawRgbAccount::default();
+    account_b.wallets.insert(
+        "default".to_string(),
+        raw_wallet("xpub_b", outpoint, "20:2"),
+    );
+    reconcile(&mut device_b, &account_b)?;
+
+    let changes = changes_since(&mut device_b, &[])?;
+    apply_changes(&mut device_a, &changes)?;
+
+    let reported = conflicts(PersistedModel::Account, &device_a)?;
+    assert_eq!(reported.len(), 1);
+    assert_eq!(reported[0].model, "account");
+    assert_eq!(reported[0].path, "wallets/default");
+    assert_eq!(reported[0].values.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn allow_report_utxos_on_many_terminals() -> Result<()> {
+    let outpoint = "9a5d21d4cc15ffa14c6f416396235c082cddb5e227abd863974445709f8e9af0:0";
+    let mut rgb_account = RawRgbAccount::default();
+    rgb_account
+        .wallets
+        .insert("default".to_string(), raw_wallet("xpub", outpoint, "20:1"));
+    assert!(utxo_conflicts(&rgb_account).is_empty());
+
+    rgb_account
+        .wallets
+        .insert("other".to_string(), raw_wallet("xpub", outpoint, "9:1"));
+    let reported = utxo_conflicts(&rgb_account);
+    assert_eq!(reported.len(), 1);
+    assert_eq!(reported[0].path, format!("utxos/{outpoint}"));
+    assert_eq!(reported[0].values, vec!["default/20:1", "other/9:1"]);
+    Ok(())
+}
+
+#[test]
+fn allow_read_sync_events_of_the_owner() -> Result<()> {
+    let document = RgbSyncDocument {
+        model: "offers".to_string(),
+        heads: vec![hex::encode([1; 32])],
+        changes: vec!["00".to_string()],
+    };
+
+    let event = sync_event(OWNER_SK, &document)?;
+    assert_ne!(event.content, serde_json::to_string(&document)?);
+    assert_eq!(read_sync_event(OWNER_SK, &event)?, document);
+    assert!(matches!(
+        read_sync_event(OTHER_SK, &event),
+        Err(SyncError::Nostr(_))
+    ));
+    Ok(())
+}
@@ -0,0 +1,32 @@
+#![cfg(not(target_arch = "wasm32"))]
+use bitmask_core::rgb::{parse_watcher_xpub, WatcherError};
+
+const XPUB: &str = "tpubDCBwP45jcvCdTBZSxn8TcCyQGx5YgietksRRptV9YJ1xnom6edMwb2JcBnNU15t6TmotHETmgnvHQ2Nki7N7CsgFhka6D91UgMaEYpTRuSh";
+
+#[tokio::test]
+async fn allow_watcher_from_descriptors() -> anyhow::Result<()> {
+    let xpub = parse_watcher_xpub(XPUB)?;
+    assert_eq!(xpub.to_string(), XPUB);
+
+    let xpub = parse_watcher_xpub(&format!("{XPUB}/*"))?;
+    assert_eq!(xpub.to_string(), XPUB);
+
+    let xpub = parse_watcher_xpub(&format!("tr({XPUB}/20/*)"))?;
+    assert_eq!(xpub.to_string(), XPUB);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn reject_unsupported_watcher_descriptors() -> anyhow::Result<()> {
+    let result = parse_watcher_xpub(&format!("wpkh({XPUB}/0/*)"));
+    assert!(matches!(result, Err(WatcherError::UnsupportedDesc(_))));
+
+    let result = parse_watcher_xpub(&format!("tr({XPUB}/0/1/*)"));
+    assert!(matches!(result, Err(WatcherError::WrongDerivation(_))));
+
+    let result = parse_watcher_xpub("tr(invalid)");
+    assert!(matches!(result, Err(WatcherError::WrongDesc(_))));
+
+    Ok(())
+}
@@ -946,6 +946,7 @@ async fn create_auction_flow() {
 
     let mut receiver = String::new();
     let mut receiver_sk = String::new();
+    let mut receiver_pub = String::new();
     let mut receiver_desc = String::new();
     let mut receiver_keys = vec![];
 
@@ -962,6 +963,7 @@ async fn create_auction_flow() {
 
             receiver = "OWNER".to_string();
             receiver_sk = owner_sk.to_string();
+            receiver_pub = owner_vault.public.nostr_pub.to_string();
             receiver_desc = owner_vault.public.rgb_assets_descriptor_xpub.to_string();
             receiver_keys = vec![
                 SecretString(owner_vault.private.rgb_assets_descriptor_xprv.clone()),
@@ -980,6 +982,7 @@ async fn create_auction_flow() {
 
             receiver = "ISSUER".to_string();
             receiver_sk = issuer_sk.to_string();
+            receiver_pub = issuer_vault.public.nostr_pub.to_string();
             receiver_desc = issuer_vault.public.rgb_assets_descriptor_xpub.to_string();
             receiver_keys = vec![
                 SecretString(issuer_vault.private.rgb_assets_descriptor_xprv.clone()),
@@ -1016,6 +1019,7 @@ async fn create_auction_flow() {
             strategy: RgbAuctionStrategy::Auction,
             offers: offers_collection.clone(),
             sign_keys: sender_keys.clone(),
+            bidders: vec![receiver_pub.clone()],
             ..Default::default()
         };
 
@@ -1272,6 +1276,7 @@ async fn create_airdrop_flow() {
 
     let mut receiver = String::new();
     let mut receiver_sk = String::new();
+    let mut receiver_pub = String::new();
     let mut receiver_desc = String::new();
     let mut receiver_keys = vec![];
 
@@ -1288,6 +1293,7 @@ async fn create_airdrop_flow() {
 
             receiver = "OWNER".to_string();
             receiver_sk = owner_sk.to_string();
+            receiver_pub = owner_vault.public.nostr_pub.to_string();
             receiver_desc = owner_vault.public.rgb_assets_descriptor_xpub.to_string();
             receiver_keys = vec![
                 SecretString(owner_vault.private.rgb_assets_descriptor_xprv.clone()),
@@ -1306,6 +1312,7 @@ async fn create_airdrop_flow() {
 
             receiver = "ISSUER".to_string();
             receiver_sk = issuer_sk.to_string();
+            receiver_pub = issuer_vault.public.nostr_pub.to_string();
             receiver_desc = issuer_vault.public.rgb_assets_descriptor_xpub.to_string();
             receiver_keys = vec![
                 SecretString(issuer_vault.private.rgb_assets_descriptor_xprv.clone()),
@@ -1345,6 +1352,7 @@ async fn create_airdrop_flow() {
             offers: offers_collection.clone(),
             sign_keys: sender_keys.clone(),
             fee: Some(PsbtFeeRequest::Value(1000)),
+            bidders: vec![receiver_pub.clone()],
         };
 
         let offer_auction_req = serde_wasm_bindgen::to_value(&offer_auction_req).expect("");
@@ -147,11 +147,13 @@ async fn wrong_network() -> Result<()> {
     let main_vault = decrypt_wallet(&hash, &encrypted_descriptors)?;
 
     let result = send_sats(
+        &main_vault.private.nostr_prv,
         &SecretString(main_vault.private.btc_descriptor_xprv.to_owned()),
         &SecretString(main_vault.private.btc_change_descriptor_xprv.to_owned()),
         "bc1pgxpvg7cz0s3akgl9vhv687rzya7frskenukgx3gwuh6q3un5wqgq7xmnhe",
         1000,
         Some(1.0),
+        None,
     )
     .await;
 
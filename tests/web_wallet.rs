@@ -182,6 +182,8 @@ async fn import_test_wallet() {
         wallet_data.address,
         1_000,
         Some(1.1),
+        encrypted_wallet_data.private.nostr_prv.clone(),
+        JsValue::UNDEFINED,
     ))
     .await;
 